@@ -0,0 +1,205 @@
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::encoding::{start_failure, EncodeSession, EncodeState};
+
+// av1an's last words, shown when the analysis fails
+const KEPT_LINES: usize = 5;
+
+pub enum AnalysisStage {
+    Idle,
+    Running(EncodeSession),
+    Done,
+    Failed(String),
+}
+
+// av1an with --sc-only, which writes the scenes it detects to the --scenes file and stops
+// before encoding
+pub struct SceneAnalysis {
+    pub stage: AnalysisStage,
+    pub scenes: PathBuf,
+
+    temp_dir: PathBuf,
+    last_lines: VecDeque<String>,
+}
+
+impl Default for SceneAnalysis {
+    fn default() -> Self {
+        SceneAnalysis {
+            stage: AnalysisStage::Idle,
+            scenes: PathBuf::new(),
+            temp_dir: std::env::temp_dir().join(format!("av1studio-scenes-{}", std::process::id())),
+            last_lines: VecDeque::with_capacity(KEPT_LINES),
+        }
+    }
+}
+
+impl SceneAnalysis {
+    pub fn is_running(&self) -> bool {
+        matches!(self.stage, AnalysisStage::Running(_))
+    }
+
+    // Its own temp folder, av1an's default one is named after the input and would be shared
+    // with an encode of it. The output only has to satisfy av1an, nothing gets written to it.
+    pub fn temp_dir(&self) -> &Path {
+        &self.temp_dir
+    }
+
+    pub fn output_path(&self) -> PathBuf {
+        self.temp_dir.join("analysis.mkv")
+    }
+
+    // Scene detection's progress from 0 to 1, once av1an reported one
+    pub fn progress(&self) -> Option<f32> {
+        match &self.stage {
+            AnalysisStage::Running(session) => match session.state {
+                EncodeState::Analyzing(progress) => progress,
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    // `analyze` is the av1an command, without --sc-only, writing its scenes to `scenes`
+    pub fn start(&mut self, scenes: PathBuf, mut analyze: Command) {
+        self.cancel();
+        self.last_lines.clear();
+        if self.temp_dir.exists() {
+            let _ = std::fs::remove_dir_all(&self.temp_dir);
+        }
+        if let Err(e) = std::fs::create_dir_all(&self.temp_dir) {
+            self.stage =
+                AnalysisStage::Failed(format!("Couldn't create {:?}: {}", self.temp_dir, e));
+            return;
+        }
+
+        analyze.arg("--sc-only");
+        self.stage = match EncodeSession::start(analyze) {
+            Ok(session) => AnalysisStage::Running(session),
+            Err(e) => AnalysisStage::Failed(start_failure(&e)),
+        };
+        self.scenes = scenes;
+    }
+
+    // The scenes file, once, right when av1an wrote it
+    pub fn poll(&mut self) -> Option<PathBuf> {
+        let AnalysisStage::Running(session) = &mut self.stage else {
+            return None;
+        };
+        session.poll();
+        // The analysis stays out of the log
+        for line in session
+            .lines
+            .drain(..)
+            .filter(|line| !line.trim().is_empty())
+        {
+            if self.last_lines.len() == KEPT_LINES {
+                self.last_lines.pop_front();
+            }
+            self.last_lines.push_back(line);
+        }
+        if !session.is_finished() {
+            return None;
+        }
+
+        let _ = std::fs::remove_dir_all(&self.temp_dir);
+        if session.succeeded() && self.scenes.is_file() {
+            self.stage = AnalysisStage::Done;
+            Some(self.scenes.clone())
+        } else {
+            let mut reason = match session.status {
+                Some(status) if !status.success() => format!("av1an exited with {}", status),
+                _ => String::from("av1an didn't write a scenes file"),
+            };
+            for line in self.last_lines.drain(..) {
+                reason.push('\n');
+                reason.push_str(&line);
+            }
+            self.stage = AnalysisStage::Failed(reason);
+            None
+        }
+    }
+
+    pub fn cancel(&mut self) {
+        if let AnalysisStage::Running(session) = &mut self.stage {
+            session.cancel();
+        }
+        self.stage = AnalysisStage::Idle;
+    }
+}
+
+// <output>.scenes.json, numbered when that's taken. An existing scenes file would be read by
+// av1an instead of detected anew.
+pub fn scenes_path_for(output: &Path) -> PathBuf {
+    let stem = output.with_extension("");
+    let path = |suffix: &str| {
+        let mut name = stem.as_os_str().to_owned();
+        name.push(format!(".scenes{}.json", suffix));
+        PathBuf::from(name)
+    };
+
+    let mut candidate = path("");
+    let mut n = 2;
+    while candidate.exists() {
+        candidate = path(&format!(".{}", n));
+        n += 1;
+    }
+
+    candidate
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scenes_file_doesnt_replace_an_existing_one() {
+        let dir =
+            std::env::temp_dir().join(format!("av1studio-scenes-path-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let output = dir.join("movie.av1.mkv");
+        assert_eq!(scenes_path_for(&output), dir.join("movie.av1.scenes.json"));
+        std::fs::write(dir.join("movie.av1.scenes.json"), "{}").unwrap();
+        assert_eq!(
+            scenes_path_for(&output),
+            dir.join("movie.av1.scenes.2.json")
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn failure_keeps_the_last_lines() {
+        let mut analysis = SceneAnalysis {
+            temp_dir: std::env::temp_dir()
+                .join(format!("av1studio-scenes-failing-{}", std::process::id())),
+            ..SceneAnalysis::default()
+        };
+        let mut cmd = Command::new("sh");
+        cmd.args([
+            "-c",
+            "for i in 1 2 3 4 5 6 7; do echo \"line $i\" >&2; done; echo 'no such file' >&2; exit 1",
+        ]);
+        analysis.start(analysis.temp_dir.join("scenes.json"), cmd);
+
+        let started = std::time::Instant::now();
+        while analysis.is_running() {
+            assert!(started.elapsed().as_secs() < 20, "av1an never finished");
+            assert!(analysis.poll().is_none());
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        match &analysis.stage {
+            AnalysisStage::Failed(reason) => {
+                assert!(reason.starts_with("av1an exited with"));
+                assert!(reason.contains("no such file"));
+                assert!(!reason.contains("line 1"));
+                assert_eq!(reason.lines().count(), 1 + KEPT_LINES);
+            }
+            _ => panic!("expected a failure"),
+        }
+    }
+}
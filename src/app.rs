@@ -1,25 +1,177 @@
-use std::io::{BufRead, BufReader};
-use std::process::Stdio;
+use std::collections::{BTreeSet, VecDeque};
+use std::path::{Path, PathBuf};
 use std::sync::mpsc;
+use std::time::{Duration, Instant};
 
 use egui::widgets::Slider;
-use egui::{Align, CollapsingHeader, ComboBox, ProgressBar, RichText, TextStyle, Visuals};
+use egui::{
+    Align, CollapsingHeader, ColorImage, ComboBox, Key, KeyboardShortcut, Modifiers, ProgressBar,
+    RichText, TextStyle, TextureHandle, TextureOptions,
+};
 use rfd::FileDialog;
 
-use crate::depcheck::{can_run, exists};
-use crate::encoding::{generate_command, parse_av1an_output};
+use crate::analyze::{scenes_path_for, AnalysisStage, SceneAnalysis};
+use crate::compare::{CompareStage, CrfComparison};
+use crate::config::{
+    config_dir, load_config, save_config, AppConfig, ConfigError, EnvOverride, StatusServerSettings,
+};
+use crate::depcheck::{
+    exists, low_disk_space, parse_version, test_binary, unknown_flags, version, EncoderVersion,
+    HelpOptions, LowDiskSpace, OptionalTools,
+};
+use crate::encoding::{
+    command_to_string, conflicting_params, custom_params_problem, encoder_params,
+    environment_overrides, format_duration, generate_command, split_args, start_failure,
+    EncodeSession, EncodeState,
+};
+use crate::grain::{GrainStage, GrainTableGenerator, GRAIN_TOOL};
+use crate::history::{SettingsHistory, SETTLE};
+use crate::join::{InputJoin, JoinStage};
+use crate::log::EncodeLog;
 use crate::models::{
-    ColorPrimaries, ColorRange, MatrixCoefficients, PixelFormat, SourceLibrary, Theme,
-    TransferCharacteristics,
+    color_metadata_problems, height_for_width, max_bitrate_problem, max_bitrate_warning,
+    photon_noise_problem, photon_noise_size_problem, photon_noise_warning, scale_size,
+    scene_length_problem, target_bitrate_problem, width_for_height, BuiltinPreset,
+    ChromaSamplePosition, ChunkMethod, ChunkOrder, ColorPrimaries, ColorRange, CustomParamsMode,
+    Deinterlace, Denoise, Encoder, GrainSynthesis, LogLevel, MatrixCoefficients, OutputContainer,
+    PixelFormat, RateControl, ResolutionPreset, ScaleAlgorithm, SourceLibrary, Theme,
+    TransferCharacteristics, Tune, BUILTIN_PRESETS,
 };
+use crate::notify::{JobReport, NotificationSettings, Notifier, NotifyEvent};
+use crate::paths::{expand_path, expand_path_string, is_writable_dir};
+use crate::probe::{extract_frame, format_timestamp, probe_input, FfmpegPaths, MediaInfo};
+use crate::queue::{
+    output_path_for, queue_file_path, queue_from_json, EncodeQueue, FolderScan, JobStatus, QueueJob,
+};
+use crate::recent::{file_exists, file_name, load_recent, save_recent, RecentFiles};
+use crate::report::{write_report, EncodeReport};
+use crate::sample::{open_in_default_player, SampleEncode, SampleStage};
+use crate::session::{
+    load_session, lock_session, remove_session, save_session, SessionLock, SessionState,
+};
+use crate::stats::{export_records, load_history, save_history, EncodeRecord};
+#[cfg(feature = "status-server")]
+use crate::status::{status_json, RejectedRequest, RemoteCommand, RemoteRequest, StatusServer};
+use crate::sysload::SystemLoad;
+use crate::tempusage::{TempUsage, LOW_SPACE};
+use crate::zones::{zones_text, ZoneEditor};
 
 use serde::{Deserialize, Serialize};
 
+// How long the preview slider has to stay still before a frame gets extracted
+const PREVIEW_DEBOUNCE: Duration = Duration::from_millis(300);
+// How many extracted preview frames to keep around for the current input
+const PREVIEW_CACHE_SIZE: usize = 16;
+
+// Where the dependency check looks for the encoder, it has no path setting yet
+const SVTAV1_PATH: &str = "/usr/local/bin/SvtAv1EncApp";
+
+// How long the fields have to stay unchanged before the session gets written
+const SESSION_IDLE: Duration = Duration::from_secs(3);
+
+// How many of av1an's latest fps readings the displayed fps averages over
+const FPS_WINDOW: usize = 10;
+
+// Handled in `handle_shortcuts`. Copying the command can't be Ctrl+C, every Ctrl+C arrives as
+// a plain Copy event, which copying selected log text sends too.
+const START_SHORTCUT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::COMMAND, Key::Enter);
+const COPY_COMMAND_SHORTCUT: KeyboardShortcut =
+    KeyboardShortcut::new(Modifiers::COMMAND.plus(Modifiers::SHIFT), Key::A);
+const CANCEL_SHORTCUT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::NONE, Key::Escape);
+const UNDO_SHORTCUT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::COMMAND, Key::Z);
+const REDO_SHORTCUT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::COMMAND, Key::Y);
+const REDO_SHIFT_SHORTCUT: KeyboardShortcut =
+    KeyboardShortcut::new(Modifiers::COMMAND.plus(Modifiers::SHIFT), Key::Z);
+
+// Bump when a preset field changes meaning, and add a step to `migrate_preset`
+const PRESET_VERSION: u32 = 3;
+
+// Whether each settings section was expanded. Only this goes into eframe's storage, so the
+// layout survives restarts.
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct OpenSections {
+    pub file_options: bool,
+    pub preview: bool,
+    pub media_info: bool,
+    pub source_settings: bool,
+    pub video_settings: bool,
+    pub encoder_settings: bool,
+    pub performance_settings: bool,
+}
+
+impl Default for OpenSections {
+    fn default() -> Self {
+        Self {
+            file_options: true,
+            preview: false,
+            media_info: false,
+            source_settings: false,
+            video_settings: false,
+            encoder_settings: true,
+            performance_settings: true,
+        }
+    }
+}
+
+const OPEN_SECTIONS_KEY: &str = "open_sections";
+
 #[derive(Serialize, Deserialize)]
 pub struct AV1Studio {
+    #[serde(skip)]
     pub av1an_verbosity_path: String,
+    #[serde(skip)]
+    pub ffmpeg_path: String,
+    #[serde(skip)]
+    pub environment: Vec<EnvOverride>,
 
+    #[serde(skip)]
     pub default_preset_path: String,
+    #[serde(skip)]
+    pub presets_folder: String,
+    #[serde(skip)]
+    pub preset_files: Vec<(PathBuf, String)>, // Path and display name
+    #[serde(skip)]
+    pub scanned_presets_folder: Option<String>,
+    #[serde(skip)]
+    pub preset_scan: Option<mpsc::Receiver<Vec<(PathBuf, String)>>>,
+
+    // Name and notes of the current preset, written into saved preset files
+    pub preset_name: String,
+    pub preset_description: String,
+    #[serde(skip)]
+    pub show_save_preset_window: bool,
+    #[serde(skip)]
+    pub preset_include_paths: bool,
+    #[serde(skip)]
+    pub preset_overwrite_path: Option<String>, // Waiting for the user to confirm the overwrite
+    #[serde(skip)]
+    pub disk_space_warning: Option<LowDiskSpace>, // Waiting for the user to start anyway
+    #[serde(skip)]
+    pub start_error: Option<String>, // Why av1an didn't start, until the next try
+    #[serde(skip)]
+    pub missing_output_dir: Option<PathBuf>, // Waiting for the user to have it created
+    #[serde(skip)]
+    pub confirm_start: bool,
+    #[serde(skip)]
+    pub write_report: bool,
+    #[serde(skip)]
+    pub pending_report: Option<EncodeReport>, // Taken at the start of the running encode
+    #[serde(skip)]
+    pub start_confirmation: Option<bool>, // Waiting for the user to start, with "Don't ask again"
+    #[serde(skip)]
+    pub cancel_confirmation: bool, // Asking before a running encode gets thrown away
+    #[serde(skip)]
+    pub out_of_memory_retry: Option<usize>, // Workers to retry with after running out of memory
+    #[serde(skip)]
+    pub preset_error: Option<String>,
+    // Saved encoder settings in the config folder, listed on first use
+    #[serde(skip)]
+    pub quality_preset_files: Option<Vec<(PathBuf, String)>>,
+    #[serde(skip)]
+    pub show_save_quality_preset_window: bool,
+    #[serde(skip)]
+    pub quality_preset_name: String,
 
     #[serde(skip)]
     pub input_file: String,
@@ -31,41 +183,220 @@ pub struct AV1Studio {
     pub zones_file: String,
 
     pub source_library: SourceLibrary,
+    pub chunk_method: ChunkMethod,
+    pub min_scene_len: String, // In frames, empty leaves av1an's default
+    pub max_scene_len: String, // av1an's --extra-split
+    pub output_container: OutputContainer,
 
     pub width: String,
     pub height: String,
+    pub lock_aspect: bool, // Keep the input's aspect ratio while editing the resolution
+    pub scale_algorithm: ScaleAlgorithm,
+    pub deinterlace: Deinterlace,
+    // Pixels cut off each side of the source before scaling
+    pub crop_top: u32,
+    pub crop_bottom: u32,
+    pub crop_left: u32,
+    pub crop_right: u32,
+    pub denoise: Denoise,
+    pub denoise_strength: f32,
 
     pub output_pixel_format: PixelFormat,
     pub color_primaries: ColorPrimaries,
     pub matrix_coefficients: MatrixCoefficients,
     pub transfer_characteristics: TransferCharacteristics,
     pub color_range: ColorRange,
+    pub chroma_sample_position: ChromaSamplePosition,
+    pub enable_hdr: bool,
 
     pub file_concatenation: String,
 
+    pub encoder: Encoder,
     pub preset: f32,
     pub crf: f32,
+    pub rate_control: RateControl,
+    pub target_bitrate: String, // Average kbps in the bitrate mode
+    pub max_bitrate: String,    // Cap in kbps for capped CRF, empty leaves it uncapped
+    pub buffer_size: String,    // Milliseconds the cap is held over
+    pub target_quality: String, // VMAF score for av1an's --target-quality, empty leaves it off
+    pub min_q: String,          // Bounds for the quality target, empty leaves av1an's
+    pub max_q: String,
+    pub tune: Tune,
+    pub passes: u8,
+    pub tile_columns: u8, // log2, like the encoder takes them
+    pub tile_rows: u8,
+    pub enable_qm: bool,
+    pub qm_min: u8,
+    pub qm_max: u8,
+    pub grain_synthesis: GrainSynthesis,
     pub synthetic_grain: String, // Synthetic grain is a String to allow editing
+    pub photon_noise: String,    // ISO value, a String for the same reason
+    pub photon_noise_width: String, // Both empty uses the output size
+    pub photon_noise_height: String,
+    pub grain_table: String,
     pub custom_encode_params: String,
+    pub custom_params_mode: CustomParamsMode,
+    pub extra_av1an_args: String, // Shell-split and appended after everything else
 
     #[serde(skip)]
     pub thread_affinity: String,
     #[serde(skip)]
     pub workers: String,
+    #[serde(skip)]
+    pub auto_workers: bool,
+    #[serde(skip)]
+    pub low_priority: bool,
+    #[serde(skip)]
+    pub temp_dir: String, // Empty lets av1an pick its own
+    #[serde(skip)]
+    pub resume: bool,
+    pub log_level: LogLevel,
+    pub chunk_order: ChunkOrder,
+    pub force: bool, // av1an's --force, skips its check of the encoder parameters
+    #[serde(skip)]
+    pub show_command_window: bool,
 
     #[serde(skip)]
     pub encoded_frames: Option<u32>,
     #[serde(skip)]
     pub total_frames: Option<u32>,
     #[serde(skip)]
-    pub fps: Option<f64>,
+    pub provisional_total_frames: Option<u32>, // From probing, until av1an reports its own count
+    #[serde(skip)]
+    pub displayed_progress: f32,
+    #[serde(skip)]
+    pub fps: Option<f64>, // Raw, as av1an last reported it
+    #[serde(skip)]
+    pub fps_samples: VecDeque<f64>, // The last FPS_WINDOW raw readings
     #[serde(skip)]
     pub eta_time: Option<String>,
 
+    #[serde(skip)]
+    pub media_info: Option<MediaInfo>,
+    #[serde(skip)]
+    pub probed_input: String,
+    #[serde(skip)]
+    pub probe_receiver: Option<mpsc::Receiver<Result<MediaInfo, String>>>,
+
+    #[serde(skip)]
+    pub preview_timestamp: f64,
+    #[serde(skip)]
+    pub preview_texture: Option<TextureHandle>,
+    #[serde(skip)]
+    pub preview_error: Option<String>,
+    #[serde(skip)]
+    pub preview_requested_at: Option<Instant>,
+    #[serde(skip)]
+    pub preview_receiver: Option<mpsc::Receiver<Result<ColorImage, String>>>,
+    #[serde(skip)]
+    pub preview_cache: Vec<(u64, TextureHandle)>, // Keyed by timestamp in milliseconds
+    #[serde(skip)]
+    pub preview_pending_key: u64,
+    #[serde(skip)]
+    pub preview_open_requested: bool,
+
     #[serde(skip)]
     pub encoding_in_progress: bool,
     #[serde(skip)]
-    pub receiver: Option<mpsc::Receiver<String>>,
+    pub encode_session: Option<EncodeSession>,
+
+    // Used for every file added through Add Folder, {name} is the input's file name
+    #[serde(skip)]
+    pub output_template: String,
+    #[serde(skip)]
+    pub queue: EncodeQueue,
+    #[serde(skip)]
+    pub folder_scan: FolderScan,
+    #[serde(skip)]
+    pub show_queue_window: bool,
+    #[serde(skip)]
+    pub confirm_abort_queue: bool,
+    #[serde(skip)]
+    pub job_editor: Option<(u64, Box<AV1Studio>)>, // Job ID and a throwaway state bound to its settings
+    #[serde(skip)]
+    pub job_editor_error: Option<String>,
+    #[serde(skip)]
+    pub max_concurrent_jobs: usize,
+
+    #[serde(skip)]
+    pub log: EncodeLog,
+    #[serde(skip)]
+    pub show_log_window: bool,
+    #[serde(skip)]
+    pub title_percentage: Option<u64>,
+
+    #[serde(skip)]
+    pub notifications: NotificationSettings,
+    #[serde(skip)]
+    pub notifier: Notifier,
+    #[serde(skip)]
+    pub status_server_settings: StatusServerSettings,
+    #[cfg(feature = "status-server")]
+    #[serde(skip)]
+    pub status_server: StatusServer,
+    #[serde(skip)]
+    pub restorable_queue: Option<Vec<QueueJob>>, // Left over from the last session, until the user decides
+    #[serde(skip)]
+    pub saved_queue_json: String,
+    #[serde(skip)]
+    pub queue_dirty: bool, // Input or a job starting or finishing may have changed the queue
+    #[serde(skip)]
+    pub restorable_session: Option<SessionState>, // Left over from a run that didn't exit cleanly
+    #[serde(skip)]
+    pub config_error: Option<ConfigError>, // config.toml didn't parse, shown until dismissed
+    #[serde(skip)]
+    pub keep_config_file: bool, // It didn't parse and couldn't be backed up
+    #[serde(skip)]
+    pub saved_session_json: String,
+    #[serde(skip)]
+    pub session_json: String, // The session as of the last change, serialized again once dirty
+    #[serde(skip)]
+    pub session_dirty: bool, // Input or a finished background task may have changed a setting
+    #[serde(skip)]
+    pub session_lock: Option<SessionLock>, // None while another instance owns session.json
+    #[serde(skip)]
+    pub pending_session: Option<(String, Instant)>, // Changed fields, and since when they're unchanged
+
+    #[serde(skip)]
+    pub settings_history: SettingsHistory,
+    #[serde(skip)]
+    pub recent: RecentFiles,
+    #[serde(skip)]
+    pub recent_settings: Option<SessionState>, // Offered for restoring after picking a recent input
+    #[serde(skip)]
+    pub encode_history: Vec<EncodeRecord>,
+    #[serde(skip)]
+    pub show_history_window: bool,
+    #[serde(skip)]
+    pub history_selection: BTreeSet<usize>, // Indices into encode_history
+    #[serde(skip)]
+    pub history_export: Option<Result<String, String>>, // Where the last export went, or why it failed
+
+    #[serde(skip)]
+    pub sample_encode: SampleEncode,
+    #[serde(skip)]
+    pub scene_analysis: SceneAnalysis,
+
+    #[serde(skip)]
+    pub show_compare_window: bool,
+    #[serde(skip)]
+    pub crf_comparison: CrfComparison,
+    #[serde(skip)]
+    pub system_load: SystemLoad,
+    #[serde(skip)]
+    pub temp_usage: TempUsage,
+    #[serde(skip)]
+    pub show_zones_window: bool,
+    #[serde(skip)]
+    pub zone_editor: ZoneEditor,
+    #[serde(skip)]
+    pub show_join_window: bool,
+    #[serde(skip)]
+    pub input_join: InputJoin,
+    #[serde(skip)]
+    pub show_grain_window: bool,
+    #[serde(skip)]
+    pub grain_generator: GrainTableGenerator,
 
     #[serde(skip)]
     pub max_label_width: Option<f32>,
@@ -74,18 +405,42 @@ pub struct AV1Studio {
 
     #[serde(skip)]
     pub show_settings_window: bool,
+    #[serde(skip)]
+    pub window_focused: bool,
 
+    #[serde(skip)]
     pub active_theme: Theme,
+    #[serde(skip)]
+    pub ui_scale: f32,
+    #[serde(skip)]
+    pub applied_ui_scale: f32,
+    #[serde(skip)]
+    pub saved_config: Option<AppConfig>, // What's in config.toml, to tell when it needs saving
+
+    #[serde(skip)]
+    pub sections: OpenSections,
 
     #[serde(skip)]
     pub av1an_verbosity_checked: bool,
     #[serde(skip)]
+    pub av1an_test_result: Option<(String, Result<String, String>)>, // For the path it was run with
+    #[serde(skip)]
     pub av1an_verbosity_found: bool,
+    #[serde(skip)]
+    pub av1an_verbosity_version: Option<String>,
 
     #[serde(skip)]
     pub svtav1_checked: bool,
     #[serde(skip)]
     pub svtav1_found: bool,
+    #[serde(skip)]
+    pub svtav1_version: Option<String>,
+    #[serde(skip)]
+    pub svtav1_options: HelpOptions,
+    #[serde(skip)]
+    pub optional_tools: Option<OptionalTools>,
+    #[serde(skip)]
+    pub show_missing_tools_warning: bool,
 
     #[serde(skip)]
     pub show_av1an_verbosity_warning: bool,
@@ -97,40 +452,181 @@ impl Default for AV1Studio {
     fn default() -> Self {
         AV1Studio {
             av1an_verbosity_path: String::new(),
+            ffmpeg_path: String::new(),
+            environment: Vec::new(),
             default_preset_path: String::new(),
+            presets_folder: String::new(),
+            preset_files: Vec::new(),
+            scanned_presets_folder: None,
+            preset_scan: None,
+            preset_name: String::new(),
+            preset_description: String::new(),
+            show_save_preset_window: false,
+            preset_include_paths: false,
+            preset_overwrite_path: None,
+            disk_space_warning: None,
+            start_error: None,
+            missing_output_dir: None,
+            confirm_start: true,
+            start_confirmation: None,
+            cancel_confirmation: false,
+            write_report: false,
+            pending_report: None,
+            out_of_memory_retry: None,
+            preset_error: None,
+            quality_preset_files: None,
+            show_save_quality_preset_window: false,
+            quality_preset_name: String::new(),
             input_file: String::new(),
             output_file: String::new(),
             scenes_file: String::new(),
             zones_file: String::new(),
             source_library: SourceLibrary::default(),
-            width: String::from("1920"),
-            height: String::from("1080"),
+            chunk_method: ChunkMethod::default(),
+            min_scene_len: String::new(),
+            max_scene_len: String::new(),
+            output_container: OutputContainer::default(),
+            // Empty keeps the input's resolution, scaling is opt-in
+            width: String::new(),
+            height: String::new(),
+            lock_aspect: true,
+            scale_algorithm: ScaleAlgorithm::default(),
+            deinterlace: Deinterlace::default(),
+            crop_top: 0,
+            crop_bottom: 0,
+            crop_left: 0,
+            crop_right: 0,
+            denoise: Denoise::default(),
+            denoise_strength: 0.0,
             output_pixel_format: PixelFormat::default(),
             color_primaries: ColorPrimaries::default(),
             matrix_coefficients: MatrixCoefficients::default(),
             transfer_characteristics: TransferCharacteristics::default(),
             color_range: ColorRange::default(),
+            chroma_sample_position: ChromaSamplePosition::default(),
+            enable_hdr: false,
             file_concatenation: String::new(),
+            encoder: Encoder::default(),
             preset: 4.0,
             crf: 27.0,
+            rate_control: RateControl::default(),
+            target_bitrate: String::new(),
+            max_bitrate: String::new(),
+            buffer_size: String::new(),
+            target_quality: String::new(),
+            min_q: String::new(),
+            max_q: String::new(),
+            tune: Tune::default(),
+            passes: 1,
+            tile_columns: 0,
+            tile_rows: 0,
+            enable_qm: false,
+            qm_min: 8,
+            qm_max: 15,
+            grain_synthesis: GrainSynthesis::default(),
             synthetic_grain: 0.to_string(),
+            photon_noise: String::new(),
+            photon_noise_width: String::new(),
+            photon_noise_height: String::new(),
+            grain_table: String::new(),
             custom_encode_params: String::new(),
+            custom_params_mode: CustomParamsMode::default(),
+            extra_av1an_args: String::new(),
+            log_level: LogLevel::default(),
+            chunk_order: ChunkOrder::default(),
+            force: true,
+            show_command_window: false,
             thread_affinity: String::new(),
             workers: num_cpus::get_physical().to_string(),
+            low_priority: false,
+            auto_workers: false,
+            temp_dir: String::new(),
+            resume: false,
             encoded_frames: None,
             total_frames: None,
+            provisional_total_frames: None,
+            displayed_progress: 0.0,
             fps: None,
+            fps_samples: VecDeque::with_capacity(FPS_WINDOW),
             eta_time: None,
+            media_info: None,
+            probed_input: String::new(),
+            probe_receiver: None,
+            preview_timestamp: 0.0,
+            preview_texture: None,
+            preview_error: None,
+            preview_requested_at: None,
+            preview_receiver: None,
+            preview_cache: Vec::new(),
+            preview_pending_key: 0,
+            preview_open_requested: false,
             encoding_in_progress: false,
-            receiver: None,
+            encode_session: None,
+            output_template: String::from("{name}_av1"),
+            queue: EncodeQueue::default(),
+            folder_scan: FolderScan::default(),
+            show_queue_window: false,
+            confirm_abort_queue: false,
+            job_editor: None,
+            job_editor_error: None,
+            max_concurrent_jobs: 1,
+            log: EncodeLog::default(),
+            show_log_window: false,
+            title_percentage: None,
+            notifications: NotificationSettings::default(),
+            notifier: Notifier::default(),
+            status_server_settings: StatusServerSettings::default(),
+            #[cfg(feature = "status-server")]
+            status_server: StatusServer::default(),
+            restorable_queue: None,
+            saved_queue_json: String::new(),
+            queue_dirty: true,
+            restorable_session: None,
+            config_error: None,
+            keep_config_file: false,
+            saved_session_json: String::new(),
+            session_json: String::new(),
+            session_dirty: true,
+            session_lock: None,
+            pending_session: None,
+            settings_history: SettingsHistory::default(),
+            recent: RecentFiles::default(),
+            recent_settings: None,
+            encode_history: Vec::new(),
+            show_history_window: false,
+            history_selection: BTreeSet::new(),
+            history_export: None,
+            sample_encode: SampleEncode::default(),
+            scene_analysis: SceneAnalysis::default(),
+            show_compare_window: false,
+            crf_comparison: CrfComparison::default(),
+            system_load: SystemLoad::default(),
+            temp_usage: TempUsage::default(),
+            show_zones_window: false,
+            zone_editor: ZoneEditor::default(),
+            show_join_window: false,
+            input_join: InputJoin::default(),
+            show_grain_window: false,
+            grain_generator: GrainTableGenerator::default(),
             max_label_width: None,
             settings_max_label_width: None,
             show_settings_window: false,
+            window_focused: false,
             active_theme: Theme::default(),
+            ui_scale: 1.0,
+            applied_ui_scale: 1.0,
+            saved_config: None,
+            sections: OpenSections::default(),
             av1an_verbosity_checked: false,
+            av1an_test_result: None,
             av1an_verbosity_found: false,
+            av1an_verbosity_version: None,
             svtav1_checked: false,
             svtav1_found: false,
+            svtav1_version: None,
+            svtav1_options: HelpOptions::default(),
+            optional_tools: None,
+            show_missing_tools_warning: false,
             show_av1an_verbosity_warning: false,
             show_svtav1_warning: false,
         }
@@ -145,116 +641,4780 @@ impl AV1Studio {
 
         cc.egui_ctx.set_style(style);
 
-        Self::default()
+        let mut app = AV1Studio {
+            sections: cc
+                .storage
+                .and_then(|storage| eframe::get_value(storage, OPEN_SECTIONS_KEY))
+                .unwrap_or_default(),
+            ..AV1Studio::default()
+        };
+
+        match load_config() {
+            Ok(Some(config)) => {
+                app.apply_app_config(config.clone());
+                app.saved_config = Some(config);
+            }
+            Ok(None) => {}
+            Err(error) => {
+                app.keep_config_file = error.backup.is_none();
+                app.config_error = Some(error);
+            }
+        }
+
+        cc.egui_ctx.set_visuals(app.active_theme.visuals());
+        cc.egui_ctx.set_zoom_factor(app.ui_scale);
+        app.applied_ui_scale = app.ui_scale;
+
+        if let Some(path) = queue_file_path().filter(|path| path.exists()) {
+            match std::fs::read_to_string(&path)
+                .map_err(|e| e.into())
+                .and_then(|json| queue_from_json(&json))
+            {
+                Ok(jobs) if !jobs.is_empty() => app.restorable_queue = Some(jobs),
+                Ok(_) => {}
+                Err(e) => eprintln!("ERROR : Couldn't read the saved queue {:?}: {}", path, e),
+            }
+        }
+        // A second instance leaves the session file to the first one
+        app.session_lock = lock_session();
+        if app.session_lock.is_some() {
+            app.restorable_session = load_session();
+        }
+        app.recent = load_recent();
+        app.encode_history = load_history();
+
+        app
     }
 
-    pub fn save_preset_to_file(&self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
-        let preset = AV1StudioPreset {
-            source_library: self.source_library.clone(),
+    fn session_state(&self) -> SessionState {
+        let mut settings = self.to_preset();
+        settings.input_file = Some(self.input_file.clone());
+        settings.output_file = Some(self.output_file.clone());
+        settings.scenes_file = Some(self.scenes_file.clone());
+        settings.zones_file = Some(self.zones_file.clone());
+
+        SessionState {
+            settings,
+            grain_table: self.grain_table.clone(),
+            thread_affinity: self.thread_affinity.clone(),
+            workers: self.workers.clone(),
+            temp_dir: self.temp_dir.clone(),
+        }
+    }
+
+    fn apply_session_state(&mut self, session: SessionState) {
+        self.apply_preset(session.settings);
+        self.grain_table = session.grain_table;
+        self.thread_affinity = session.thread_affinity;
+        self.workers = session.workers;
+        self.temp_dir = session.temp_dir;
+    }
+
+    fn app_config(&self) -> AppConfig {
+        AppConfig {
+            av1an_verbosity_path: self.av1an_verbosity_path.clone(),
+            ffmpeg_path: self.ffmpeg_path.clone(),
+            environment: self.environment.clone(),
+            default_preset_path: self.default_preset_path.clone(),
+            presets_folder: self.presets_folder.clone(),
+            theme: self.active_theme,
+            ui_scale: self.ui_scale,
+            output_template: self.output_template.clone(),
+            max_concurrent_jobs: self.max_concurrent_jobs,
+            low_priority: self.low_priority,
+            auto_workers: self.auto_workers,
+            confirm_start: self.confirm_start,
+            write_report: self.write_report,
+            notifications: self.notifications.clone(),
+            status_server: self.status_server_settings.clone(),
+        }
+    }
+
+    fn apply_app_config(&mut self, config: AppConfig) {
+        self.av1an_verbosity_path = config.av1an_verbosity_path;
+        self.ffmpeg_path = config.ffmpeg_path;
+        self.environment = config.environment;
+        self.default_preset_path = config.default_preset_path;
+        self.presets_folder = config.presets_folder;
+        self.active_theme = config.theme;
+        self.ui_scale = config.ui_scale;
+        self.output_template = config.output_template;
+        self.max_concurrent_jobs = config.max_concurrent_jobs;
+        self.low_priority = config.low_priority;
+        self.confirm_start = config.confirm_start;
+        self.write_report = config.write_report;
+        self.auto_workers = config.auto_workers;
+        self.notifications = config.notifications;
+        self.status_server_settings = config.status_server;
+    }
+
+    // Writes config.toml whenever an app setting changed
+    fn save_app_config(&mut self) {
+        let config = self.app_config();
+        if self.saved_config.as_ref() == Some(&config) {
+            return;
+        }
+        // Without a backup, saving would lose the user's unparseable file
+        if self.keep_config_file {
+            return;
+        }
+
+        if let Err(e) = save_config(&config) {
+            eprintln!("ERROR : Couldn't save the app config: {}", e);
+        }
+        self.saved_config = Some(config);
+    }
+
+    fn to_preset(&self) -> AV1StudioPreset {
+        AV1StudioPreset {
+            version: PRESET_VERSION,
+            name: self.preset_name.clone(),
+            description: self.preset_description.clone(),
+            source_library: self.source_library,
+            chunk_method: self.chunk_method,
+            min_scene_len: self.min_scene_len.clone(),
+            max_scene_len: self.max_scene_len.clone(),
+            output_container: self.output_container,
             width: self.width.clone(),
             height: self.height.clone(),
-            output_pixel_format: self.output_pixel_format.clone(),
-            color_primaries: self.color_primaries.clone(),
-            matrix_coefficients: self.matrix_coefficients.clone(),
-            transfer_characteristics: self.transfer_characteristics.clone(),
-            color_range: self.color_range.clone(),
+            lock_aspect: self.lock_aspect,
+            scale_algorithm: self.scale_algorithm,
+            deinterlace: self.deinterlace,
+            crop_top: self.crop_top,
+            crop_bottom: self.crop_bottom,
+            crop_left: self.crop_left,
+            crop_right: self.crop_right,
+            denoise: self.denoise,
+            denoise_strength: self.denoise_strength,
+            output_pixel_format: self.output_pixel_format,
+            color_primaries: self.color_primaries,
+            matrix_coefficients: self.matrix_coefficients,
+            transfer_characteristics: self.transfer_characteristics,
+            color_range: self.color_range,
+            chroma_sample_position: self.chroma_sample_position,
+            enable_hdr: self.enable_hdr,
             file_concatenation: self.file_concatenation.clone(),
+            encoder: self.encoder,
             preset: self.preset,
             crf: self.crf,
+            rate_control: self.rate_control,
+            target_bitrate: self.target_bitrate.clone(),
+            max_bitrate: self.max_bitrate.clone(),
+            buffer_size: self.buffer_size.clone(),
+            target_quality: self.target_quality.clone(),
+            min_q: self.min_q.clone(),
+            max_q: self.max_q.clone(),
+            tune: self.tune,
+            passes: self.passes,
+            tile_columns: self.tile_columns,
+            tile_rows: self.tile_rows,
+            enable_qm: self.enable_qm,
+            qm_min: self.qm_min,
+            qm_max: self.qm_max,
+            grain_synthesis: self.grain_synthesis,
             synthetic_grain: self.synthetic_grain.clone(),
+            photon_noise: self.photon_noise.clone(),
+            photon_noise_width: self.photon_noise_width.clone(),
+            photon_noise_height: self.photon_noise_height.clone(),
+            grain_table: self.grain_table.clone(),
             custom_encode_params: self.custom_encode_params.clone(),
-        };
-
-        let yaml = serde_yaml::to_string(&preset)?;
-        std::fs::write(path, yaml)?;
-
-        Ok(())
+            custom_params_mode: self.custom_params_mode,
+            chunk_order: self.chunk_order,
+            force: self.force,
+            extra_av1an_args: self.extra_av1an_args.clone(),
+            input_file: None,
+            output_file: None,
+            scenes_file: None,
+            zones_file: None,
+        }
     }
 
-    pub fn load_preset_from_file(&mut self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
-        let file_content = std::fs::read_to_string(path)?;
-        let preset: AV1StudioPreset = serde_yaml::from_str(&file_content)?;
-
+    fn apply_preset(&mut self, preset: AV1StudioPreset) {
+        self.preset_name = preset.name;
+        self.preset_description = preset.description;
         self.source_library = preset.source_library;
+        self.chunk_method = preset.chunk_method;
+        self.min_scene_len = preset.min_scene_len;
+        self.max_scene_len = preset.max_scene_len;
+        self.output_container = preset.output_container;
         self.width = preset.width;
         self.height = preset.height;
+        self.lock_aspect = preset.lock_aspect;
+        self.scale_algorithm = preset.scale_algorithm;
+        self.deinterlace = preset.deinterlace;
+        self.crop_top = preset.crop_top;
+        self.crop_bottom = preset.crop_bottom;
+        self.crop_left = preset.crop_left;
+        self.crop_right = preset.crop_right;
+        self.denoise = preset.denoise;
+        self.denoise_strength = preset.denoise_strength;
         self.output_pixel_format = preset.output_pixel_format;
         self.color_primaries = preset.color_primaries;
         self.matrix_coefficients = preset.matrix_coefficients;
         self.transfer_characteristics = preset.transfer_characteristics;
         self.color_range = preset.color_range;
+        self.chroma_sample_position = preset.chroma_sample_position;
+        self.enable_hdr = preset.enable_hdr;
         self.file_concatenation = preset.file_concatenation;
+        self.encoder = preset.encoder;
         self.preset = preset.preset;
         self.crf = preset.crf;
+        self.rate_control = preset.rate_control;
+        self.target_bitrate = preset.target_bitrate;
+        self.max_bitrate = preset.max_bitrate;
+        self.buffer_size = preset.buffer_size;
+        self.target_quality = preset.target_quality;
+        self.min_q = preset.min_q;
+        self.max_q = preset.max_q;
+        self.tune = preset.tune;
+        self.passes = preset.passes;
+        self.tile_columns = preset.tile_columns;
+        self.tile_rows = preset.tile_rows;
+        self.enable_qm = preset.enable_qm;
+        self.qm_min = preset.qm_min;
+        self.qm_max = preset.qm_max;
+        self.grain_synthesis = preset.grain_synthesis;
         self.synthetic_grain = preset.synthetic_grain;
+        self.photon_noise = preset.photon_noise;
+        self.photon_noise_width = preset.photon_noise_width;
+        self.photon_noise_height = preset.photon_noise_height;
+        // Presets from before grain tables were kept leave the current one alone
+        if !preset.grain_table.is_empty() {
+            self.grain_table = preset.grain_table;
+        }
         self.custom_encode_params = preset.custom_encode_params;
+        self.custom_params_mode = preset.custom_params_mode;
+        self.chunk_order = preset.chunk_order;
+        self.force = preset.force;
+        self.extra_av1an_args = preset.extra_av1an_args;
 
-        Ok(())
+        if let Some(input_file) = preset.input_file {
+            self.input_file = input_file;
+        }
+        if let Some(output_file) = preset.output_file {
+            self.output_file = output_file;
+        }
+        if let Some(scenes_file) = preset.scenes_file {
+            self.scenes_file = scenes_file;
+        }
+        if let Some(zones_file) = preset.zones_file {
+            self.zones_file = zones_file;
+        }
     }
-}
-
-#[derive(Serialize, Deserialize)]
-struct AV1StudioPreset {
-    source_library: SourceLibrary,
-    width: String,
-    height: String,
-    output_pixel_format: PixelFormat,
-    color_primaries: ColorPrimaries,
-    matrix_coefficients: MatrixCoefficients,
-    transfer_characteristics: TransferCharacteristics,
-    color_range: ColorRange,
-    file_concatenation: String,
-    preset: f32,
-    crf: f32,
-    synthetic_grain: String,
-    custom_encode_params: String,
-}
 
-impl eframe::App for AV1Studio {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        if self.max_label_width.is_none() {
-            ctx.request_repaint();
-            self.max_label_width = Some(0.0);
+    fn to_quality_preset(&self) -> QualityPreset {
+        QualityPreset {
+            name: self.quality_preset_name.clone(),
+            encoder: self.encoder,
+            preset: self.preset,
+            crf: self.crf,
+            rate_control: self.rate_control,
+            target_bitrate: self.target_bitrate.clone(),
+            tune: self.tune,
+            grain_synthesis: self.grain_synthesis,
+            synthetic_grain: self.synthetic_grain.clone(),
+            photon_noise: self.photon_noise.clone(),
+            custom_encode_params: self.custom_encode_params.clone(),
+            custom_params_mode: self.custom_params_mode,
         }
+    }
 
-        // Please tell me there's a better way to do this
-        if !self.av1an_verbosity_checked {
-            let path = std::path::Path::new("/usr/local/bin/av1an-verbosity");
-            self.av1an_verbosity_found = exists(path);
-            self.av1an_verbosity_checked = true;
+    // Only the encoder fields, so it goes on top of whatever source and video settings are set
+    fn apply_quality_preset(&mut self, quality: QualityPreset) {
+        self.quality_preset_name = quality.name;
+        self.encoder = quality.encoder;
+        self.preset = quality.preset;
+        self.crf = quality.crf;
+        self.rate_control = quality.rate_control;
+        self.target_bitrate = quality.target_bitrate;
+        self.tune = quality.tune;
+        self.grain_synthesis = quality.grain_synthesis;
+        self.synthetic_grain = quality.synthetic_grain;
+        self.photon_noise = quality.photon_noise;
+        self.custom_encode_params = quality.custom_encode_params;
+        self.custom_params_mode = quality.custom_params_mode;
+    }
+
+    fn apply_builtin_preset(&mut self, builtin: &BuiltinPreset) {
+        self.preset_name = builtin.name.to_string();
+        self.preset_description = builtin.description.to_string();
+        self.preset = builtin.preset;
+        self.crf = builtin.crf;
+        self.rate_control = RateControl::Crf;
+        self.grain_synthesis = GrainSynthesis::Synthetic;
+        self.synthetic_grain = builtin.synthetic_grain.to_string();
+        self.tune = builtin.tune;
+        self.color_primaries = builtin.color_primaries;
+        self.transfer_characteristics = builtin.transfer_characteristics;
+        self.matrix_coefficients = builtin.matrix_coefficients;
+        self.color_range = builtin.color_range;
+    }
+
+    // A throwaway copy of everything that goes into the av1an command, for encodes that differ
+    // from the main one only in a few fields
+    fn encode_settings_copy(&self) -> AV1Studio {
+        let mut copy = AV1Studio {
+            av1an_verbosity_path: self.av1an_verbosity_path.clone(),
+            ffmpeg_path: self.ffmpeg_path.clone(),
+            environment: self.environment.clone(),
+            input_file: self.input_file.clone(),
+            output_file: self.output_file.clone(),
+            scenes_file: self.scenes_file.clone(),
+            zones_file: self.zones_file.clone(),
+            grain_table: self.grain_table.clone(),
+            thread_affinity: self.thread_affinity.clone(),
+            workers: self.workers.clone(),
+            low_priority: self.low_priority,
+            auto_workers: self.auto_workers,
+            ..AV1Studio::default()
+        };
+        copy.apply_preset(self.to_preset());
+
+        copy
+    }
+
+    // The settings for encoding a short cut of the input. The scenes and zones files count the
+    // full input's frames, which don't line up with the cut.
+    fn sample_settings(&self, input: &Path, output: &Path) -> AV1Studio {
+        let mut settings = self.encode_settings_copy();
+        settings.input_file = input.display().to_string();
+        settings.output_file = output.display().to_string();
+        settings.scenes_file.clear();
+        settings.zones_file.clear();
+
+        settings
+    }
+
+    // One of the two Compare CRFs encodes. A target bitrate or target quality would decide the
+    // quality on its own and both encodes would come out the same.
+    fn comparison_settings(&self, index: usize) -> AV1Studio {
+        let mut settings = self.sample_settings(
+            &self.crf_comparison.sample_path(),
+            &self.crf_comparison.output_path(index),
+        );
+        settings.crf = self.crf_comparison.crf[index];
+        settings.rate_control = RateControl::Crf;
+        settings.target_quality.clear();
+
+        settings
+    }
+
+    // Scene detection only, into a new scenes file next to the output (or the input, without one)
+    fn start_scene_analysis(&mut self) {
+        let next_to = if self.output_file.trim().is_empty() {
+            &self.input_file
+        } else {
+            &self.output_file
+        };
+        let scenes = scenes_path_for(&expand_path(next_to));
 
-            if !self.av1an_verbosity_found {
-                self.show_av1an_verbosity_warning = true;
+        let mut settings = self.encode_settings_copy();
+        settings.scenes_file = scenes.display().to_string();
+        settings.zones_file.clear();
+        settings.resume = false;
+        settings.temp_dir = self.scene_analysis.temp_dir().display().to_string();
+        settings.output_file = self.scene_analysis.output_path().display().to_string();
+        let cmd = match generate_command(&settings) {
+            Ok(cmd) => cmd,
+            Err(problem) => {
+                self.log
+                    .push(format!("Scene detection didn't start: {}", problem));
+                return;
             }
+        };
+        println!("{}", command_to_string(&cmd));
+        self.scene_analysis.start(scenes, cmd);
+    }
 
-            if !can_run(path) {
-                eprintln!("WARNING: {:?} can't be found, you will have to give its path manually in the Settings menu", path);
+    fn queue_current(&mut self) {
+        let job = self.queue_job(self.input_file.clone(), self.output_file.clone());
+        if job.overwrites_input() {
+            self.log.push(String::from(
+                "Not queued, the output file is the input file",
+            ));
+            return;
+        }
+        self.queue.push(job);
+    }
+
+    fn queue_job(&self, input_file: String, output_file: String) -> QueueJob {
+        QueueJob {
+            id: 0,
+            input_file,
+            output_file,
+            scenes_file: self.scenes_file.clone(),
+            zones_file: self.zones_file.clone(),
+            grain_table: self.grain_table.clone(),
+            thread_affinity: self.thread_affinity.clone(),
+            // Jobs keep auto as empty workers
+            workers: if self.auto_workers {
+                String::new()
             } else {
-                println!("{:?} found", path);
-            }
+                self.workers.clone()
+            },
+            settings: self.to_preset(),
+            status: JobStatus::Pending,
+            temp_dir: String::new(),
+            resume: false,
+            session: None,
         }
+    }
 
-        if !self.svtav1_checked {
-            let path = std::path::Path::new("/usr/local/bin/SvtAv1EncApp");
-            self.svtav1_found = exists(path);
-            self.svtav1_checked = true;
+    // The state a queue job's av1an command gets generated from
+    fn job_settings(&self, job: &QueueJob) -> AV1Studio {
+        let mut settings = AV1Studio {
+            av1an_verbosity_path: self.av1an_verbosity_path.clone(),
+            ffmpeg_path: self.ffmpeg_path.clone(),
+            environment: self.environment.clone(),
+            input_file: job.input_file.clone(),
+            output_file: job.output_file.clone(),
+            scenes_file: job.scenes_file.clone(),
+            zones_file: job.zones_file.clone(),
+            grain_table: job.grain_table.clone(),
+            thread_affinity: job.thread_affinity.clone(),
+            workers: job.workers.clone(),
+            low_priority: self.low_priority,
+            // The job's workers already say whether they're auto
+            auto_workers: false,
+            log_level: self.log_level,
+            temp_dir: job.temp_dir.clone(),
+            resume: job.resume,
+            ..AV1Studio::default()
+        };
+        settings.apply_preset(job.settings.clone());
 
-            if !self.svtav1_found {
-                self.show_svtav1_warning = true;
-            }
+        settings
+    }
 
-            if !can_run(path) {
-                eprintln!("WARNING: {:?} can't be found", path);
-            } else {
-                println!("{:?} found", path);
-            }
+    pub fn save_preset_to_file(&self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let mut preset = self.to_preset();
+        if self.preset_include_paths {
+            preset.input_file = Some(self.input_file.clone());
+            preset.output_file = Some(self.output_file.clone());
+            preset.scenes_file = Some(self.scenes_file.clone());
+            preset.zones_file = Some(self.zones_file.clone());
         }
 
-        if self.show_av1an_verbosity_warning {
-            egui::Window::new("Warning")
-                .open(&mut self.show_av1an_verbosity_warning)
-                .show(ctx, |ui| {
-                    ui.label("/usr/local/bin/av1an-verbosity not found! You will have to set a path for it manually in the Settings menu.");
+        let content = if is_json_preset(std::path::Path::new(path)) {
+            serde_json::to_string_pretty(&preset)?
+        } else {
+            serde_yaml::to_string(&preset)?
+        };
+        std::fs::write(path, content)?;
+
+        Ok(())
+    }
+
+    pub fn load_preset_from_file(&mut self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let file_content = std::fs::read_to_string(path)?;
+        let preset = if is_json_preset(std::path::Path::new(path)) {
+            parse_preset_value(serde_json::from_str(&file_content)?)?
+        } else {
+            parse_preset(&file_content)?
+        };
+
+        self.apply_preset(preset);
+
+        Ok(())
+    }
+
+    fn save_quality_preset(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let dir = quality_presets_dir().ok_or("No config folder")?;
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.join(quality_preset_file_name(&self.quality_preset_name));
+        std::fs::write(&path, serde_yaml::to_string(&self.to_quality_preset())?)?;
+        self.quality_preset_files = None;
+
+        Ok(())
+    }
+
+    fn load_quality_preset(
+        &mut self,
+        path: &std::path::Path,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let content = std::fs::read_to_string(path)?;
+        self.apply_quality_preset(serde_yaml::from_str(&content)?);
+
+        Ok(())
+    }
+
+    // Lists the presets in the presets folder, again whenever the folder setting changes. Each
+    // keystroke in the folder field changes it, so the files are read on another thread.
+    fn refresh_preset_files(&mut self) {
+        if self.scanned_presets_folder.as_ref() != Some(&self.presets_folder) {
+            self.scanned_presets_folder = Some(self.presets_folder.clone());
+            self.preset_files.clear();
+            self.preset_scan = None;
+            if !self.presets_folder.is_empty() {
+                let (sender, receiver) = mpsc::channel();
+                let folder = self.presets_folder.clone();
+                std::thread::spawn(move || {
+                    let _ = sender.send(scan_presets_folder(&folder));
+                });
+                self.preset_scan = Some(receiver);
+            }
+        }
+
+        if let Some(receiver) = &self.preset_scan {
+            match receiver.try_recv() {
+                Ok(files) => {
+                    self.preset_files = files;
+                    self.preset_scan = None;
+                }
+                Err(mpsc::TryRecvError::Empty) => {}
+                Err(mpsc::TryRecvError::Disconnected) => self.preset_scan = None,
+            }
+        }
+    }
+
+    // The bitrate mode always runs two passes, the first one measures where the bits are
+    // needed. rav1e only does one pass in av1an.
+    pub fn encode_passes(&self) -> u8 {
+        match (self.rate_control, self.encoder) {
+            (RateControl::TargetBitrate, Encoder::SvtAv1 | Encoder::Aom) => 2,
+            _ => self.passes,
+        }
+    }
+
+    // SVT-AV1 only makes use of multiple passes with a target bitrate
+    fn wasted_pass_warning(&self) -> Option<&'static str> {
+        if self.encoder != Encoder::SvtAv1
+            || self.rate_control != RateControl::Crf
+            || self.encode_passes() < 2
+        {
+            return None;
+        }
+
+        Some(if self.target_quality.trim().is_empty() {
+            "SVT-AV1 only does multi-pass with a target bitrate. With CRF the second pass only adds encoding time."
+        } else {
+            "Target quality encodes each chunk at the CRF it found, so the second pass only adds encoding time."
+        })
+    }
+
+    // VapourSynth scripts are handed to av1an as-is, it sources the frames through vspipe
+    pub fn is_vapoursynth_script(&self) -> bool {
+        std::path::Path::new(&self.input_file)
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| e.eq_ignore_ascii_case("vpy"))
+    }
+
+    // Makes the output file's extension match the selected container
+    fn correct_output_extension(&mut self) {
+        if self.output_file.is_empty() {
+            return;
+        }
+
+        let path = std::path::Path::new(&self.output_file);
+        if path.extension().and_then(|e| e.to_str()) != Some(self.output_container.extension()) {
+            self.output_file = path
+                .with_extension(self.output_container.extension())
+                .display()
+                .to_string();
+        }
+    }
+
+    // Av1an's fps readings jump around with several workers, so show their recent average
+    fn smoothed_fps(&self) -> Option<f64> {
+        if self.fps_samples.is_empty() {
+            return None;
+        }
+
+        Some(self.fps_samples.iter().sum::<f64>() / self.fps_samples.len() as f64)
+    }
+
+    // The input's size after cropping
+    pub fn source_resolution(&self) -> Option<(u32, u32)> {
+        let (width, height) = self
+            .media_info
+            .as_ref()
+            .and_then(|info| info.width.zip(info.height))?;
+
+        Some((
+            width.saturating_sub(self.crop_left + self.crop_right),
+            height.saturating_sub(self.crop_top + self.crop_bottom),
+        ))
+    }
+
+    // The set output size, or the input's when the output isn't scaled
+    fn output_resolution(&self) -> Option<(u32, u32)> {
+        match self.scaled_resolution() {
+            Some(resolution) => Some(resolution),
+            None => self.source_resolution(),
+        }
+    }
+
+    // What the scale filter resizes to, when that's known before ffmpeg runs
+    fn scaled_resolution(&self) -> Option<(u32, u32)> {
+        let (width, height) = scale_size(&self.width, &self.height, self.source_resolution())?;
+        width.parse().ok().zip(height.parse().ok())
+    }
+
+    // Sizes that encode but likely aren't what was meant
+    fn resolution_warnings(&self) -> Vec<String> {
+        let Some((width, height)) = self.scaled_resolution() else {
+            return Vec::new();
+        };
+
+        let mut warnings = Vec::new();
+        if width % 2 != 0 || height % 2 != 0 {
+            warnings.push(format!(
+                "⚠ {}x{} has an odd side. 4:2:0 needs even sizes, so the scaler or encoder will round or fail.",
+                width, height
+            ));
+        }
+        if let Some((source_width, source_height)) = self.source_resolution() {
+            if width > source_width || height > source_height {
+                warnings.push(format!(
+                    "⚠ {}x{} is larger than the input ({}x{}). Upscaling adds size, not detail.",
+                    width, height, source_width, source_height
+                ));
+            }
+        }
+
+        warnings
+    }
+
+    // Only known for tables generated here
+    fn grain_resolution_warning(&self) -> Option<String> {
+        if std::path::Path::new(&self.grain_table) != self.grain_generator.output {
+            return None;
+        }
+        let table = self.grain_generator.resolution?;
+        let output = self.output_resolution()?;
+
+        (table != output).then(|| {
+            format!(
+                "⚠ The grain table was generated for {}x{}, but the output is {}x{}. The grain will look too coarse or too fine.",
+                table.0, table.1, output.0, output.1
+            )
+        })
+    }
+
+    fn tools(&self) -> &OptionalTools {
+        self.optional_tools.as_ref().unwrap_or(&OptionalTools::NONE)
+    }
+
+    fn update_probe(&mut self) {
+        if self.input_file != self.probed_input {
+            self.probed_input = self.input_file.clone();
+            self.media_info = None;
+            self.provisional_total_frames = None;
+            self.probe_receiver = None;
+            self.preview_texture = None;
+            self.preview_error = None;
+            self.preview_timestamp = 0.0;
+            self.preview_requested_at = None;
+            self.preview_receiver = None;
+            self.preview_cache.clear();
+
+            // ffprobe can't read VapourSynth scripts
+            if expand_path(&self.input_file).is_file()
+                && !self.is_vapoursynth_script()
+                && self.tools().has_ffprobe()
+            {
+                let (sender, receiver) = mpsc::channel();
+                let path = expand_path_string(&self.input_file);
+                let tools = self.ffmpeg_paths();
+                std::thread::spawn(move || {
+                    let _ = sender.send(probe_input(&tools, &path).map_err(|e| e.to_string()));
+                });
+                self.probe_receiver = Some(receiver);
+            }
+        }
+
+        if let Some(receiver) = &self.probe_receiver {
+            match receiver.try_recv() {
+                Ok(Ok(info)) => {
+                    self.session_dirty = true;
+                    self.sample_encode.timestamp = info.duration.unwrap_or(0.0) / 2.0;
+                    self.recent
+                        .add_input(&self.input_file, &self.output_file, None);
+                    save_recent(&self.recent);
+                    if let Some(position) = info
+                        .chroma_location
+                        .as_deref()
+                        .and_then(ChromaSamplePosition::from_ffprobe)
+                    {
+                        self.chroma_sample_position = position;
+                    }
+                    if let Some(format) = info
+                        .pixel_format
+                        .as_deref()
+                        .and_then(PixelFormat::from_ffprobe)
+                    {
+                        self.output_pixel_format = self.encoder.closest_pixel_format(format);
+                    }
+                    self.media_info = Some(info);
+                    self.probe_receiver = None;
+                    self.preview_requested_at = Some(Instant::now());
+                }
+                Ok(Err(e)) => {
+                    eprintln!("ERROR : Couldn't probe {}: {}", self.probed_input, e);
+                    self.preview_error = Some(e);
+                    self.probe_receiver = None;
+                }
+                Err(mpsc::TryRecvError::Empty) => {}
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    self.probe_receiver = None;
+                }
+            }
+        }
+    }
+
+    fn update_preview(&mut self, ctx: &egui::Context) {
+        if let Some(receiver) = &self.preview_receiver {
+            match receiver.try_recv() {
+                Ok(Ok(image)) => {
+                    let texture = ctx.load_texture("input_preview", image, TextureOptions::LINEAR);
+                    if self.preview_cache.len() >= PREVIEW_CACHE_SIZE {
+                        let _ = self.preview_cache.remove(0);
+                    }
+                    self.preview_cache
+                        .push((self.preview_pending_key, texture.clone()));
+                    self.preview_texture = Some(texture);
+                    self.preview_error = None;
+                    self.preview_receiver = None;
+                }
+                Ok(Err(e)) => {
+                    self.preview_texture = None;
+                    self.preview_error = Some(e);
+                    self.preview_receiver = None;
+                }
+                Err(mpsc::TryRecvError::Empty) => {}
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    self.preview_texture = None;
+                    self.preview_error =
+                        Some(String::from("frame extraction stopped unexpectedly"));
+                    self.preview_receiver = None;
+                }
+            }
+        }
+
+        // Only one ffmpeg at a time, and only once the slider has settled
+        if self.preview_receiver.is_none() {
+            if let Some(requested_at) = self.preview_requested_at {
+                if requested_at.elapsed() >= PREVIEW_DEBOUNCE {
+                    self.preview_requested_at = None;
+
+                    let key = (self.preview_timestamp * 1000.0) as u64;
+                    if let Some((_, texture)) = self.preview_cache.iter().find(|(k, _)| *k == key) {
+                        self.preview_texture = Some(texture.clone());
+                        self.preview_error = None;
+                        return;
+                    }
+                    self.preview_pending_key = key;
+
+                    if !self.tools().has_ffmpeg() {
+                        self.preview_error = Some(String::from("ffmpeg isn't installed"));
+                        return;
+                    }
+
+                    let (sender, receiver) = mpsc::channel();
+                    let path = expand_path_string(&self.input_file);
+                    let timestamp = self.preview_timestamp;
+                    let tools = self.ffmpeg_paths();
+                    std::thread::spawn(move || {
+                        let _ = sender.send(
+                            extract_frame(&tools, &path, timestamp).map_err(|e| e.to_string()),
+                        );
+                    });
+                    self.preview_receiver = Some(receiver);
+                }
+            }
+        }
+    }
+}
+
+impl AV1Studio {
+    fn show_compare_window(&mut self, ctx: &egui::Context) {
+        if !self.show_compare_window {
+            if self.crf_comparison.is_running() {
+                self.crf_comparison.cancel();
+            }
+            if !matches!(self.crf_comparison.stage, CompareStage::Idle) {
+                self.crf_comparison.cleanup();
+                self.crf_comparison.results.clear();
+                self.crf_comparison.stage = CompareStage::Idle;
+            }
+            return;
+        }
+
+        self.crf_comparison.poll();
+
+        let mut open = self.show_compare_window;
+        let mut run_requested = false;
+        egui::Window::new("Compare CRFs")
+            .open(&mut open)
+            .show(ctx, |ui| {
+                let running = self.crf_comparison.is_running();
+                let ffmpeg_found = self.tools().has_ffmpeg();
+                let comparison = &mut self.crf_comparison;
+
+                ui.add_enabled_ui(!running, |ui| {
+                    egui::Grid::new("compare_settings_grid").show(ui, |ui| {
+                        ui.label("CRF A");
+                        ui.add(
+                            egui::DragValue::new(&mut comparison.crf[0])
+                                .range(0.0..=70.0)
+                                .speed(0.25),
+                        );
+                        ui.end_row();
+
+                        ui.label("CRF B");
+                        ui.add(
+                            egui::DragValue::new(&mut comparison.crf[1])
+                                .range(0.0..=70.0)
+                                .speed(0.25),
+                        );
+                        ui.end_row();
+
+                        ui.label("Sample Start");
+                        ui.add(
+                            egui::DragValue::new(&mut comparison.sample_start)
+                                .range(0.0..=f64::MAX)
+                                .custom_formatter(|n, _| format_timestamp(n)),
+                        );
+                        ui.end_row();
+
+                        ui.label("Sample Length");
+                        ui.add(
+                            egui::DragValue::new(&mut comparison.sample_length)
+                                .range(1.0..=120.0)
+                                .suffix(" s"),
+                        );
+                        ui.end_row();
+                    });
+                    ui.checkbox(&mut comparison.keep_files, "Keep sample files");
+                });
+
+                ui.add_space(ui.spacing().item_spacing.y * 2.0);
+
+                match &comparison.stage {
+                    CompareStage::Idle | CompareStage::Done | CompareStage::Failed(_) => {
+                        let can_run = !self.input_file.is_empty() && ffmpeg_found;
+                        if ui
+                            .add_enabled(can_run, egui::Button::new("Run Comparison"))
+                            .clicked()
+                        {
+                            run_requested = true;
+                        }
+                    }
+                    CompareStage::Cutting(_) => {
+                        ui.horizontal(|ui| {
+                            ui.spinner();
+                            ui.label("Cutting sample...");
+                        });
+                    }
+                    CompareStage::Encoding(index, session) => {
+                        let (ef, tf) = (
+                            session.encoded_frames.unwrap_or_default(),
+                            session.total_frames.unwrap_or_default(),
+                        );
+                        let progress = if tf == 0 { 0.0 } else { ef as f32 / tf as f32 };
+                        ui.label(format!("Sample {} of 2", index + 1));
+                        ui.add(
+                            ProgressBar::new((*index as f32 + progress) / 2.0).show_percentage(),
+                        );
+                    }
+                    CompareStage::Measuring(_) => {
+                        ui.horizontal(|ui| {
+                            ui.spinner();
+                            ui.label("Measuring samples...");
+                        });
+                    }
+                }
+
+                if let CompareStage::Failed(e) = &comparison.stage {
+                    ui.colored_label(ui.visuals().error_fg_color, e);
+                }
+
+                if running && ui.button("Cancel").clicked() {
+                    comparison.cancel();
+                }
+
+                if matches!(comparison.stage, CompareStage::Done) {
+                    ui.separator();
+                    let mut adopt = None;
+                    ui.columns(comparison.results.len().max(1), |columns| {
+                        for (ui, result) in columns.iter_mut().zip(comparison.results.iter_mut()) {
+                            ui.heading(format!("CRF {}", result.crf));
+                            ui.label(match result.size {
+                                Some(size) => {
+                                    format!("Size: {:.2} MiB", size as f64 / 1024.0 / 1024.0)
+                                }
+                                None => String::from("Size: unknown"),
+                            });
+                            ui.label(match result.bitrate {
+                                Some(bitrate) => format!("Bitrate: {:.0} kbps", bitrate),
+                                None => String::from("Bitrate: unknown"),
+                            });
+
+                            if let Some(image) = result.frame.take() {
+                                result.texture = Some(ui.ctx().load_texture(
+                                    format!("compare_crf_{}", result.crf),
+                                    image,
+                                    TextureOptions::LINEAR,
+                                ));
+                            }
+                            if let Some(texture) = &result.texture {
+                                let size = texture.size_vec2();
+                                let width = ui.available_width().min(480.0);
+                                ui.image((texture.id(), size * (width / size.x)));
+                            }
+
+                            if comparison.keep_files {
+                                ui.label(RichText::new(result.path.display().to_string()).weak());
+                            }
+                            if ui.button(format!("Use CRF {}", result.crf)).clicked() {
+                                adopt = Some(result.crf);
+                            }
+                        }
+                    });
+                    if let Some(crf) = adopt {
+                        self.crf = crf;
+                    }
+                }
+            });
+        self.show_compare_window = open;
+
+        if run_requested {
+            let encodes = (0..2)
+                .map(|index| generate_command(&self.comparison_settings(index)))
+                .collect::<Result<Vec<_>, _>>();
+            let encodes = match encodes {
+                Ok(encodes) => encodes,
+                Err(problem) => {
+                    self.log
+                        .push(format!("The comparison didn't start: {}", problem));
+                    return;
+                }
+            };
+            self.crf_comparison.start(
+                &self.ffmpeg_paths(),
+                &expand_path_string(&self.input_file),
+                encodes,
+            );
+        }
+    }
+
+    // Per-scene overrides on top of the scenes file, written to a zones file for --zones
+    fn show_zones_window(&mut self, ctx: &egui::Context) {
+        if !self.show_zones_window {
+            return;
+        }
+
+        let scenes_file = expand_path(&self.scenes_file);
+        if self.zone_editor.scenes_file != scenes_file.display().to_string() {
+            self.zone_editor.load(&scenes_file);
+        }
+
+        let (encoder, crf, preset) = (self.encoder, self.crf, self.preset);
+        let ranges = encoder.ranges();
+        let mut open = self.show_zones_window;
+        let mut use_zones = false;
+        egui::Window::new("Zones from Scenes")
+            .open(&mut open)
+            .default_width(500.0)
+            .show(ctx, |ui| {
+                let editor = &mut self.zone_editor;
+                ui.horizontal(|ui| {
+                    ui.label(format!(
+                        "{} scenes from {}",
+                        editor.scenes.len(),
+                        editor.scenes_file
+                    ));
+                    if ui.button("Reload").clicked() {
+                        editor.load(&scenes_file);
+                    }
+                });
+                if let Some(error) = &editor.error {
+                    ui.colored_label(
+                        ui.visuals().error_fg_color,
+                        format!("⚠ Couldn't read the scenes file: {}", error),
+                    );
+                }
+                ui.label(
+                    RichText::new(format!(
+                        "Scenes without an override keep the main {} and {}.",
+                        ranges.quality_label, ranges.speed_label
+                    ))
+                    .weak(),
+                );
+                ui.separator();
+
+                let row_height = ui.spacing().interact_size.y;
+                egui::ScrollArea::vertical().max_height(400.0).show_rows(
+                    ui,
+                    row_height,
+                    editor.scenes.len(),
+                    |ui, rows| {
+                        for index in rows {
+                            let scene = &mut editor.scenes[index];
+                            ui.horizontal(|ui| {
+                                ui.label(format!("{}.", index + 1));
+                                ui.label(
+                                    RichText::new(format!(
+                                        "{}–{} ({} frames)",
+                                        scene.start,
+                                        scene.end,
+                                        scene.end.saturating_sub(scene.start)
+                                    ))
+                                    .weak(),
+                                );
+
+                                let mut has_crf = scene.crf.is_some();
+                                ui.checkbox(&mut has_crf, ranges.quality_label);
+                                scene.crf = match (has_crf, scene.crf) {
+                                    (true, value) => Some(value.unwrap_or(crf)),
+                                    (false, _) => None,
+                                };
+                                if let Some(value) = &mut scene.crf {
+                                    ui.add(
+                                        egui::DragValue::new(value)
+                                            .range(ranges.quality.0..=ranges.quality.1)
+                                            .speed(ranges.quality_step),
+                                    );
+                                    *value = (*value / ranges.quality_step).round()
+                                        * ranges.quality_step;
+                                }
+
+                                let mut has_preset = scene.preset.is_some();
+                                ui.checkbox(&mut has_preset, ranges.speed_label);
+                                scene.preset = match (has_preset, scene.preset) {
+                                    (true, value) => Some(value.unwrap_or(preset)),
+                                    (false, _) => None,
+                                };
+                                if let Some(value) = &mut scene.preset {
+                                    ui.add(
+                                        egui::DragValue::new(value)
+                                            .range(ranges.speed.0..=ranges.speed.1)
+                                            .speed(0.1)
+                                            .fixed_decimals(0),
+                                    );
+                                    *value = value.round();
+                                }
+                            });
+                        }
+                    },
+                );
+
+                ui.separator();
+                let overridden = editor
+                    .scenes
+                    .iter()
+                    .filter(|scene| scene.is_overridden())
+                    .count();
+                ui.horizontal(|ui| {
+                    use_zones = ui
+                        .add_enabled(overridden > 0, egui::Button::new("Use Zones"))
+                        .on_hover_text(
+                            "Writes the zones to a temporary file and sets it as the Zones File",
+                        )
+                        .clicked();
+                    if ui
+                        .add_enabled(overridden > 0, egui::Button::new("Save As…"))
+                        .clicked()
+                    {
+                        if let Some(path) = FileDialog::new()
+                            .add_filter("TXT Files", &["txt"])
+                            .set_file_name("zones.txt")
+                            .save_file()
+                        {
+                            if let Err(e) =
+                                std::fs::write(&path, zones_text(&editor.scenes, encoder))
+                            {
+                                editor.error = Some(format!("Couldn't write {:?}: {}", path, e));
+                            }
+                        }
+                    }
+                    if ui
+                        .add_enabled(overridden > 0, egui::Button::new("Clear Overrides"))
+                        .clicked()
+                    {
+                        for scene in &mut editor.scenes {
+                            scene.crf = None;
+                            scene.preset = None;
+                        }
+                    }
+                    ui.label(RichText::new(format!("{} overridden", overridden)).weak());
+                });
+            });
+
+        if use_zones {
+            match self.zone_editor.write_temp(encoder) {
+                Ok(path) => self.zones_file = path.display().to_string(),
+                Err(e) => {
+                    self.zone_editor.error = Some(format!("Couldn't write the zones file: {}", e))
+                }
+            }
+        }
+        self.show_zones_window = open;
+    }
+
+    pub fn ffmpeg_paths(&self) -> FfmpegPaths {
+        FfmpegPaths::new(&expand_path(&self.ffmpeg_path))
+    }
+
+    // Where av1an's temp files go, it uses the working directory by default
+    fn temp_folder(&self) -> PathBuf {
+        if self.temp_dir.is_empty() {
+            PathBuf::from(".")
+        } else {
+            expand_path(&self.temp_dir)
+        }
+    }
+
+    // Deletes the joined input once nothing is going to encode it anymore
+    fn remove_joined_input(&mut self) {
+        let joined = self.input_join.output.display().to_string();
+        if joined.is_empty() {
+            return;
+        }
+        let in_use = (self.encode_session.is_some() && self.input_file == joined)
+            || self
+                .queue
+                .jobs
+                .iter()
+                .any(|job| job.input_file == joined && !job.status.is_finished());
+        if in_use {
+            return;
+        }
+        if let Some(output) = self.input_join.remove_output() {
+            self.log
+                .push(format!("Removed the joined input {}", output.display()));
+        }
+    }
+
+    // Deletes the zones files the editor wrote once no job that may still run points to them.
+    // `keep_current` keeps the one the Zones File setting points to as well.
+    fn remove_unused_zone_exports(&mut self, keep_current: bool) {
+        let mut in_use: Vec<&str> = self
+            .queue
+            .jobs
+            .iter()
+            .filter(|job| job.status != JobStatus::Done)
+            .map(|job| job.zones_file.as_str())
+            .collect();
+        if keep_current {
+            in_use.push(&self.zones_file);
+        }
+        self.zone_editor.remove_unused_exports(&in_use);
+    }
+
+    fn show_join_window(&mut self, ctx: &egui::Context) {
+        if !self.show_join_window {
+            if self.input_join.is_running() {
+                self.input_join.cancel();
+            }
+            return;
+        }
+
+        if self.input_join.poll() {
+            self.session_dirty = true;
+            self.input_file = self.input_join.output.display().to_string();
+            // Named after the first segment, rather than the temporary joined file
+            if self.output_file.is_empty() {
+                let first = std::path::Path::new(&self.input_join.files[0]);
+                self.output_file = output_path_for(&self.output_template, first, &self.to_preset())
+                    .display()
+                    .to_string();
+            }
+        }
+
+        let ffmpeg_found = self.tools().has_ffmpeg();
+        let temp_folder = self.temp_folder();
+        let tools = self.ffmpeg_paths();
+        let mut open = self.show_join_window;
+        egui::Window::new("Join Input Files")
+            .open(&mut open)
+            .default_width(600.0)
+            .show(ctx, |ui| {
+                let join = &mut self.input_join;
+                let running = join.is_running();
+
+                ui.add_enabled_ui(!running, |ui| {
+                    let mut moved = None;
+                    let mut removed = None;
+                    let count = join.files.len();
+                    egui::Grid::new("join_files_grid").striped(true).show(ui, |ui| {
+                        for (index, (file, info)) in join.files.iter().zip(&join.infos).enumerate() {
+                            ui.label(format!("{}.", index + 1));
+                            ui.label(file);
+                            match info {
+                                Some(Ok(info)) => ui.label(
+                                    RichText::new(format!(
+                                        "{}x{} @ {:.3} fps",
+                                        info.width.unwrap_or_default(),
+                                        info.height.unwrap_or_default(),
+                                        info.fps.unwrap_or_default()
+                                    ))
+                                    .weak(),
+                                ),
+                                Some(Err(e)) => ui.colored_label(ui.visuals().error_fg_color, "⚠ Unreadable").on_hover_text(e),
+                                None => ui.spinner(),
+                            };
+                            ui.horizontal(|ui| {
+                                if ui.add_enabled(index > 0, egui::Button::new("⏶")).clicked() {
+                                    moved = Some((index, index - 1));
+                                }
+                                if ui.add_enabled(index + 1 < count, egui::Button::new("⏷")).clicked() {
+                                    moved = Some((index, index + 1));
+                                }
+                                if ui.button("🗑").on_hover_text("Remove").clicked() {
+                                    removed = Some(index);
+                                }
+                            });
+                            ui.end_row();
+                        }
+                    });
+                    if let Some((a, b)) = moved {
+                        join.swap(a, b);
+                    }
+                    if let Some(index) = removed {
+                        join.remove(index);
+                    }
+
+                    if ui.button("Add Files").clicked() {
+                        if let Some(paths) = FileDialog::new().add_filter("Video Files", &["mkv", "m2ts", "mts", "ts", "mp4"]).pick_files() {
+                            let mut paths: Vec<String> = paths.iter().map(|path| path.display().to_string()).collect();
+                            paths.sort();
+                            for path in paths {
+                                join.add(&tools, path);
+                            }
+                        }
+                    }
+                });
+
+                ui.add_space(ui.spacing().item_spacing.y * 2.0);
+
+                let mismatch = join.mismatch();
+                if let Some(mismatch) = &mismatch {
+                    ui.colored_label(ui.visuals().warn_fg_color, format!("⚠ {}.", mismatch));
+                }
+                if !ffmpeg_found {
+                    ui.colored_label(ui.visuals().warn_fg_color, "⚠ Joining needs ffmpeg.");
+                }
+                match &join.stage {
+                    JoinStage::Joining(_) => {
+                        ui.horizontal(|ui| {
+                            ui.spinner();
+                            ui.label("Joining…");
+                        });
+                    }
+                    JoinStage::Done => {
+                        ui.label(format!("Joined into {}, which is now the input.", join.output.display()));
+                    }
+                    JoinStage::Failed(error) => {
+                        ui.colored_label(ui.visuals().error_fg_color, format!("⚠ {}", error));
+                    }
+                    JoinStage::Idle => {}
+                }
+
+                ui.horizontal(|ui| {
+                    let can_join = !running && mismatch.is_none() && ffmpeg_found;
+                    if ui
+                        .add_enabled(can_join, egui::Button::new("Join"))
+                        .on_hover_text("Stream-copies the files into one temporary MKV, in this order, and uses it as the input")
+                        .clicked()
+                    {
+                        join.start(&tools, &temp_folder);
+                    }
+                    if running && ui.button("Cancel").clicked() {
+                        join.cancel();
+                    }
+                });
+            });
+        self.show_join_window = open;
+    }
+
+    fn show_grain_window(&mut self, ctx: &egui::Context) {
+        if !self.show_grain_window {
+            if self.grain_generator.is_running() {
+                self.grain_generator.cancel();
+            }
+            return;
+        }
+
+        if self.grain_generator.poll() {
+            self.session_dirty = true;
+            self.grain_table = self.grain_generator.output.display().to_string();
+            self.grain_synthesis = GrainSynthesis::GrainTable;
+        }
+
+        let output_resolution = self.output_resolution();
+        let mut open = self.show_grain_window;
+        let mut generate = None;
+        egui::Window::new("Generate Grain Table")
+            .open(&mut open)
+            .show(ctx, |ui| {
+                let generator = &mut self.grain_generator;
+                let running = generator.is_running();
+
+                ui.add_enabled_ui(!running, |ui| {
+                    egui::Grid::new("grain_settings_grid").show(ui, |ui| {
+                        ui.label("ISO");
+                        ui.add(egui::DragValue::new(&mut generator.iso).range(100..=51200).speed(25));
+                        ui.end_row();
+
+                        let (hint_width, hint_height) = match output_resolution {
+                            Some((width, height)) => (width.to_string(), height.to_string()),
+                            None => (String::from("width"), String::from("height")),
+                        };
+                        ui.label("Resolution");
+                        ui.horizontal(|ui| {
+                            ui.add(
+                                egui::TextEdit::singleline(&mut generator.width)
+                                    .hint_text(hint_width)
+                                    .desired_width(60.0),
+                            );
+                            ui.label("x");
+                            ui.add(
+                                egui::TextEdit::singleline(&mut generator.height)
+                                    .hint_text(hint_height)
+                                    .desired_width(60.0),
+                            );
+                            ui.label(RichText::new("ℹ").weak()).on_hover_ui(|ui| {
+                                ui.style_mut().interaction.selectable_labels = true;
+                                ui.label("Photon noise tables are made for one resolution. Leave empty to use the output resolution.");
+                            });
+                        });
+                        ui.end_row();
+                    });
+                });
+
+                let target = generator.target_resolution(output_resolution);
+                match (target, output_resolution) {
+                    (None, _) => {
+                        ui.colored_label(
+                            ui.visuals().warn_fg_color,
+                            "⚠ Set a resolution, or pick an input first.",
+                        );
+                    }
+                    (Some(target), Some(output)) if target != output => {
+                        ui.colored_label(
+                            ui.visuals().warn_fg_color,
+                            format!(
+                                "⚠ The output is {}x{}, the grain will look too coarse or too fine.",
+                                output.0, output.1
+                            ),
+                        );
+                    }
+                    _ => {}
+                }
+
+                ui.add_space(ui.spacing().item_spacing.y * 2.0);
+
+                match &generator.stage {
+                    GrainStage::Running(..) => {
+                        ui.horizontal(|ui| {
+                            ui.spinner();
+                            ui.label(format!("Running {}…", GRAIN_TOOL));
+                        });
+                    }
+                    GrainStage::Done => {
+                        ui.label(format!("Wrote {}", generator.output.display()));
+                    }
+                    GrainStage::Failed(error) => {
+                        ui.colored_label(ui.visuals().error_fg_color, format!("⚠ {}", error));
+                    }
+                    GrainStage::Idle => {}
+                }
+
+                if ui
+                    .add_enabled(!running && target.is_some(), egui::Button::new("Generate"))
+                    .on_hover_text(format!(
+                        "Runs {} from libaom, which has to be on the PATH. The table is saved in the config folder and filled into the Grain Table field.",
+                        GRAIN_TOOL
+                    ))
+                    .clicked()
+                {
+                    generate = target;
+                }
+            });
+
+        if let Some(resolution) = generate {
+            self.grain_generator.start(resolution);
+        }
+        self.show_grain_window = open;
+    }
+
+    fn show_preset_error_window(&mut self, ctx: &egui::Context) {
+        let Some(error) = &self.preset_error else {
+            return;
+        };
+
+        let mut open = true;
+        let mut dismissed = false;
+        egui::Window::new("Couldn't Load Preset")
+            .open(&mut open)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                ui.style_mut().interaction.selectable_labels = true;
+                ui.colored_label(ui.visuals().error_fg_color, error);
+                ui.add_space(ui.spacing().item_spacing.y * 2.0);
+                ui.with_layout(egui::Layout::top_down(egui::Align::Center), |ui| {
+                    dismissed = ui.button("OK").clicked();
+                });
+            });
+
+        if !open || dismissed {
+            self.preset_error = None;
+        }
+    }
+
+    fn show_save_preset_window(&mut self, ctx: &egui::Context) {
+        if !self.show_save_preset_window {
+            return;
+        }
+
+        let mut open = true;
+        let mut save_requested = false;
+        egui::Window::new("Save Preset")
+            .open(&mut open)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                egui::Grid::new("save_preset_grid").show(ui, |ui| {
+                    ui.label("Name");
+                    ui.add_sized(
+                        [400.0, 20.0],
+                        egui::TextEdit::singleline(&mut self.preset_name),
+                    );
+                    ui.end_row();
+
+                    ui.label("Description");
+                    ui.add_sized(
+                        [400.0, 60.0],
+                        egui::TextEdit::multiline(&mut self.preset_description),
+                    );
+                    ui.end_row();
+                });
+                ui.checkbox(&mut self.preset_include_paths, "Include file paths")
+                    .on_hover_text("Also store the input, output, scenes and zones paths, for jobs that always use the same files.");
+                ui.add_space(ui.spacing().item_spacing.y * 2.0);
+                ui.with_layout(egui::Layout::top_down(egui::Align::Center), |ui| {
+                    save_requested = ui.button("Save").clicked();
+                });
+            });
+        self.show_save_preset_window = open && !save_requested;
+
+        if save_requested {
+            let mut dialog = FileDialog::new()
+                .add_filter("YAML Files", &["yaml", "yml"])
+                .add_filter("JSON Files", &["json"]);
+            if !self.preset_name.trim().is_empty() {
+                dialog = dialog.set_file_name(format!("{}.yaml", self.preset_name.trim()));
+            }
+
+            if let Some(path) = dialog.save_file() {
+                let path_string = path.display().to_string();
+                let file_path = if path_string.ends_with(".yaml")
+                    || path_string.ends_with(".yml")
+                    || path_string.ends_with(".json")
+                {
+                    path_string
+                } else {
+                    format!("{}.yaml", path_string)
+                };
+
+                if std::path::Path::new(&file_path).exists() {
+                    self.preset_overwrite_path = Some(file_path);
+                } else {
+                    self.save_preset(&file_path);
+                }
+            }
+        }
+    }
+
+    fn show_save_quality_preset_window(&mut self, ctx: &egui::Context) {
+        if !self.show_save_quality_preset_window {
+            return;
+        }
+
+        let exists = quality_presets_dir()
+            .map(|dir| dir.join(quality_preset_file_name(&self.quality_preset_name)))
+            .is_some_and(|path| path.exists());
+        let mut open = true;
+        let mut save_requested = false;
+        egui::Window::new("Save Quality Preset")
+            .open(&mut open)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                ui.label("Stores the encoder, preset, CRF, tune, grain and custom parameters, so they can be applied on top of any source and video settings.");
+                ui.horizontal(|ui| {
+                    ui.label("Name");
+                    ui.add_sized(
+                        [300.0, 20.0],
+                        egui::TextEdit::singleline(&mut self.quality_preset_name),
+                    );
+                });
+                if exists {
+                    ui.colored_label(
+                        ui.visuals().warn_fg_color,
+                        "⚠ Replaces the quality preset with this name.",
+                    );
+                }
+                ui.add_space(ui.spacing().item_spacing.y * 2.0);
+                ui.with_layout(egui::Layout::top_down(egui::Align::Center), |ui| {
+                    save_requested = ui
+                        .add_enabled(
+                            !self.quality_preset_name.trim().is_empty(),
+                            egui::Button::new("Save"),
+                        )
+                        .clicked();
+                });
+            });
+        self.show_save_quality_preset_window = open && !save_requested;
+
+        if save_requested {
+            if let Err(e) = self.save_quality_preset() {
+                println!("Error saving quality preset: {}", e);
+                self.log
+                    .push(format!("Couldn't save the quality preset: {}", e));
+            }
+        }
+    }
+
+    fn remember_preset(&mut self, path: &str) {
+        self.recent.add_preset(path);
+        save_recent(&self.recent);
+    }
+
+    // Both paths, and the settings of the last encode of it to restore if the user wants
+    fn pick_recent_input(&mut self, index: usize) {
+        let Some(recent) = self.recent.inputs.get(index).cloned() else {
+            return;
+        };
+        self.input_file = recent.input;
+        self.output_file = recent.output;
+        self.recent_settings = recent.settings;
+    }
+
+    fn restore_recent_settings(&mut self) {
+        let Some(settings) = self.recent_settings.take() else {
+            return;
+        };
+        let (input, output) = (self.input_file.clone(), self.output_file.clone());
+        self.apply_session_state(settings);
+        self.input_file = input;
+        self.output_file = output;
+    }
+
+    fn save_preset(&mut self, file_path: &str) {
+        match self.save_preset_to_file(file_path) {
+            Ok(_) => {
+                println!("Preset saved successfully to {}", file_path);
+                self.scanned_presets_folder = None;
+                self.remember_preset(file_path);
+            }
+            Err(e) => {
+                println!("Error saving preset: {}", e);
+            }
+        }
+    }
+
+    // What the encode is about to do, as label and value
+    fn start_summary(&self) -> Vec<(&'static str, String)> {
+        let mut summary = vec![(
+            "Files",
+            format!("{} → {}", self.input_file, self.output_file),
+        )];
+
+        let source = self.source_resolution();
+        let resolution = match (self.output_resolution(), source) {
+            (Some(output), Some(source)) if output != source => {
+                format!("{}x{} → {}x{}", source.0, source.1, output.0, output.1)
+            }
+            (Some(output), _) if self.scaled_resolution().is_some() => {
+                format!("{}x{}", output.0, output.1)
+            }
+            (Some(output), _) => format!("{}x{}, unchanged", output.0, output.1),
+            (None, _) => String::from("unchanged"),
+        };
+        summary.push(("Resolution", resolution));
+        summary.push((
+            "Pixel Format",
+            self.output_pixel_format.as_str().to_string(),
+        ));
+
+        let ranges = self.encoder.ranges();
+        let quality = if self.rate_control == RateControl::TargetBitrate {
+            format!("{} kbps, 2-pass", self.target_bitrate.trim())
+        } else if self.target_quality.trim().is_empty() {
+            format!("{} {}", ranges.quality_label, self.crf)
+        } else {
+            format!("Target Quality {}", self.target_quality.trim())
+        };
+        let custom = self.custom_encode_params.trim();
+        if !custom.is_empty() && self.custom_params_mode == CustomParamsMode::Replace {
+            summary.push((
+                "Encoder",
+                format!("{}, custom params only", self.encoder.as_str()),
+            ));
+        } else {
+            summary.push((
+                "Encoder",
+                format!(
+                    "{}, {}, {} {}",
+                    self.encoder.as_str(),
+                    quality,
+                    ranges.speed_label,
+                    self.preset
+                ),
+            ));
+            let grain = match self.grain_synthesis {
+                GrainSynthesis::Synthetic if self.synthetic_grain.trim() == "0" => {
+                    String::from("off")
+                }
+                GrainSynthesis::Synthetic => format!("synthetic, {}", self.synthetic_grain.trim()),
+                GrainSynthesis::PhotonNoise => match self.photon_noise_size() {
+                    Some((width, height)) => format!(
+                        "photon noise, ISO {} at {}x{}",
+                        self.photon_noise.trim(),
+                        width,
+                        height
+                    ),
+                    None => format!("photon noise, ISO {}", self.photon_noise.trim()),
+                },
+                GrainSynthesis::GrainTable => format!("table {}", self.grain_table),
+            };
+            summary.push(("Grain", grain));
+        }
+        if !custom.is_empty() {
+            summary.push(("Custom Params", custom.to_string()));
+        }
+        summary.push((
+            "Workers",
+            if self.auto_workers {
+                String::from("Auto")
+            } else {
+                self.workers.clone()
+            },
+        ));
+
+        summary
+    }
+
+    // The input's colors, when it's HDR but the color settings were left unspecified. The
+    // output would be signaled as SDR then and look grey and washed out.
+    fn hdr_color_fix(
+        &self,
+    ) -> Option<(ColorPrimaries, TransferCharacteristics, MatrixCoefficients)> {
+        let info = self.media_info.as_ref().filter(|info| info.is_hdr())?;
+        let left_unspecified = self.color_primaries == ColorPrimaries::Unspecified
+            || self.transfer_characteristics == TransferCharacteristics::Unpsecified
+            || self.matrix_coefficients == MatrixCoefficients::Unspecified;
+        if !left_unspecified {
+            return None;
+        }
+
+        // HDR sources without primaries or a matrix tagged are BT.2020 all the same
+        Some((
+            info.color_primaries
+                .as_deref()
+                .and_then(ColorPrimaries::from_ffprobe)
+                .unwrap_or(ColorPrimaries::Bt2020),
+            info.color_transfer
+                .as_deref()
+                .and_then(TransferCharacteristics::from_ffprobe)?,
+            info.color_space
+                .as_deref()
+                .and_then(MatrixCoefficients::from_ffprobe)
+                .unwrap_or(MatrixCoefficients::Bt2020Ncl),
+        ))
+    }
+
+    fn hdr_defaults_warning(&self) -> Option<String> {
+        let (_, transfer, _) = self.hdr_color_fix()?;
+        let name = match transfer {
+            TransferCharacteristics::Hlg => "HLG",
+            _ => "PQ",
+        };
+
+        Some(format!(
+            "The input is HDR ({}), but the colors are left unspecified. The output will look grey and washed out.",
+            name
+        ))
+    }
+
+    // Settings that encode, but likely not into what was meant
+    fn start_warnings(&mut self) -> Vec<String> {
+        let mut warnings = Vec::new();
+
+        let scenes_file = self.scenes_file.trim();
+        if !scenes_file.is_empty() && !expand_path(scenes_file).is_file() {
+            warnings.push(format!("⚠ The Scenes File {} doesn't exist.", scenes_file));
+        }
+        let zones_file = self.zones_file.trim();
+        if !zones_file.is_empty() && !expand_path(zones_file).is_file() {
+            warnings.push(format!("⚠ The Zones File {} doesn't exist.", zones_file));
+        } else if !zones_file.is_empty()
+            && scenes_file.is_empty()
+            && !self.zone_editor.scenes_file.is_empty()
+        {
+            warnings.push(format!(
+                "⚠ The zones were made from the scenes in {}, which isn't set as the Scenes File anymore. Av1an's own scenes may not line up with them.",
+                self.zone_editor.scenes_file
+            ));
+        }
+
+        if let Some(info) = &self.media_info {
+            let output_depth = self.output_pixel_format.bit_depth();
+            if let Some(depth) = info.bit_depth().filter(|&depth| depth > output_depth) {
+                warnings.push(format!(
+                    "⚠ The input is {}-bit, but {} is {}-bit. Gradients may band.",
+                    depth,
+                    self.output_pixel_format.as_str(),
+                    output_depth
+                ));
+            }
+            let hdr_transfer = matches!(
+                self.transfer_characteristics,
+                TransferCharacteristics::Smpte2084 | TransferCharacteristics::Hlg
+            );
+            if let Some(warning) = self.hdr_defaults_warning() {
+                warnings.push(format!("⚠ {}", warning));
+            } else if info.is_hdr() && !hdr_transfer {
+                warnings.push(format!(
+                    "⚠ The input is HDR ({}), but the Transfer Characteristics are ({}). The output won't be shown as HDR.",
+                    info.color_transfer.as_deref().unwrap_or_default(),
+                    self.transfer_characteristics.as_str()
+                ));
+            }
+        }
+
+        warnings.extend(
+            color_metadata_problems(
+                self.color_primaries,
+                self.transfer_characteristics,
+                self.matrix_coefficients,
+            )
+            .into_iter()
+            .map(|problem| format!("⚠ {}", problem)),
+        );
+        if let Some(problem) = self.encoder.pixel_format_problem(self.output_pixel_format) {
+            warnings.push(format!("⚠ {}", problem));
+        }
+        warnings.extend(self.resolution_warnings());
+        if let Some(warning) = self.low_max_bitrate_warning() {
+            warnings.push(format!("⚠ {}", warning));
+        }
+        let unknown = self.unknown_encoder_flags();
+        if !unknown.is_empty() {
+            warnings.push(format!(
+                "⚠ The installed SvtAv1EncApp doesn't list {}.",
+                unknown.join(", ")
+            ));
+        }
+
+        warnings
+    }
+
+    fn show_start_confirmation_window(&mut self, ctx: &egui::Context) {
+        let Some(mut dont_ask) = self.start_confirmation else {
+            return;
+        };
+
+        let summary = self.start_summary();
+        let warnings = self.start_warnings();
+        let mut start = false;
+        let mut back = false;
+        egui::Window::new("Start Encoding?")
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                egui::Grid::new("start_summary_grid")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        for (label, value) in &summary {
+                            ui.label(RichText::new(*label).weak());
+                            ui.label(value);
+                            ui.end_row();
+                        }
+                    });
+                for warning in &warnings {
+                    ui.colored_label(ui.visuals().warn_fg_color, warning);
+                }
+                ui.add_space(ui.spacing().item_spacing.y * 2.0);
+                ui.checkbox(&mut dont_ask, "Don't ask again")
+                    .on_hover_text("Can be turned back on in the Settings");
+                ui.horizontal(|ui| {
+                    start = ui.button("Start").clicked();
+                    back = ui.button("Back").clicked();
+                });
+            });
+
+        self.start_confirmation = Some(dont_ask);
+        if start {
+            self.start_confirmation = None;
+            if dont_ask {
+                self.confirm_start = false;
+            }
+            self.start_encoding_now();
+        } else if back {
+            self.start_confirmation = None;
+        }
+    }
+
+    fn show_cancel_confirmation_window(&mut self, ctx: &egui::Context) {
+        // The encode may have ended on its own in the meantime
+        if !self.encoding_in_progress {
+            self.cancel_confirmation = false;
+        }
+        if !self.cancel_confirmation {
+            return;
+        }
+
+        let elapsed = self
+            .encode_session
+            .as_ref()
+            .map(|session| format_duration(session.started_at.elapsed()));
+        let mut cancel = false;
+        let mut keep = false;
+        egui::Window::new("Cancel Encoding?")
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                match elapsed {
+                    Some(elapsed) => ui.label(format!(
+                        "The encode has been running for {}. Cancelling stops Av1an and its encoders.",
+                        elapsed
+                    )),
+                    None => ui.label("Cancelling stops Av1an and its encoders."),
+                };
+                ui.add_space(ui.spacing().item_spacing.y * 2.0);
+                ui.horizontal(|ui| {
+                    cancel = ui.button("Cancel Encode").clicked();
+                    keep = ui.button("Keep Encoding").clicked();
+                });
+            });
+
+        if cancel {
+            self.cancel_confirmation = false;
+            self.cancel_encoding();
+        } else if keep {
+            self.cancel_confirmation = false;
+        }
+    }
+
+    fn show_missing_output_dir_window(&mut self, ctx: &egui::Context) {
+        let Some(dir) = &self.missing_output_dir else {
+            return;
+        };
+
+        let mut create = false;
+        let mut cancel = false;
+        egui::Window::new("Output Folder Missing")
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "The output folder {} doesn't exist.",
+                    dir.display()
+                ));
+                ui.add_space(ui.spacing().item_spacing.y * 2.0);
+                ui.horizontal(|ui| {
+                    create = ui.button("Create and Start").clicked();
+                    cancel = ui.button("Cancel").clicked();
+                });
+            });
+
+        if create {
+            let dir = self.missing_output_dir.take().unwrap_or_default();
+            match std::fs::create_dir_all(&dir) {
+                Ok(()) => self.start_encoding_now(),
+                Err(e) => {
+                    self.start_error = Some(format!(
+                        "Couldn't create the output folder {}: {}",
+                        dir.display(),
+                        e
+                    ))
+                }
+            }
+        } else if cancel {
+            self.missing_output_dir = None;
+        }
+    }
+
+    fn show_disk_space_window(&mut self, ctx: &egui::Context) {
+        let Some(low) = &self.disk_space_warning else {
+            return;
+        };
+
+        let gib = |bytes: u64| bytes as f64 / 1024.0 / 1024.0 / 1024.0;
+        let mut start = false;
+        let mut cancel = false;
+        egui::Window::new("Low Disk Space")
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "The input is {:.1} GiB, but there's less free space than that in:",
+                    gib(low.input_size)
+                ));
+                for (folder, available) in &low.folders {
+                    ui.label(format!(
+                        "• {} ({:.1} GiB free)",
+                        folder.display(),
+                        gib(*available)
+                    ));
+                }
+                ui.label("The encode may fail once the disk is full.");
+                ui.add_space(ui.spacing().item_spacing.y * 2.0);
+                ui.horizontal(|ui| {
+                    start = ui.button("Start Anyway").clicked();
+                    cancel = ui.button("Cancel").clicked();
+                });
+            });
+
+        if start {
+            self.disk_space_warning = None;
+            self.spawn_encode();
+        } else if cancel {
+            self.disk_space_warning = None;
+        }
+    }
+
+    fn show_preset_overwrite_window(&mut self, ctx: &egui::Context) {
+        let Some(file_path) = &self.preset_overwrite_path else {
+            return;
+        };
+
+        let mut overwrite = false;
+        let mut cancel = false;
+        egui::Window::new("Overwrite Preset?")
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label(format!("{} already exists. Replace it?", file_path));
+                ui.add_space(ui.spacing().item_spacing.y * 2.0);
+                ui.horizontal(|ui| {
+                    overwrite = ui.button("Overwrite").clicked();
+                    cancel = ui.button("Cancel").clicked();
+                });
+            });
+
+        if overwrite {
+            if let Some(file_path) = self.preset_overwrite_path.take() {
+                self.save_preset(&file_path);
+            }
+        } else if cancel {
+            self.preset_overwrite_path = None;
+        }
+    }
+
+    fn update_queue(&mut self) {
+        for (status, report, record) in self.queue.poll(&mut self.log) {
+            self.queue_dirty = true;
+            let event = match status {
+                JobStatus::Done => NotifyEvent::JobFinished,
+                JobStatus::Failed(_) => NotifyEvent::JobFailed,
+                _ => continue,
+            };
+            self.add_to_history(record);
+            self.notifier.send(&self.notifications, event, report);
+            self.remove_joined_input();
+            self.remove_unused_zone_exports(true);
+        }
+        for error in self.notifier.errors() {
+            eprintln!("WARNING: {}", error);
+            self.log.push(error);
+        }
+        self.folder_scan.poll();
+
+        if !self.queue.running {
+            return;
+        }
+
+        while self.queue.active_count() < self.max_concurrent_jobs.max(1) {
+            let Some(index) = self.queue.next_pending() else {
+                if self.queue.active_count() == 0 {
+                    self.queue.running = false;
+                    self.notifier.send(
+                        &self.notifications,
+                        NotifyEvent::QueueEmpty,
+                        JobReport::default(),
+                    );
+                }
+                return;
+            };
+            // Whether it starts or fails, its status changes
+            self.queue_dirty = true;
+
+            let settings = self.job_settings(&self.queue.jobs[index]);
+            let problem = output_dir_problem(&output_dir_for(&settings.output_file));
+            let cmd = match problem.map_or_else(|| generate_command(&settings), Err) {
+                Ok(cmd) => cmd,
+                Err(problem) => {
+                    let job = &mut self.queue.jobs[index];
+                    self.log.push(format!("[job {}] {}", job.id, problem));
+                    job.status = JobStatus::Failed(problem);
+                    continue;
+                }
+            };
+            let command = command_to_string(&cmd);
+            println!("{}", command);
+            self.log
+                .push(format!("[job {}] {}", self.queue.jobs[index].id, command));
+
+            let job = &mut self.queue.jobs[index];
+            match EncodeSession::start(cmd) {
+                Ok(session) => {
+                    job.status = JobStatus::Running;
+                    job.session = Some(session);
+                    self.notifier.send(
+                        &self.notifications,
+                        NotifyEvent::JobStarted,
+                        JobReport::new(&job.output_file, None, "running"),
+                    );
+                }
+                Err(e) => {
+                    let failure = start_failure(&e);
+                    eprintln!("ERROR : {}", failure);
+                    self.log.push(format!("[job {}] {}", job.id, failure));
+                    job.status = JobStatus::Failed(failure);
+                }
+            }
+        }
+    }
+
+    // Video Settings controls, also used by the queue's job editor
+    fn video_settings_ui(&mut self, ui: &mut egui::Ui, max_width: &mut f32) {
+        ui.horizontal(|ui| {
+            let label_text = "*(Output) Resolution";
+            let label_width = ui.label(label_text).rect.max.x - ui.min_rect().min.x;
+            *max_width = max_width.max(label_width);
+            if label_width < *max_width {
+                ui.allocate_space(egui::vec2(*max_width - label_width, 1.0));
+            }
+            let source = self.source_resolution();
+            let (hint_width, hint_height) = match source {
+                Some((width, height)) => (width.to_string(), height.to_string()),
+                None => (String::new(), String::new()),
+            };
+            let width = ui.add_sized(
+                [100.0, 20.0],
+                egui::TextEdit::singleline(&mut self.width).hint_text(hint_width),
+            );
+            ui.label("×");
+            let height = ui.add_sized(
+                [100.0, 20.0],
+                egui::TextEdit::singleline(&mut self.height).hint_text(hint_height),
+            );
+            if let Some(source) = source.filter(|_| self.lock_aspect) {
+                if width.changed() {
+                    if let Ok(value) = self.width.trim().parse() {
+                        self.height = height_for_width(value, source).to_string();
+                    }
+                } else if height.changed() {
+                    if let Ok(value) = self.height.trim().parse() {
+                        self.width = width_for_height(value, source).to_string();
+                    }
+                }
+            }
+            ui.checkbox(&mut self.lock_aspect, "Lock Aspect");
+            ComboBox::from_id_salt("resolution_preset_combobox")
+                .selected_text("Presets")
+                .show_ui(ui, |ui| {
+                    for preset in ResolutionPreset::ALL {
+                        let available =
+                            source.is_some() || preset != ResolutionPreset::HalfSource;
+                        if ui
+                            .add_enabled(available, egui::Button::new(preset.as_str()).frame(false))
+                            .clicked()
+                        {
+                            let (width, height) = preset
+                                .resolution(source, self.lock_aspect)
+                                .map_or((String::new(), String::new()), |(w, h)| {
+                                    (w.to_string(), h.to_string())
+                                });
+                            self.width = width;
+                            self.height = height;
+                            ui.close_menu();
+                        }
+                    }
+                });
+            ui.label(RichText::new("ℹ").weak()).on_hover_ui(|ui| {
+                ui.style_mut().interaction.selectable_labels = true;
+                ui.label("Resolution to resize the output video to. Both are empty by default, which keeps the input's resolution, so nothing gets scaled unless you set a size here. Setting only one side (leaving the other empty, -1 or -2) scales to the input's aspect ratio, rounded to even. With Lock Aspect on, editing one side sets the other to match the input's aspect ratio (rounded to even), and the presets only set the width.");
+            });
+        });
+        for warning in self.resolution_warnings() {
+            ui.colored_label(ui.visuals().warn_fg_color, warning);
+        }
+
+        ui.horizontal(|ui| {
+            let label_text = "Deinterlace";
+            let label_width = ui.label(label_text).rect.max.x - ui.min_rect().min.x;
+            *max_width = max_width.max(label_width);
+            if label_width < *max_width {
+                ui.allocate_space(egui::vec2(*max_width - label_width, 1.0));
+            }
+            ComboBox::from_id_salt("deinterlace_combobox")
+                .selected_text(self.deinterlace.as_str())
+                .show_ui(ui, |ui| {
+                    for deinterlace in Deinterlace::ALL {
+                        ui.selectable_value(&mut self.deinterlace, deinterlace, deinterlace.as_str())
+                            .on_hover_text(deinterlace.description());
+                    }
+                });
+            ui.label(RichText::new("ℹ").weak()).on_hover_ui(|ui| {
+                ui.style_mut().interaction.selectable_labels = true;
+                ui.label("Removes combing from interlaced sources, like many DVDs. Use IVTC for telecined film and bwdif or yadif for video that was shot interlaced. Runs before scaling. Can be set for sources ffprobe reports as progressive too, it gets that wrong sometimes.");
+            });
+        });
+        if let Some(info) = self.media_info.as_ref().filter(|info| info.is_interlaced()) {
+            if self.deinterlace == Deinterlace::None {
+                ui.colored_label(
+                    ui.visuals().warn_fg_color,
+                    format!(
+                        "⚠ The input is interlaced ({}), it will come out combed without deinterlacing.",
+                        info.field_order.as_deref().unwrap_or_default()
+                    ),
+                );
+            }
+        }
+
+        ui.horizontal(|ui| {
+            let label_text = "Crop";
+            let label_width = ui.label(label_text).rect.max.x - ui.min_rect().min.x;
+            *max_width = max_width.max(label_width);
+            if label_width < *max_width {
+                ui.allocate_space(egui::vec2(*max_width - label_width, 1.0));
+            }
+            for (side, value) in [
+                ("Top", &mut self.crop_top),
+                ("Bottom", &mut self.crop_bottom),
+                ("Left", &mut self.crop_left),
+                ("Right", &mut self.crop_right),
+            ] {
+                ui.label(RichText::new(side).weak());
+                ui.add(egui::DragValue::new(value).range(0..=4096).speed(2.0));
+            }
+            ui.label(RichText::new("ℹ").weak()).on_hover_ui(|ui| {
+                ui.style_mut().interaction.selectable_labels = true;
+                ui.label("Pixels to cut off each side of the input, e.g. letterbox bars. Happens before denoising and scaling, and Lock Aspect follows the cropped size. Keep the values even for 4:2:0.");
+            });
+        });
+        if let Some((width, height)) = self
+            .media_info
+            .as_ref()
+            .and_then(|info| info.width.zip(info.height))
+        {
+            if self.crop_left + self.crop_right >= width
+                || self.crop_top + self.crop_bottom >= height
+            {
+                ui.colored_label(
+                    ui.visuals().warn_fg_color,
+                    format!(
+                        "⚠ The crop is larger than the input ({}x{}).",
+                        width, height
+                    ),
+                );
+            }
+        }
+        if [
+            self.crop_top,
+            self.crop_bottom,
+            self.crop_left,
+            self.crop_right,
+        ]
+        .iter()
+        .any(|value| value % 2 != 0)
+        {
+            ui.colored_label(
+                ui.visuals().warn_fg_color,
+                "⚠ Odd crop values can't be cut exactly from 4:2:0 video.",
+            );
+        }
+
+        ui.horizontal(|ui| {
+            let label_text = "Denoise";
+            let label_width = ui.label(label_text).rect.max.x - ui.min_rect().min.x;
+            *max_width = max_width.max(label_width);
+            if label_width < *max_width {
+                ui.allocate_space(egui::vec2(*max_width - label_width, 1.0));
+            }
+            let previous = self.denoise;
+            ComboBox::from_id_salt("denoise_combobox")
+                .selected_text(self.denoise.as_str())
+                .show_ui(ui, |ui| {
+                    for denoise in [Denoise::None, Denoise::Hqdn3d, Denoise::Nlmeans] {
+                        ui.selectable_value(&mut self.denoise, denoise, denoise.as_str());
+                    }
+                });
+            if self.denoise != previous {
+                self.denoise_strength = self.denoise.default_strength();
+            }
+            if self.denoise != Denoise::None {
+                let (min, max) = self.denoise.strength_range();
+                ui.add(Slider::new(&mut self.denoise_strength, min..=max).step_by(0.5).text("Strength"));
+            }
+            ui.label(RichText::new("ℹ").weak()).on_hover_ui(|ui| {
+                ui.style_mut().interaction.selectable_labels = true;
+                ui.label("Removes noise before encoding, which saves bitrate on noisy sources but softens detail. hqdn3d is fast, nlmeans is much slower but keeps edges better. For grain you want to keep, use grain synthesis instead.");
+            });
+        });
+
+        // Same condition as the scale filter in `generate_command`
+        if !self.width.is_empty() && !self.height.is_empty() {
+            ui.horizontal(|ui| {
+                let label_text = "Scaling Algorithm";
+                let label_width = ui.label(label_text).rect.max.x - ui.min_rect().min.x;
+                *max_width = max_width.max(label_width);
+                if label_width < *max_width {
+                    ui.allocate_space(egui::vec2(*max_width - label_width, 1.0));
+                }
+                ComboBox::from_id_salt("scale_algorithm_combobox")
+                    .selected_text(self.scale_algorithm.as_str())
+                    .show_ui(ui, |ui| {
+                        for algorithm in ScaleAlgorithm::ALL {
+                            ui.selectable_value(
+                                &mut self.scale_algorithm,
+                                algorithm,
+                                algorithm.as_str(),
+                            )
+                            .on_hover_text(algorithm.description());
+                        }
+                    });
+                ui.label(RichText::new("ℹ").weak()).on_hover_ui(|ui| {
+                    ui.style_mut().interaction.selectable_labels = true;
+                    ui.label(format!("How ffmpeg resizes to the output resolution. {} Lanczos or Spline keep more detail when downscaling.", self.scale_algorithm.description()));
+                });
+            });
+        }
+
+        ui.horizontal(|ui| {
+            let label_text = "*(Output) Pixel Format";
+            let label_width = ui.label(label_text).rect.max.x - ui.min_rect().min.x;
+            *max_width = max_width.max(label_width);
+            if label_width < *max_width {
+                ui.allocate_space(egui::vec2(*max_width - label_width, 1.0));
+            }
+            ComboBox::from_id_salt("output_pixel_format_combobox")
+                .selected_text(self.output_pixel_format.as_str())
+                .show_ui(ui, |ui| {
+                    for format in [
+                        PixelFormat::Yuv420p10le,
+                        PixelFormat::Yuv420p,
+                        PixelFormat::Yuv420p12le,
+                        PixelFormat::Yuv422p10le,
+                        PixelFormat::Yuv422p,
+                        PixelFormat::Yuv444p10le,
+                        PixelFormat::Yuv444p,
+                    ] {
+                        ui.selectable_value(&mut self.output_pixel_format, format, format.as_str());
+                    }
+                });
+            ui.label(RichText::new("ℹ").weak()).on_hover_ui(|ui| {
+                ui.style_mut().interaction.selectable_labels = true;
+                ui.label("FFmpeg pixel format to use. It's best to go with yuv420p10le (10-bit color format), even if the input video has 8-bit colors. 4:2:2, 4:4:4 and 12-bit keep more color detail, e.g. for screen captures or film scans, but SVT-AV1 can't encode them. Gets set from the input when one is picked.");
+            });
+        });
+        if let Some(problem) = self.encoder.pixel_format_problem(self.output_pixel_format) {
+            ui.colored_label(ui.visuals().warn_fg_color, format!("⚠ {}.", problem));
+        }
+
+        ui.horizontal(|ui| {
+            let label_text = "Color Primaries";
+            let label_width = ui.label(label_text).rect.max.x - ui.min_rect().min.x;
+            *max_width = max_width.max(label_width);
+            if label_width < *max_width {
+                ui.allocate_space(egui::vec2(*max_width - label_width, 1.0));
+            }
+            filterable_combo(
+                ui,
+                "color_primaries_combobox",
+                &mut self.color_primaries,
+                &ColorPrimaries::ALL,
+                ColorPrimaries::label,
+                ColorPrimaries::as_str,
+            );
+            ui.label(RichText::new("ℹ").weak()).on_hover_ui(|ui| {
+                ui.style_mut().interaction.selectable_labels = true;
+                ui.label("Color primaries, refer to the (SVT-AV1-PSY) user guide Appendix A.2 for full details. If you don't know what you're doing, just use the default option (2).");
+            });
+        });
+
+        ui.horizontal(|ui| {
+            let label_text = "Matrix Coefficients";
+            let label_width = ui.label(label_text).rect.max.x - ui.min_rect().min.x;
+            *max_width = max_width.max(label_width);
+            if label_width < *max_width {
+                ui.allocate_space(egui::vec2(*max_width - label_width, 1.0));
+            }
+            filterable_combo(
+                ui,
+                "matrix_coefficients_combobox",
+                &mut self.matrix_coefficients,
+                &MatrixCoefficients::ALL,
+                MatrixCoefficients::label,
+                MatrixCoefficients::as_str,
+            );
+            ui.label(RichText::new("ℹ").weak()).on_hover_ui(|ui| {
+                ui.style_mut().interaction.selectable_labels = true;
+                ui.label("Matrix coefficients, refer to the (SVT-AV1-PSY) user guide Appendix A.2 for full details. If you don't know what you're doing, just use the default option (2).");
+            });
+        });
+
+        ui.horizontal(|ui| {
+            let label_text = "Transfer Characteristics";
+            let label_width = ui.label(label_text).rect.max.x - ui.min_rect().min.x;
+            *max_width = max_width.max(label_width);
+            if label_width < *max_width {
+                ui.allocate_space(egui::vec2(*max_width - label_width, 1.0));
+            }
+            filterable_combo(
+                ui,
+                "transfer_characteristics_combobox",
+                &mut self.transfer_characteristics,
+                &TransferCharacteristics::ALL,
+                TransferCharacteristics::label,
+                TransferCharacteristics::as_str,
+            );
+            ui.label(RichText::new("ℹ").weak()).on_hover_ui(|ui| {
+                ui.style_mut().interaction.selectable_labels = true;
+                ui.label("Transfer characteristics, refer to the user guide Appendix A.2 for full details. If you don't know what you're doing, just use the default option (2).");
+            });
+        });
+        if let (Some(warning), Some((primaries, transfer, matrix))) =
+            (self.hdr_defaults_warning(), self.hdr_color_fix())
+        {
+            ui.horizontal(|ui| {
+                ui.colored_label(ui.visuals().warn_fg_color, format!("⚠ {}", warning));
+                if ui
+                    .button("Fix")
+                    .on_hover_text(format!(
+                        "Use the input's primaries ({}), transfer ({}) and matrix ({}), and turn on HDR signaling",
+                        primaries.as_str(),
+                        transfer.as_str(),
+                        matrix.as_str()
+                    ))
+                    .clicked()
+                {
+                    self.color_primaries = primaries;
+                    self.transfer_characteristics = transfer;
+                    self.matrix_coefficients = matrix;
+                    self.enable_hdr = true;
+                }
+            });
+        }
+        let color_problems = color_metadata_problems(
+            self.color_primaries,
+            self.transfer_characteristics,
+            self.matrix_coefficients,
+        );
+        if !color_problems.is_empty() {
+            for problem in &color_problems {
+                ui.colored_label(ui.visuals().warn_fg_color, format!("⚠ {}.", problem));
+            }
+            let hdr = self.transfer_characteristics == TransferCharacteristics::Smpte2084
+                || self.color_primaries == ColorPrimaries::Bt2020;
+            if hdr
+                && ui
+                    .button("Use BT.2020 PQ")
+                    .on_hover_text("Primaries (9), transfer (16) PQ, matrix (9) and HDR signaling, for HDR10 sources")
+                    .clicked()
+            {
+                self.color_primaries = ColorPrimaries::Bt2020;
+                self.transfer_characteristics = TransferCharacteristics::Smpte2084;
+                self.matrix_coefficients = MatrixCoefficients::Bt2020Ncl;
+                self.enable_hdr = true;
+            }
+        }
+
+        ui.horizontal(|ui| {
+            let label_text = "Color Range";
+            let label_width = ui.label(label_text).rect.max.x - ui.min_rect().min.x;
+            *max_width = max_width.max(label_width);
+            if label_width < *max_width {
+                ui.allocate_space(egui::vec2(*max_width - label_width, 1.0));
+            }
+            ComboBox::from_id_salt("color_range_combobox")
+                .selected_text(self.color_range.as_str())
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(
+                        &mut self.color_range,
+                        ColorRange::Studio,
+                        "(0) studio, default",
+                    );
+                    ui.selectable_value(
+                        &mut self.color_range,
+                        ColorRange::Full,
+                        "(1) full",
+                    );
+                });
+            ui.label(RichText::new("ℹ").weak()).on_hover_ui(|ui| {
+                ui.style_mut().interaction.selectable_labels = true;
+                ui.label("Color range. If you don't know whast you're doing, just go with the default option (0).");
+            });
+        });
+
+        ui.horizontal(|ui| {
+            let label_text = "Chroma Sample Position";
+            let label_width = ui.label(label_text).rect.max.x - ui.min_rect().min.x;
+            *max_width = max_width.max(label_width);
+            if label_width < *max_width {
+                ui.allocate_space(egui::vec2(*max_width - label_width, 1.0));
+            }
+            ComboBox::from_id_salt("chroma_sample_position_combobox")
+                .selected_text(self.chroma_sample_position.as_str())
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(
+                        &mut self.chroma_sample_position,
+                        ChromaSamplePosition::Unknown,
+                        "unknown, default",
+                    );
+                    ui.selectable_value(
+                        &mut self.chroma_sample_position,
+                        ChromaSamplePosition::Vertical,
+                        "vertical (left)",
+                    );
+                    ui.selectable_value(
+                        &mut self.chroma_sample_position,
+                        ChromaSamplePosition::Colocated,
+                        "colocated (top left)",
+                    );
+                });
+            ui.label(RichText::new("ℹ").weak()).on_hover_ui(|ui| {
+                ui.style_mut().interaction.selectable_labels = true;
+                ui.label("Where the chroma samples sit relative to the luma samples, so players line the colors up correctly. Filled in from the input when it's probed. Most 4:2:0 video is vertical (left), BT.2020 video is often colocated (top left).");
+            });
+        });
+
+        ui.horizontal(|ui| {
+            let label_text = "HDR Signaling";
+            let label_width = ui.label(label_text).rect.max.x - ui.min_rect().min.x;
+            *max_width = max_width.max(label_width);
+            if label_width < *max_width {
+                ui.allocate_space(egui::vec2(*max_width - label_width, 1.0));
+            }
+            ui.checkbox(&mut self.enable_hdr, "");
+            ui.label(RichText::new("ℹ").weak()).on_hover_ui(|ui| {
+                ui.style_mut().interaction.selectable_labels = true;
+                ui.label("Passes --enable-hdr 1, which marks the stream as HDR for SVT-AV1. Use it together with BT.2020 primaries and a PQ (SMPTE 2084) or HLG transfer for HDR sources. Off leaves the encoder's default. For still images, use Tune (4) Still Picture instead.");
+            });
+        });
+    }
+
+    // Encoder Settings controls, also used by the queue's job editor
+    fn encoder_settings_ui(&mut self, ui: &mut egui::Ui, max_width: &mut f32) {
+        ui.horizontal(|ui| {
+            let label_text = "Encoder";
+            let label_width = ui.label(label_text).rect.max.x - ui.min_rect().min.x;
+            *max_width = max_width.max(label_width);
+            if label_width < *max_width {
+                ui.allocate_space(egui::vec2(*max_width - label_width, 1.0));
+            }
+            ComboBox::from_id_salt("encoder_combobox")
+                .selected_text(self.encoder.name())
+                .show_ui(ui, |ui| {
+                    for encoder in [Encoder::SvtAv1, Encoder::Aom, Encoder::Rav1e] {
+                        ui.selectable_value(&mut self.encoder, encoder, encoder.name());
+                    }
+                });
+            ui.label(RichText::new("ℹ").weak()).on_hover_ui(|ui| {
+                ui.style_mut().interaction.selectable_labels = true;
+                ui.label("The AV1 encoder av1an runs. It has to be installed for av1an to find it. The quality and speed ranges below follow the encoder.");
+            });
+        });
+        if self.encoder != Encoder::SvtAv1 {
+            ui.colored_label(
+                ui.visuals().warn_fg_color,
+                format!(
+                    "⚠ Tune, tiles, quantization matrices, grain synthesis and the chroma sample position only apply to SVT-AV1, {} gets quality and speed only.",
+                    self.encoder.name()
+                ),
+            );
+        }
+
+        // Values carried over from another encoder are pulled into this one's ranges
+        let ranges = self.encoder.ranges();
+        self.preset = self.preset.clamp(ranges.speed.0, ranges.speed.1);
+        self.crf = self.crf.clamp(ranges.quality.0, ranges.quality.1);
+
+        ui.horizontal(|ui| {
+            let label_text = format!("*{}", ranges.speed_label);
+            let label_width = ui.label(label_text).rect.max.x - ui.min_rect().min.x;
+            *max_width = max_width.max(label_width);
+            if label_width < *max_width {
+                ui.allocate_space(egui::vec2(*max_width - label_width, 1.0));
+            }
+            // The slider and the number edit the same value, so they stay in sync
+            ui.add(
+                Slider::new(&mut self.preset, ranges.speed.0..=ranges.speed.1)
+                    .step_by(1.0)
+                    .show_value(false),
+            );
+            ui.add(
+                egui::DragValue::new(&mut self.preset)
+                    .range(ranges.speed.0..=ranges.speed.1)
+                    .speed(0.1)
+                    .fixed_decimals(0),
+            );
+            self.preset = self.preset.round();
+            ui.label(RichText::new("ℹ").weak()).on_hover_ui(|ui| {
+                ui.style_mut().interaction.selectable_labels = true;
+                match self.encoder {
+                    Encoder::SvtAv1 => ui.label("Encoding preset to use. A very simple explanation is that you trade quality for encoding speed, the lower you go. Can be set from a range of 0-13. Generally, the sweet spot will be between 2-4-6, of course, depending on how powerful your CPU is, you might want to go higher."),
+                    _ => ui.label(format!("How much effort the encoder puts in, lower is slower and better. Can be set from a range of {}-{}.", ranges.speed.0, ranges.speed.1)),
+                };
+            });
+        });
+
+        ui.horizontal(|ui| {
+            let label_text = "Rate Control";
+            let label_width = ui.label(label_text).rect.max.x - ui.min_rect().min.x;
+            *max_width = max_width.max(label_width);
+            if label_width < *max_width {
+                ui.allocate_space(egui::vec2(*max_width - label_width, 1.0));
+            }
+            ComboBox::from_id_salt("rate_control_combobox")
+                .selected_text(self.rate_control.as_str())
+                .show_ui(ui, |ui| {
+                    for rate_control in RateControl::ALL {
+                        ui.selectable_value(&mut self.rate_control, rate_control, rate_control.as_str());
+                    }
+                });
+            ui.label(RichText::new("ℹ").weak()).on_hover_ui(|ui| {
+                ui.style_mut().interaction.selectable_labels = true;
+                ui.label(format!("Constant quality encodes at the set {}, the file gets as large as the video needs. Target bitrate hits an average bitrate instead, for delivery targets that ask for one, and runs two passes so the bits go where they're needed (rav1e runs one).", ranges.quality_label));
+            });
+        });
+
+        if self.rate_control == RateControl::TargetBitrate {
+            ui.horizontal(|ui| {
+                let label_text = "*Target Bitrate";
+                let label_width = ui.label(label_text).rect.max.x - ui.min_rect().min.x;
+                *max_width = max_width.max(label_width);
+                if label_width < *max_width {
+                    ui.allocate_space(egui::vec2(*max_width - label_width, 1.0));
+                }
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.target_bitrate)
+                        .hint_text("e.g. 4000")
+                        .desired_width(60.0),
+                );
+                ui.label("kbps");
+                ui.label(RichText::new("ℹ").weak()).on_hover_ui(|ui| {
+                    ui.style_mut().interaction.selectable_labels = true;
+                    ui.label(match self.encoder {
+                        Encoder::SvtAv1 => "The average video bitrate in kbps, passed as --rc 1 --tbr. Audio and subtitles come on top.",
+                        Encoder::Aom => "The average video bitrate in kbps, passed as --end-usage=vbr --target-bitrate. Audio and subtitles come on top.",
+                        Encoder::Rav1e => "The average video bitrate in kbps, passed as --bitrate. Audio and subtitles come on top.",
+                    });
+                });
+            });
+            if let Some(problem) = self.rate_control_problem() {
+                ui.colored_label(ui.visuals().warn_fg_color, format!("⚠ {}", problem));
+            }
+        }
+
+        if self.rate_control == RateControl::Crf {
+            ui.horizontal(|ui| {
+                let label_text = format!("*{}", ranges.quality_label);
+                let label_width = ui.label(label_text).rect.max.x - ui.min_rect().min.x;
+                *max_width = max_width.max(label_width);
+                if label_width < *max_width {
+                    ui.allocate_space(egui::vec2(*max_width - label_width, 1.0));
+                }
+                ui.add(
+                    Slider::new(&mut self.crf, ranges.quality.0..=ranges.quality.1)
+                        .step_by(1.0)
+                        .show_value(false),
+                );
+                // Typed values can use the quarter steps the slider skips
+                ui.add(
+                    egui::DragValue::new(&mut self.crf)
+                        .range(ranges.quality.0..=ranges.quality.1)
+                        .speed(ranges.quality_step)
+                        .max_decimals(2),
+                );
+                self.crf = (self.crf / ranges.quality_step).round() * ranges.quality_step;
+                ui.label(RichText::new("ℹ").weak()).on_hover_ui(|ui| {
+                    ui.style_mut().interaction.selectable_labels = true;
+                    match self.encoder {
+                        Encoder::SvtAv1 => ui.label("Sets CRF value. A simple explanation is that you trade file size for quality, the lower you go. Can be set from a range of 0-70, can be set in quarter steps (0.25). Generally, the sweet spot will be between 27-23."),
+                        _ => ui.label(format!("The constant quality target. You trade file size for quality, the lower you go. Can be set from a range of {}-{}.", ranges.quality.0, ranges.quality.1)),
+                    };
+                });
+            });
+        }
+
+        if self.encoder == Encoder::SvtAv1 && self.rate_control == RateControl::Crf {
+            ui.horizontal(|ui| {
+                let label_text = "Max Bitrate | Buffer";
+                let label_width = ui.label(label_text).rect.max.x - ui.min_rect().min.x;
+                *max_width = max_width.max(label_width);
+                if label_width < *max_width {
+                    ui.allocate_space(egui::vec2(*max_width - label_width, 1.0));
+                }
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.max_bitrate)
+                        .hint_text("Off")
+                        .desired_width(60.0),
+                );
+                ui.label("kbps |");
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.buffer_size)
+                        .hint_text("Default")
+                        .desired_width(60.0),
+                );
+                ui.label("ms");
+                ui.label(RichText::new("ℹ").weak()).on_hover_ui(|ui| {
+                    ui.style_mut().interaction.selectable_labels = true;
+                    ui.label("Capped CRF: encodes at the CRF, but keeps the bitrate under the max bitrate (SVT-AV1's --mbr), e.g. for streaming or players with a bandwidth limit. The buffer (--buf-sz) is how many milliseconds the cap is averaged over; a larger one allows short peaks. Leave the max bitrate empty for plain CRF. Needs an SVT-AV1 build with --mbr, like SVT-AV1-PSY.");
+                });
+            });
+            if self.custom_params_mode == CustomParamsMode::Replace
+                && !self.max_bitrate.trim().is_empty()
+            {
+                ui.label(
+                    RichText::new("Ignored while the custom parameters replace the generated ones")
+                        .weak(),
+                );
+            } else if let Some(problem) = max_bitrate_problem(&self.max_bitrate, &self.buffer_size)
+            {
+                ui.colored_label(ui.visuals().warn_fg_color, format!("⚠ {}", problem));
+            } else if let Some(warning) = self.low_max_bitrate_warning() {
+                ui.colored_label(ui.visuals().warn_fg_color, format!("⚠ {}", warning));
+            }
+        }
+
+        ui.horizontal(|ui| {
+            let label_text = "Target Quality";
+            let label_width = ui.label(label_text).rect.max.x - ui.min_rect().min.x;
+            *max_width = max_width.max(label_width);
+            if label_width < *max_width {
+                ui.allocate_space(egui::vec2(*max_width - label_width, 1.0));
+            }
+            ui.add(
+                egui::TextEdit::singleline(&mut self.target_quality)
+                    .hint_text("Off")
+                    .desired_width(60.0),
+            );
+            ui.label(RichText::new("ℹ").weak()).on_hover_ui(|ui| {
+                ui.style_mut().interaction.selectable_labels = true;
+                ui.label(format!("Av1an's --target-quality, a VMAF score (e.g. 95) that Av1an hits by probing each chunk and picking its {} on its own. The {} above is ignored then. Needs an ffmpeg with libvmaf, see the FFmpeg Path in the Settings. Leave empty to encode at the set {}.", ranges.quality_label, ranges.quality_label, ranges.quality_label));
+            });
+        });
+        if !self.target_quality.trim().is_empty() {
+            ui.horizontal(|ui| {
+                let label_text = "Min Q | Max Q";
+                let label_width = ui.label(label_text).rect.max.x - ui.min_rect().min.x;
+                *max_width = max_width.max(label_width);
+                if label_width < *max_width {
+                    ui.allocate_space(egui::vec2(*max_width - label_width, 1.0));
+                }
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.min_q)
+                        .hint_text("Auto")
+                        .desired_width(40.0),
+                );
+                ui.label("|");
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.max_q)
+                        .hint_text("Auto")
+                        .desired_width(40.0),
+                );
+                ui.label(RichText::new("ℹ").weak()).on_hover_ui(|ui| {
+                    ui.style_mut().interaction.selectable_labels = true;
+                    ui.label(format!("Av1an's --min-q and --max-q, the lowest and highest {} the target quality search may pick. Keeps it from wasting bits on easy scenes or starving hard ones. Leave empty for Av1an's defaults.", ranges.quality_label));
+                });
+            });
+            if let Some(problem) =
+                self.encoder
+                    .target_quality_problem(&self.target_quality, &self.min_q, &self.max_q)
+            {
+                ui.colored_label(ui.visuals().warn_fg_color, format!("⚠ {}", problem));
+            }
+        }
+
+        ui.horizontal(|ui| {
+            let label_text = "Tune";
+            let label_width = ui.label(label_text).rect.max.x - ui.min_rect().min.x;
+            *max_width = max_width.max(label_width);
+            if label_width < *max_width {
+                ui.allocate_space(egui::vec2(*max_width - label_width, 1.0));
+            }
+            ComboBox::from_id_salt("tune_combobox")
+                .selected_text(self.tune.as_str())
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.tune, Tune::Vq, "(0) VQ");
+                    ui.selectable_value(&mut self.tune, Tune::Psnr, "(1) PSNR");
+                    ui.selectable_value(&mut self.tune, Tune::Ssim, "(2) SSIM, default");
+                    ui.selectable_value(
+                        &mut self.tune,
+                        Tune::SubjectiveSsim,
+                        "(3) Subjective SSIM (SVT-AV1-PSY)",
+                    );
+                    ui.selectable_value(
+                        &mut self.tune,
+                        Tune::StillPicture,
+                        "(4) Still Picture (SVT-AV1-PSY)",
+                    );
+                });
+            ui.label(RichText::new("ℹ").weak()).on_hover_ui(|ui| {
+                ui.style_mut().interaction.selectable_labels = true;
+                ui.label("What the encoder optimizes for. VQ favors how the video looks to people, PSNR and SSIM favor the respective metrics. If you don't know what you're doing, just use the default option (2).");
+            });
+        });
+
+        ui.horizontal(|ui| {
+            let label_text = "Passes";
+            let label_width = ui.label(label_text).rect.max.x - ui.min_rect().min.x;
+            *max_width = max_width.max(label_width);
+            if label_width < *max_width {
+                ui.allocate_space(egui::vec2(*max_width - label_width, 1.0));
+            }
+            let fixed = self.encode_passes() != self.passes;
+            ui.add_enabled_ui(!fixed, |ui| {
+                ComboBox::from_id_salt("passes_combobox")
+                    .selected_text(self.encode_passes().to_string())
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut self.passes, 1, "1");
+                        ui.selectable_value(&mut self.passes, 2, "2");
+                    });
+            })
+            .response
+            .on_disabled_hover_text("The target bitrate always runs two passes");
+            ui.label(RichText::new("ℹ").weak()).on_hover_ui(|ui| {
+                ui.style_mut().interaction.selectable_labels = true;
+                ui.label("Number of encoding passes Av1an runs per chunk. Two passes can give better rate control in bitrate-targeted (VBR) encodes, at roughly twice the encoding time. With CRF a single pass is all you need. The Target bitrate rate control always uses two.");
+            });
+        });
+        if let Some(warning) = self.wasted_pass_warning() {
+            ui.colored_label(ui.visuals().warn_fg_color, format!("⚠ {}", warning));
+        }
+
+        ui.horizontal(|ui| {
+            let label_text = "Tile Columns";
+            let label_width = ui.label(label_text).rect.max.x - ui.min_rect().min.x;
+            *max_width = max_width.max(label_width);
+            if label_width < *max_width {
+                ui.allocate_space(egui::vec2(*max_width - label_width, 1.0));
+            }
+            ui.add(egui::DragValue::new(&mut self.tile_columns).range(0..=4));
+            ui.label(RichText::new(format!("{} columns", 1 << self.tile_columns)).weak());
+            ui.label(RichText::new("ℹ").weak()).on_hover_ui(|ui| {
+                ui.style_mut().interaction.selectable_labels = true;
+                ui.label("Number of tile columns as a power of two (0 = 1 column, 2 = 4 columns). Tiles can be encoded and decoded in parallel, which helps weaker players with high resolution video, but every tile boundary costs a little compression efficiency. Leave it at 0 unless playback needs it; Av1an already parallelizes encoding through its workers.");
+            });
+        });
+
+        ui.horizontal(|ui| {
+            let label_text = "Tile Rows";
+            let label_width = ui.label(label_text).rect.max.x - ui.min_rect().min.x;
+            *max_width = max_width.max(label_width);
+            if label_width < *max_width {
+                ui.allocate_space(egui::vec2(*max_width - label_width, 1.0));
+            }
+            ui.add(egui::DragValue::new(&mut self.tile_rows).range(0..=6));
+            ui.label(RichText::new(format!("{} rows", 1 << self.tile_rows)).weak());
+            ui.label(RichText::new("ℹ").weak()).on_hover_ui(|ui| {
+                ui.style_mut().interaction.selectable_labels = true;
+                ui.label("Number of tile rows as a power of two (0 = 1 row). Same tradeoff as the tile columns: faster multi-threaded decoding, slightly lower compression efficiency.");
+            });
+        });
+
+        ui.horizontal(|ui| {
+            let label_text = "Quantization Matrices";
+            let label_width = ui.label(label_text).rect.max.x - ui.min_rect().min.x;
+            *max_width = max_width.max(label_width);
+            if label_width < *max_width {
+                ui.allocate_space(egui::vec2(*max_width - label_width, 1.0));
+            }
+            ui.checkbox(&mut self.enable_qm, "Enable");
+            ui.add_enabled_ui(self.enable_qm, |ui| {
+                ui.label("Min");
+                let min_changed = ui
+                    .add(Slider::new(&mut self.qm_min, 0..=15))
+                    .changed();
+                ui.label("Max");
+                ui.add(Slider::new(&mut self.qm_max, 0..=15));
+                // Keep min ≤ max by moving whichever one wasn't just dragged
+                if self.qm_min > self.qm_max {
+                    if min_changed {
+                        self.qm_max = self.qm_min;
+                    } else {
+                        self.qm_min = self.qm_max;
+                    }
+                }
+            });
+            ui.label(RichText::new("ℹ").weak()).on_hover_ui(|ui| {
+                ui.style_mut().interaction.selectable_labels = true;
+                ui.label("Quantization matrices quantize fine detail (high frequencies) more coarsely than coarse detail, which usually looks better at the same size. Min and max set the range of matrix levels the encoder picks from, lower levels are flatter. Sent as --enable-qm 1 --qm-min and --qm-max.");
+            });
+        });
+
+        ui.horizontal(|ui| {
+            let label_text = "Grain Synthesis";
+            let label_width = ui.label(label_text).rect.max.x - ui.min_rect().min.x;
+            *max_width = max_width.max(label_width);
+            if label_width < *max_width {
+                ui.allocate_space(egui::vec2(*max_width - label_width, 1.0));
+            }
+            ComboBox::from_id_salt("grain_synthesis_combobox")
+                .selected_text(self.grain_synthesis.as_str())
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(
+                        &mut self.grain_synthesis,
+                        GrainSynthesis::Synthetic,
+                        "Synthetic",
+                    );
+                    ui.selectable_value(
+                        &mut self.grain_synthesis,
+                        GrainSynthesis::PhotonNoise,
+                        "Photon Noise",
+                    );
+                    ui.selectable_value(
+                        &mut self.grain_synthesis,
+                        GrainSynthesis::GrainTable,
+                        "Grain Table",
+                    );
+                });
+            ui.label(RichText::new("ℹ").weak()).on_hover_ui(|ui| {
+                ui.style_mut().interaction.selectable_labels = true;
+                ui.label("Where the film grain comes from. Synthetic uses SVT-AV1's own grain strength, Photon Noise has Av1an generate grain modelled on a camera ISO, and Grain Table applies a pre-made grain table file. Only one of them can be used at a time.");
+            });
+        });
+
+        match self.grain_synthesis {
+            GrainSynthesis::Synthetic => {
+                ui.horizontal(|ui| {
+                    let label_text = "*Synthetic Grain";
+                    let label_width = ui.label(label_text).rect.max.x - ui.min_rect().min.x;
+                    *max_width = max_width.max(label_width);
+                    if label_width < *max_width {
+                        ui.allocate_space(egui::vec2(*max_width - label_width, 1.0));
+                    }
+                    ui.add_sized(
+                        [100.0, 20.0],
+                        egui::TextEdit::singleline(&mut self.synthetic_grain),
+                    );
+                    ui.label(RichText::new("ℹ").weak()).on_hover_ui(|ui| {
+                        ui.style_mut().interaction.selectable_labels = true;
+                        ui.label("Sets the strength of the synthetic grain applied to the video.");
+                    });
+                });
+            }
+            GrainSynthesis::PhotonNoise => {
+                ui.horizontal(|ui| {
+                    let label_text = "*Photon Noise (ISO)";
+                    let label_width = ui.label(label_text).rect.max.x - ui.min_rect().min.x;
+                    *max_width = max_width.max(label_width);
+                    if label_width < *max_width {
+                        ui.allocate_space(egui::vec2(*max_width - label_width, 1.0));
+                    }
+                    ui.add_sized(
+                        [100.0, 20.0],
+                        egui::TextEdit::singleline(&mut self.photon_noise),
+                    );
+                    ui.label(RichText::new("ℹ").weak()).on_hover_ui(|ui| {
+                        ui.style_mut().interaction.selectable_labels = true;
+                        ui.label("Generates a grain table resembling the sensor noise of a camera at this ISO value. Higher values give stronger grain, somewhere between 100 and 6400 is a good range to try. Av1an takes it as a strength from 0 to 64, the ISO divided by 100.");
+                    });
+                });
+                if let Some(problem) = photon_noise_problem(&self.photon_noise)
+                    .or_else(|| photon_noise_warning(&self.photon_noise))
+                {
+                    ui.colored_label(ui.visuals().warn_fg_color, format!("⚠ {}.", problem));
+                }
+                ui.horizontal(|ui| {
+                    let label_text = "Photon Noise Size";
+                    let label_width = ui.label(label_text).rect.max.x - ui.min_rect().min.x;
+                    *max_width = max_width.max(label_width);
+                    if label_width < *max_width {
+                        ui.allocate_space(egui::vec2(*max_width - label_width, 1.0));
+                    }
+                    let (width_hint, height_hint) = match self.output_resolution() {
+                        Some((width, height)) => (width.to_string(), height.to_string()),
+                        None => (String::from("Output"), String::from("Output")),
+                    };
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.photon_noise_width)
+                            .hint_text(width_hint)
+                            .desired_width(50.0),
+                    );
+                    ui.label("x");
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.photon_noise_height)
+                            .hint_text(height_hint)
+                            .desired_width(50.0),
+                    );
+                    ui.label(RichText::new("ℹ").weak()).on_hover_ui(|ui| {
+                        ui.style_mut().interaction.selectable_labels = true;
+                        ui.label("Av1an's --photon-noise-width and --photon-noise-height, the resolution the grain is modelled for. Grain made for the wrong size looks too coarse or too fine, so leave them empty to use the output size, or set them to encode grain for a different size, e.g. the one the video will be watched at.");
+                    });
+                });
+                if let Some(problem) =
+                    photon_noise_size_problem(&self.photon_noise_width, &self.photon_noise_height)
+                {
+                    ui.colored_label(ui.visuals().warn_fg_color, format!("⚠ {}.", problem));
+                }
+            }
+            GrainSynthesis::GrainTable => {
+                ui.horizontal(|ui| {
+                    let label_text = "*Grain Table";
+                    let label_width = ui.label(label_text).rect.max.x - ui.min_rect().min.x;
+                    *max_width = max_width.max(label_width);
+                    if label_width < *max_width {
+                        ui.allocate_space(egui::vec2(*max_width - label_width, 1.0));
+                    }
+                    ui.add_sized(
+                        [500.0, 20.0],
+                        egui::TextEdit::singleline(&mut self.grain_table),
+                    );
+                    if ui.button("Browse").clicked() {
+                        if let Some(path) = FileDialog::new().pick_file() {
+                            self.grain_table = path.display().to_string();
+                        }
+                    }
+                    if ui.button("Generate…").clicked() {
+                        self.show_grain_window = true;
+                    }
+                    ui.label(RichText::new("ℹ").weak()).on_hover_ui(|ui| {
+                        ui.style_mut().interaction.selectable_labels = true;
+                        ui.label("Full path to a film grain table file, passed to SVT-AV1 with --fgs-table.");
+                    });
+                });
+                if !expand_path(&self.grain_table).is_file() {
+                    ui.colored_label(
+                        ui.visuals().warn_fg_color,
+                        "⚠ The grain table file doesn't exist.",
+                    );
+                } else if let Some(warning) = self.grain_resolution_warning() {
+                    ui.colored_label(ui.visuals().warn_fg_color, warning);
+                }
+            }
+        }
+
+        ui.horizontal(|ui| {
+            let label_text = "Custom Encoder Parameters";
+            let label_width = ui.label(label_text).rect.max.x - ui.min_rect().min.x;
+            *max_width = max_width.max(label_width);
+            if label_width < *max_width {
+                ui.allocate_space(egui::vec2(*max_width - label_width, 1.0));
+            } else {
+                ui.allocate_space(egui::vec2(0.5, 1.0));
+            }
+            ui.add_sized(
+                [500.0, 20.0],
+                egui::TextEdit::singleline(&mut self.custom_encode_params),
+            );
+            ComboBox::from_id_salt("custom_params_mode_combobox")
+                .selected_text(self.custom_params_mode.as_str())
+                .show_ui(ui, |ui| {
+                    for mode in [CustomParamsMode::Append, CustomParamsMode::Replace] {
+                        ui.selectable_value(&mut self.custom_params_mode, mode, mode.as_str());
+                    }
+                });
+            if ui
+                .button("📋")
+                .on_hover_text("Copy the encoder parameters passed to Av1an with -v")
+                .clicked()
+            {
+                match encoder_params(self) {
+                    Ok(params) => ui.ctx().copy_text(params),
+                    Err(problem) => self.log.push(format!("Nothing copied: {}", problem)),
+                }
+            }
+            ui.label(RichText::new("ℹ").weak()).on_hover_ui(|ui| {
+                ui.style_mut().interaction.selectable_labels = true;
+                ui.label("Provides SVT-AV1-PSY custom encoder parameters. Append adds them after the parameters the settings above generate, and a flag set in both gets the custom value. Replace entirely passes only these and ignores the settings above. Quote values that contain spaces.");
+            });
+        });
+        match encoder_params(self) {
+            Ok(params) if !self.custom_encode_params.trim().is_empty() => {
+                ui.horizontal(|ui| {
+                    ui.style_mut().interaction.selectable_labels = true;
+                    ui.label(RichText::new("Effective parameters:").weak());
+                    ui.label(RichText::new(params).monospace().weak());
+                });
+            }
+            Ok(_) => {}
+            // Encoding without them isn't what the user asked for, so nothing starts
+            Err(problem) => {
+                ui.colored_label(ui.visuals().warn_fg_color, format!("⚠ {}.", problem));
+            }
+        }
+        if let Some(output) = &self.svtav1_version {
+            let params = encoder_params(self)
+                .ok()
+                .and_then(|params| split_args(&params).ok())
+                .unwrap_or_default();
+            for (option, reason) in EncoderVersion::parse(output).unsupported(&params) {
+                ui.colored_label(
+                    ui.visuals().warn_fg_color,
+                    format!(
+                        "⚠ The installed encoder ({}) likely doesn't support {}, it {}.",
+                        output, option, reason
+                    ),
+                );
+            }
+        }
+        let unknown = self.unknown_encoder_flags();
+        if !unknown.is_empty() {
+            ui.colored_label(
+                ui.visuals().warn_fg_color,
+                format!(
+                    "⚠ The installed SvtAv1EncApp doesn't list {}. It stops on options it doesn't know, check for typos.",
+                    unknown.join(", ")
+                ),
+            );
+        }
+        let conflicts = conflicting_params(&self.custom_encode_params);
+        if self.custom_params_mode == CustomParamsMode::Append && !conflicts.is_empty() {
+            ui.colored_label(
+                ui.visuals().warn_fg_color,
+                format!(
+                    "⚠ Custom Encoder Parameters also set {}, which the settings above control too. The custom value is what the encoder gets.",
+                    conflicts.join(", "),
+                ),
+            );
+        }
+    }
+
+    fn show_job_editor_window(&mut self, ctx: &egui::Context) {
+        let Some((id, settings)) = &mut self.job_editor else {
+            return;
+        };
+        let id = *id;
+
+        let mut open = true;
+        let mut apply = false;
+        let mut cancel = false;
+        egui::Window::new(format!("Edit Job {}", id))
+            .open(&mut open)
+            .default_height(600.0)
+            .show(ctx, |ui| {
+                ui.label(RichText::new(&settings.input_file).weak());
+                if ui.button("Load Preset").clicked() {
+                    if let Some(path) = FileDialog::new()
+                        .add_filter("Presets", &["yaml", "yml", "json"])
+                        .pick_file()
+                    {
+                        if let Err(e) = settings.load_preset_from_file(&path.display().to_string())
+                        {
+                            self.job_editor_error = Some(format!("{}: {}", path.display(), e));
+                        }
+                    }
+                }
+                if let Some(error) = &self.job_editor_error {
+                    ui.colored_label(ui.visuals().error_fg_color, error);
+                }
+                ui.separator();
+
+                egui::ScrollArea::vertical()
+                    .max_height(500.0)
+                    .show(ui, |ui| {
+                        let mut max_width = 0.0;
+                        ui.label(RichText::new("Video Settings").weak());
+                        settings.video_settings_ui(ui, &mut max_width);
+                        ui.add_space(ui.spacing().item_spacing.y * 2.0);
+                        ui.label(RichText::new("Encoder Settings").weak());
+                        settings.encoder_settings_ui(ui, &mut max_width);
+                    });
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    apply = ui.button("Apply").clicked();
+                    cancel = ui.button("Cancel").clicked();
+                });
+            });
+
+        if apply {
+            match self.queue.jobs.iter_mut().find(|job| job.id == id) {
+                Some(job) if job.is_editable() => {
+                    job.settings = settings.to_preset();
+                    job.grain_table = settings.grain_table.clone();
+                    self.job_editor = None;
+                    self.job_editor_error = None;
+                }
+                Some(job) => {
+                    self.job_editor_error = Some(job.edit_blocked_reason().to_string());
+                }
+                None => {
+                    self.job_editor_error =
+                        Some(String::from("This job was removed from the queue."));
+                }
+            }
+        } else if cancel || !open {
+            self.job_editor = None;
+            self.job_editor_error = None;
+        }
+    }
+
+    // Writes the queue to the config directory whenever it changed, so a crash doesn't lose it
+    fn save_queue(&mut self) {
+        // Don't overwrite the last session's queue before the user chose whether to restore it
+        if self.restorable_queue.is_some() || !std::mem::take(&mut self.queue_dirty) {
+            return;
+        }
+
+        let Some(path) = queue_file_path() else {
+            return;
+        };
+        let json = match self.queue.to_json() {
+            Ok(json) => json,
+            Err(e) => {
+                eprintln!("ERROR : Couldn't serialize the queue: {}", e);
+                return;
+            }
+        };
+        if json == self.saved_queue_json {
+            return;
+        }
+
+        let result = match path.parent() {
+            Some(dir) => std::fs::create_dir_all(dir),
+            None => Ok(()),
+        }
+        .and_then(|_| std::fs::write(&path, &json));
+        if let Err(e) = result {
+            eprintln!("ERROR : Couldn't save the queue to {:?}: {}", path, e);
+        }
+        self.saved_queue_json = json;
+    }
+
+    // The settings as the autosave writes them, also what undo and redo go back and forth between
+    fn session_json(&self) -> Option<String> {
+        match serde_json::to_string_pretty(&self.session_state()) {
+            Ok(json) => Some(json),
+            Err(e) => {
+                eprintln!("ERROR : Couldn't serialize the session: {}", e);
+                None
+            }
+        }
+    }
+
+    // Writes the fields to session.json once they stayed unchanged for a moment, so typing
+    // doesn't write on every key
+    fn autosave_session(&mut self, ctx: &egui::Context, json: &str) {
+        // Don't overwrite the last session before the user chose whether to restore it
+        if self.restorable_session.is_some() || self.session_lock.is_none() {
+            return;
+        }
+
+        let json = json.to_string();
+        if json == self.saved_session_json {
+            self.pending_session = None;
+            return;
+        }
+        match &self.pending_session {
+            Some((pending, since)) if *pending == json => {
+                if since.elapsed() >= SESSION_IDLE {
+                    save_session(&json);
+                    self.saved_session_json = json;
+                    self.pending_session = None;
+                }
+            }
+            _ => {
+                self.pending_session = Some((json, Instant::now()));
+                ctx.request_repaint_after(SESSION_IDLE);
+            }
+        }
+    }
+
+    fn show_config_error_window(&mut self, ctx: &egui::Context) {
+        let Some(error) = &self.config_error else {
+            return;
+        };
+
+        let mut dismiss = false;
+        egui::Window::new("Config Error")
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.colored_label(ui.visuals().error_fg_color, &error.message);
+                match &error.backup {
+                    Some(backup) => ui.label(format!(
+                        "The app settings are back to their defaults. The old file was copied to {}.",
+                        backup.display()
+                    )),
+                    None => ui.label(
+                        "The app settings are back to their defaults. The file couldn't be backed up, so changes to them won't be saved until AV1Studio restarts.",
+                    ),
+                };
+                ui.add_space(ui.spacing().item_spacing.y * 2.0);
+                dismiss = ui.button("OK").clicked();
+            });
+
+        if dismiss {
+            self.config_error = None;
+        }
+    }
+
+    fn show_restore_session_window(&mut self, ctx: &egui::Context) {
+        let Some(session) = &self.restorable_session else {
+            return;
+        };
+
+        let mut restore = false;
+        let mut discard = false;
+        egui::Window::new("Restore Session")
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label("AV1Studio didn't exit cleanly last time. Restore the files and settings it had?");
+                let input = session.settings.input_file.as_deref().unwrap_or_default();
+                if !input.is_empty() {
+                    ui.label(RichText::new(format!("Input: {}", input)).weak());
+                }
+                ui.label(RichText::new("Nothing starts encoding on its own.").weak());
+                ui.add_space(ui.spacing().item_spacing.y * 2.0);
+                ui.horizontal(|ui| {
+                    restore = ui.button("Restore").clicked();
+                    discard = ui.button("Discard").clicked();
+                });
+            });
+
+        if restore {
+            if let Some(session) = self.restorable_session.take() {
+                self.apply_session_state(session);
+            }
+        } else if discard {
+            self.restorable_session = None;
+        }
+    }
+
+    fn show_restore_queue_window(&mut self, ctx: &egui::Context) {
+        let Some(jobs) = &self.restorable_queue else {
+            return;
+        };
+
+        let unfinished = jobs.iter().filter(|job| !job.status.is_finished()).count();
+        let interrupted = jobs
+            .iter()
+            .filter(|job| job.status == JobStatus::Interrupted)
+            .count();
+
+        let mut restore = false;
+        let mut discard = false;
+        egui::Window::new("Restore Queue")
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "The last session left {} jobs in the queue, {} of them not started yet.",
+                    jobs.len(),
+                    unfinished
+                ));
+                if interrupted > 0 {
+                    ui.colored_label(
+                        ui.visuals().warn_fg_color,
+                        format!(
+                            "⚠ {} jobs were interrupted while encoding, they can be resumed from the queue.",
+                            interrupted
+                        ),
+                    );
+                }
+                ui.add_space(ui.spacing().item_spacing.y * 2.0);
+                ui.horizontal(|ui| {
+                    restore = ui.button("Restore").clicked();
+                    discard = ui.button("Discard").clicked();
+                });
+            });
+
+        if restore {
+            if let Some(jobs) = self.restorable_queue.take() {
+                self.queue.restore(jobs);
+                self.show_queue_window = true;
+            }
+        } else if discard {
+            self.restorable_queue = None;
+        }
+    }
+
+    #[cfg(feature = "status-server")]
+    fn update_status_server(&mut self) {
+        self.status_server.update(&self.status_server_settings);
+        // The server listens on every interface by default, so these are worth seeing
+        let rejected: Vec<RejectedRequest> = self.status_server.rejected().collect();
+        for (path, source) in rejected {
+            self.log
+                .push(format!("Rejected unauthorized {} from {}", path, source));
+        }
+        let commands: Vec<RemoteRequest> = self.status_server.commands().collect();
+        self.queue_dirty |= !commands.is_empty();
+        for (command, source) in commands {
+            self.log
+                .push(format!("Remote {} from {}", command.as_str(), source));
+            match command {
+                RemoteCommand::StartEncode if self.encoding_in_progress => {
+                    self.log.push(String::from("Already encoding, ignored"))
+                }
+                RemoteCommand::StartEncode => match self.start_problem() {
+                    Some(problem) => self.log.push(format!("Not started: {}", problem)),
+                    // Nobody's at the machine to confirm
+                    None => self.start_remote_encode(),
+                },
+                RemoteCommand::CancelEncode if self.encoding_in_progress => self.cancel_encoding(),
+                // The queue's jobs aren't what "cancel encode" is about
+                RemoteCommand::CancelEncode => self
+                    .log
+                    .push(String::from("Nothing encoding outside the queue, ignored")),
+                RemoteCommand::PauseQueue => self.queue.running = false,
+                RemoteCommand::ResumeQueue => self.queue.running = true,
+            }
+        }
+
+        if self.status_server.is_running() {
+            self.status_server.publish(status_json(
+                self.encode_session.as_ref(),
+                &self.input_file,
+                &self.output_file,
+                &self.queue,
+            ));
+        }
+    }
+
+    // Asks first, unless the user turned that off
+    fn start_encoding(&mut self) {
+        // The button is disabled while encoding, this catches whatever gets past it
+        if self.encoding_in_progress {
+            return;
+        }
+        if self.confirm_start {
+            self.start_confirmation = Some(false);
+        } else {
+            self.start_encoding_now();
+        }
+    }
+
+    // Asks first when the output or temp folder looks short on space
+    fn start_encoding_now(&mut self) {
+        let output_dir = output_dir_for(&self.output_file);
+        self.start_error = None;
+        if !output_dir.is_dir() {
+            self.missing_output_dir = Some(output_dir);
+            return;
+        }
+        if let Some(problem) = output_dir_problem(&output_dir) {
+            self.start_error = Some(problem);
+            return;
+        }
+        match self.disk_space_check(output_dir) {
+            Some(low) => self.disk_space_warning = Some(low),
+            None => self.spawn_encode(),
+        }
+    }
+
+    // Nobody's at the machine to create a missing output folder
+    #[cfg(feature = "status-server")]
+    fn start_remote_encode(&mut self) {
+        let output_dir = output_dir_for(&self.output_file);
+        if let Some(problem) = output_dir_problem(&output_dir) {
+            self.log.push(format!("Not started: {}", problem));
+            return;
+        }
+        // Or to start anyway on low disk space, so that only gets logged
+        if let Some(low) = self.disk_space_check(output_dir) {
+            for (folder, _) in low.folders {
+                self.log.push(format!(
+                    "Less free space in {} than the input's size",
+                    folder.display()
+                ));
+            }
+        }
+        self.spawn_encode();
+    }
+
+    fn disk_space_check(&self, output_dir: PathBuf) -> Option<LowDiskSpace> {
+        let temp_dir = self.temp_folder();
+        let mut folders = vec![output_dir];
+        if !folders.contains(&temp_dir) {
+            folders.push(temp_dir);
+        }
+        low_disk_space(&expand_path(&self.input_file), &folders)
+    }
+
+    fn spawn_encode(&mut self) {
+        // A second av1an would replace the running session and leave the first one orphaned
+        if self.encoding_in_progress || self.encode_session.is_some() {
+            self.log
+                .push(String::from("Already encoding, ignored the second start"));
+            return;
+        }
+        let cmd = match generate_command(self) {
+            Ok(cmd) => cmd,
+            Err(problem) => {
+                self.start_error = Some(problem);
+                return;
+            }
+        };
+        let command = command_to_string(&cmd);
+        println!("{}", command);
+        self.log.push(command.clone());
+        self.recent.add_input(
+            &self.input_file,
+            &self.output_file,
+            Some(self.session_state()),
+        );
+        save_recent(&self.recent);
+        // Taken even without write_report, the history is built from it
+        self.pending_report = Some(EncodeReport::start(command, self.session_state()));
+
+        self.start_error = None;
+        match EncodeSession::start(cmd) {
+            Ok(session) => {
+                self.encode_session = Some(session);
+                self.encoding_in_progress = true;
+                self.out_of_memory_retry = None;
+                self.temp_usage.start();
+                self.encoded_frames = None;
+                self.total_frames = None;
+                self.provisional_total_frames = self
+                    .media_info
+                    .as_ref()
+                    .and_then(|info| info.frame_count)
+                    .map(|frames| self.deinterlace.output_frames(frames) as u32);
+                self.displayed_progress = 0.0;
+                self.fps_samples.clear();
+                self.notifier.send(
+                    &self.notifications,
+                    NotifyEvent::JobStarted,
+                    JobReport::new(&self.output_file, None, "running"),
+                );
+            }
+            Err(e) => {
+                let failure = start_failure(&e);
+                eprintln!("ERROR : {}", failure);
+                self.log.push(failure.clone());
+                self.start_error = Some(failure);
+                self.encoding_in_progress = false;
+                self.pending_report = None;
+            }
+        }
+    }
+
+    // The -w av1an gets, or what it most likely picks on its own with Auto
+    fn current_workers(&self) -> usize {
+        self.workers
+            .trim()
+            .parse()
+            .ok()
+            .filter(|&workers| !self.auto_workers && workers > 0)
+            .unwrap_or_else(num_cpus::get_physical)
+    }
+
+    fn cancel_encoding(&mut self) {
+        if let Some(mut session) = self.encode_session.take() {
+            session.cancel();
+            self.log.push(String::from("Encoding cancelled"));
+            self.remove_joined_input();
+        }
+        self.encoding_in_progress = false;
+        self.eta_time = None;
+        self.displayed_progress = 0.0;
+    }
+
+    // Shows the overall progress of everything that's encoding in the window title
+    fn update_title(&mut self, ctx: &egui::Context) {
+        let (mut encoded, mut total) = self.queue.progress();
+        if let Some(session) = &self.encode_session {
+            encoded += session.encoded_frames.unwrap_or_default();
+            total += session.total_frames.unwrap_or_default();
+        }
+
+        let busy = self.encode_session.is_some() || self.queue.active_count() > 0;
+        let percentage = (busy && total > 0).then(|| encoded as u64 * 100 / total as u64);
+        if percentage != self.title_percentage {
+            self.title_percentage = percentage;
+            let title = match percentage {
+                Some(percentage) => format!("AV1Studio - {}%", percentage),
+                None => String::from("AV1Studio"),
+            };
+            ctx.send_viewport_cmd(egui::ViewportCommand::Title(title));
+        }
+    }
+
+    // The full av1an command, with the extra arguments highlighted
+    fn show_command_window(&mut self, ctx: &egui::Context) {
+        if !self.show_command_window {
+            return;
+        }
+
+        let command = generate_command(self);
+        let mut args: Vec<String> = match &command {
+            Ok(cmd) => std::iter::once(cmd.get_program())
+                .chain(cmd.get_args())
+                .map(|arg| shell_words::quote(&arg.to_string_lossy()).to_string())
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+        let extra_count = split_args(&self.extra_av1an_args).map_or(0, |args| args.len());
+        let extra = args.split_off(args.len().saturating_sub(extra_count));
+        let environment = environment_overrides(self);
+        let unknown = self.unknown_encoder_flags();
+
+        let mut copy = false;
+        egui::Window::new("Command Preview")
+            .open(&mut self.show_command_window)
+            .default_width(700.0)
+            .show(ctx, |ui| {
+                ui.horizontal_wrapped(|ui| {
+                    ui.spacing_mut().item_spacing.x = 0.0;
+                    let space = ui.fonts(|f| {
+                        f.glyph_width(&egui::TextStyle::Monospace.resolve(ui.style()), ' ')
+                    });
+                    for arg in &args {
+                        ui.label(RichText::new(arg).monospace());
+                        ui.add_space(space);
+                    }
+                    for arg in &extra {
+                        ui.label(
+                            RichText::new(arg)
+                                .monospace()
+                                .color(ui.visuals().warn_fg_color),
+                        )
+                        .on_hover_text("From Extra Av1an Arguments");
+                        ui.add_space(space);
+                    }
+                });
+                if let Err(problem) = &command {
+                    ui.colored_label(ui.visuals().error_fg_color, format!("⚠ {}", problem));
+                }
+                if !unknown.is_empty() {
+                    ui.colored_label(
+                        ui.visuals().warn_fg_color,
+                        format!(
+                            "⚠ Not listed by the installed SvtAv1EncApp: {}",
+                            unknown.join(", ")
+                        ),
+                    );
+                }
+                if !environment.is_empty() {
+                    ui.separator();
+                    ui.label(RichText::new("Environment").weak());
+                    for (key, value) in &environment {
+                        let text = if value.is_empty() {
+                            format!("{} (removed)", key)
+                        } else {
+                            format!("{}={}", key, value)
+                        };
+                        ui.label(RichText::new(text).monospace());
+                    }
+                }
+                ui.separator();
+                if ui
+                    .add_enabled(command.is_ok(), egui::Button::new("Copy"))
+                    .on_hover_text(ctx.format_shortcut(&COPY_COMMAND_SHORTCUT))
+                    .clicked()
+                {
+                    copy = true;
+                }
+            });
+        if copy {
+            self.copy_command(ctx);
+        }
+    }
+
+    // Options in the encoder parameters the installed SvtAv1EncApp doesn't list in its --help.
+    // Nothing for the other encoders, or when SvtAv1EncApp couldn't be asked.
+    fn unknown_encoder_flags(&mut self) -> Vec<String> {
+        if self.encoder != Encoder::SvtAv1 {
+            return Vec::new();
+        }
+        let params = encoder_params(self)
+            .ok()
+            .and_then(|params| split_args(&params).ok())
+            .unwrap_or_default();
+        match self.svtav1_options.get(std::path::Path::new(SVTAV1_PATH)) {
+            Some(options) => unknown_flags(&params, options),
+            None => Vec::new(),
+        }
+    }
+
+    // The command as one shell line, prefixed with env(1) so it runs with the same overrides
+    fn copy_command(&mut self, ctx: &egui::Context) {
+        match generate_command(self) {
+            Ok(cmd) => ctx.copy_text(command_to_string(&cmd)),
+            Err(problem) => self.log.push(format!("Nothing copied: {}", problem)),
+        }
+    }
+
+    // The cap only goes with SVT-AV1's CRF mode, and only into generated parameters
+    fn max_bitrate_applies(&self) -> bool {
+        self.encoder == Encoder::SvtAv1
+            && self.rate_control == RateControl::Crf
+            && self.custom_params_mode != CustomParamsMode::Replace
+    }
+
+    fn low_max_bitrate_warning(&self) -> Option<String> {
+        if !self.max_bitrate_applies() {
+            return None;
+        }
+        let kbps = self.max_bitrate.trim().parse().ok()?;
+        max_bitrate_warning(kbps, self.output_resolution()?)
+    }
+
+    // Why the bitrate mode can't run as set
+    fn rate_control_problem(&self) -> Option<String> {
+        if self.rate_control != RateControl::TargetBitrate {
+            return None;
+        }
+        if !self.target_quality.trim().is_empty() {
+            return Some(String::from(
+                "Target Quality picks a quality per chunk, turn it off for a target bitrate",
+            ));
+        }
+        target_bitrate_problem(&self.target_bitrate)
+    }
+
+    // Why av1an would refuse the target quality settings
+    fn target_quality_problem(&self) -> Option<String> {
+        if self.target_quality.trim().is_empty() {
+            return None;
+        }
+        self.encoder
+            .target_quality_problem(&self.target_quality, &self.min_q, &self.max_q)
+    }
+
+    // The set photon noise size, or the output's. None leaves it to av1an, which uses the input's.
+    pub fn photon_noise_size(&self) -> Option<(u32, u32)> {
+        match (
+            self.photon_noise_width.trim().parse(),
+            self.photon_noise_height.trim().parse(),
+        ) {
+            (Ok(width), Ok(height)) => Some((width, height)),
+            _ => self.output_resolution(),
+        }
+    }
+
+    // What has to be fixed before av1an can run, for the Start Encoding button's hover
+    fn start_problem(&self) -> Option<String> {
+        if let Some(problem) = scene_length_problem(&self.min_scene_len, &self.max_scene_len) {
+            return Some(problem);
+        }
+        if self.grain_synthesis == GrainSynthesis::PhotonNoise {
+            if let Some(problem) = photon_noise_problem(&self.photon_noise).or_else(|| {
+                photon_noise_size_problem(&self.photon_noise_width, &self.photon_noise_height)
+            }) {
+                return Some(problem);
+            }
+        }
+        if let Some(problem) = self.rate_control_problem() {
+            return Some(problem);
+        }
+        if let Err(problem) = split_args(&self.custom_encode_params) {
+            return Some(custom_params_problem(problem));
+        }
+        if self.max_bitrate_applies() {
+            if let Some(problem) = max_bitrate_problem(&self.max_bitrate, &self.buffer_size) {
+                return Some(problem);
+            }
+        }
+        self.target_quality_problem()
+    }
+
+    fn can_start_encoding(&self) -> bool {
+        !self.encoding_in_progress
+            && self.disk_space_warning.is_none()
+            && self.missing_output_dir.is_none()
+            && self.start_confirmation.is_none()
+            && self.start_problem().is_none()
+            && !self.input_file.trim().is_empty()
+            && !self.output_file.trim().is_empty()
+    }
+
+    fn handle_shortcuts(&mut self, ctx: &egui::Context) {
+        // Esc belongs to the text field while one is being edited
+        let editing = ctx.wants_keyboard_input();
+        let (start, copy, cancel) = ctx.input_mut(|i| {
+            (
+                i.consume_shortcut(&START_SHORTCUT),
+                !editing && i.consume_shortcut(&COPY_COMMAND_SHORTCUT),
+                !editing && i.consume_shortcut(&CANCEL_SHORTCUT),
+            )
+        });
+        // Text fields have an undo of their own. Ctrl+Shift+Z goes first, Ctrl+Z would match it too.
+        let (undo, redo) = ctx.input_mut(|i| {
+            if editing {
+                return (false, false);
+            }
+            let redo =
+                i.consume_shortcut(&REDO_SHIFT_SHORTCUT) || i.consume_shortcut(&REDO_SHORTCUT);
+            (i.consume_shortcut(&UNDO_SHORTCUT), redo)
+        });
+        if undo || redo {
+            self.undo_settings(redo);
+        }
+
+        if start && self.can_start_encoding() {
+            self.start_encoding();
+        }
+        if copy {
+            self.copy_command(ctx);
+        }
+        // Esc answers the question if it's already open
+        if cancel && self.cancel_confirmation {
+            self.cancel_confirmation = false;
+        } else if cancel && self.encoding_in_progress {
+            self.cancel_confirmation = true;
+        }
+    }
+
+    fn undo_settings(&mut self, redo: bool) {
+        let Some(json) = self.session_json() else {
+            return;
+        };
+        let snapshot = if redo {
+            self.settings_history.redo()
+        } else {
+            self.settings_history.undo(&json)
+        };
+        let Some(snapshot) = snapshot else {
+            return;
+        };
+
+        match serde_json::from_str(&snapshot) {
+            Ok(session) => {
+                self.apply_session_state(session);
+                if let Some(json) = self.session_json() {
+                    self.settings_history.applied(&json);
+                }
+            }
+            Err(e) => eprintln!("ERROR : Couldn't restore the settings: {}", e),
+        }
+    }
+
+    fn show_log_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_log_window;
+        egui::Window::new("Log")
+            .open(&mut open)
+            .default_width(700.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    if ui.button("Copy").clicked() {
+                        let text: Vec<&str> = self.log.lines().map(String::as_str).collect();
+                        ctx.copy_text(text.join("\n"));
+                    }
+                    if ui.button("Clear").clicked() {
+                        self.log.clear();
+                    }
+                });
+                ui.separator();
+                egui::ScrollArea::vertical()
+                    .max_height(400.0)
+                    .stick_to_bottom(true)
+                    .show(ui, |ui| {
+                        for line in self.log.lines() {
+                            ui.label(RichText::new(line).monospace());
+                        }
+                    });
+            });
+        self.show_log_window = open;
+    }
+
+    // Finished encodes, kept in encode_history.json. Only SVT-AV1's version is known, the one
+    // installed now.
+    fn add_to_history(&mut self, mut record: EncodeRecord) {
+        if let Some(version) = &self.svtav1_version {
+            if record.encoder_version == Encoder::SvtAv1.as_str() {
+                record.encoder_version = version.trim().to_string();
+            }
+        }
+        self.encode_history.push(record);
+        save_history(&self.encode_history);
+    }
+
+    fn show_history_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_history_window;
+        let mut export = false;
+        let mut clear = false;
+        egui::Window::new("History")
+            .open(&mut open)
+            .default_width(700.0)
+            .show(ctx, |ui| {
+                if self.encode_history.is_empty() {
+                    ui.label("No finished encodes yet.");
+                    return;
+                }
+
+                ui.horizontal(|ui| {
+                    let label = if self.history_selection.is_empty() {
+                        String::from("Export Statistics…")
+                    } else {
+                        format!(
+                            "Export Statistics ({} selected)…",
+                            self.history_selection.len()
+                        )
+                    };
+                    export = ui
+                        .button(label)
+                        .on_hover_text(
+                            "Writes the selected encodes, or all of them, to a CSV or JSON file",
+                        )
+                        .clicked();
+                    if ui
+                        .add_enabled(
+                            !self.history_selection.is_empty(),
+                            egui::Button::new("Select None"),
+                        )
+                        .clicked()
+                    {
+                        self.history_selection.clear();
+                    }
+                    clear = ui.button("Clear").clicked();
+                });
+                match &self.history_export {
+                    Some(Ok(path)) => {
+                        ui.label(RichText::new(format!("Exported to {}", path)).weak());
+                    }
+                    Some(Err(e)) => {
+                        ui.colored_label(
+                            ui.visuals().error_fg_color,
+                            format!("⚠ Couldn't export: {}", e),
+                        );
+                    }
+                    None => {}
+                }
+                ui.separator();
+                egui::ScrollArea::vertical()
+                    .max_height(400.0)
+                    .show(ui, |ui| {
+                        // Newest first
+                        for (index, record) in self.encode_history.iter().enumerate().rev() {
+                            ui.horizontal(|ui| {
+                                let mut selected = self.history_selection.contains(&index);
+                                if ui.checkbox(&mut selected, "").changed() {
+                                    if selected {
+                                        self.history_selection.insert(index);
+                                    } else {
+                                        self.history_selection.remove(&index);
+                                    }
+                                }
+                                ui.label(RichText::new(&record.date).monospace().weak());
+                                ui.label(file_name(&record.input))
+                                    .on_hover_text(&record.input);
+                                if let Some(seconds) = record.duration_seconds {
+                                    ui.label(format_duration(Duration::from_secs_f64(seconds)));
+                                }
+                                if let Some(fps) = record.average_fps {
+                                    ui.label(format!("{:.2} fps", fps));
+                                }
+                                if record.exit_status.eq_ignore_ascii_case("done") {
+                                    ui.label(RichText::new("✔").weak());
+                                } else {
+                                    ui.colored_label(ui.visuals().error_fg_color, "✖")
+                                        .on_hover_text(&record.exit_status);
+                                }
+                            });
+                        }
+                    });
+            });
+        self.show_history_window = open;
+
+        if clear {
+            self.encode_history.clear();
+            self.history_selection.clear();
+            self.history_export = None;
+            save_history(&self.encode_history);
+        }
+        if export {
+            let records: Vec<EncodeRecord> = if self.history_selection.is_empty() {
+                self.encode_history.clone()
+            } else {
+                self.history_selection
+                    .iter()
+                    .filter_map(|&index| self.encode_history.get(index).cloned())
+                    .collect()
+            };
+            if let Some(path) = FileDialog::new()
+                .add_filter("CSV Files", &["csv"])
+                .add_filter("JSON Files", &["json"])
+                .set_file_name("av1studio-statistics.csv")
+                .save_file()
+            {
+                let path = if path.extension().is_some() {
+                    path
+                } else {
+                    path.with_extension("csv")
+                };
+                self.history_export =
+                    Some(export_records(&records, &path).map(|()| path.display().to_string()));
+            }
+        }
+    }
+
+    // Queues every file found by the folder scan, skipping ones whose output is already there
+    fn add_scanned_files(&mut self) {
+        let Some(found) = self.folder_scan.found.take() else {
+            return;
+        };
+
+        let settings = self.to_preset();
+        let mut added = 0;
+        let mut skipped = 0;
+        let mut same_as_input = 0;
+        for input in &found.files {
+            let output = output_path_for(&self.output_template, input, &settings);
+            let job = self.queue_job(input.display().to_string(), output.display().to_string());
+            if job.overwrites_input() {
+                same_as_input += 1;
+                continue;
+            }
+            if output.exists() && !self.folder_scan.overwrite {
+                skipped += 1;
+                continue;
+            }
+
+            self.queue.push(job);
+            added += 1;
+        }
+
+        let mut report = format!("Added {} files to the queue", added);
+        if skipped > 0 {
+            report.push_str(&format!(", skipped {} with an existing output", skipped));
+        }
+        if same_as_input > 0 {
+            report.push_str(&format!(
+                ", skipped {} whose output would replace the input",
+                same_as_input
+            ));
+        }
+        if !found.unreadable.is_empty() {
+            report.push_str(&format!(
+                "\nCouldn't read {} files:",
+                found.unreadable.len()
+            ));
+            for (path, e) in &found.unreadable {
+                report.push_str(&format!("\n{}: {}", path.display(), e));
+            }
+        }
+        self.folder_scan.report = Some(report);
+    }
+
+    fn show_queue_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_queue_window;
+        let mut add_confirmed = false;
+        egui::Window::new("Queue")
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    if ui.button("Add Current").clicked() {
+                        self.queue_current();
+                    }
+                    let scanning = self.folder_scan.receiver.is_some();
+                    if ui
+                        .add_enabled(!scanning, egui::Button::new("Add Folder…"))
+                        .clicked()
+                    {
+                        if let Some(dir) = FileDialog::new().pick_folder() {
+                            self.folder_scan.start(dir);
+                        }
+                    }
+                    ui.checkbox(&mut self.folder_scan.recursive, "Include subfolders");
+                    if scanning {
+                        ui.spinner();
+                    }
+                });
+
+                ui.horizontal(|ui| {
+                    ui.label("Output Template");
+                    ui.add_sized(
+                        [300.0, 20.0],
+                        egui::TextEdit::singleline(&mut self.output_template),
+                    );
+                    ui.label(RichText::new("ℹ").weak()).on_hover_ui(|ui| {
+                        ui.style_mut().interaction.selectable_labels = true;
+                        ui.label("Output file name for files added with Add Folder. {name} is replaced with the input's file name, {crf} and {preset} with the current settings. The extension comes from the output container, and relative paths are placed next to the input.");
+                    });
+                });
+
+                if let Some(found) = &self.folder_scan.found {
+                    ui.separator();
+                    ui.label(format!("Add {} files to queue?", found.files.len()));
+                    ui.checkbox(&mut self.folder_scan.overwrite, "Overwrite existing outputs");
+                    ui.horizontal(|ui| {
+                        add_confirmed = ui.button("Add").clicked();
+                        if ui.button("Cancel").clicked() {
+                            self.folder_scan.found = None;
+                        }
+                    });
+                }
+
+                if let Some(report) = &self.folder_scan.report {
+                    ui.separator();
+                    ui.label(report);
+                    if ui.button("Dismiss").clicked() {
+                        self.folder_scan.report = None;
+                    }
+                }
+
+                ui.separator();
+
+                egui::ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+                    let mut removed = None;
+                    let mut cancelled = None;
+                    let mut retried = None;
+                    let mut edited = None;
+                    egui::Grid::new("queue_grid").striped(true).show(ui, |ui| {
+                        for job in &self.queue.jobs {
+                            let input_name = std::path::Path::new(&job.input_file)
+                                .file_name()
+                                .map(|name| name.to_string_lossy().to_string())
+                                .unwrap_or_default();
+                            ui.label(input_name).on_hover_text(&job.input_file);
+                            ui.label(&job.output_file);
+                            ui.label(RichText::new(job.settings.summary()).weak());
+
+                            match (&job.status, &job.session) {
+                                (JobStatus::Running, Some(session)) => {
+                                    let progress = match (session.encoded_frames, session.total_frames) {
+                                        (Some(ef), Some(tf)) if tf > 0 => ef as f32 / tf as f32,
+                                        _ => 0.0,
+                                    };
+                                    ui.horizontal(|ui| {
+                                        if let EncodeState::Analyzing(scene_progress) = session.state {
+                                            ui.add(
+                                                ProgressBar::new(scene_progress.unwrap_or_default())
+                                                    .desired_width(150.0)
+                                                    .text("Analyzing scenes…"),
+                                            );
+                                            return;
+                                        }
+                                        ui.add(
+                                            ProgressBar::new(progress)
+                                                .desired_width(150.0)
+                                                .show_percentage(),
+                                        );
+                                        if let Some(fps) = session.fps {
+                                            ui.label(format!("{:.2} fps", fps));
+                                        }
+                                    });
+                                }
+                                (JobStatus::Failed(reason), _) => {
+                                    ui.colored_label(ui.visuals().error_fg_color, "Failed")
+                                        .on_hover_text(reason);
+                                }
+                                (status, _) => {
+                                    ui.label(status.as_str());
+                                }
+                            }
+
+                            ui.horizontal(|ui| {
+                                if job.status == JobStatus::Running {
+                                    if ui.small_button("Cancel").clicked() {
+                                        cancelled = Some(job.id);
+                                    }
+                                    return;
+                                }
+                                if job.status == JobStatus::Interrupted
+                                    && ui
+                                        .small_button("Resume")
+                                        .on_hover_text("Retry with --resume, continuing from the chunks Av1an already encoded")
+                                        .clicked()
+                                {
+                                    retried = Some((job.id, true));
+                                }
+                                if matches!(job.status, JobStatus::Failed(_) | JobStatus::Cancelled)
+                                    && ui.small_button("Retry").clicked()
+                                {
+                                    retried = Some((job.id, false));
+                                }
+                                if ui
+                                    .add_enabled(job.is_editable(), egui::Button::new("Edit").small())
+                                    .on_disabled_hover_text(job.edit_blocked_reason())
+                                    .clicked()
+                                {
+                                    edited = Some(job.id);
+                                }
+                                if ui.small_button("Remove").clicked() {
+                                    removed = Some(job.id);
+                                }
+                            });
+                            ui.end_row();
+                        }
+                    });
+                    if let Some(id) = removed {
+                        self.queue.remove(id);
+                    }
+                    if let Some(id) = edited {
+                        if let Some(job) = self.queue.jobs.iter().find(|job| job.id == id) {
+                            self.job_editor = Some((id, Box::new(self.job_settings(job))));
+                        }
+                    }
+                    if let Some((id, resume)) = retried {
+                        self.queue.retry(id, resume);
+                    }
+                    if let Some(id) = cancelled {
+                        self.queue.cancel_job(id);
+                        self.log.push(format!("[job {}] Cancelled", id));
+                    }
+                });
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if self.queue.running {
+                        if ui.button("Stop Queue").clicked() {
+                            self.queue.cancel();
+                        }
+                    } else if ui
+                        .add_enabled(
+                            self.queue.next_pending().is_some(),
+                            egui::Button::new("Start Queue"),
+                        )
+                        .clicked()
+                    {
+                        self.queue.running = true;
+                    }
+                    if ui.button("Clear Finished").clicked() {
+                        self.queue.clear_finished();
+                    }
+                    if ui
+                        .add_enabled(
+                            self.queue.active_count() > 0 || self.queue.pending_count() > 0,
+                            egui::Button::new("Abort All…"),
+                        )
+                        .on_hover_text("Stop the running jobs and remove the pending ones")
+                        .clicked()
+                    {
+                        self.confirm_abort_queue = true;
+                    }
+                    ui.separator();
+                    ui.label("Concurrent Jobs");
+                    ui.add(egui::DragValue::new(&mut self.max_concurrent_jobs).range(1..=16));
+                    ui.label(RichText::new("ℹ").weak()).on_hover_ui(|ui| {
+                        ui.style_mut().interaction.selectable_labels = true;
+                        ui.label("How many queue jobs to encode at the same time, each with its own Av1an. Useful on CPUs with more cores than a single Av1an job keeps busy. Lower the workers per job so they add up to about the number of CPU threads.");
+                    });
+                });
+                if self.confirm_abort_queue {
+                    ui.separator();
+                    ui.label(format!(
+                        "Stop {} running and remove {} pending jobs? Only Stop keeps the pending ones to start later.",
+                        self.queue.active_count(),
+                        self.queue.pending_count()
+                    ));
+                    ui.horizontal(|ui| {
+                        if ui.button("Abort All").clicked() {
+                            let removed = self.queue.abort_all();
+                            self.log.push(format!("Queue aborted, {} pending jobs removed", removed));
+                            self.confirm_abort_queue = false;
+                        }
+                        if ui.button("Only Stop").clicked() {
+                            self.queue.cancel();
+                            self.confirm_abort_queue = false;
+                        }
+                        if ui.button("Cancel").clicked() {
+                            self.confirm_abort_queue = false;
+                        }
+                    });
+                }
+                let workers = self.queue.planned_workers(self.max_concurrent_jobs);
+                let threads = num_cpus::get();
+                if self.max_concurrent_jobs > 1 && workers > threads {
+                    ui.colored_label(
+                        ui.visuals().warn_fg_color,
+                        format!(
+                            "⚠ The next {} jobs use {} workers in total, but there are only {} CPU threads. They'll slow each other down, lower the workers per job.",
+                            self.max_concurrent_jobs, workers, threads
+                        ),
+                    );
+                }
+                let (encoded, total) = self.queue.progress();
+                if self.queue.active_count() > 1 && total > 0 {
+                    ui.add(
+                        ProgressBar::new(encoded as f32 / total as f32)
+                            .text(format!("{} running jobs: {} / {} frames", self.queue.active_count(), encoded, total)),
+                    );
+                }
+            });
+        self.show_queue_window = open;
+
+        if add_confirmed {
+            self.add_scanned_files();
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AV1StudioPreset {
+    #[serde(default = "legacy_preset_version")]
+    pub version: u32,
+    pub name: String,
+    pub description: String,
+    pub source_library: SourceLibrary,
+    pub chunk_method: ChunkMethod,
+    pub min_scene_len: String,
+    pub max_scene_len: String,
+    pub output_container: OutputContainer,
+    pub width: String,
+    pub height: String,
+    pub lock_aspect: bool,
+    pub scale_algorithm: ScaleAlgorithm,
+    pub deinterlace: Deinterlace,
+    pub crop_top: u32,
+    pub crop_bottom: u32,
+    pub crop_left: u32,
+    pub crop_right: u32,
+    pub denoise: Denoise,
+    pub denoise_strength: f32,
+    pub output_pixel_format: PixelFormat,
+    pub color_primaries: ColorPrimaries,
+    pub matrix_coefficients: MatrixCoefficients,
+    pub transfer_characteristics: TransferCharacteristics,
+    pub color_range: ColorRange,
+    pub chroma_sample_position: ChromaSamplePosition,
+    pub enable_hdr: bool,
+    pub file_concatenation: String,
+    pub encoder: Encoder,
+    pub preset: f32,
+    pub crf: f32,
+    pub rate_control: RateControl,
+    pub target_bitrate: String,
+    pub max_bitrate: String,
+    pub buffer_size: String,
+    pub target_quality: String,
+    pub min_q: String,
+    pub max_q: String,
+    pub tune: Tune,
+    pub passes: u8,
+    pub tile_columns: u8,
+    pub tile_rows: u8,
+    pub enable_qm: bool,
+    pub qm_min: u8,
+    pub qm_max: u8,
+    pub grain_synthesis: GrainSynthesis,
+    pub synthetic_grain: String,
+    pub photon_noise: String,
+    pub photon_noise_width: String,
+    pub photon_noise_height: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub grain_table: String,
+    pub custom_encode_params: String,
+    pub custom_params_mode: CustomParamsMode,
+    pub chunk_order: ChunkOrder,
+    pub force: bool,
+    pub extra_av1an_args: String,
+
+    // Only stored when the user asks for it, presets are otherwise independent of any files
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub input_file: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_file: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scenes_file: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub zones_file: Option<String>,
+}
+
+// Just the encoder settings of a preset ("my high-quality settings"), independent of the
+// source, resolution and colors
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct QualityPreset {
+    pub name: String,
+    pub encoder: Encoder,
+    pub preset: f32,
+    pub crf: f32,
+    pub rate_control: RateControl, // Constant quality for quality presets written before it
+    pub target_bitrate: String,
+    pub tune: Tune,
+    pub grain_synthesis: GrainSynthesis,
+    pub synthetic_grain: String,
+    pub photon_noise: String,
+    pub custom_encode_params: String,
+    pub custom_params_mode: CustomParamsMode,
+}
+
+impl Default for QualityPreset {
+    fn default() -> Self {
+        AV1Studio::default().to_quality_preset()
+    }
+}
+
+fn quality_presets_dir() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("quality_presets"))
+}
+
+// The name with the characters file systems don't like swapped out
+fn quality_preset_file_name(name: &str) -> String {
+    let stem: String = name
+        .trim()
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c => c,
+        })
+        .collect();
+
+    format!("{}.yaml", if stem.is_empty() { "Untitled" } else { &stem })
+}
+
+fn list_quality_presets() -> Vec<(PathBuf, String)> {
+    let Some(entries) = quality_presets_dir().and_then(|dir| std::fs::read_dir(dir).ok()) else {
+        return Vec::new();
+    };
+
+    let mut files: Vec<(PathBuf, String)> = entries
+        .map_while(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().and_then(|e| e.to_str()) == Some("yaml"))
+        .map(|path| {
+            let name = std::fs::read_to_string(&path)
+                .ok()
+                .and_then(|content| serde_yaml::from_str::<QualityPreset>(&content).ok())
+                .map(|quality| quality.name)
+                .filter(|name| !name.is_empty())
+                .or_else(|| {
+                    path.file_stem()
+                        .map(|stem| stem.to_string_lossy().to_string())
+                })
+                .unwrap_or_default();
+            (path, name)
+        })
+        .collect();
+    files.sort_by_key(|(_, name)| name.to_lowercase());
+
+    files
+}
+
+// Fields missing from presets written by older versions fall back to the app defaults
+impl Default for AV1StudioPreset {
+    fn default() -> Self {
+        AV1Studio::default().to_preset()
+    }
+}
+
+impl AV1StudioPreset {
+    // Short rundown of the settings that usually differ between jobs
+    pub fn summary(&self) -> String {
+        let grain = self.grain_summary();
+        let resolution = match scale_size(&self.width, &self.height, None) {
+            Some((width, height)) => format!("{}x{}", width, height).replace("-2", "auto"),
+            None => String::from("source res"),
+        };
+
+        let ranges = self.encoder.ranges();
+        let quality = match self.rate_control {
+            RateControl::Crf => format!("{} {}", ranges.quality_label, self.crf),
+            RateControl::TargetBitrate => format!("{} kbps", self.target_bitrate.trim()),
+        };
+
+        format!(
+            "{} · {} {} · {} · {}",
+            quality, ranges.speed_label, self.preset, grain, resolution
+        )
+    }
+
+    pub fn grain_summary(&self) -> String {
+        match self.grain_synthesis {
+            GrainSynthesis::Synthetic => format!("grain {}", self.synthetic_grain),
+            GrainSynthesis::PhotonNoise => format!("photon noise ISO {}", self.photon_noise),
+            GrainSynthesis::GrainTable => String::from("grain table"),
+        }
+    }
+}
+
+// Presets without a version field were written before versioning, which counts as version 1
+fn legacy_preset_version() -> u32 {
+    1
+}
+
+// Presets are YAML unless the file ends in .json
+fn is_json_preset(path: &std::path::Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("json"))
+}
+
+// The presets in a folder with their display names, sorted by name
+fn scan_presets_folder(folder: &str) -> Vec<(PathBuf, String)> {
+    let entries = match std::fs::read_dir(folder) {
+        Ok(entries) => entries,
+        Err(e) => {
+            eprintln!(
+                "WARNING: Couldn't read the presets folder {}: {}",
+                folder, e
+            );
+            return Vec::new();
+        }
+    };
+
+    let mut files = Vec::new();
+    for path in entries.map_while(Result::ok).map(|entry| entry.path()) {
+        let is_preset = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| e == "yaml" || e == "yml" || e == "json");
+        if !is_preset {
+            continue;
+        }
+
+        let name = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| preset_value(&path, &content).ok())
+            .and_then(|value| value.get("name")?.as_str().map(str::to_string))
+            .filter(|name| !name.is_empty())
+            .or_else(|| {
+                path.file_stem()
+                    .map(|stem| stem.to_string_lossy().to_string())
+            })
+            .unwrap_or_default();
+        files.push((path, name));
+    }
+    files.sort_by_key(|(_, name)| name.to_lowercase());
+    files
+}
+
+// JSON presets go through the same YAML value, so they get the same checks and migrations
+fn preset_value(
+    path: &std::path::Path,
+    content: &str,
+) -> Result<serde_yaml::Value, Box<dyn std::error::Error>> {
+    if is_json_preset(path) {
+        Ok(serde_json::from_str(content)?)
+    } else {
+        Ok(serde_yaml::from_str(content)?)
+    }
+}
+
+fn parse_preset(content: &str) -> Result<AV1StudioPreset, Box<dyn std::error::Error>> {
+    parse_preset_value(serde_yaml::from_str(content)?)
+}
+
+fn parse_preset_value(
+    mut value: serde_yaml::Value,
+) -> Result<AV1StudioPreset, Box<dyn std::error::Error>> {
+    let version = value
+        .get("version")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(legacy_preset_version() as u64);
+
+    if version > PRESET_VERSION as u64 {
+        eprintln!(
+            "WARNING: Preset was saved by a newer AV1Studio (version {}), some settings may be lost",
+            version
+        );
+    }
+
+    // Presets from newer versions may carry fields this one doesn't know, which get ignored.
+    // Fields only written when set need a value to show up.
+    let all_fields = AV1StudioPreset {
+        input_file: Some(String::new()),
+        output_file: Some(String::new()),
+        scenes_file: Some(String::new()),
+        zones_file: Some(String::new()),
+        grain_table: String::from("grain.tbl"),
+        ..AV1StudioPreset::default()
+    };
+    if let (serde_yaml::Value::Mapping(loaded), Ok(serde_yaml::Value::Mapping(known))) =
+        (&value, serde_yaml::to_value(all_fields))
+    {
+        for key in loaded.keys().filter(|key| !known.contains_key(*key)) {
+            eprintln!("WARNING: Ignoring unknown preset field {:?}", key);
+        }
+    }
+
+    migrate_preset(&mut value, version);
+
+    let mut preset: AV1StudioPreset = serde_yaml::from_value(value)?;
+    preset.version = PRESET_VERSION;
+
+    Ok(preset)
+}
+
+// Brings a preset written by an older version up to the current layout, one version at a time
+fn migrate_preset(value: &mut serde_yaml::Value, version: u64) {
+    let serde_yaml::Value::Mapping(preset) = value else {
+        return;
+    };
+
+    if version < 2 {
+        // Version 1 had no output container, the concatenation method decided the output format
+        // and ivf would now get swapped for mkvmerge
+        if preset.get("file_concatenation").and_then(|v| v.as_str()) == Some("ivf")
+            && !preset.contains_key("output_container")
+        {
+            preset.insert("output_container".into(), "Ivf".into());
+        }
+    }
+    if version < 3 {
+        // Custom parameters used to replace the generated ones. Presets written since they get
+        // appended by default say which they want.
+        let has_custom_params = preset
+            .get("custom_encode_params")
+            .and_then(|v| v.as_str())
+            .is_some_and(|params| !params.trim().is_empty());
+        if has_custom_params && !preset.contains_key("custom_params_mode") {
+            preset.insert("custom_params_mode".into(), "Replace".into());
+        }
+    }
+}
+
+// For the settings stored with queued jobs and sessions, so they get the preset migrations too
+pub fn deserialize_preset<'de, D>(deserializer: D) -> Result<AV1StudioPreset, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let value = serde_yaml::Value::deserialize(deserializer)?;
+    parse_preset_value(value).map_err(serde::de::Error::custom)
+}
+
+// The folder the output file goes into, the working directory for a bare file name
+fn output_dir_for(output_file: &str) -> PathBuf {
+    expand_path(output_file)
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .map_or_else(|| PathBuf::from("."), |dir| dir.to_path_buf())
+}
+
+// av1an would only find out once the encode is done and the chunks get joined
+fn output_dir_problem(dir: &Path) -> Option<String> {
+    if !dir.is_dir() {
+        Some(format!("The output folder {} doesn't exist", dir.display()))
+    } else if !is_writable_dir(dir) {
+        Some(format!(
+            "The output folder {} isn't writable",
+            dir.display()
+        ))
+    } else {
+        None
+    }
+}
+
+// Typing, cutting, pasting and clicking can change a setting, moving the pointer alone can't.
+// A drag ends in a button release.
+fn edits_something(event: &egui::Event) -> bool {
+    matches!(
+        event,
+        egui::Event::Key { .. }
+            | egui::Event::Text(_)
+            | egui::Event::Cut
+            | egui::Event::Paste(_)
+            | egui::Event::PointerButton { .. }
+    )
+}
+
+// Whether the filter's letters and digits appear together in the label's, ignoring case and
+// everything else, so "2020", "bt2020" and "BT.2020" all find "(9) BT.2020, BT.2100"
+fn matches_filter(label: &str, filter: &str) -> bool {
+    let normalize = |text: &str| -> String {
+        text.chars()
+            .filter(|c| c.is_alphanumeric())
+            .flat_map(char::to_lowercase)
+            .collect()
+    };
+
+    normalize(label).contains(&normalize(filter))
+}
+
+// A combo box with a filter field on top of its list. The field has focus when the list opens,
+// Enter picks the first match, and Tab and the arrow keys move through the options as usual.
+fn filterable_combo<T: PartialEq + Copy>(
+    ui: &mut egui::Ui,
+    id_salt: &str,
+    value: &mut T,
+    options: &[T],
+    label: fn(&T) -> &'static str,
+    selected_text: fn(&T) -> &str,
+) {
+    let filter_id = ui.make_persistent_id(id_salt).with("filter");
+    let response = ComboBox::from_id_salt(id_salt)
+        .selected_text(selected_text(value))
+        .close_behavior(egui::PopupCloseBehavior::CloseOnClickOutside)
+        .show_ui(ui, |ui| {
+            // Nothing's stored right after opening
+            let stored = ui.data(|data| data.get_temp::<String>(filter_id));
+            let opened = stored.is_none();
+            let mut filter = stored.unwrap_or_default();
+            let field = ui.add(
+                egui::TextEdit::singleline(&mut filter)
+                    .hint_text("Filter…")
+                    .desired_width(f32::INFINITY),
+            );
+            if opened {
+                field.request_focus();
+            }
+
+            let matches: Vec<T> = options
+                .iter()
+                .copied()
+                .filter(|option| matches_filter(label(option), &filter))
+                .collect();
+            let mut picked = field.lost_focus()
+                && ui.input(|i| i.key_pressed(Key::Enter))
+                && matches.first().is_some_and(|first| {
+                    *value = *first;
+                    true
+                });
+            for option in &matches {
+                picked |= ui.selectable_value(value, *option, label(option)).clicked();
+            }
+            if matches.is_empty() {
+                ui.label(RichText::new("No matches").weak());
+            }
+
+            if picked {
+                ui.memory_mut(|memory| memory.close_popup());
+            }
+            ui.data_mut(|data| data.insert_temp(filter_id, filter));
+        });
+    // Starts over with an empty filter next time
+    if response.inner.is_none() {
+        ui.data_mut(|data| data.remove::<String>(filter_id));
+    }
+}
+
+impl eframe::App for AV1Studio {
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, OPEN_SECTIONS_KEY, &self.sections);
+    }
+
+    // A session file left behind means a crash, unless the user hasn't decided on it yet
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        // av1an runs in its own process group, nothing else would stop it. Running queue jobs
+        // were saved as running, so they're offered for resuming next time.
+        self.cancel_encoding();
+        self.queue.cancel();
+        self.sample_encode.cancel();
+        self.crf_comparison.cancel();
+        self.scene_analysis.cancel();
+        self.grain_generator.cancel();
+        self.input_join.remove_output();
+        // The queue is offered again next time, so its jobs keep theirs
+        self.remove_unused_zone_exports(false);
+        if self.restorable_session.is_none() && self.session_lock.is_some() {
+            remove_session();
+        }
+    }
+
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        if self.max_label_width.is_none() {
+            ctx.request_repaint();
+            self.max_label_width = Some(0.0);
+        }
+
+        // The OS preference might have changed while the window was in the background
+        let focused = ctx.input(|i| i.focused);
+        if focused && !self.window_focused && self.active_theme == Theme::System {
+            ctx.set_visuals(self.active_theme.visuals());
+        }
+        self.window_focused = focused;
+
+        if ctx.input(|i| i.events.iter().any(edits_something) || !i.raw.dropped_files.is_empty()) {
+            self.session_dirty = true;
+            self.queue_dirty = true;
+        }
+
+        // Ctrl +/- zooms too, so the scale can change from either side
+        if self.ui_scale != self.applied_ui_scale {
+            ctx.set_zoom_factor(self.ui_scale);
+        } else {
+            self.ui_scale = ctx.zoom_factor();
+        }
+        self.applied_ui_scale = self.ui_scale;
+        self.save_app_config();
+
+        if self.optional_tools.is_none() {
+            let tools = OptionalTools::detect(&self.ffmpeg_paths());
+            let missing = tools.missing();
+            if !missing.is_empty() {
+                eprintln!("WARNING: {} not found on the PATH", missing.join(", "));
+            }
+            self.show_missing_tools_warning = !missing.is_empty();
+            self.optional_tools = Some(tools);
+        }
+
+        self.refresh_preset_files();
+        self.update_probe();
+        #[cfg(feature = "status-server")]
+        self.update_status_server();
+        if self.sample_encode.poll() {
+            if let Err(e) = open_in_default_player(&self.sample_encode.output) {
+                eprintln!(
+                    "ERROR : Couldn't open {:?}: {}",
+                    self.sample_encode.output, e
+                );
+            }
+        }
+        if let Some(scenes) = self.scene_analysis.poll() {
+            self.log
+                .push(format!("Wrote the detected scenes to {:?}", scenes));
+            self.scenes_file = scenes.display().to_string();
+            self.session_dirty = true;
+        }
+        self.update_preview(ctx);
+        self.handle_shortcuts(ctx);
+        self.show_compare_window(ctx);
+        self.show_grain_window(ctx);
+        self.show_join_window(ctx);
+        self.show_zones_window(ctx);
+        self.update_queue();
+        self.save_queue();
+        self.show_config_error_window(ctx);
+        self.show_restore_session_window(ctx);
+        self.show_restore_queue_window(ctx);
+        self.show_queue_window(ctx);
+        self.show_job_editor_window(ctx);
+        self.show_log_window(ctx);
+        self.show_history_window(ctx);
+        self.show_command_window(ctx);
+        self.update_title(ctx);
+        self.show_save_preset_window(ctx);
+        self.show_save_quality_preset_window(ctx);
+        self.show_preset_overwrite_window(ctx);
+        self.show_start_confirmation_window(ctx);
+        self.show_cancel_confirmation_window(ctx);
+        self.show_missing_output_dir_window(ctx);
+        self.show_disk_space_window(ctx);
+        self.show_preset_error_window(ctx);
+
+        // Please tell me there's a better way to do this
+        if !self.av1an_verbosity_checked {
+            let path = if self.av1an_verbosity_path.is_empty() {
+                std::path::PathBuf::from("/usr/local/bin/av1an-verbosity")
+            } else {
+                expand_path(&self.av1an_verbosity_path)
+            };
+            let path = path.as_path();
+            self.av1an_verbosity_found = exists(path);
+            self.av1an_verbosity_checked = true;
+            self.av1an_verbosity_version = version(path, "--version");
+            self.show_av1an_verbosity_warning = !self.av1an_verbosity_found;
+
+            if self.av1an_verbosity_version.is_none() {
+                eprintln!("WARNING: {:?} can't be found, you will have to give its path manually in the Settings menu", path);
+            } else {
+                println!("{:?} found", path);
+            }
+        }
+
+        if !self.svtav1_checked {
+            let path = std::path::Path::new(SVTAV1_PATH);
+            self.svtav1_found = exists(path);
+            self.svtav1_checked = true;
+            self.svtav1_version = version(path, "--version");
+            self.svtav1_options.clear();
+            self.svtav1_options.get(path);
+            self.show_svtav1_warning = !self.svtav1_found;
+
+            if self.svtav1_version.is_none() {
+                eprintln!("WARNING: {:?} can't be found", path);
+            } else {
+                println!("{:?} found", path);
+            }
+        }
+
+        if self.show_av1an_verbosity_warning {
+            egui::Window::new("Warning")
+                .open(&mut self.show_av1an_verbosity_warning)
+                .show(ctx, |ui| {
+                    ui.label("/usr/local/bin/av1an-verbosity not found! You will have to set a path for it manually in the Settings menu.");
+                });
+        }
+
+        if self.show_missing_tools_warning {
+            let missing = self.tools().missing().join(", ");
+            egui::Window::new("Missing Tools")
+                .open(&mut self.show_missing_tools_warning)
+                .show(ctx, |ui| {
+                    ui.label(format!("{} couldn't be found on the PATH. Encoding still works, but the features that need them are disabled: ffprobe for the media info, ffmpeg for previews, samples and ffmpeg concatenation, mkvmerge for mkvmerge concatenation.", missing));
                 });
         }
 
@@ -297,11 +5457,53 @@ impl eframe::App for AV1Studio {
                                             self.av1an_verbosity_path = path.display().to_string();
                                         }
                                     }
+                                    // Runs the same program the encode would
+                                    if ui.button("Test").on_hover_text("Check that the binary runs and show its version").clicked() {
+                                        let program = if self.av1an_verbosity_path.is_empty() {
+                                            String::from("av1an-verbosity")
+                                        } else {
+                                            expand_path_string(&self.av1an_verbosity_path)
+                                        };
+                                        self.av1an_test_result = Some((self.av1an_verbosity_path.clone(), test_binary(&program)));
+                                    }
                                     ui.label(RichText::new("ℹ").weak()).on_hover_ui(|ui| {
                                         ui.style_mut().interaction.selectable_labels = true;
                                         ui.label("Full path to the Av1an-verbosity binary.");
                                     });
                                 });
+                                match &self.av1an_test_result {
+                                    Some((path, Ok(version))) if *path == self.av1an_verbosity_path => {
+                                        ui.label(format!("✔ {}", version));
+                                    }
+                                    Some((path, Err(error))) if *path == self.av1an_verbosity_path => {
+                                        ui.colored_label(ui.visuals().error_fg_color, format!("⚠ Couldn't run it: {}", error));
+                                    }
+                                    _ => {}
+                                }
+                                ui.horizontal(|ui| {
+                                    let label_text = "FFmpeg Path";
+                                    let label_width = ui.label(label_text).rect.max.x - ui.min_rect().min.x;
+                                    settings_max_label_width = settings_max_label_width.max(label_width);
+                                    if label_width < settings_max_label_width {
+                                        ui.allocate_space(egui::vec2(settings_max_label_width - label_width, 1.0));
+                                    }
+                                    ui.add_sized(
+                                        [500.0, 20.0],
+                                        egui::TextEdit::singleline(&mut self.ffmpeg_path).hint_text("ffmpeg on the PATH"),
+                                    );
+                                    if ui.button("Browse").clicked() {
+                                        if let Some(path) = FileDialog::new().pick_file() {
+                                            self.ffmpeg_path = path.display().to_string();
+                                        }
+                                    }
+                                    ui.label(RichText::new("ℹ").weak()).on_hover_ui(|ui| {
+                                        ui.style_mut().interaction.selectable_labels = true;
+                                        ui.label("Full path to the ffmpeg binary av1an should use, e.g. a build with libvmaf. Its folder gets put in front of av1an's PATH. Leave empty to use the first ffmpeg on the PATH. Re-check the dependencies after changing it.");
+                                    });
+                                });
+                                if !self.ffmpeg_path.is_empty() && !exists(&expand_path(&self.ffmpeg_path)) {
+                                    ui.colored_label(ui.visuals().warn_fg_color, "⚠ There's no file at the FFmpeg path.");
+                                }
                                 ui.horizontal(|ui| {
                                     let label_text = "Default Preset Path";
                                     let label_width = ui.label(label_text).rect.max.x - ui.min_rect().min.x;
@@ -323,6 +5525,177 @@ impl eframe::App for AV1Studio {
                                         ui.label("Path to the YAML preset file that gets loaded every time AV1Studio is started.");
                                     });
                                 });
+                                ui.horizontal(|ui| {
+                                    let label_text = "Presets Folder";
+                                    let label_width = ui.label(label_text).rect.max.x - ui.min_rect().min.x;
+                                    settings_max_label_width = settings_max_label_width.max(label_width);
+                                    if label_width < settings_max_label_width {
+                                        ui.allocate_space(egui::vec2(settings_max_label_width - label_width, 1.0));
+                                    }
+                                    ui.add_sized(
+                                        [500.0, 20.0],
+                                        egui::TextEdit::singleline(&mut self.presets_folder),
+                                    );
+                                    if ui.button("Browse").clicked() {
+                                        if let Some(path) = FileDialog::new().pick_folder() {
+                                            self.presets_folder = path.display().to_string();
+                                        }
+                                    }
+                                    ui.label(RichText::new("ℹ").weak()).on_hover_ui(|ui| {
+                                        ui.style_mut().interaction.selectable_labels = true;
+                                        ui.label("Folder with YAML presets to pick from in the Presets dropdown.");
+                                    });
+                                });
+                                ui.add_space(ui.spacing().item_spacing.y * 2.0);
+                                ui.horizontal(|ui| {
+                                    ui.label(RichText::new("Environment").weak());
+                                    ui.label(RichText::new("ℹ").weak()).on_hover_ui(|ui| {
+                                        ui.style_mut().interaction.selectable_labels = true;
+                                        ui.label("Environment variables set for av1an, e.g. VAPOURSYNTH_LIB_DIR. They're applied after the FFmpeg path, so a PATH here replaces it. A variable with an empty value gets removed from av1an's environment instead. The Command Preview lists what gets changed.");
+                                    });
+                                });
+                                let mut removed = None;
+                                for (index, row) in self.environment.iter_mut().enumerate() {
+                                    ui.horizontal(|ui| {
+                                        ui.add(egui::TextEdit::singleline(&mut row.key).hint_text("NAME").desired_width(180.0));
+                                        ui.label("=");
+                                        ui.add(egui::TextEdit::singleline(&mut row.value).hint_text("value").desired_width(300.0));
+                                        if ui.button("🗑").on_hover_text("Remove").clicked() {
+                                            removed = Some(index);
+                                        }
+                                    });
+                                }
+                                if let Some(index) = removed {
+                                    self.environment.remove(index);
+                                }
+                                if ui.button("Add Variable").clicked() {
+                                    self.environment.push(EnvOverride::default());
+                                }
+                                ui.add_space(ui.spacing().item_spacing.y * 2.0);
+                                ui.label(RichText::new("Notifications").weak());
+                                ui.horizontal(|ui| {
+                                    let label_text = "Webhook URL";
+                                    let label_width = ui.label(label_text).rect.max.x - ui.min_rect().min.x;
+                                    settings_max_label_width = settings_max_label_width.max(label_width);
+                                    if label_width < settings_max_label_width {
+                                        ui.allocate_space(egui::vec2(settings_max_label_width - label_width, 1.0));
+                                    }
+                                    // Webhook URLs double as credentials, so keep them off screen
+                                    ui.add_sized(
+                                        [500.0, 20.0],
+                                        egui::TextEdit::singleline(&mut self.notifications.webhook_url).password(true),
+                                    );
+                                    ui.label(RichText::new("ℹ").weak()).on_hover_ui(|ui| {
+                                        ui.style_mut().interaction.selectable_labels = true;
+                                        ui.label("Webhook (e.g. a Discord channel webhook) that gets a short JSON message with the file name, encoding time, size, average fps and status for the events below. Leave empty to disable notifications.");
+                                    });
+                                });
+                                ui.horizontal(|ui| {
+                                    let label_text = "Notify On";
+                                    let label_width = ui.label(label_text).rect.max.x - ui.min_rect().min.x;
+                                    settings_max_label_width = settings_max_label_width.max(label_width);
+                                    if label_width < settings_max_label_width {
+                                        ui.allocate_space(egui::vec2(settings_max_label_width - label_width, 1.0));
+                                    }
+                                    ui.checkbox(&mut self.notifications.job_started, "Job started");
+                                    ui.checkbox(&mut self.notifications.job_finished, "Job finished");
+                                    ui.checkbox(&mut self.notifications.job_failed, "Job failed");
+                                    ui.checkbox(&mut self.notifications.queue_empty, "Queue empty");
+                                });
+                                #[cfg(feature = "status-server")]
+                                {
+                                    ui.horizontal(|ui| {
+                                        let label_text = "Status Server";
+                                        let label_width = ui.label(label_text).rect.max.x - ui.min_rect().min.x;
+                                        settings_max_label_width = settings_max_label_width.max(label_width);
+                                        if label_width < settings_max_label_width {
+                                            ui.allocate_space(egui::vec2(settings_max_label_width - label_width, 1.0));
+                                        }
+                                        ui.checkbox(&mut self.status_server_settings.enabled, "");
+                                        ui.add_enabled(
+                                            !self.status_server_settings.enabled,
+                                            egui::TextEdit::singleline(&mut self.status_server_settings.address)
+                                                .desired_width(150.0),
+                                        );
+                                        ui.label(RichText::new("ℹ").weak()).on_hover_ui(|ui| {
+                                            ui.style_mut().interaction.selectable_labels = true;
+                                            ui.label("Serves the encoding progress and the queue at this address, as a small web page on / and as JSON on /status.json, e.g. to check on an encode from your phone. 0.0.0.0 makes it reachable from the whole network, 127.0.0.1 only from this computer. There's no authentication.");
+                                        });
+                                    });
+                                    ui.horizontal(|ui| {
+                                        let label_text = "Remote Token";
+                                        let label_width = ui.label(label_text).rect.max.x - ui.min_rect().min.x;
+                                        settings_max_label_width = settings_max_label_width.max(label_width);
+                                        if label_width < settings_max_label_width {
+                                            ui.allocate_space(egui::vec2(settings_max_label_width - label_width, 1.0));
+                                        }
+                                        ui.add_enabled(
+                                            !self.status_server_settings.enabled,
+                                            egui::TextEdit::singleline(&mut self.status_server_settings.token)
+                                                .password(true)
+                                                .desired_width(150.0),
+                                        );
+                                        ui.label(RichText::new("ℹ").weak()).on_hover_ui(|ui| {
+                                            ui.style_mut().interaction.selectable_labels = true;
+                                            ui.label("Shared secret for controlling AV1Studio remotely. POST to /encode/start, /encode/cancel (the main encode only, not queued jobs), /queue/pause or /queue/resume with an \"Authorization: Bearer <token>\" header. Leave empty to only allow viewing the status.");
+                                        });
+                                    });
+                                    if let Some(error) = &self.status_server.error {
+                                        ui.colored_label(ui.visuals().error_fg_color, format!("⚠ {}", error));
+                                    }
+                                }
+                                ui.add_space(ui.spacing().item_spacing.y * 2.0);
+                                ui.label(RichText::new("Dependencies").weak());
+                                let optional_tools = self.optional_tools.as_ref().unwrap_or(&OptionalTools::NONE);
+                                let products = ["Av1an", "SVT-AV1", "FFmpeg", "ffprobe", "mkvmerge"];
+                                let dependencies: Vec<(&str, &str, Option<&str>)> = [
+                                    ("Av1an-verbosity", self.av1an_verbosity_version.as_deref()),
+                                    ("SvtAv1EncApp", self.svtav1_version.as_deref()),
+                                ]
+                                .into_iter()
+                                .chain(optional_tools.versions())
+                                .zip(products)
+                                .map(|((name, output), product)| (name, product, output))
+                                .collect();
+                                for (name, product, output) in &dependencies {
+                                    ui.horizontal(|ui| {
+                                        let label_width = ui.label(*name).rect.max.x - ui.min_rect().min.x;
+                                        settings_max_label_width = settings_max_label_width.max(label_width);
+                                        if label_width < settings_max_label_width {
+                                            ui.allocate_space(egui::vec2(settings_max_label_width - label_width, 1.0));
+                                        }
+                                        // Fall back to whatever it printed if there's no version number in it
+                                        match output {
+                                            Some(output) => match parse_version(output) {
+                                                Some(version) => ui
+                                                    .label(format!("{} {}", product, version))
+                                                    .on_hover_text(*output),
+                                                None => ui.label(RichText::new(*output).weak()),
+                                            },
+                                            None => ui.colored_label(ui.visuals().warn_fg_color, "⚠ Not found"),
+                                        };
+                                    });
+                                }
+                                let mut recheck = false;
+                                ui.horizontal(|ui| {
+                                    recheck = ui.button("Re-check Dependencies").clicked();
+                                    if ui
+                                        .button("Copy Versions")
+                                        .on_hover_text("Copy the versions for a bug report")
+                                        .clicked()
+                                    {
+                                        let versions: Vec<String> = dependencies
+                                            .iter()
+                                            .map(|(name, _, output)| format!("{}: {}", name, output.unwrap_or("not found")))
+                                            .collect();
+                                        ui.ctx().copy_text(versions.join("\n"));
+                                    }
+                                });
+                                if recheck {
+                                    self.av1an_verbosity_checked = false;
+                                    self.svtav1_checked = false;
+                                    self.optional_tools = None;
+                                }
                                 ui.add_space(ui.spacing().item_spacing.y * 2.0);
                                 ui.label(RichText::new("Looks").weak());
                                 ui.horizontal(|ui| {
@@ -345,74 +5718,341 @@ impl eframe::App for AV1Studio {
                                                 Theme::Light,
                                                 "Light",
                                             );
+                                            ui.selectable_value(
+                                                &mut self.active_theme,
+                                                Theme::System,
+                                                "System",
+                                            );
                                         });
                                     ui.label(RichText::new("").weak()).on_hover_ui(|ui| {
                                         ui.style_mut().interaction.selectable_labels = true;
-                                        ui.label("Name of the active theme.");
+                                        ui.label("Name of the active theme. System follows the OS's light/dark preference.");
+                                    });
+                                });
+                                ui.horizontal(|ui| {
+                                    let label_text = "Confirm Before Encoding";
+                                    let label_width = ui.label(label_text).rect.max.x - ui.min_rect().min.x;
+                                    settings_max_label_width = settings_max_label_width.max(label_width);
+                                    if label_width < settings_max_label_width {
+                                        ui.allocate_space(egui::vec2(settings_max_label_width - label_width, 1.0));
+                                    }
+                                    ui.checkbox(&mut self.confirm_start, "");
+                                    ui.label(RichText::new("ℹ").weak()).on_hover_ui(|ui| {
+                                        ui.style_mut().interaction.selectable_labels = true;
+                                        ui.label("Shows what's about to be encoded, with the files, size, encoder settings and anything that looks off, before Av1an starts.");
+                                    });
+                                });
+                                ui.horizontal(|ui| {
+                                    let label_text = "Write Encode Reports";
+                                    let label_width = ui.label(label_text).rect.max.x - ui.min_rect().min.x;
+                                    settings_max_label_width = settings_max_label_width.max(label_width);
+                                    if label_width < settings_max_label_width {
+                                        ui.allocate_space(egui::vec2(settings_max_label_width - label_width, 1.0));
+                                    }
+                                    ui.checkbox(&mut self.write_report, "");
+                                    ui.label(RichText::new("ℹ").weak()).on_hover_ui(|ui| {
+                                        ui.style_mut().interaction.selectable_labels = true;
+                                        ui.label("After a successful encode, writes <output>.report.json next to the output, with the command, all settings, timing, frame counts, the output size and the VMAF score if Av1an printed one. Handy for redoing an encode or sharing how it was made.");
+                                    });
+                                });
+                                ui.horizontal(|ui| {
+                                    let label_text = "UI Scale";
+                                    let label_width = ui.label(label_text).rect.max.x - ui.min_rect().min.x;
+                                    settings_max_label_width = settings_max_label_width.max(label_width);
+                                    if label_width < settings_max_label_width {
+                                        ui.allocate_space(egui::vec2(settings_max_label_width - label_width, 1.0));
+                                    }
+                                    ui.add(Slider::new(&mut self.ui_scale, 0.5..=2.0).step_by(0.05));
+                                    ui.label(RichText::new("ℹ").weak()).on_hover_ui(|ui| {
+                                        ui.style_mut().interaction.selectable_labels = true;
+                                        ui.label("Size of the whole interface. Ctrl + and Ctrl - change it too.");
                                     });
                                 });
                                 ui.add_space(ui.spacing().item_spacing.y * 2.0);
                                 ui.with_layout(egui::Layout::top_down(egui::Align::Center), |ui| {
                                     if ui.button("Save").clicked() {
-                                        if self.active_theme == Theme::Dark {
-                                            ctx.set_visuals(Visuals::dark());
-                                        } else if self.active_theme == Theme::Light {
-                                            ctx.set_visuals(Visuals::light());
-                                        }
+                                        ctx.set_visuals(self.active_theme.visuals());
                                     }
                                 });
                             });
                     }
+                    if ui.button("Log").clicked() {
+                        self.show_log_window = true;
+                    }
+                    if ui.button(format!("Queue ({})", self.queue.jobs.len())).clicked() {
+                        self.show_queue_window = true;
+                    }
+                    if ui.button("History").clicked() {
+                        self.show_history_window = true;
+                    }
+                    if ui.button("Compare CRFs").clicked() {
+                        self.show_compare_window = true;
+                        if let Some(duration) = self.media_info.as_ref().and_then(|info| info.duration) {
+                            // Sample from the middle of the input, where credits and logos aren't
+                            self.crf_comparison.sample_start =
+                                (duration / 2.0 - self.crf_comparison.sample_length / 2.0).max(0.0);
+                        }
+                    }
+                    if !self.presets_folder.is_empty() {
+                        let mut selected = None;
+                        let mut refresh = false;
+                        ComboBox::from_id_salt("preset_switcher_combobox")
+                            .selected_text("Presets")
+                            .show_ui(ui, |ui| {
+                                for (path, name) in &self.preset_files {
+                                    if ui
+                                        .selectable_label(false, name)
+                                        .on_hover_text(path.display().to_string())
+                                        .clicked()
+                                    {
+                                        selected = Some(path.clone());
+                                    }
+                                }
+                                if self.preset_scan.is_some() {
+                                    ui.label(RichText::new("Looking for presets…").weak());
+                                } else if self.preset_files.is_empty() {
+                                    ui.label(RichText::new("No presets found").weak());
+                                }
+                                ui.separator();
+                                refresh = ui.selectable_label(false, "⟳ Refresh").clicked();
+                            });
+                        if let Some(path) = selected {
+                            match self.load_preset_from_file(&path.display().to_string()) {
+                                Ok(_) => {
+                                    println!("Preset loaded successfully from {}", path.display());
+                                    self.remember_preset(&path.display().to_string());
+                                }
+                                Err(e) => {
+                                    println!("Error loading preset: {}", e);
+                                    self.preset_error = Some(format!("{}\n\n{}", path.display(), e));
+                                }
+                            }
+                        }
+                        if refresh {
+                            self.scanned_presets_folder = None;
+                        }
+                    }
+                    ComboBox::from_id_salt("builtin_presets_combobox")
+                        .selected_text("Built-in Presets")
+                        .show_ui(ui, |ui| {
+                            for builtin in BUILTIN_PRESETS {
+                                if ui
+                                    .selectable_label(false, builtin.name)
+                                    .on_hover_text(builtin.description)
+                                    .clicked()
+                                {
+                                    self.apply_builtin_preset(builtin);
+                                }
+                            }
+                        });
+                    let mut selected_quality = None;
+                    ComboBox::from_id_salt("quality_presets_combobox")
+                        .selected_text("Quality Presets")
+                        .show_ui(ui, |ui| {
+                            let files = self.quality_preset_files.get_or_insert_with(list_quality_presets);
+                            for (path, name) in files.iter() {
+                                if ui
+                                    .selectable_label(false, name)
+                                    .on_hover_text("Sets only the encoder settings")
+                                    .clicked()
+                                {
+                                    selected_quality = Some(path.clone());
+                                }
+                            }
+                            if files.is_empty() {
+                                ui.label(RichText::new("No quality presets yet").weak());
+                            }
+                            ui.separator();
+                            if ui.selectable_label(false, "Save Current…").clicked() {
+                                self.show_save_quality_preset_window = true;
+                            }
+                        });
+                    if let Some(path) = selected_quality {
+                        if let Err(e) = self.load_quality_preset(&path) {
+                            println!("Error loading quality preset: {}", e);
+                            self.preset_error = Some(format!("{}\n\n{}", path.display(), e));
+                        }
+                    }
                     if ui.button("Load Preset").clicked() {
                         if let Some(path) = FileDialog::new()
-                            .add_filter("YAML Files", &["yaml", "yml"])
+                            .add_filter("Presets", &["yaml", "yml", "json"])
                             .pick_file()
                         {
                             match self.load_preset_from_file(&path.display().to_string()) {
                                 Ok(_) => {
                                     println!("Preset loaded successfully from {}", path.display());
+                                    self.remember_preset(&path.display().to_string());
                                 },
                                 Err(e) => {
                                     println!("Error loading preset: {}", e);
+                                    self.preset_error = Some(format!("{}\n\n{}", path.display(), e));
                                 }
                             }
                         }
                     }
-                    if ui.button("Save Preset").clicked() {
-                        if let Some(path) = FileDialog::new()
-                            .add_filter("YAML Files", &["yaml", "yml"])
-                            .save_file()
-                        {
-                            let path_string = path.display().to_string();
-                            let file_path = if path_string.ends_with(".yaml") || path_string.ends_with(".yml") {
-                                path_string
-                            } else {
-                                format!("{}.yaml", path_string)
-                            };
-
-                            match self.save_preset_to_file(&file_path) {
-                                Ok(_) => {
-                                    println!("Preset saved successfully to {}", file_path);
-                                },
+                    if !self.recent.presets.is_empty() {
+                        let mut load = None;
+                        let mut remove = None;
+                        ui.menu_button("Recent Presets", |ui| {
+                            for (index, path) in self.recent.presets.iter().enumerate() {
+                                if file_exists(path) {
+                                    if ui.button(file_name(path)).on_hover_text(path).clicked() {
+                                        load = Some(path.clone());
+                                        ui.close_menu();
+                                    }
+                                } else {
+                                    ui.horizontal(|ui| {
+                                        ui.add_enabled(false, egui::Button::new(file_name(path)))
+                                            .on_disabled_hover_text(format!("{} doesn't exist anymore", path));
+                                        if ui.small_button("Remove").clicked() {
+                                            remove = Some(index);
+                                        }
+                                    });
+                                }
+                            }
+                        });
+                        if let Some(path) = load {
+                            match self.load_preset_from_file(&path) {
+                                Ok(_) => self.remember_preset(&path),
                                 Err(e) => {
-                                    println!("Error saving preset: {}", e);
+                                    println!("Error loading preset: {}", e);
+                                    self.preset_error = Some(format!("{}\n\n{}", path, e));
                                 }
                             }
                         }
+                        if let Some(index) = remove {
+                            self.recent.presets.remove(index);
+                            save_recent(&self.recent);
+                        }
+                    }
+                    if ui.button("Save Preset").clicked() {
+                        self.show_save_preset_window = true;
+                    }
+                    let json = self.session_json.clone();
+                    if ui
+                        .add_enabled(self.settings_history.can_redo(), egui::Button::new("Redo"))
+                        .on_hover_text(ctx.format_shortcut(&REDO_SHORTCUT))
+                        .clicked()
+                    {
+                        self.undo_settings(true);
+                    }
+                    if ui
+                        .add_enabled(self.settings_history.can_undo(&json), egui::Button::new("Undo"))
+                        .on_hover_text(format!("Undo the last settings change ({})", ctx.format_shortcut(&UNDO_SHORTCUT)))
+                        .clicked()
+                    {
+                        self.undo_settings(false);
                     }
                 });
             });
+            if !self.preset_name.is_empty() {
+                ui.horizontal(|ui| {
+                    ui.label(RichText::new(&self.preset_name).strong());
+                    if !self.preset_description.is_empty() {
+                        ui.label(RichText::new(&self.preset_description).weak());
+                    }
+                });
+            }
             ui.separator();
 
             // Wrap the main content in a ScrollArea
             egui::ScrollArea::vertical().show(ui, |ui| {
                 let mut max_width = self.max_label_width.unwrap_or(0.0);
 
-                CollapsingHeader::new(RichText::from("File Options").weak())
-                    .default_open(true)
-                    .show(ui, |ui| {
+                let file_options = CollapsingHeader::new(RichText::from("File Options").weak())
+                    .default_open(self.sections.file_options)
+                    .show(ui, |ui| {
+                        ui.horizontal(|ui| {
+                            let label_text = "*Input File";
+                            let label_width = ui.label(label_text).rect.max.x - ui.min_rect().min.x;
+                            max_width = max_width.max(label_width);
+                            if label_width < max_width {
+                                ui.allocate_space(egui::vec2(max_width - label_width, 1.0));
+                            }
+                            ui.add_sized(
+                                [500.0, 20.0],
+                                egui::TextEdit::singleline(&mut self.input_file),
+                            );
+                            if ui.button("Browse").clicked() {
+                                if let Some(path) = FileDialog::new()
+                                    .add_filter("Video Files", &[".mkv"])
+                                    .add_filter("VapourSynth Scripts", &["vpy"])
+                                    .pick_file()
+                                {
+                                    self.input_file = path.display().to_string();
+                                }
+                            }
+                            if !self.recent.inputs.is_empty() {
+                                let mut pick = None;
+                                let mut remove = None;
+                                ui.menu_button("Recent", |ui| {
+                                    for (index, recent) in self.recent.inputs.iter().enumerate() {
+                                        let label = if recent.output.is_empty() {
+                                            file_name(&recent.input)
+                                        } else {
+                                            format!("{} → {}", file_name(&recent.input), file_name(&recent.output))
+                                        };
+                                        let hover = format!("{}\n→ {}", recent.input, recent.output);
+                                        if file_exists(&recent.input) {
+                                            if ui.button(label).on_hover_text(hover).clicked() {
+                                                pick = Some(index);
+                                                ui.close_menu();
+                                            }
+                                        } else {
+                                            ui.horizontal(|ui| {
+                                                ui.add_enabled(false, egui::Button::new(label))
+                                                    .on_disabled_hover_text(format!("{} doesn't exist anymore", recent.input));
+                                                if ui.small_button("Remove").clicked() {
+                                                    remove = Some(index);
+                                                }
+                                            });
+                                        }
+                                    }
+                                });
+                                if let Some(index) = pick {
+                                    self.pick_recent_input(index);
+                                }
+                                if let Some(index) = remove {
+                                    self.recent.inputs.remove(index);
+                                    save_recent(&self.recent);
+                                }
+                            }
+                            if ui
+                                .button("Join…")
+                                .on_hover_text("Join several files of one source into the input")
+                                .clicked()
+                            {
+                                self.show_join_window = true;
+                            }
+                            let can_preview = self.media_info.is_some();
+                            if ui.add_enabled(can_preview, egui::Button::new("Preview")).clicked() {
+                                let duration = self.media_info.as_ref().and_then(|info| info.duration);
+                                self.preview_timestamp = duration.unwrap_or(0.0) / 2.0;
+                                // Skip the slider debounce, the user asked for this frame explicitly
+                                self.preview_requested_at = Some(
+                                    Instant::now().checked_sub(PREVIEW_DEBOUNCE).unwrap_or_else(Instant::now),
+                                );
+                                self.preview_open_requested = true;
+                            }
+                            ui.label(RichText::new("ℹ").weak()).on_hover_ui(|ui| {
+                                ui.style_mut().interaction.selectable_labels = true;
+                                ui.label("Full path to the input MKV file, or to a VapourSynth (.vpy) script. Recent lists the last inputs with the outputs they went to.");
+                            });
+                        });
+                        if self.recent_settings.is_some() {
+                            ui.horizontal(|ui| {
+                                ui.label("This file was encoded before. Use the settings from back then?");
+                                if ui.small_button("Restore").clicked() {
+                                    self.restore_recent_settings();
+                                }
+                                if ui.small_button("Dismiss").clicked() {
+                                    self.recent_settings = None;
+                                }
+                            });
+                        }
+
                         ui.horizontal(|ui| {
-                            let label_text = "*Input File";
+                            let label_text = "*Output File";
                             let label_width = ui.label(label_text).rect.max.x - ui.min_rect().min.x;
                             max_width = max_width.max(label_width);
                             if label_width < max_width {
@@ -420,46 +6060,93 @@ impl eframe::App for AV1Studio {
                             }
                             ui.add_sized(
                                 [500.0, 20.0],
-                                egui::TextEdit::singleline(&mut self.input_file),
+                                egui::TextEdit::singleline(&mut self.output_file),
                             );
                             if ui.button("Browse").clicked() {
                                 if let Some(path) = FileDialog::new()
-                                    .add_filter("Video Files", &[".mkv"])
-                                    .pick_file()
+                                    .add_filter(
+                                        format!("{} Files", self.output_container.as_str()),
+                                        &[self.output_container.extension()],
+                                    )
+                                    .save_file()
                                 {
-                                    self.input_file = path.display().to_string();
+                                    self.output_file = path.display().to_string();
+                                    self.correct_output_extension();
                                 }
                             }
                             ui.label(RichText::new("ℹ").weak()).on_hover_ui(|ui| {
                                 ui.style_mut().interaction.selectable_labels = true;
-                                ui.label("Full path to the input MKV file.");
+                                ui.label("Full path to the output file.");
                             });
                         });
 
                         ui.horizontal(|ui| {
-                            let label_text = "*Output File";
+                            let label_text = "*Output Container";
                             let label_width = ui.label(label_text).rect.max.x - ui.min_rect().min.x;
                             max_width = max_width.max(label_width);
                             if label_width < max_width {
                                 ui.allocate_space(egui::vec2(max_width - label_width, 1.0));
                             }
-                            ui.add_sized(
-                                [500.0, 20.0],
-                                egui::TextEdit::singleline(&mut self.output_file),
-                            );
-                            if ui.button("Browse").clicked() {
-                                if let Some(path) = FileDialog::new()
-                                    .add_filter("Video Files", &["mkv"])
-                                    .pick_file()
-                                {
-                                    self.output_file = path.display().to_string();
-                                }
+                            let previous_container = self.output_container;
+                            ComboBox::from_id_salt("output_container_combobox")
+                                .selected_text(self.output_container.as_str())
+                                .show_ui(ui, |ui| {
+                                    ui.selectable_value(
+                                        &mut self.output_container,
+                                        OutputContainer::Mkv,
+                                        "MKV",
+                                    );
+                                    ui.selectable_value(
+                                        &mut self.output_container,
+                                        OutputContainer::Mp4,
+                                        "MP4",
+                                    );
+                                    ui.selectable_value(
+                                        &mut self.output_container,
+                                        OutputContainer::WebM,
+                                        "WebM",
+                                    );
+                                    ui.selectable_value(
+                                        &mut self.output_container,
+                                        OutputContainer::Ivf,
+                                        "IVF",
+                                    );
+                                });
+                            if self.output_container != previous_container {
+                                self.correct_output_extension();
                             }
                             ui.label(RichText::new("ℹ").weak()).on_hover_ui(|ui| {
                                 ui.style_mut().interaction.selectable_labels = true;
-                                ui.label("Full path to the output MKV file.");
+                                ui.label("Container format of the output file. MKV is the most flexible, MP4 plays on the most devices, WebM is meant for the web, and IVF holds just the raw AV1 stream without audio.");
                             });
                         });
+                        // The input's audio gets copied over, so it has to fit the container
+                        if let Some(info) = &self.media_info {
+                            let mut codecs: Vec<&str> = info
+                                .audio_tracks
+                                .iter()
+                                .filter_map(|track| track.codec.as_deref())
+                                .filter(|codec| !self.output_container.supports_audio(codec))
+                                .collect();
+                            // Each codec once, however the tracks are ordered
+                            codecs.sort_unstable();
+                            codecs.dedup();
+                            if self.output_container == OutputContainer::Ivf && !codecs.is_empty() {
+                                ui.colored_label(
+                                    ui.visuals().warn_fg_color,
+                                    "⚠ IVF can't hold audio, the input's audio tracks will be left out.",
+                                );
+                            } else if !codecs.is_empty() {
+                                ui.colored_label(
+                                    ui.visuals().warn_fg_color,
+                                    format!(
+                                        "⚠ {} can't hold {} audio from the input, the encode will fail at concatenation. Use MKV, or convert the audio with Extra Av1an Arguments (e.g. --audio-params \"-c:a libopus\").",
+                                        self.output_container.as_str(),
+                                        codecs.join(", ")
+                                    ),
+                                );
+                            }
+                        }
 
                         ui.horizontal(|ui| {
                             let label_text = "Scenes File";
@@ -480,10 +6167,27 @@ impl eframe::App for AV1Studio {
                                     self.scenes_file = path.display().to_string();
                                 }
                             }
+                            if self.scene_analysis.is_running() {
+                                if ui.button("Cancel").clicked() {
+                                    self.scene_analysis.cancel();
+                                }
+                                ui.spinner();
+                                match self.scene_analysis.progress() {
+                                    Some(progress) => ui.label(format!("{:.0}%", progress * 100.0)),
+                                    None => ui.label("Analyzing..."),
+                                };
+                            } else if ui
+                                .add_enabled(!self.input_file.trim().is_empty(), egui::Button::new("Analyze"))
+                                .on_hover_text("Only run Av1an's scene detection and fill in the scenes file it writes")
+                                .on_disabled_hover_text("Needs an input file")
+                                .clicked()
+                            {
+                                self.start_scene_analysis();
+                            }
                             ui.label(RichText::new("ℹ").weak()).on_hover_ui(|ui| {
                                 ui.style_mut().interaction.selectable_labels = true;
                                 ui.with_layout(egui::Layout::left_to_right(egui::Align::Min), |ui| {
-                                    ui.label("Full path to a scenes file. (Check out");
+                                    ui.label("Full path to a scenes file. Analyze runs only Av1an's scene detection with the current settings and writes one next to the output. (Check out");
                                     ui.hyperlink_to(
                                         RichText::new("Trix's Auto Boost Script")
                                             .color(egui::Color32::from_rgb(4, 165, 229)),
@@ -494,6 +6198,13 @@ impl eframe::App for AV1Studio {
                             });
                         });
 
+                        if let AnalysisStage::Failed(error) = &self.scene_analysis.stage {
+                            ui.colored_label(
+                                ui.visuals().error_fg_color,
+                                format!("Scene detection failed: {}", error),
+                            );
+                        }
+
                         ui.horizontal(|ui| {
                             let label_text = "Zones File";
                             let label_width = ui.label(label_text).rect.max.x - ui.min_rect().min.x;
@@ -513,6 +6224,13 @@ impl eframe::App for AV1Studio {
                                     self.zones_file = path.display().to_string();
                                 }
                             }
+                            if ui
+                                .add_enabled(!self.scenes_file.is_empty(), egui::Button::new("From Scenes…"))
+                                .on_hover_text("Give scenes from the scenes file their own CRF or preset")
+                                .clicked()
+                            {
+                                self.show_zones_window = true;
+                            }
                             ui.label(RichText::new("ℹ").weak()).on_hover_ui(|ui| {
                                 ui.style_mut().interaction.selectable_labels = true;
                                 ui.with_layout(egui::Layout::left_to_right(egui::Align::Min), |ui| {
@@ -529,521 +6247,511 @@ impl eframe::App for AV1Studio {
 
                         ui.add_space(ui.spacing().item_spacing.y * 2.0);
                     });
+                self.sections.file_options = !file_options.fully_closed();
 
-                CollapsingHeader::new(RichText::from("Source Settings").weak())
-                    .default_open(false)
+                let preview = CollapsingHeader::new(RichText::from("Preview").weak())
+                    .default_open(self.sections.preview)
+                    .open(std::mem::take(&mut self.preview_open_requested).then_some(true))
                     .show(ui, |ui| {
-                        ui.horizontal(|ui| {
-                            let label_text = "*Source Library";
-                            let label_width = ui.label(label_text).rect.max.x - ui.min_rect().min.x;
-                            max_width = max_width.max(label_width);
-                            if label_width < max_width {
-                                ui.allocate_space(egui::vec2(max_width - label_width, 1.0));
-                            }
-                            ComboBox::from_id_salt("source_library_combobox")
-                                .selected_text(self.source_library.as_str())
-                                .show_ui(ui, |ui| {
-                                    ui.selectable_value(
-                                        &mut self.source_library,
-                                        SourceLibrary::BestSource,
-                                        "BestSource",
-                                    );
-                                    ui.selectable_value(
-                                        &mut self.source_library,
-                                        SourceLibrary::FFMS2,
-                                        "FFMS2",
-                                    );
-                                    ui.selectable_value(
-                                        &mut self.source_library,
-                                        SourceLibrary::LSMASH,
-                                        "L-SMASH",
-                                    );
-                                });
-                            ui.label(RichText::new("ℹ").weak()).on_hover_ui(|ui| {
-                                ui.style_mut().interaction.selectable_labels = true;
-                                ui.label("Method to use for piping exact ranges of frames to the encoder (determines how frames are extracted and sent to the encoder). BestSource is now, supposedly, the best best and most accurate option, but slightly slower than L-SMASH and ffms2. L-SMASH can sometimes fuck up the frame orders completely. ffms2 might corrupt frames on problematic sources.");
-                            });
-                        });
+                        let duration = self.media_info.as_ref().and_then(|info| info.duration);
 
-                        ui.horizontal(|ui| {
-                            let label_text = "File Concatenation";
-                            let label_width = ui.label(label_text).rect.max.x - ui.min_rect().min.x;
-                            max_width = max_width.max(label_width);
-                            if label_width < max_width {
-                                ui.allocate_space(egui::vec2(max_width - label_width, 1.0));
-                            }
-                            ui.add_sized(
-                                [100.0, 20.0],
-                                egui::TextEdit::singleline(&mut self.file_concatenation),
-                            );
-                            ui.label(RichText::new("ℹ").weak()).on_hover_ui(|ui| {
-                                ui.style_mut().interaction.selectable_labels = true;
-                                ui.label("Method to use for concatenating encoded chunks and audio into output file. If you don't know what you're doing, just go with the default option.");
+                        if self.probe_receiver.is_some() {
+                            ui.horizontal(|ui| {
+                                ui.spinner();
+                                ui.label("Probing input file...");
                             });
-                        });
-                    });
-
-                ui.add_space(ui.spacing().item_spacing.y * 2.0);
-
-                CollapsingHeader::new(RichText::from("Video Settings").weak())
-                    .default_open(false)
-                    .show(ui, |ui| {
-                        ui.horizontal(|ui| {
-                            let label_text = "*(Output) Resolution";
-                            let label_width = ui.label(label_text).rect.max.x - ui.min_rect().min.x;
-                            max_width = max_width.max(label_width);
-                            if label_width < max_width {
-                                ui.allocate_space(egui::vec2(max_width - label_width, 1.0));
-                            }
-                            ui.add_sized(
-                                [100.0, 20.0],
-                                egui::TextEdit::singleline(&mut self.width),
-                            );
-                            ui.label("×");
-                            ui.add_sized(
-                                [100.0, 20.0],
-                                egui::TextEdit::singleline(&mut self.height),
-                            );
-                            ui.label(RichText::new("ℹ").weak()).on_hover_ui(|ui| {
-                                ui.style_mut().interaction.selectable_labels = true;
-                                ui.label("Resolution to resize the output video to.");
+                        } else if let Some(duration) = duration {
+                            ui.horizontal(|ui| {
+                                let label_text = "Timestamp";
+                                let label_width = ui.label(label_text).rect.max.x - ui.min_rect().min.x;
+                                max_width = max_width.max(label_width);
+                                if label_width < max_width {
+                                    ui.allocate_space(egui::vec2(max_width - label_width, 1.0));
+                                }
+                                let response = ui.add(
+                                    Slider::new(&mut self.preview_timestamp, 0.0..=duration)
+                                        .custom_formatter(|n, _| format_timestamp(n)),
+                                );
+                                if response.changed() {
+                                    self.preview_requested_at = Some(Instant::now());
+                                }
+                                if self.preview_receiver.is_some() {
+                                    ui.spinner();
+                                }
+                                ui.label(RichText::new("ℹ").weak()).on_hover_ui(|ui| {
+                                    ui.style_mut().interaction.selectable_labels = true;
+                                    ui.label("Position in the input file to show a frame from. The frame is extracted with FFmpeg once you stop dragging the slider.");
+                                });
                             });
-                        });
+                        }
 
-                        ui.horizontal(|ui| {
-                            let label_text = "*(Output) Pixel Format";
-                            let label_width = ui.label(label_text).rect.max.x - ui.min_rect().min.x;
-                            max_width = max_width.max(label_width);
-                            if label_width < max_width {
-                                ui.allocate_space(egui::vec2(max_width - label_width, 1.0));
-                            }
-                            ComboBox::from_id_salt("output_pixel_format_combobox")
-                                .selected_text(self.output_pixel_format.as_str())
-                                .show_ui(ui, |ui| {
-                                    ui.selectable_value(
-                                        &mut self.output_pixel_format,
-                                        PixelFormat::Yuv420p10le,
-                                        "yuv420p10le",
-                                    );
-                                    ui.selectable_value(
-                                        &mut self.output_pixel_format,
-                                        PixelFormat::Yuv420p,
-                                        "yuv420p",
-                                    );
+                        if let Some(duration) = duration {
+                            ui.horizontal(|ui| {
+                                let label_text = "Sample Encode";
+                                let label_width = ui.label(label_text).rect.max.x - ui.min_rect().min.x;
+                                max_width = max_width.max(label_width);
+                                if label_width < max_width {
+                                    ui.allocate_space(egui::vec2(max_width - label_width, 1.0));
+                                }
+                                let running = self.sample_encode.is_running();
+                                ui.add_enabled(
+                                    !running,
+                                    egui::DragValue::new(&mut self.sample_encode.timestamp)
+                                        .range(0.0..=duration)
+                                        .custom_formatter(|n, _| format_timestamp(n)),
+                                );
+                                if running {
+                                    if ui.button("Cancel").clicked() {
+                                        self.sample_encode.cancel();
+                                    }
+                                    ui.spinner();
+                                    if let SampleStage::Encoding(session) = &self.sample_encode.stage {
+                                        if let (Some(ef), Some(tf)) = (session.encoded_frames, session.total_frames) {
+                                            ui.label(format!("{} / {}", ef, tf));
+                                        }
+                                    } else {
+                                        ui.label("Cutting...");
+                                    }
+                                } else if ui
+                                    .add_enabled(self.tools().has_ffmpeg(), egui::Button::new("Encode Sample"))
+                                    .clicked()
+                                {
+                                    let output = self.sample_encode.output_path(self.output_container.extension());
+                                    let settings = self.sample_settings(&self.sample_encode.source_path(), &output);
+                                    match generate_command(&settings) {
+                                        Ok(cmd) => {
+                                            println!("{}", command_to_string(&cmd));
+                                            self.sample_encode.start(&self.ffmpeg_paths(), &expand_path_string(&self.input_file), output, cmd);
+                                        }
+                                        Err(problem) => self.log.push(format!("The sample didn't start: {}", problem)),
+                                    }
+                                }
+                                if matches!(self.sample_encode.stage, SampleStage::Done)
+                                    && ui.button("Open").clicked()
+                                {
+                                    if let Err(e) = open_in_default_player(&self.sample_encode.output) {
+                                        eprintln!("ERROR : Couldn't open {:?}: {}", self.sample_encode.output, e);
+                                    }
+                                }
+                                ui.label(RichText::new("ℹ").weak()).on_hover_ui(|ui| {
+                                    ui.style_mut().interaction.selectable_labels = true;
+                                    ui.label("Encodes 5 seconds around this timestamp with the current settings and opens the result in your default player, so you can check the quality before the full encode.");
                                 });
-                            ui.label(RichText::new("ℹ").weak()).on_hover_ui(|ui| {
-                                ui.style_mut().interaction.selectable_labels = true;
-                                ui.label("FFmpeg pixel format to use. It's best to go with yuv420p10le (10-bit color format), even if the input video has 8-bit colors.");
                             });
-                        });
-
-                        ui.horizontal(|ui| {
-                            let label_text = "Color Primaries";
-                            let label_width = ui.label(label_text).rect.max.x - ui.min_rect().min.x;
-                            max_width = max_width.max(label_width);
-                            if label_width < max_width {
-                                ui.allocate_space(egui::vec2(max_width - label_width, 1.0));
+                            if let SampleStage::Failed(error) = &self.sample_encode.stage {
+                                ui.colored_label(ui.visuals().error_fg_color, error);
                             }
-                            ComboBox::from_id_salt("color_primaries_combobox")
-                                .selected_text(self.color_primaries.as_str())
-                                .show_ui(ui, |ui| {
-                                    ui.selectable_value(
-                                        &mut self.color_primaries,
-                                        ColorPrimaries::Bt709,
-                                        "(1) BT.709",
-                                    );
-                                    ui.selectable_value(
-                                        &mut self.color_primaries,
-                                        ColorPrimaries::Unspecified,
-                                        "(2) Unspecified, Default",
-                                    );
-                                    ui.selectable_value(
-                                        &mut self.color_primaries,
-                                        ColorPrimaries::Bt470m,
-                                        "(4) BT.470 System M (historical)",
-                                    );
-                                    ui.selectable_value(
-                                        &mut self.color_primaries,
-                                        ColorPrimaries::Bt470bg,
-                                        "(5) BT.470 System B, G (historical)",
-                                    );
-                                    ui.selectable_value(
-                                        &mut self.color_primaries,
-                                        ColorPrimaries::Bt601,
-                                        "(6) BT.601",
-                                    );
-                                    ui.selectable_value(
-                                        &mut self.color_primaries,
-                                        ColorPrimaries::Smpte240,
-                                        "(7) SMPTE 240",
-                                    );
-                                    ui.selectable_value(
-                                        &mut self.color_primaries,
-                                        ColorPrimaries::Film,
-                                        "(8) Generic Film (color filters using illuminant C)",
-                                    );
-                                    ui.selectable_value(
-                                        &mut self.color_primaries,
-                                        ColorPrimaries::Bt2020,
-                                        "(9) BT.2020, BT.2100",
-                                    );
-                                    ui.selectable_value(
-                                        &mut self.color_primaries,
-                                        ColorPrimaries::Xyz,
-                                        "(10) SMPTE 428 (CIE 1921 XYZ)",
-                                    );
-                                    ui.selectable_value(
-                                        &mut self.color_primaries,
-                                        ColorPrimaries::Smpte431,
-                                        "(11) SMPTE RP 431-2",
-                                    );
-                                    ui.selectable_value(
-                                        &mut self.color_primaries,
-                                        ColorPrimaries::Smpte432,
-                                        "(12) SMPT EG 432-1",
-                                    );
-                                    ui.selectable_value(
-                                        &mut self.color_primaries,
-                                        ColorPrimaries::Ebu3213,
-                                        "(22) EBU Tech. 3213-E",
+                        }
+
+                        if let Some(texture) = &self.preview_texture {
+                            let size = texture.size_vec2();
+                            let width = ui.available_width().min(640.0);
+                            ui.image((texture.id(), size * (width / size.x)));
+                        } else if let Some(error) = &self.preview_error {
+                            egui::Frame::group(ui.style()).show(ui, |ui| {
+                                ui.set_min_size(egui::vec2(320.0, 180.0));
+                                ui.centered_and_justified(|ui| {
+                                    ui.colored_label(
+                                        ui.visuals().error_fg_color,
+                                        format!("Preview unavailable: {}", error),
                                     );
                                 });
-                            ui.label(RichText::new("ℹ").weak()).on_hover_ui(|ui| {
-                                ui.style_mut().interaction.selectable_labels = true;
-                                ui.label("Color primaries, refer to the (SVT-AV1-PSY) user guide Appendix A.2 for full details. If you don't know what you're doing, just use the default option (2).");
                             });
-                        });
+                        } else if self.media_info.is_none() && self.probe_receiver.is_none() {
+                            ui.label(RichText::new("Select an input file to preview it.").weak());
+                        }
 
-                        ui.horizontal(|ui| {
-                            let label_text = "Matrix Coefficients";
-                            let label_width = ui.label(label_text).rect.max.x - ui.min_rect().min.x;
-                            max_width = max_width.max(label_width);
-                            if label_width < max_width {
-                                ui.allocate_space(egui::vec2(max_width - label_width, 1.0));
-                            }
-                            ComboBox::from_id_salt("matrix_coefficients_combobox")
-                                .selected_text(self.matrix_coefficients.as_str())
-                                .show_ui(ui, |ui| {
-                                    ui.selectable_value(
-                                        &mut self.matrix_coefficients,
-                                        MatrixCoefficients::Identity,
-                                        "(0) Identity matrix",
-                                    );
-                                    ui.selectable_value(
-                                        &mut self.matrix_coefficients,
-                                        MatrixCoefficients::Bt709,
-                                        "(1) BT.709",
-                                    );
-                                    ui.selectable_value(
-                                        &mut self.matrix_coefficients,
-                                        MatrixCoefficients::Unspecified,
-                                        "(2) unspecified, default",
-                                    );
-                                    ui.selectable_value(
-                                        &mut self.matrix_coefficients,
-                                        MatrixCoefficients::Fcc,
-                                        "(4) US FCC 73.628",
-                                    );
-                                    ui.selectable_value(
-                                        &mut self.matrix_coefficients,
-                                        MatrixCoefficients::Bt470bg,
-                                        "(5) BT.470 System B, G (historical)",
-                                    );
-                                    ui.selectable_value(
-                                        &mut self.matrix_coefficients,
-                                        MatrixCoefficients::Bt601,
-                                        "(6) BT.601",
-                                    );
-                                    ui.selectable_value(
-                                        &mut self.matrix_coefficients,
-                                        MatrixCoefficients::Smpte240,
-                                        "(7) SMPTE 240 M",
-                                    );
-                                    ui.selectable_value(
-                                        &mut self.matrix_coefficients,
-                                        MatrixCoefficients::Ycgco,
-                                        "(8) YCgCo",
-                                    );
-                                    ui.selectable_value(
-                                        &mut self.matrix_coefficients,
-                                        MatrixCoefficients::Bt2020Ncl,
-                                        "(9) BT.2020 non-constant luminance, BT.2100 YCbCr",
-                                    );
-                                    ui.selectable_value(
-                                        &mut self.matrix_coefficients,
-                                        MatrixCoefficients::Bt2020Cl,
-                                        "(10) BT.2020 constant luminance",
-                                    );
-                                    ui.selectable_value(
-                                        &mut self.matrix_coefficients,
-                                        MatrixCoefficients::Smpte2085,
-                                        "(11) SMPTE ST 2085 YDzDx",
-                                    );
-                                    ui.selectable_value(
-                                        &mut self.matrix_coefficients,
-                                        MatrixCoefficients::ChromaNcl,
-                                        "(12) Chromaticity-derived non-constant luminance",
-                                    );
-                                    ui.selectable_value(
-                                        &mut self.matrix_coefficients,
-                                        MatrixCoefficients::ChromaCl,
-                                        "(13) Chromaticity-derived constant luminance",
-                                    );
-                                    ui.selectable_value(
-                                        &mut self.matrix_coefficients,
-                                        MatrixCoefficients::Ictcp,
-                                        "(14) BT.2100 ICtCp",
+                        ui.add_space(ui.spacing().item_spacing.y * 2.0);
+                    });
+                self.sections.preview = !preview.fully_closed();
+
+                let media_info = CollapsingHeader::new(RichText::from("Media Info").weak())
+                    .default_open(self.sections.media_info)
+                    .show(ui, |ui| {
+                        if let Some(info) = &self.media_info {
+                            let unknown = || String::from("Unknown");
+                            egui::Grid::new("media_info_grid")
+                                .num_columns(2)
+                                .striped(true)
+                                .show(ui, |ui| {
+                                    ui.label("Duration");
+                                    ui.label(info.duration.map(format_timestamp).unwrap_or_else(unknown));
+                                    ui.end_row();
+
+                                    ui.label("Frame Count");
+                                    ui.label(info.frame_count.map(|n| n.to_string()).unwrap_or_else(unknown));
+                                    ui.end_row();
+
+                                    ui.label("Frame Rate");
+                                    ui.label(info.fps.map(|fps| format!("{:.3} fps", fps)).unwrap_or_else(unknown));
+                                    ui.end_row();
+
+                                    ui.label("Resolution");
+                                    ui.label(match (info.width, info.height) {
+                                        (Some(width), Some(height)) => format!("{}×{}", width, height),
+                                        _ => unknown(),
+                                    });
+                                    ui.end_row();
+
+                                    ui.label("Pixel Format");
+                                    ui.label(info.pixel_format.clone().unwrap_or_else(unknown));
+                                    ui.end_row();
+
+                                    ui.label("Field Order");
+                                    ui.label(info.field_order.clone().unwrap_or_else(unknown));
+                                    ui.end_row();
+
+                                    ui.label("Codec");
+                                    ui.label(info.codec.clone().unwrap_or_else(unknown));
+                                    ui.end_row();
+
+                                    ui.label("Bitrate");
+                                    ui.label(
+                                        info.bitrate
+                                            .map(|b| format!("{:.0} kbps", b as f64 / 1000.0))
+                                            .unwrap_or_else(unknown),
                                     );
+                                    ui.end_row();
+
+                                    ui.label("Audio Tracks");
+                                    ui.vertical(|ui| {
+                                        if info.audio_tracks.is_empty() {
+                                            ui.label("None");
+                                        }
+                                        for (index, track) in info.audio_tracks.iter().enumerate() {
+                                            ui.label(format!(
+                                                "#{} {}, {} channels, {}",
+                                                index + 1,
+                                                track.codec.as_deref().unwrap_or("unknown codec"),
+                                                track.channels.map(|c| c.to_string()).unwrap_or_else(|| String::from("?")),
+                                                track.language.as_deref().unwrap_or("und"),
+                                            ));
+                                        }
+                                    });
+                                    ui.end_row();
                                 });
-                            ui.label(RichText::new("ℹ").weak()).on_hover_ui(|ui| {
-                                ui.style_mut().interaction.selectable_labels = true;
-                                ui.label("Matrix coefficients, refer to the (SVT-AV1-PSY) user guide Appendix A.2 for full details. If you don't know what you're doing, just use the default option (2).");
+                        } else if self.probe_receiver.is_some() {
+                            ui.horizontal(|ui| {
+                                ui.spinner();
+                                ui.label("Probing input file...");
                             });
-                        });
+                        } else if !self.tools().has_ffprobe() {
+                            ui.colored_label(ui.visuals().warn_fg_color, "⚠ ffprobe isn't installed, so there's no media info.");
+                        } else {
+                            ui.label(RichText::new("Select an input file to see its details.").weak());
+                        }
+
+                        ui.add_space(ui.spacing().item_spacing.y * 2.0);
+                    });
+                self.sections.media_info = !media_info.fully_closed();
 
+                let source_settings = CollapsingHeader::new(RichText::from("Source Settings").weak())
+                    .default_open(self.sections.source_settings)
+                    .show(ui, |ui| {
                         ui.horizontal(|ui| {
-                            let label_text = "Transfer Characteristics";
+                            let label_text = "*Source Library";
                             let label_width = ui.label(label_text).rect.max.x - ui.min_rect().min.x;
                             max_width = max_width.max(label_width);
                             if label_width < max_width {
-                                ui.allocate_space(egui::vec2(max_width - label_width, 1.0));
-                            }
-                            ComboBox::from_id_salt("transfer_characteristics_combobox")
-                                .selected_text(self.transfer_characteristics.as_str())
-                                .show_ui(ui, |ui| {
-                                    ui.selectable_value(
-                                        &mut self.transfer_characteristics,
-                                        TransferCharacteristics::Bt709,
-                                        "(1) BT.709",
-                                    );
-                                    ui.selectable_value(
-                                        &mut self.transfer_characteristics,
-                                        TransferCharacteristics::Unpsecified,
-                                        "(2) unspecified, default",
-                                    );
-                                    ui.selectable_value(
-                                        &mut self.transfer_characteristics,
-                                        TransferCharacteristics::Bt470m,
-                                        "(4) BT.470 System M (historical)",
-                                    );
-                                    ui.selectable_value(
-                                        &mut self.transfer_characteristics,
-                                        TransferCharacteristics::Bt470bg,
-                                        "(5) BT.470 System B, G (historical)",
-                                    );
-                                    ui.selectable_value(
-                                        &mut self.transfer_characteristics,
-                                        TransferCharacteristics::Bt601,
-                                        "(6) BT.601",
-                                    );
-                                    ui.selectable_value(
-                                        &mut self.transfer_characteristics,
-                                        TransferCharacteristics::Smpte240,
-                                        "(7) SMPTE 240 M",
-                                    );
-                                    ui.selectable_value(
-                                        &mut self.transfer_characteristics,
-                                        TransferCharacteristics::Linear,
-                                        "(8) Linear",
-                                    );
-                                    ui.selectable_value(
-                                        &mut self.transfer_characteristics,
-                                        TransferCharacteristics::Log100,
-                                        "(9) Logarithmic (100 : 1 range)",
-                                    );
-                                    ui.selectable_value(
-                                        &mut self.transfer_characteristics,
-                                        TransferCharacteristics::Log100Sqrt10,
-                                        "(10) Logarithmic (100 * Sqrt(10) : 1 range)",
-                                    );
-                                    ui.selectable_value(
-                                        &mut self.transfer_characteristics,
-                                        TransferCharacteristics::Iec61966,
-                                        "(11) IEC 61966-2-4",
-                                    );
-                                    ui.selectable_value(
-                                        &mut self.transfer_characteristics,
-                                        TransferCharacteristics::Bt1361,
-                                        "(12) BT.1361",
-                                    );
-                                    ui.selectable_value(
-                                        &mut self.transfer_characteristics,
-                                        TransferCharacteristics::Srgb,
-                                        "(13) sRGB or sYCC",
-                                    );
-                                    ui.selectable_value(
-                                        &mut self.transfer_characteristics,
-                                        TransferCharacteristics::Bt202010,
-                                        "(14) BT.2020 10-bit systems",
-                                    );
-                                    ui.selectable_value(
-                                        &mut self.transfer_characteristics,
-                                        TransferCharacteristics::Bt202012,
-                                        "(15) BT.2020 12-bit systems",
-                                    );
-                                    ui.selectable_value(
-                                        &mut self.transfer_characteristics,
-                                        TransferCharacteristics::Smpte2084,
-                                        "(16) SMPTE ST 2084, ITU BT.2100 PQ",
-                                    );
-                                    ui.selectable_value(
-                                        &mut self.transfer_characteristics,
-                                        TransferCharacteristics::Smpte428,
-                                        "(17) SMPTE ST 428",
-                                    );
-                                    ui.selectable_value(
-                                        &mut self.transfer_characteristics,
-                                        TransferCharacteristics::Hlg,
-                                        "(18) BT.2100 HLG, ARIB STD-B67",
-                                    );
+                                ui.allocate_space(egui::vec2(max_width - label_width, 1.0));
+                            }
+                            if self.is_vapoursynth_script() {
+                                ui.label(RichText::new("VapourSynth script").weak());
+                            } else if self.chunk_method != ChunkMethod::SourceLibrary {
+                                ui.label(RichText::new(format!("Not used by {}", self.chunk_method.as_str())).weak());
+                            } else {
+                                ComboBox::from_id_salt("source_library_combobox")
+                                    .selected_text(self.source_library.as_str())
+                                    .show_ui(ui, |ui| {
+                                        ui.selectable_value(
+                                            &mut self.source_library,
+                                            SourceLibrary::BestSource,
+                                            "BestSource",
+                                        );
+                                        ui.selectable_value(
+                                            &mut self.source_library,
+                                            SourceLibrary::FFMS2,
+                                            "FFMS2",
+                                        );
+                                        ui.selectable_value(
+                                            &mut self.source_library,
+                                            SourceLibrary::LSMASH,
+                                            "L-SMASH",
+                                        );
+                                    });
+                            }
+                            ui.label(RichText::new("ℹ").weak()).on_hover_ui(|ui| {
+                                ui.style_mut().interaction.selectable_labels = true;
+                                ui.label("Method to use for piping exact ranges of frames to the encoder (determines how frames are extracted and sent to the encoder). Not used for VapourSynth script inputs, which provide their own source. BestSource is now, supposedly, the best best and most accurate option, but slightly slower than L-SMASH and ffms2. L-SMASH can sometimes fuck up the frame orders completely. ffms2 might corrupt frames on problematic sources.");
+                            });
+                        });
+
+                        if !self.is_vapoursynth_script() {
+                            ui.horizontal(|ui| {
+                                let label_text = "Chunk Method";
+                                let label_width = ui.label(label_text).rect.max.x - ui.min_rect().min.x;
+                                max_width = max_width.max(label_width);
+                                if label_width < max_width {
+                                    ui.allocate_space(egui::vec2(max_width - label_width, 1.0));
+                                }
+                                ComboBox::from_id_salt("chunk_method_combobox")
+                                    .selected_text(self.chunk_method.as_str())
+                                    .show_ui(ui, |ui| {
+                                        for method in [ChunkMethod::SourceLibrary, ChunkMethod::Hybrid, ChunkMethod::Select] {
+                                            ui.selectable_value(&mut self.chunk_method, method, method.as_str());
+                                        }
+                                    });
+                                ui.label(RichText::new("ℹ").weak()).on_hover_ui(|ui| {
+                                    ui.style_mut().interaction.selectable_labels = true;
+                                    ui.label("Av1an's -m. Source Library cuts the chunks with the library above, through VapourSynth. Hybrid and Select cut with ffmpeg instead, for machines without VapourSynth: Hybrid splits at keyframes and seeks the rest, Select decodes from the start for every chunk and is slow but exact.");
                                 });
+                            });
+                        }
+
+                        ui.horizontal(|ui| {
+                            let label_text = "Min | Max Scene Length";
+                            let label_width = ui.label(label_text).rect.max.x - ui.min_rect().min.x;
+                            max_width = max_width.max(label_width);
+                            if label_width < max_width {
+                                ui.allocate_space(egui::vec2(max_width - label_width, 1.0));
+                            }
+                            ui.add(
+                                egui::TextEdit::singleline(&mut self.min_scene_len)
+                                    .hint_text("24")
+                                    .desired_width(50.0),
+                            );
+                            ui.label("|");
+                            ui.add(
+                                egui::TextEdit::singleline(&mut self.max_scene_len)
+                                    .hint_text("240")
+                                    .desired_width(50.0),
+                            );
+                            ui.label("frames");
                             ui.label(RichText::new("ℹ").weak()).on_hover_ui(|ui| {
                                 ui.style_mut().interaction.selectable_labels = true;
-                                ui.label("Transfer characteristics, refer to the user guide Appendix A.2 for full details. If you don't know what you're doing, just use the default option (2).");
+                                ui.label("Av1an's --min-scene-len and --extra-split. Scenes shorter than the min get merged into their neighbours, so sources full of quick cuts don't end up as lots of tiny chunks. Scenes longer than the max get split, 0 never splits them. Leave empty for Av1an's defaults (24 and 240 frames).");
                             });
                         });
+                        if let Some(problem) = scene_length_problem(&self.min_scene_len, &self.max_scene_len) {
+                            ui.colored_label(ui.visuals().warn_fg_color, format!("⚠ {}.", problem));
+                        }
 
                         ui.horizontal(|ui| {
-                            let label_text = "Color Range";
+                            let label_text = "File Concatenation";
                             let label_width = ui.label(label_text).rect.max.x - ui.min_rect().min.x;
                             max_width = max_width.max(label_width);
                             if label_width < max_width {
                                 ui.allocate_space(egui::vec2(max_width - label_width, 1.0));
                             }
-                            ComboBox::from_id_salt("color_range_combobox")
-                                .selected_text(self.color_range.as_str())
-                                .show_ui(ui, |ui| {
-                                    ui.selectable_value(
-                                        &mut self.color_range,
-                                        ColorRange::Studio,
-                                        "(0) studio, default",
-                                    );
-                                    ui.selectable_value(
-                                        &mut self.color_range,
-                                        ColorRange::Full,
-                                        "(1) full",
-                                    );
-                                });
+                            ui.add_sized(
+                                [100.0, 20.0],
+                                egui::TextEdit::singleline(&mut self.file_concatenation),
+                            );
                             ui.label(RichText::new("ℹ").weak()).on_hover_ui(|ui| {
                                 ui.style_mut().interaction.selectable_labels = true;
-                                ui.label("Color range. If you don't know whast you're doing, just go with the default option (0).");
+                                ui.label("Method to use for concatenating encoded chunks and audio into output file. If you don't know what you're doing, just go with the default option.");
                             });
                         });
+                        let concat = self.file_concatenation.trim().to_string();
+                        if !concat.is_empty() && !self.output_container.supports_concat(&concat) {
+                            let default_concat = self.output_container.default_concat().to_string();
+                            ui.horizontal(|ui| {
+                                ui.colored_label(
+                                    ui.visuals().warn_fg_color,
+                                    format!(
+                                        "⚠ {} can't produce {} output, {} will be used instead.",
+                                        concat,
+                                        self.output_container.as_str(),
+                                        default_concat,
+                                    ),
+                                );
+                                if ui.button(format!("Use {}", default_concat)).clicked() {
+                                    self.file_concatenation = default_concat;
+                                }
+                            });
+                        }
+                        let effective_concat = if concat.is_empty() || !self.output_container.supports_concat(&concat) {
+                            self.output_container.default_concat()
+                        } else {
+                            &concat
+                        };
+                        if !self.tools().supports_concat(effective_concat) {
+                            ui.colored_label(
+                                ui.visuals().warn_fg_color,
+                                format!("⚠ {} isn't installed, av1an won't be able to put the output together.", effective_concat),
+                            );
+                        }
                     });
+                self.sections.source_settings = !source_settings.fully_closed();
+
+                ui.add_space(ui.spacing().item_spacing.y * 2.0);
+
+                let video_settings = CollapsingHeader::new(RichText::from("Video Settings").weak())
+                    .default_open(self.sections.video_settings)
+                    .show(ui, |ui| self.video_settings_ui(ui, &mut max_width));
+                self.sections.video_settings = !video_settings.fully_closed();
+
+                ui.add_space(ui.spacing().item_spacing.y * 2.0);
+
+                let encoder_settings = CollapsingHeader::new(RichText::from("Encoder Settings").weak())
+                    .default_open(self.sections.encoder_settings)
+                    .show(ui, |ui| self.encoder_settings_ui(ui, &mut max_width));
+                self.sections.encoder_settings = !encoder_settings.fully_closed();
 
                 ui.add_space(ui.spacing().item_spacing.y * 2.0);
 
-                CollapsingHeader::new(RichText::from("Encoder Settings").weak())
-                    .default_open(true)
+                let performance_settings = CollapsingHeader::new(RichText::from("Performance Settings").weak())
+                    .default_open(self.sections.performance_settings)
                     .show(ui, |ui| {
                         ui.horizontal(|ui| {
-                            let label_text = "*Preset";
+                            let label_text = "*Thread Affinity";
                             let label_width = ui.label(label_text).rect.max.x - ui.min_rect().min.x;
                             max_width = max_width.max(label_width);
                             if label_width < max_width {
                                 ui.allocate_space(egui::vec2(max_width - label_width, 1.0));
                             }
-                            ui.add(
-                                Slider::new(&mut self.preset, 0.0..=13.0)
-                                    .step_by(1.0)
-                                    .custom_formatter(|n, _| format!("{}", n as i32)),
+                            ui.add_sized(
+                                [100.0, 20.0],
+                                egui::TextEdit::singleline(&mut self.thread_affinity),
                             );
                             ui.label(RichText::new("ℹ").weak()).on_hover_ui(|ui| {
                                 ui.style_mut().interaction.selectable_labels = true;
-                                ui.label("Encoding preset to use. A very simple explanation is that you trade quality for encoding speed, the lower you go. Can be set from a range of 0-13. Generally, the sweet spot will be between 2-4-6, of course, depending on how powerful your CPU is, you might want to go higher.");
+                                ui.label("Pin each worker to a specific set of threads of this size. Leaving this option unspecified allows the OS to schedule all processes spawned.");
                             });
                         });
 
                         ui.horizontal(|ui| {
-                            let label_text = "*CRF";
+                            let label_text = "*Workers";
                             let label_width = ui.label(label_text).rect.max.x - ui.min_rect().min.x;
                             max_width = max_width.max(label_width);
                             if label_width < max_width {
                                 ui.allocate_space(egui::vec2(max_width - label_width, 1.0));
                             }
-                            ui.add(Slider::new(&mut self.crf, 0.0..=70.0).step_by(1.0));
+                            ui.add_enabled(
+                                !self.auto_workers,
+                                egui::TextEdit::singleline(&mut self.workers).desired_width(100.0),
+                            );
+                            ui.checkbox(&mut self.auto_workers, "Auto");
                             ui.label(RichText::new("ℹ").weak()).on_hover_ui(|ui| {
                                 ui.style_mut().interaction.selectable_labels = true;
-                                ui.label("Sets CRF value. A simple explanation is that you trade file size for quality, the lower you go. Can be set from a range of 0-70, can be set in quarter steps (0.25). Generally, the sweet spot will be between 27-23.");
+                                ui.label("Number of workers to spawn. It's generally recommended, if you have enough RAM, to set this to the total amount of CPU cores you have for better encoding speeds. Defaults to the number of physical cores. With Auto, -w is left out and Av1an figures out the amount of workers to spawn itself.");
                             });
                         });
 
                         ui.horizontal(|ui| {
-                            let label_text = "*Synthetic Grain";
+                            let label_text = "Chunk Order";
                             let label_width = ui.label(label_text).rect.max.x - ui.min_rect().min.x;
                             max_width = max_width.max(label_width);
                             if label_width < max_width {
                                 ui.allocate_space(egui::vec2(max_width - label_width, 1.0));
                             }
-                            ui.add_sized(
-                                [100.0, 20.0],
-                                egui::TextEdit::singleline(&mut self.synthetic_grain),
-                            );
+                            ComboBox::from_id_salt("chunk_order_combobox")
+                                .selected_text(self.chunk_order.as_str())
+                                .show_ui(ui, |ui| {
+                                    for order in [
+                                        ChunkOrder::LongToShort,
+                                        ChunkOrder::ShortToLong,
+                                        ChunkOrder::Sequential,
+                                        ChunkOrder::Random,
+                                    ] {
+                                        ui.selectable_value(&mut self.chunk_order, order, order.as_str());
+                                    }
+                                });
                             ui.label(RichText::new("ℹ").weak()).on_hover_ui(|ui| {
                                 ui.style_mut().interaction.selectable_labels = true;
-                                ui.label("Sets the strength of the synthetic grain applied to the video.");
+                                ui.label("Av1an's --chunk-order, which chunks get encoded first. long-to-short (Av1an's default) keeps the workers busy until the end. short-to-long and sequential show finished chunks sooner, random makes the progress estimate settle fastest.");
                             });
                         });
 
                         ui.horizontal(|ui| {
-                            let label_text = "Custom Encoder Parameters";
+                            let label_text = "Force";
                             let label_width = ui.label(label_text).rect.max.x - ui.min_rect().min.x;
                             max_width = max_width.max(label_width);
                             if label_width < max_width {
                                 ui.allocate_space(egui::vec2(max_width - label_width, 1.0));
-                            } else {
-                                ui.allocate_space(egui::vec2(0.5, 1.0));
                             }
-                            ui.add_sized(
-                                [500.0, 20.0],
-                                egui::TextEdit::singleline(&mut self.custom_encode_params),
-                            );
+                            ui.checkbox(&mut self.force, "");
                             ui.label(RichText::new("ℹ").weak()).on_hover_ui(|ui| {
                                 ui.style_mut().interaction.selectable_labels = true;
-                                ui.label("Provides SVT-AV1-PSY custom encoder parameters on top of the already included parameters.");
+                                ui.label("Av1an's --force: skips Av1an's check of the encoder parameters and passes them on as they are. Needed for parameters Av1an doesn't know about. Applies to custom parameters too.");
                             });
                         });
-                    });
 
-                ui.add_space(ui.spacing().item_spacing.y * 2.0);
+                        ui.horizontal(|ui| {
+                            let label_text = "Run at Low Priority";
+                            let label_width = ui.label(label_text).rect.max.x - ui.min_rect().min.x;
+                            max_width = max_width.max(label_width);
+                            if label_width < max_width {
+                                ui.allocate_space(egui::vec2(max_width - label_width, 1.0));
+                            }
+                            ui.checkbox(&mut self.low_priority, "");
+                            ui.label(RichText::new("ℹ").weak()).on_hover_ui(|ui| {
+                                ui.style_mut().interaction.selectable_labels = true;
+                                ui.label("Runs Av1an and everything it starts at a lower priority (nice 10 on Linux and macOS, below normal on Windows), so the encode yields to whatever else you're doing on the machine. Encoding is only slower while other programs need the CPU.");
+                            });
+                        });
 
-                CollapsingHeader::new(RichText::from("Performance Settings").weak())
-                    .default_open(true)
-                    .show(ui, |ui| {
                         ui.horizontal(|ui| {
-                            let label_text = "*Thread Affinity";
+                            let label_text = "Verbosity";
                             let label_width = ui.label(label_text).rect.max.x - ui.min_rect().min.x;
                             max_width = max_width.max(label_width);
                             if label_width < max_width {
                                 ui.allocate_space(egui::vec2(max_width - label_width, 1.0));
                             }
-                            ui.add_sized(
-                                [100.0, 20.0],
-                                egui::TextEdit::singleline(&mut self.thread_affinity),
-                            );
+                            ComboBox::from_id_salt("log_level_combobox")
+                                .selected_text(self.log_level.as_str())
+                                .show_ui(ui, |ui| {
+                                    for level in [
+                                        LogLevel::Default,
+                                        LogLevel::Error,
+                                        LogLevel::Warn,
+                                        LogLevel::Info,
+                                        LogLevel::Debug,
+                                        LogLevel::Trace,
+                                    ] {
+                                        ui.selectable_value(&mut self.log_level, level, level.as_str());
+                                    }
+                                });
                             ui.label(RichText::new("ℹ").weak()).on_hover_ui(|ui| {
                                 ui.style_mut().interaction.selectable_labels = true;
-                                ui.label("Pin each worker to a specific set of threads of this size. Leaving this option unspecified allows the OS to schedule all processes spawned.");
+                                ui.label("Av1an's --log-level, how much it writes to its log file. Default leaves it up to Av1an. Debug or trace help when tracking down a failing encode.");
                             });
                         });
 
                         ui.horizontal(|ui| {
-                            let label_text = "*Workers";
+                            let label_text = "Extra Av1an Arguments";
                             let label_width = ui.label(label_text).rect.max.x - ui.min_rect().min.x;
                             max_width = max_width.max(label_width);
                             if label_width < max_width {
                                 ui.allocate_space(egui::vec2(max_width - label_width, 1.0));
                             }
                             ui.add_sized(
-                                [100.0, 20.0],
-                                egui::TextEdit::singleline(&mut self.workers),
+                                [500.0, 20.0],
+                                egui::TextEdit::singleline(&mut self.extra_av1an_args),
                             );
+                            if ui
+                                .button("Show Command")
+                                .on_hover_text(format!(
+                                    "Copy it with {}",
+                                    ctx.format_shortcut(&COPY_COMMAND_SHORTCUT)
+                                ))
+                                .clicked()
+                            {
+                                self.show_command_window = true;
+                            }
                             ui.label(RichText::new("ℹ").weak()).on_hover_ui(|ui| {
                                 ui.style_mut().interaction.selectable_labels = true;
-                                ui.label("Number of workers to spawn. It's generally recommended, if you have enough RAM, to set this to the total amount of CPU cores you have for better encoding speeds. Leaving this at the default value will allow Av1an to figure out the amount of workers to spawn automatically.");
+                                ui.label("Av1an flags AV1Studio has no setting for, e.g. --sc-downscale-height 720. They're added after everything else. Quote values that contain spaces.");
                             });
                         });
+                        if let Err(e) = split_args(&self.extra_av1an_args) {
+                            ui.colored_label(
+                                ui.visuals().warn_fg_color,
+                                format!("⚠ Couldn't parse the extra arguments, they'll be left out: {}", e),
+                            );
+                        }
                     });
+                self.sections.performance_settings = !performance_settings.fully_closed();
 
                 self.max_label_width = Some(max_width);
             });
@@ -1051,85 +6759,792 @@ impl eframe::App for AV1Studio {
             // Pin the Start Encoding section to the bottom
             ui.with_layout(egui::Layout::bottom_up(Align::Center), |ui| {
                 if self.encoding_in_progress {
-                    if let Some(receiver) = &self.receiver {
-                        loop {
-                            match receiver.try_recv() {
-                                Ok(line) => {
-                                    println!("Received from channel: {}", line);
-                                    parse_av1an_output(
-                                        &line,
-                                        &mut self.encoded_frames,
-                                        &mut self.total_frames,
-                                        &mut self.fps,
-                                        &mut self.eta_time,
-                                    )
+                    let workers = self.current_workers();
+                    if let Some(session) = &mut self.encode_session {
+                        session.poll();
+                        for line in session.lines.drain(..) {
+                            self.log.push(line);
+                        }
+                        // One sample per progress line, not per repaint
+                        if session.encoded_frames != self.encoded_frames {
+                            if let Some(fps) = session.fps {
+                                if self.fps_samples.len() == FPS_WINDOW {
+                                    self.fps_samples.pop_front();
+                                }
+                                self.fps_samples.push_back(fps);
+                            }
+                        }
+                        self.encoded_frames = session.encoded_frames;
+                        self.total_frames = session.total_frames;
+                        self.fps = session.fps;
+                        self.eta_time = session.eta_time.clone();
+
+                        if session.is_finished() {
+                            let report = self.pending_report.take();
+                            let (event, status) = if session.succeeded() {
+                                if let Some(mut report) = report.clone().filter(|_| self.write_report) {
+                                    let output = expand_path(
+                                        report.settings.settings.output_file.as_deref().unwrap_or_default(),
+                                    );
+                                    report.finish(session, &output);
+                                    match write_report(&report, &output) {
+                                        Ok(path) => self.log.push(format!("Wrote the report to {:?}", path)),
+                                        Err(e) => {
+                                            eprintln!("ERROR : Couldn't write the encode report: {}", e);
+                                            self.log.push(format!("Couldn't write the encode report: {}", e));
+                                        }
+                                    }
                                 }
-                                Err(mpsc::TryRecvError::Empty) => break,
-                                Err(mpsc::TryRecvError::Disconnected) => {
-                                    self.encoding_in_progress = false;
-                                    self.receiver = None;
-                                    break;
+                                (NotifyEvent::JobFinished, String::from("done"))
+                            } else {
+                                let status = match session.status {
+                                    Some(status) => format!("av1an exited with {}", status),
+                                    None => String::from("av1an stopped unexpectedly"),
+                                };
+                                if session.ran_out_of_memory() && workers > 1 {
+                                    self.out_of_memory_retry = Some(workers / 2);
                                 }
+                                (NotifyEvent::JobFailed, status)
+                            };
+                            self.notifier.send(
+                                &self.notifications,
+                                event,
+                                JobReport::new(&self.output_file, Some(session), &status),
+                            );
+                            if let Some(report) = report {
+                                let settings = &report.settings.settings;
+                                let record = EncodeRecord::new(
+                                    settings.input_file.as_deref().unwrap_or_default(),
+                                    settings.output_file.as_deref().unwrap_or_default(),
+                                    settings,
+                                    session,
+                                    &status,
+                                );
+                                self.add_to_history(record);
                             }
+
+                            self.encoding_in_progress = false;
+                            self.encode_session = None;
+                            self.eta_time = None;
+                            self.displayed_progress = 0.0;
+                            self.remove_joined_input();
                         }
                     }
                 }
 
+                let provisional = self.total_frames.is_none() && self.provisional_total_frames.is_some();
                 let (ef, tf) = (
                     self.encoded_frames.unwrap_or_default(),
-                    self.total_frames.unwrap_or_default(),
+                    self.total_frames.or(self.provisional_total_frames).unwrap_or_default(),
                 );
-                let progress = if tf == 0 { 0.0 } else { ef as f32 / tf as f32 };
-                ui.add(ProgressBar::new(progress).show_percentage());
+                // Switching phases, or from the estimate to av1an's count, mustn't make the bar
+                // jump back
+                let progress = match &self.encode_session {
+                    Some(session) => session.overall_progress(self.provisional_total_frames),
+                    None if tf == 0 => 0.0,
+                    None => (ef as f32 / tf as f32).min(1.0),
+                };
+                self.displayed_progress = self.displayed_progress.max(progress);
+                if self.encode_session.as_ref().is_some_and(|session| session.paused) {
+                    ui.scope(|ui| {
+                        ui.set_opacity(0.4);
+                        ui.add(ProgressBar::new(self.displayed_progress).text(format!(
+                            "Paused at {:.0}%",
+                            self.displayed_progress * 100.0
+                        )));
+                    });
+                } else {
+                    ui.add(ProgressBar::new(self.displayed_progress).show_percentage());
+                }
+
+                if let Some(EncodeState::Analyzing(scene_progress)) =
+                    self.encode_session.as_ref().map(|session| session.state)
+                {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        match scene_progress {
+                            Some(scene_progress) => {
+                                ui.label(format!("Analyzing scenes… {:.0}%", scene_progress * 100.0));
+                                ui.add(ProgressBar::new(scene_progress).desired_width(150.0));
+                            }
+                            None => {
+                                ui.label("Analyzing scenes…");
+                            }
+                        }
+                    });
+                }
 
                 ui.horizontal(|ui| {
                     ui.label("Encoded frames | Total frames:");
-                    ui.label(&format!("{} | {}", ef, tf));
+                    if provisional {
+                        ui.label(format!("{} |", ef));
+                        ui.label(RichText::new(format!("~{}", tf)).weak().italics())
+                            .on_hover_text("Estimated from the input, until Av1an reports the exact frame count");
+                    } else {
+                        ui.label(format!("{} | {}", ef, tf));
+                    }
+                    if let Some(session) = &self.encode_session {
+                        ui.separator();
+                        ui.label(format!(
+                            "Elapsed: {}",
+                            format_duration(session.started_at.elapsed())
+                        ));
+                    }
+                    if let Some(session) = &self.encode_session {
+                        ui.separator();
+                        ui.label(format!("Remaining: {}", session.eta_text()));
+                    }
+                    if let Some(fps) = self.smoothed_fps() {
+                        ui.separator();
+                        ui.label(format!("{:.2} fps", fps)).on_hover_text(format!(
+                            "Average of the last {} readings, Av1an last reported {:.2} fps",
+                            self.fps_samples.len(),
+                            self.fps.unwrap_or(fps)
+                        ));
+                    }
+                    if self.encoding_in_progress || self.queue.active_count() > 0 {
+                        self.system_load.sample();
+                        let gib = |bytes: u64| bytes as f64 / 1024.0 / 1024.0 / 1024.0;
+                        if let Some(cpu) = self.system_load.cpu {
+                            ui.separator();
+                            ui.label(format!("CPU {:.0}%", cpu)).on_hover_text(
+                                "Load of the whole machine. Well below 100% means the encode could use more workers, or waits on something else (e.g. decoding or the disk).",
+                            );
+                        }
+                        if self.system_load.memory_total > 0 {
+                            ui.separator();
+                            ui.label(format!(
+                                "RAM {:.1} / {:.1} GiB",
+                                gib(self.system_load.memory_used),
+                                gib(self.system_load.memory_total)
+                            ))
+                            .on_hover_text("Each worker needs its own memory, lower the workers if this gets close to full.");
+                        }
+                    } else {
+                        self.system_load.reset();
+                    }
+                    if self.encoding_in_progress {
+                        let configured = (!self.temp_dir.is_empty()).then(|| expand_path(&self.temp_dir));
+                        self.temp_usage.sample(configured.as_deref());
+                        let gib = |bytes: u64| bytes as f64 / 1024.0 / 1024.0 / 1024.0;
+                        if let (Some(used), Some(folder)) = (self.temp_usage.used, &self.temp_usage.folder) {
+                            ui.separator();
+                            let label = match self.temp_usage.available {
+                                Some(available) => format!("Temp {:.1} GiB ({:.1} GiB free)", gib(used), gib(available)),
+                                None => format!("Temp {:.1} GiB", gib(used)),
+                            };
+                            ui.label(label).on_hover_text(format!(
+                                "Size of Av1an's temp folder {}. Its chunks can add up to more than the input until they're joined.",
+                                folder.display()
+                            ));
+                        }
+                    }
                 });
+                if self.encoding_in_progress && self.temp_usage.is_low() {
+                    ui.colored_label(
+                        ui.visuals().warn_fg_color,
+                        format!(
+                            "⚠ Less than {} GiB are left on the temp folder's disk. The encode fails if it fills up, free some space now.",
+                            LOW_SPACE / 1024 / 1024 / 1024
+                        ),
+                    );
+                }
 
-                if ui.button("Start Encoding").clicked() {
-                    let mut cmd = generate_command(self);
-                    println!("{:?}", cmd);
-                    let (sender, receiver) = mpsc::channel();
-                    self.receiver = Some(receiver);
-                    self.encoding_in_progress = true;
-
-                    std::thread::spawn(move || {
-                        let mut child = cmd
-                            .stdout(Stdio::piped())
-                            .stderr(Stdio::piped())
-                            .spawn()
-                            .expect("failed to start av1an");
-
-                        let stdout = child.stdout.take().unwrap();
-                        let stderr = child.stderr.take().unwrap();
-                        let sender_stdout = sender.clone();
-                        let sender_stderr = sender.clone();
-
-                        std::thread::spawn(move || {
-                            let reader = BufReader::new(stdout);
-                            for line in reader.lines() {
-                                if let Ok(line) = line {
-                                    sender_stdout.send(line).unwrap();
-                                }
-                            }
-                        });
+                if let Some(error) = &self.start_error {
+                    ui.colored_label(ui.visuals().error_fg_color, format!("⚠ {}", error));
+                }
 
-                        std::thread::spawn(move || {
-                            let reader = BufReader::new(stderr);
-                            for line in reader.lines() {
-                                if let Ok(line) = line {
-                                    sender_stderr.send(line).unwrap();
-                                }
+                ui.horizontal(|ui| {
+                    if ui
+                        .add_enabled(self.can_start_encoding(), egui::Button::new("Start Encoding"))
+                        .on_hover_text(ctx.format_shortcut(&START_SHORTCUT))
+                        .on_disabled_hover_text(if self.encoding_in_progress {
+                            String::from("Already encoding")
+                        } else if let Some(problem) = self.start_problem() {
+                            problem
+                        } else {
+                            format!(
+                                "Needs an input and an output file ({})",
+                                ctx.format_shortcut(&START_SHORTCUT)
+                            )
+                        })
+                        .clicked()
+                    {
+                        self.start_encoding();
+                    }
+                    if self.encoding_in_progress
+                        && ui
+                            .button("Cancel")
+                            .on_hover_text(ctx.format_shortcut(&CANCEL_SHORTCUT))
+                            .clicked()
+                    {
+                        self.cancel_confirmation = true;
+                    }
+                    if let Some(session) = &mut self.encode_session {
+                        let label = if session.paused { "Resume" } else { "Pause" };
+                        if ui
+                            .add_enabled(EncodeSession::CAN_PAUSE, egui::Button::new(label))
+                            .on_hover_text("Stops Av1an and its encoders until resumed, they keep their memory in the meantime")
+                            .on_disabled_hover_text("Pausing is only supported on Linux and macOS")
+                            .clicked()
+                        {
+                            let paused = !session.paused;
+                            match session.set_paused(paused) {
+                                Ok(()) => self.log.push(String::from(if paused {
+                                    "Encoding paused"
+                                } else {
+                                    "Encoding resumed"
+                                })),
+                                Err(e) => self.log.push(format!("Couldn't pause or resume av1an: {}", e)),
                             }
-                        });
+                        }
+                    }
+                    if ui.button("Add to Queue").clicked() {
+                        self.queue_current();
+                    }
+                });
 
-                        let _ = child.wait();
+                // Above the buttons, the layout goes bottom up
+                if let Some(workers) = self.out_of_memory_retry {
+                    ui.horizontal(|ui| {
+                        ui.colored_label(
+                            ui.visuals().warn_fg_color,
+                            "⚠ The encode looks like it ran out of memory, every worker needs its own.",
+                        );
+                        if ui
+                            .add_enabled(
+                                !self.encoding_in_progress,
+                                egui::Button::new(format!("Retry with half the workers ({})", workers)),
+                            )
+                            .clicked()
+                        {
+                            self.workers = workers.to_string();
+                            self.auto_workers = false;
+                            self.log.push(format!("Retrying with {} workers", workers));
+                            self.start_encoding();
+                        }
+                        if ui.small_button("Dismiss").clicked() {
+                            self.out_of_memory_retry = None;
+                        }
                     });
                 }
 
                 ctx.request_repaint();
             });
         });
+
+        // After the widgets, so this frame's changes are in
+        if std::mem::take(&mut self.session_dirty) {
+            if let Some(json) = self.session_json() {
+                self.session_json = json;
+            }
+        }
+        let json = self.session_json.clone();
+        if self.settings_history.track(&json, Instant::now()) {
+            ctx.request_repaint_after(SETTLE);
+        }
+        self.autosave_session(ctx, &json);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::probe::parse_probe_output;
+
+    #[test]
+    fn loads_version_1_preset() {
+        let yaml = "source_library: FFMS2
+width: ''
+height: ''
+output_pixel_format: Yuv420p10le
+color_primaries: Bt709
+matrix_coefficients: Bt709
+transfer_characteristics: Bt709
+color_range: Studio
+file_concatenation: mkvmerge
+preset: 6.0
+crf: 30.0
+synthetic_grain: '4'
+custom_encode_params: ''
+";
+        let preset = parse_preset(yaml).unwrap();
+
+        assert_eq!(preset.version, PRESET_VERSION);
+        assert!(preset.source_library == SourceLibrary::FFMS2);
+        assert_eq!(preset.preset, 6.0);
+        assert_eq!(preset.crf, 30.0);
+        assert_eq!(preset.synthetic_grain, "4");
+        assert!(preset.output_container == OutputContainer::Mkv);
+        assert!(preset.grain_synthesis == GrainSynthesis::Synthetic);
+        assert_eq!(preset.passes, AV1Studio::default().passes);
+        assert!(preset.rate_control == RateControl::Crf);
+        assert_eq!(preset.target_bitrate, "");
+    }
+
+    #[test]
+    fn older_custom_params_keep_replacing() {
+        let yaml = "version: 2
+custom_encode_params: --crf 20 --preset 4
+";
+        let preset = parse_preset(yaml).unwrap();
+        assert_eq!(preset.custom_params_mode, CustomParamsMode::Replace);
+
+        // Written since appending became the default, or without custom parameters
+        let yaml = "version: 2
+custom_encode_params: --enable-overlays 1
+custom_params_mode: Append
+";
+        let preset = parse_preset(yaml).unwrap();
+        assert_eq!(preset.custom_params_mode, CustomParamsMode::Append);
+        let preset = parse_preset("version: 2\ncustom_encode_params: ''\n").unwrap();
+        assert_eq!(preset.custom_params_mode, CustomParamsMode::Append);
+        let yaml = "version: 3
+custom_encode_params: --enable-overlays 1
+";
+        let preset = parse_preset(yaml).unwrap();
+        assert_eq!(preset.custom_params_mode, CustomParamsMode::Append);
+    }
+
+    #[test]
+    fn quality_presets_only_change_the_encoder_settings() {
+        let mut source = AV1Studio {
+            crf: 18.0,
+            preset: 2.0,
+            tune: Tune::Psnr,
+            custom_encode_params: String::from("--enable-overlays 1"),
+            quality_preset_name: String::from("High Quality"),
+            ..AV1Studio::default()
+        };
+        source.synthetic_grain = String::from("12");
+        let yaml = serde_yaml::to_string(&source.to_quality_preset()).unwrap();
+
+        let mut target = AV1Studio {
+            width: String::from("1280"),
+            height: String::from("720"),
+            color_primaries: ColorPrimaries::Bt2020,
+            ..AV1Studio::default()
+        };
+        target.apply_quality_preset(serde_yaml::from_str(&yaml).unwrap());
+
+        assert_eq!(target.crf, 18.0);
+        assert_eq!(target.preset, 2.0);
+        assert!(target.tune == Tune::Psnr);
+        assert_eq!(target.synthetic_grain, "12");
+        assert_eq!(target.custom_encode_params, "--enable-overlays 1");
+        assert_eq!(target.quality_preset_name, "High Quality");
+        assert_eq!(
+            (target.width.as_str(), target.height.as_str()),
+            ("1280", "720")
+        );
+        assert!(target.color_primaries == ColorPrimaries::Bt2020);
+    }
+
+    #[test]
+    fn quality_presets_carry_the_rate_control() {
+        let bitrate = AV1Studio {
+            rate_control: RateControl::TargetBitrate,
+            target_bitrate: String::from("6000"),
+            ..AV1Studio::default()
+        };
+        let yaml = serde_yaml::to_string(&bitrate.to_quality_preset()).unwrap();
+        let mut target = AV1Studio::default();
+        target.apply_quality_preset(serde_yaml::from_str(&yaml).unwrap());
+        assert!(target.rate_control == RateControl::TargetBitrate);
+        assert_eq!(target.target_bitrate, "6000");
+
+        // Written before the rate control was part of them
+        target.apply_quality_preset(serde_yaml::from_str("crf: 24.0\n").unwrap());
+        assert!(target.rate_control == RateControl::Crf);
+        assert_eq!(target.crf, 24.0);
+
+        let mut builtin = bitrate;
+        builtin.apply_builtin_preset(&BUILTIN_PRESETS[0]);
+        assert!(builtin.rate_control == RateControl::Crf);
+    }
+
+    #[test]
+    fn loads_minimal_preset() {
+        let preset = parse_preset("crf: 20.0\n").unwrap();
+
+        assert_eq!(preset.crf, 20.0);
+        assert_eq!(preset.preset, AV1Studio::default().preset);
+    }
+
+    #[test]
+    fn migrates_version_1_ivf_concatenation() {
+        let preset = parse_preset("file_concatenation: ivf\n").unwrap();
+
+        assert!(preset.output_container == OutputContainer::Ivf);
+    }
+
+    #[test]
+    fn ignores_unknown_fields() {
+        let preset = parse_preset("version: 99\ncrf: 25.0\naudio_codec: opus\n").unwrap();
+
+        assert_eq!(preset.crf, 25.0);
+    }
+
+    #[test]
+    fn current_preset_round_trips() {
+        let state = AV1Studio {
+            preset_name: String::from("Test"),
+            crf: 18.5,
+            output_container: OutputContainer::WebM,
+            file_concatenation: String::from("ivf"),
+            ..AV1Studio::default()
+        };
+
+        let yaml = serde_yaml::to_string(&state.to_preset()).unwrap();
+        let preset = parse_preset(&yaml).unwrap();
+
+        assert_eq!(preset.name, "Test");
+        assert_eq!(preset.crf, 18.5);
+        assert!(preset.output_container == OutputContainer::WebM);
+    }
+
+    #[test]
+    fn missing_output_folder_is_asked_about_first() {
+        let dir = std::env::temp_dir().join(format!("av1studio-no-output-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut state = AV1Studio {
+            input_file: String::from("in.mkv"),
+            output_file: dir.join("out.mkv").display().to_string(),
+            ..AV1Studio::default()
+        };
+
+        state.start_encoding_now();
+        assert_eq!(state.missing_output_dir, Some(dir));
+        assert!(!state.encoding_in_progress);
+        assert!(!state.can_start_encoding());
+    }
+
+    #[test]
+    fn queued_job_fails_without_its_output_folder() {
+        let dir =
+            std::env::temp_dir().join(format!("av1studio-no-job-output-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut state = AV1Studio::default();
+        let job = state.queue_job(
+            String::from("in.mkv"),
+            dir.join("out.mkv").display().to_string(),
+        );
+        state.queue.push(job);
+        state.queue.running = true;
+
+        state.update_queue();
+        match &state.queue.jobs[0].status {
+            JobStatus::Failed(problem) => assert!(problem.contains("doesn't exist")),
+            _ => panic!("the job should have failed"),
+        }
+        assert!(state.queue.jobs[0].session.is_none());
+    }
+
+    #[test]
+    fn second_start_is_ignored_while_encoding() {
+        let mut state = AV1Studio {
+            input_file: String::from("in.mkv"),
+            output_file: std::env::temp_dir().join("out.mkv").display().to_string(),
+            encoding_in_progress: true,
+            ..AV1Studio::default()
+        };
+
+        state.start_encoding();
+        assert!(state.start_confirmation.is_none());
+        state.spawn_encode();
+        assert!(state.encode_session.is_none());
+        assert!(state.pending_report.is_none());
+        assert!(!state.can_start_encoding());
+    }
+
+    #[test]
+    fn json_preset_round_trips() {
+        let state = AV1Studio {
+            preset_name: String::from("Delivery"),
+            crf: 22.25,
+            rate_control: RateControl::TargetBitrate,
+            target_bitrate: String::from("6000"),
+            tune: Tune::Psnr,
+            width: String::from("1280"),
+            ..AV1Studio::default()
+        };
+        let path =
+            std::env::temp_dir().join(format!("av1studio-preset-{}.json", std::process::id()));
+        state
+            .save_preset_to_file(&path.display().to_string())
+            .unwrap();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(serde_json::from_str::<serde_json::Value>(&content).is_ok());
+
+        let mut loaded = AV1Studio::default();
+        loaded
+            .load_preset_from_file(&path.display().to_string())
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            serde_yaml::to_string(&loaded.to_preset()).unwrap(),
+            serde_yaml::to_string(&state.to_preset()).unwrap()
+        );
+    }
+
+    #[test]
+    fn samples_leave_out_the_scenes_and_zones() {
+        let state = AV1Studio {
+            input_file: String::from("/videos/movie.mkv"),
+            scenes_file: String::from("/videos/movie.scenes.json"),
+            zones_file: String::from("/videos/zones.txt"),
+            crf: 24.0,
+            ..AV1Studio::default()
+        };
+        let settings =
+            state.sample_settings(Path::new("/tmp/sample.mkv"), Path::new("/tmp/out.mkv"));
+
+        assert_eq!(settings.input_file, "/tmp/sample.mkv");
+        assert!(settings.scenes_file.is_empty());
+        assert!(settings.zones_file.is_empty());
+        assert_eq!(settings.crf, 24.0);
+    }
+
+    #[test]
+    fn comparisons_encode_at_their_crfs() {
+        let state = AV1Studio {
+            rate_control: RateControl::TargetBitrate,
+            target_bitrate: String::from("4000"),
+            target_quality: String::from("95"),
+            ..AV1Studio::default()
+        };
+
+        for index in 0..2 {
+            let settings = state.comparison_settings(index);
+            assert!(settings.rate_control == RateControl::Crf);
+            assert!(settings.target_quality.is_empty());
+            assert_eq!(settings.crf, state.crf_comparison.crf[index]);
+        }
+    }
+
+    #[test]
+    fn presets_keep_the_grain_table() {
+        let state = AV1Studio {
+            grain_synthesis: GrainSynthesis::GrainTable,
+            grain_table: String::from("/tables/iso400.tbl"),
+            ..AV1Studio::default()
+        };
+        let yaml = serde_yaml::to_string(&state.to_preset()).unwrap();
+
+        let mut loaded = AV1Studio::default();
+        loaded.apply_preset(parse_preset(&yaml).unwrap());
+        assert_eq!(loaded.grain_table, "/tables/iso400.tbl");
+    }
+
+    #[test]
+    fn queued_jobs_keep_their_settings() {
+        let mut state = AV1Studio {
+            crf: 24.0,
+            width: String::from("1920"),
+            height: String::from("1080"),
+            grain_table: String::from("/tables/iso400.tbl"),
+            ..AV1Studio::default()
+        };
+        let job = state.queue_job(String::from("a.mkv"), String::from("a_av1.mkv"));
+
+        // Setting up the next job
+        state.crf = 32.0;
+        state.width = String::from("1280");
+        state.height = String::from("720");
+        state.tune = Tune::Psnr;
+        state.grain_table = String::new();
+
+        let settings = state.job_settings(&job);
+        assert_eq!(settings.crf, 24.0);
+        assert_eq!(
+            (settings.width.as_str(), settings.height.as_str()),
+            ("1920", "1080")
+        );
+        assert!(settings.tune == AV1Studio::default().tune);
+        assert_eq!(settings.grain_table, "/tables/iso400.tbl");
+        assert_eq!(settings.input_file, "a.mkv");
+    }
+
+    #[test]
+    fn pointer_moves_dont_dirty_the_session() {
+        let pos = egui::pos2(10.0, 10.0);
+        assert!(!edits_something(&egui::Event::PointerMoved(pos)));
+        assert!(!edits_something(&egui::Event::PointerGone));
+        assert!(edits_something(&egui::Event::Text(String::from("4"))));
+        assert!(edits_something(&egui::Event::PointerButton {
+            pos,
+            button: egui::PointerButton::Primary,
+            pressed: false,
+            modifiers: egui::Modifiers::NONE,
+        }));
+    }
+
+    #[test]
+    fn queued_jobs_keep_their_workers() {
+        let mut state = AV1Studio {
+            workers: String::from("6"),
+            auto_workers: false,
+            ..AV1Studio::default()
+        };
+        let fixed = state.queue_job(String::from("a.mkv"), String::from("a_av1.mkv"));
+        state.auto_workers = true;
+        let automatic = state.queue_job(String::from("b.mkv"), String::from("b_av1.mkv"));
+
+        // Auto is still on when the fixed job starts
+        let settings = state.job_settings(&fixed);
+        assert!(!settings.auto_workers);
+        assert_eq!(settings.workers, "6");
+
+        state.auto_workers = false;
+        assert_eq!(state.job_settings(&automatic).workers, "");
+    }
+
+    #[test]
+    fn hdr_sources_with_unspecified_colors_get_a_fix() {
+        let probed = |json: &[u8]| AV1Studio {
+            media_info: Some(parse_probe_output(json).unwrap()),
+            ..AV1Studio::default()
+        };
+
+        let sdr = probed(include_bytes!("../tests/fixtures/ffprobe_sdr.json"));
+        assert!(sdr.hdr_color_fix().is_none());
+
+        let mut hdr10 = probed(include_bytes!("../tests/fixtures/ffprobe_hdr10.json"));
+        assert_eq!(
+            hdr10.hdr_color_fix(),
+            Some((
+                ColorPrimaries::Bt2020,
+                TransferCharacteristics::Smpte2084,
+                MatrixCoefficients::Bt2020Ncl
+            ))
+        );
+        assert!(hdr10.hdr_defaults_warning().unwrap().contains("(PQ)"));
+        // One field left alone is enough to wash out the output
+        hdr10.color_primaries = ColorPrimaries::Bt2020;
+        hdr10.transfer_characteristics = TransferCharacteristics::Smpte2084;
+        assert!(hdr10.hdr_color_fix().is_some());
+        hdr10.matrix_coefficients = MatrixCoefficients::Bt2020Ncl;
+        assert!(hdr10.hdr_color_fix().is_none());
+
+        let mut hlg = probed(include_bytes!("../tests/fixtures/ffprobe_hlg.json"));
+        assert_eq!(
+            hlg.hdr_color_fix().map(|(_, transfer, _)| transfer),
+            Some(TransferCharacteristics::Hlg)
+        );
+        assert!(hlg
+            .start_warnings()
+            .iter()
+            .any(|warning| warning.contains("(HLG)")));
+    }
+
+    #[test]
+    fn color_filter_ignores_case_and_punctuation() {
+        for filter in ["2020", "bt2020", "BT.2020", "bt 2020"] {
+            assert!(
+                matches_filter(ColorPrimaries::Bt2020.label(), filter),
+                "{}",
+                filter
+            );
+        }
+        assert!(matches_filter(
+            TransferCharacteristics::Smpte2084.label(),
+            "pq"
+        ));
+        assert!(!matches_filter(ColorPrimaries::Bt709.label(), "2020"));
+        let bt2020_matrices = MatrixCoefficients::ALL
+            .iter()
+            .filter(|matrix| matches_filter(matrix.label(), "2020"))
+            .count();
+        assert_eq!(bt2020_matrices, 2);
+    }
+
+    #[test]
+    fn session_restores_skipped_fields() {
+        let state = AV1Studio {
+            input_file: String::from("/videos/in.mkv"),
+            output_file: String::from("/videos/out.mkv"),
+            zones_file: String::from("/videos/zones.txt"),
+            workers: String::from("3"),
+            thread_affinity: String::from("2"),
+            temp_dir: String::from("/scratch"),
+            crf: 22.0,
+            ..AV1Studio::default()
+        };
+        let json = serde_json::to_string(&state.session_state()).unwrap();
+
+        let mut restored = AV1Studio::default();
+        restored.apply_session_state(serde_json::from_str(&json).unwrap());
+        assert_eq!(restored.input_file, "/videos/in.mkv");
+        assert_eq!(restored.output_file, "/videos/out.mkv");
+        assert_eq!(restored.zones_file, "/videos/zones.txt");
+        assert_eq!(restored.workers, "3");
+        assert_eq!(restored.thread_affinity, "2");
+        assert_eq!(restored.temp_dir, "/scratch");
+        assert_eq!(restored.crf, 22.0);
+        assert!(!restored.encoding_in_progress);
+    }
+
+    #[test]
+    fn start_warnings_catch_lossy_settings() {
+        let mut app = AV1Studio {
+            zones_file: String::from("/nonexistent/zones.txt"),
+            output_pixel_format: PixelFormat::Yuv420p,
+            color_primaries: ColorPrimaries::Bt709,
+            transfer_characteristics: TransferCharacteristics::Bt709,
+            matrix_coefficients: MatrixCoefficients::Bt709,
+            media_info: Some(MediaInfo {
+                pixel_format: Some(String::from("yuv420p10le")),
+                color_transfer: Some(String::from("smpte2084")),
+                ..MediaInfo::default()
+            }),
+            ..AV1Studio::default()
+        };
+        let warnings = app.start_warnings();
+
+        assert!(warnings
+            .iter()
+            .any(|w| w.contains("zones.txt doesn't exist")));
+        assert!(warnings.iter().any(|w| w.contains("10-bit")));
+        assert!(warnings.iter().any(|w| w.contains("HDR (smpte2084)")));
+
+        app.output_pixel_format = PixelFormat::Yuv420p10le;
+        app.transfer_characteristics = TransferCharacteristics::Smpte2084;
+        app.color_primaries = ColorPrimaries::Bt2020;
+        app.matrix_coefficients = MatrixCoefficients::Bt2020Ncl;
+        app.zones_file.clear();
+        assert!(app.start_warnings().is_empty());
+    }
+
+    #[test]
+    fn second_passes_are_only_wasted_at_a_constant_quality() {
+        let mut state = AV1Studio {
+            encoder: Encoder::SvtAv1,
+            passes: 2,
+            ..AV1Studio::default()
+        };
+        assert!(state
+            .wasted_pass_warning()
+            .is_some_and(|warning| warning.contains("With CRF")));
+
+        // Custom parameters don't change the rate control av1an is given
+        state.custom_encode_params = String::from("--rc 1 --tbr 2000");
+        assert!(state.wasted_pass_warning().is_some());
+
+        state.target_quality = String::from("95");
+        assert!(state
+            .wasted_pass_warning()
+            .is_some_and(|warning| warning.contains("Target quality")));
+
+        state.rate_control = RateControl::TargetBitrate;
+        assert_eq!(state.wasted_pass_warning(), None);
+
+        state.rate_control = RateControl::Crf;
+        state.passes = 1;
+        assert_eq!(state.wasted_pass_warning(), None);
+
+        state.passes = 2;
+        state.encoder = Encoder::Aom;
+        assert_eq!(state.wasted_pass_warning(), None);
     }
 }
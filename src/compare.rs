@@ -0,0 +1,210 @@
+use std::path::PathBuf;
+use std::process::{Child, Command};
+use std::sync::mpsc;
+
+use egui::{ColorImage, TextureHandle};
+
+use crate::encoding::{start_failure, EncodeSession};
+use crate::probe::{cut_sample, extract_frame, probe_input, FfmpegPaths};
+
+pub enum CompareStage {
+    Idle,
+    Cutting(Child),
+    Encoding(usize, Box<EncodeSession>),
+    Measuring(mpsc::Receiver<Vec<SampleResult>>),
+    Done,
+    Failed(String),
+}
+
+pub struct SampleResult {
+    pub crf: f32,
+    pub path: PathBuf,
+    pub size: Option<u64>,    // In bytes
+    pub bitrate: Option<f64>, // In kbps
+    pub frame: Option<ColorImage>,
+    pub texture: Option<TextureHandle>,
+}
+
+// Two sample encodes of the same stretch of the input, differing only in CRF
+pub struct CrfComparison {
+    pub crf: [f32; 2],
+    pub sample_start: f64,
+    pub sample_length: f64,
+    pub keep_files: bool,
+
+    pub stage: CompareStage,
+    pub results: Vec<SampleResult>,
+
+    temp_dir: PathBuf,
+    encodes: Vec<Command>,
+    tools: FfmpegPaths,
+}
+
+impl Default for CrfComparison {
+    fn default() -> Self {
+        CrfComparison {
+            crf: [23.0, 27.0],
+            sample_start: 0.0,
+            sample_length: 10.0,
+            keep_files: false,
+            stage: CompareStage::Idle,
+            results: Vec::new(),
+            temp_dir: std::env::temp_dir()
+                .join(format!("av1studio-compare-{}", std::process::id())),
+            encodes: Vec::new(),
+            tools: FfmpegPaths::default(),
+        }
+    }
+}
+
+impl CrfComparison {
+    pub fn is_running(&self) -> bool {
+        matches!(
+            self.stage,
+            CompareStage::Cutting(_) | CompareStage::Encoding(..) | CompareStage::Measuring(_)
+        )
+    }
+
+    pub fn sample_path(&self) -> PathBuf {
+        self.temp_dir.join("sample.mkv")
+    }
+
+    pub fn output_path(&self, index: usize) -> PathBuf {
+        self.temp_dir
+            .join(format!("sample_{}_crf{}.mkv", index + 1, self.crf[index]))
+    }
+
+    // `encodes` are the av1an commands for both samples, reading from `sample_path()`
+    pub fn start(&mut self, tools: &FfmpegPaths, input: &str, encodes: Vec<Command>) {
+        self.cleanup();
+        self.tools = tools.clone();
+        self.results.clear();
+
+        if let Err(e) = std::fs::create_dir_all(&self.temp_dir) {
+            self.stage =
+                CompareStage::Failed(format!("Couldn't create {:?}: {}", self.temp_dir, e));
+            return;
+        }
+
+        let cut = cut_sample(
+            tools,
+            input,
+            self.sample_start,
+            self.sample_length,
+            &self.sample_path(),
+        );
+
+        self.stage = match cut {
+            Ok(child) => CompareStage::Cutting(child),
+            Err(e) => CompareStage::Failed(format!("Couldn't start ffmpeg: {}", e)),
+        };
+        self.encodes = encodes;
+    }
+
+    pub fn poll(&mut self) {
+        let stage = std::mem::replace(&mut self.stage, CompareStage::Idle);
+
+        self.stage = match stage {
+            CompareStage::Cutting(mut child) => match child.try_wait() {
+                Ok(Some(status)) if status.success() => self.next_encode(0),
+                Ok(Some(status)) => {
+                    CompareStage::Failed(format!("ffmpeg couldn't cut the sample ({})", status))
+                }
+                Ok(None) => CompareStage::Cutting(child),
+                Err(e) => CompareStage::Failed(e.to_string()),
+            },
+            CompareStage::Encoding(index, mut session) => {
+                session.poll();
+                session.lines.clear(); // Sample encodes stay out of the log
+                if !session.is_finished() {
+                    CompareStage::Encoding(index, session)
+                } else if session.succeeded() {
+                    self.next_encode(index + 1)
+                } else {
+                    CompareStage::Failed(format!("Sample {} failed to encode", index + 1))
+                }
+            }
+            CompareStage::Measuring(receiver) => match receiver.try_recv() {
+                Ok(results) => {
+                    self.results = results;
+                    CompareStage::Done
+                }
+                Err(mpsc::TryRecvError::Empty) => CompareStage::Measuring(receiver),
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    CompareStage::Failed(String::from("Couldn't measure the samples"))
+                }
+            },
+            stage => stage,
+        };
+    }
+
+    fn next_encode(&mut self, index: usize) -> CompareStage {
+        if self.encodes.is_empty() {
+            let samples: Vec<(f32, PathBuf)> = (0..index)
+                .map(|i| (self.crf[i], self.output_path(i)))
+                .collect();
+            let (sender, receiver) = mpsc::channel();
+            let tools = self.tools.clone();
+            std::thread::spawn(move || {
+                let results = samples
+                    .into_iter()
+                    .map(|(crf, path)| measure_sample(&tools, crf, path))
+                    .collect();
+                let _ = sender.send(results);
+            });
+            return CompareStage::Measuring(receiver);
+        }
+
+        match EncodeSession::start(self.encodes.remove(0)) {
+            Ok(session) => CompareStage::Encoding(index, Box::new(session)),
+            Err(e) => CompareStage::Failed(start_failure(&e)),
+        }
+    }
+
+    pub fn cancel(&mut self) {
+        match &mut self.stage {
+            CompareStage::Cutting(child) => {
+                let _ = child.kill();
+                let _ = child.wait();
+            }
+            CompareStage::Encoding(_, session) => session.cancel(),
+            _ => {}
+        }
+        self.stage = CompareStage::Idle;
+        self.encodes.clear();
+    }
+
+    // Removes the temporary sample files, unless the user asked to keep them
+    pub fn cleanup(&mut self) {
+        if !self.keep_files && self.temp_dir.exists() {
+            if let Err(e) = std::fs::remove_dir_all(&self.temp_dir) {
+                eprintln!("WARNING: Couldn't remove {:?}: {}", self.temp_dir, e);
+            }
+        }
+    }
+}
+
+fn measure_sample(tools: &FfmpegPaths, crf: f32, path: PathBuf) -> SampleResult {
+    let size = std::fs::metadata(&path).map(|m| m.len()).ok();
+    let path_string = path.display().to_string();
+    let duration = probe_input(tools, &path_string)
+        .ok()
+        .and_then(|info| info.duration);
+
+    let bitrate = match (size, duration) {
+        (Some(size), Some(duration)) if duration > 0.0 => {
+            Some(size as f64 * 8.0 / duration / 1000.0)
+        }
+        _ => None,
+    };
+    let frame = extract_frame(tools, &path_string, duration.unwrap_or(0.0) / 2.0).ok();
+
+    SampleResult {
+        crf,
+        path,
+        size,
+        bitrate,
+        frame,
+        texture: None,
+    }
+}
@@ -0,0 +1,161 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::models::Theme;
+use crate::notify::NotificationSettings;
+
+// Machine-local settings, kept out of presets so those stay portable
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppConfig {
+    pub av1an_verbosity_path: String,
+    pub ffmpeg_path: String, // Empty uses whatever is on the PATH
+    pub environment: Vec<EnvOverride>,
+    pub default_preset_path: String,
+    pub presets_folder: String,
+    pub theme: Theme,
+    pub ui_scale: f32,
+    pub output_template: String,
+    pub max_concurrent_jobs: usize,
+    pub low_priority: bool,
+    pub auto_workers: bool,  // Leave -w out and let av1an pick
+    pub confirm_start: bool, // Show a summary before encoding
+    pub write_report: bool,  // <output>.report.json after each encode
+    pub notifications: NotificationSettings,
+    pub status_server: StatusServerSettings,
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        AppConfig {
+            av1an_verbosity_path: String::new(),
+            ffmpeg_path: String::new(),
+            environment: Vec::new(),
+            default_preset_path: String::new(),
+            presets_folder: String::new(),
+            theme: Theme::default(),
+            ui_scale: 1.0,
+            output_template: String::from("{name}_av1"),
+            max_concurrent_jobs: 1,
+            low_priority: false,
+            auto_workers: false,
+            confirm_start: true,
+            write_report: false,
+            notifications: NotificationSettings::default(),
+            status_server: StatusServerSettings::default(),
+        }
+    }
+}
+
+// An environment variable set for av1an, an empty value removes it instead
+#[derive(Clone, PartialEq, Default, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EnvOverride {
+    pub key: String,
+    pub value: String,
+}
+
+// Only used by builds with the status-server feature, but kept in the config either way
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StatusServerSettings {
+    pub enabled: bool,
+    pub address: String,
+    pub token: String, // Required for the remote control routes, which are off while it's empty
+}
+
+impl Default for StatusServerSettings {
+    fn default() -> Self {
+        StatusServerSettings {
+            enabled: false,
+            address: String::from("0.0.0.0:8787"),
+            token: String::new(),
+        }
+    }
+}
+
+pub fn config_dir() -> Option<PathBuf> {
+    directories::ProjectDirs::from("", "", "AV1Studio").map(|dirs| dirs.config_dir().to_path_buf())
+}
+
+fn config_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("config.toml"))
+}
+
+// config.toml was there but couldn't be parsed
+#[derive(Debug)]
+pub struct ConfigError {
+    pub message: String,
+    pub backup: Option<PathBuf>, // Where the unparseable file was copied to, if that worked
+}
+
+// None if there's no config file yet, or it couldn't be read
+pub fn load_config() -> Result<Option<AppConfig>, ConfigError> {
+    match config_path() {
+        Some(path) => read_config(&path),
+        None => Ok(None),
+    }
+}
+
+fn read_config(path: &Path) -> Result<Option<AppConfig>, ConfigError> {
+    let Ok(content) = std::fs::read_to_string(path) else {
+        return Ok(None);
+    };
+
+    toml::from_str(&content).map(Some).map_err(|e| {
+        eprintln!("ERROR : Couldn't parse {:?}: {}", path, e);
+        // Keep a copy, the next save writes the defaults over it
+        let backup = path.with_extension("toml.bak");
+        let backup = match std::fs::copy(path, &backup) {
+            Ok(_) => Some(backup),
+            Err(e) => {
+                eprintln!("ERROR : Couldn't back up {:?}: {}", path, e);
+                None
+            }
+        };
+        ConfigError {
+            message: format!("Couldn't parse {}: {}", path.display(), e),
+            backup,
+        }
+    })
+}
+
+pub fn save_config(config: &AppConfig) -> Result<(), Box<dyn std::error::Error>> {
+    let path = config_path().ok_or("couldn't determine the config directory")?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, toml::to_string_pretty(config)?)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unparseable_configs_are_backed_up() {
+        let dir = std::env::temp_dir().join(format!("av1studio-config-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+
+        assert!(read_config(&path).unwrap().is_none());
+
+        std::fs::write(&path, "ui_scale = \"big\"\n").unwrap();
+        let error = read_config(&path).err().unwrap();
+        assert!(error.message.contains("ui_scale"));
+        let backup = error.backup.unwrap();
+        assert_eq!(backup, dir.join("config.toml.bak"));
+        assert_eq!(
+            std::fs::read_to_string(backup).unwrap(),
+            "ui_scale = \"big\"\n"
+        );
+
+        std::fs::write(&path, "ui_scale = 1.5\n").unwrap();
+        assert_eq!(read_config(&path).unwrap().unwrap().ui_scale, 1.5);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
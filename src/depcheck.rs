@@ -1,5 +1,8 @@
-use std::path::Path;
-use std::process::Command;
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::probe::FfmpegPaths;
 
 pub fn exists(path: &Path) -> bool {
     let p = Path::new(path);
@@ -11,13 +14,429 @@ pub fn exists(path: &Path) -> bool {
     true
 }
 
-pub fn can_run(path: &Path) -> bool {
-    // I'm dumb, so there's probably a better way to do this
-    Command::new(path)
-        .arg("--version")
+// The first line the program prints for its version flag, None if it can't be run
+pub fn version(program: impl AsRef<std::ffi::OsStr>, version_arg: &str) -> Option<String> {
+    let output = Command::new(program)
+        .arg(version_arg)
+        .stdin(Stdio::null())
         .output()
-        .map(|output| output.status.success())
-        .unwrap_or(false);
+        .ok()
+        .filter(|output| output.status.success())?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let line = stdout
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())?;
 
-    true
+    // FFmpeg tacks its copyright onto the version line
+    Some(line.split(" Copyright").next().unwrap_or(line).to_string())
+}
+
+// Runs the program with --version, or --help for builds without one. The version line if it
+// responds, why it couldn't be run otherwise.
+pub fn test_binary(program: &str) -> Result<String, String> {
+    if let Some(line) = version(program, "--version") {
+        return Ok(line);
+    }
+
+    match Command::new(program)
+        .arg("--help")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+    {
+        Ok(status) if status.success() => Ok(String::from("Runs, but printed no version")),
+        Ok(status) => Err(format!("Exited with {}", status)),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+pub struct LowDiskSpace {
+    pub input_size: u64,
+    pub folders: Vec<(PathBuf, u64)>, // With how much they have free
+}
+
+// Folders the encode writes to that have less free space than the input's size. Av1an's chunks
+// and the output together rarely get bigger than the input, so less than that is suspicious,
+// not necessarily too little.
+pub fn low_disk_space(input: &Path, folders: &[PathBuf]) -> Option<LowDiskSpace> {
+    let input_size = std::fs::metadata(input).map(|m| m.len()).ok()?;
+
+    let folders: Vec<_> = folders
+        .iter()
+        .filter_map(|folder| {
+            // The folder itself may only get created by av1an
+            let existing = folder.ancestors().find(|dir| dir.is_dir())?;
+            let available = fs2::available_space(existing).ok()?;
+            (available < input_size).then(|| (folder.clone(), available))
+        })
+        .collect();
+    (!folders.is_empty()).then_some(LowDiskSpace {
+        input_size,
+        folders,
+    })
+}
+
+// "SVT-AV1-PSY v2.3.0-A (release)" -> "2.3.0", None if there's no version number in it
+pub fn parse_version(output: &str) -> Option<String> {
+    let re = regex::Regex::new(r"(\d+\.\d+(?:\.\d+)?)").unwrap();
+    re.captures(output).map(|caps| caps[1].to_string())
+}
+
+// What an encoder option needs from SvtAv1EncApp. Options are a flag, optionally followed by
+// the value that needs it, e.g. "--tune 3". Add entries here as encoder versions change.
+pub struct Capability {
+    pub option: &'static str,
+    pub psy_only: bool,
+    pub since: Option<(u32, u32, u32)>,
+}
+
+pub const ENCODER_CAPABILITIES: &[Capability] = &[
+    Capability {
+        option: "--tune 3",
+        psy_only: true,
+        since: None,
+    },
+    Capability {
+        option: "--tune 4",
+        psy_only: true,
+        since: None,
+    },
+    // Variance boost came to mainline SVT-AV1 in 2.0.0
+    Capability {
+        option: "--enable-variance-boost 1",
+        psy_only: false,
+        since: Some((2, 0, 0)),
+    },
+    Capability {
+        option: "--variance-boost-strength",
+        psy_only: false,
+        since: Some((2, 0, 0)),
+    },
+    Capability {
+        option: "--variance-octile",
+        psy_only: false,
+        since: Some((2, 0, 0)),
+    },
+];
+
+// The installed SvtAv1EncApp, as far as its version output tells
+pub struct EncoderVersion {
+    pub psy: bool,
+    pub version: Option<(u32, u32, u32)>,
+}
+
+impl EncoderVersion {
+    pub fn parse(output: &str) -> Self {
+        let version = parse_version(output).map(|version| {
+            let mut parts = version.split('.').map(|part| part.parse().unwrap_or(0));
+            (
+                parts.next().unwrap_or(0),
+                parts.next().unwrap_or(0),
+                parts.next().unwrap_or(0),
+            )
+        });
+
+        EncoderVersion {
+            psy: output.to_lowercase().contains("psy"),
+            version,
+        }
+    }
+
+    // Why the encoder likely can't handle the option, None if it should be fine
+    fn missing(&self, capability: &Capability) -> Option<String> {
+        if capability.psy_only && !self.psy {
+            return Some(String::from("needs SVT-AV1-PSY"));
+        }
+        match (capability.since, self.version) {
+            (Some(since), Some(version)) if version < since => Some(format!(
+                "needs {}.{}.{} or newer",
+                since.0, since.1, since.2
+            )),
+            _ => None,
+        }
+    }
+
+    // Options in the encoder parameters the installed encoder likely doesn't support, with why
+    pub fn unsupported(&self, params: &[String]) -> Vec<(&'static str, String)> {
+        ENCODER_CAPABILITIES
+            .iter()
+            .filter(|capability| uses_option(params, capability.option))
+            .filter_map(|capability| {
+                self.missing(capability)
+                    .map(|reason| (capability.option, reason))
+            })
+            .collect()
+    }
+}
+
+// Matches "--flag", "--flag=value", and "--flag value" against options like "--flag value"
+fn uses_option(params: &[String], option: &str) -> bool {
+    let (flag, value) = match option.split_once(' ') {
+        Some((flag, value)) => (flag, Some(value)),
+        None => (option, None),
+    };
+
+    params.iter().enumerate().any(|(i, param)| {
+        let (param_flag, inline_value) = match param.split_once('=') {
+            Some((param_flag, inline_value)) => (param_flag, Some(inline_value)),
+            None => (param.as_str(), None),
+        };
+        param_flag == flag
+            && match value {
+                Some(value) => {
+                    inline_value.or(params.get(i + 1).map(String::as_str)) == Some(value)
+                }
+                None => true,
+            }
+    })
+}
+
+// The long options an encoder lists in its --help. Only the ones an option line starts with
+// count, like "    --preset   ..." or "    -q, --qp   ...", not the ones in examples and
+// descriptions.
+pub fn parse_help_options(help: &str) -> BTreeSet<String> {
+    let re = regex::Regex::new(r"^\s*(?:-[\w-]+,\s*)?(--[a-z0-9][a-z0-9-]*)\s").unwrap();
+
+    help.lines()
+        .filter_map(|line| re.captures(line).map(|caps| caps[1].to_string()))
+        .collect()
+}
+
+// The options each encoder binary supports, from running it with --help once. None for
+// binaries that couldn't be run or listed nothing, which skips the check.
+#[derive(Default)]
+pub struct HelpOptions {
+    by_path: HashMap<PathBuf, Option<BTreeSet<String>>>,
+}
+
+impl HelpOptions {
+    pub fn get(&mut self, program: &Path) -> Option<&BTreeSet<String>> {
+        self.by_path
+            .entry(program.to_path_buf())
+            .or_insert_with(|| {
+                // Some builds print the help to stderr or exit non-zero after it
+                let output = Command::new(program)
+                    .arg("--help")
+                    .stdin(Stdio::null())
+                    .output()
+                    .ok()?;
+                let help = String::from_utf8_lossy(&output.stdout).to_string()
+                    + &String::from_utf8_lossy(&output.stderr);
+                Some(parse_help_options(&help)).filter(|options| !options.is_empty())
+            })
+            .as_ref()
+    }
+
+    pub fn clear(&mut self) {
+        self.by_path.clear();
+    }
+}
+
+// The long options in the parameters that aren't in `supported`, each once
+pub fn unknown_flags(params: &[String], supported: &BTreeSet<String>) -> Vec<String> {
+    let mut unknown: Vec<String> = Vec::new();
+    for param in params.iter().filter(|param| param.starts_with("--")) {
+        let flag = param
+            .split_once('=')
+            .map_or(param.as_str(), |(flag, _)| flag);
+        if !supported.contains(flag) && !unknown.iter().any(|known| known == flag) {
+            unknown.push(flag.to_string());
+        }
+    }
+
+    unknown
+}
+
+// Tools some features need, looked up on the PATH unless a path is configured, with the
+// version each one reported. None of them are required to encode.
+#[derive(Clone, Debug, Default)]
+pub struct OptionalTools {
+    pub ffmpeg: Option<String>, // Preview frames, samples, ffmpeg concatenation
+    pub ffprobe: Option<String>, // Media info
+    pub mkvmerge: Option<String>, // mkvmerge concatenation
+}
+
+impl OptionalTools {
+    // Before the check ran, everything counts as missing
+    pub const NONE: OptionalTools = OptionalTools {
+        ffmpeg: None,
+        ffprobe: None,
+        mkvmerge: None,
+    };
+
+    // ffmpeg and ffprobe are the ones AV1Studio runs, so availability matches what's used
+    pub fn detect(ffmpeg: &FfmpegPaths) -> Self {
+        OptionalTools {
+            ffmpeg: version(&ffmpeg.ffmpeg, "-version"),
+            ffprobe: version(&ffmpeg.ffprobe, "-version"),
+            mkvmerge: version("mkvmerge", "--version"),
+        }
+    }
+
+    pub fn has_ffmpeg(&self) -> bool {
+        self.ffmpeg.is_some()
+    }
+
+    pub fn has_ffprobe(&self) -> bool {
+        self.ffprobe.is_some()
+    }
+
+    pub fn versions(&self) -> [(&'static str, Option<&str>); 3] {
+        [
+            ("ffmpeg", self.ffmpeg.as_deref()),
+            ("ffprobe", self.ffprobe.as_deref()),
+            ("mkvmerge", self.mkvmerge.as_deref()),
+        ]
+    }
+
+    pub fn missing(&self) -> Vec<&'static str> {
+        self.versions()
+            .into_iter()
+            .filter(|(_, version)| version.is_none())
+            .map(|(name, _)| name)
+            .collect()
+    }
+
+    // Whether the tool behind a concatenation method is installed, "ivf" is built into av1an
+    pub fn supports_concat(&self, method: &str) -> bool {
+        match method {
+            "mkvmerge" => self.mkvmerge.is_some(),
+            "ffmpeg" => self.ffmpeg.is_some(),
+            _ => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAINLINE_HELP: &str = include_str!("../tests/fixtures/svtav1_help.txt");
+    const PSY_HELP: &str = include_str!("../tests/fixtures/svtav1_psy_help.txt");
+
+    fn params(params: &str) -> Vec<String> {
+        shell_words::split(params).unwrap()
+    }
+
+    #[test]
+    fn help_options_come_from_option_lines() {
+        let options = parse_help_options(MAINLINE_HELP);
+
+        assert!(options.contains("--preset"));
+        assert!(options.contains("--crf"));
+        // After a short option
+        assert!(options.contains("--help"));
+        assert!(options.contains("--qp"));
+        assert!(options.contains("--color-range"));
+        // Short options, and flags only named in examples or descriptions, aren't options
+        assert!(!options.contains("-i"));
+        assert!(!options.iter().any(|option| !option.starts_with("--")));
+        assert!(!options.contains("--enable-hdr"));
+    }
+
+    #[test]
+    fn psy_help_adds_its_own_options() {
+        let mainline = parse_help_options(MAINLINE_HELP);
+        let psy = parse_help_options(PSY_HELP);
+
+        assert!(mainline.is_subset(&psy));
+        for option in [
+            "--enable-hdr",
+            "--psy-rd",
+            "--frame-luma-bias",
+            "--color-help",
+        ] {
+            assert!(psy.contains(option), "{}", option);
+            assert!(!mainline.contains(option), "{}", option);
+        }
+    }
+
+    #[test]
+    fn unknown_flags_are_listed_once() {
+        let mainline = parse_help_options(MAINLINE_HELP);
+        let psy = parse_help_options(PSY_HELP);
+        let generated = params(
+            "--tune 2 --keyint 1 --lp 2 --irefresh-type 2 --crf 27 --preset 4 --color-primaries 1 --transfer-characteristics 1 --matrix-coefficients 1 --color-range 0 --tile-columns 1 --enable-qm 1 --qm-min 8 --qm-max 15 --film-grain 8",
+        );
+        assert!(unknown_flags(&generated, &mainline).is_empty());
+
+        let custom =
+            params("--crf=24 --psy-rd 1.0 --enable-hdr 1 --frame-lumabias 10 --psy-rd 2.0");
+        assert_eq!(
+            unknown_flags(&custom, &mainline),
+            ["--psy-rd", "--enable-hdr", "--frame-lumabias"]
+        );
+        // A typo stays unknown to the fork too
+        assert_eq!(unknown_flags(&custom, &psy), ["--frame-lumabias"]);
+    }
+
+    #[test]
+    fn encoder_versions_parse() {
+        let psy = EncoderVersion::parse("SVT-AV1-PSY v2.3.0-A (release)");
+        assert!(psy.psy);
+        assert_eq!(psy.version, Some((2, 3, 0)));
+
+        let mainline = EncoderVersion::parse("SVT-AV1 v1.8 (release)");
+        assert!(!mainline.psy);
+        assert_eq!(mainline.version, Some((1, 8, 0)));
+
+        let unknown = EncoderVersion::parse("SvtAv1EncApp");
+        assert!(!unknown.psy);
+        assert_eq!(unknown.version, None);
+    }
+
+    #[test]
+    fn options_match_in_every_form() {
+        assert!(uses_option(&params("--tune 3 --crf 27"), "--tune 3"));
+        assert!(uses_option(&params("--crf 27 --tune=3"), "--tune 3"));
+        assert!(!uses_option(&params("--tune 2"), "--tune 3"));
+        assert!(!uses_option(&params("--tune"), "--tune 3"));
+        // A flag without a value matches whatever follows it
+        assert!(uses_option(
+            &params("--variance-octile 5"),
+            "--variance-octile"
+        ));
+        assert!(uses_option(
+            &params("--variance-octile=5"),
+            "--variance-octile"
+        ));
+        assert!(!uses_option(&params("--variance"), "--variance-octile"));
+    }
+
+    #[test]
+    fn unsupported_options_say_why() {
+        let custom = params("--tune 3 --enable-variance-boost 1 --variance-octile 5");
+
+        let old_mainline = EncoderVersion::parse("SVT-AV1 v1.8.0 (release)");
+        assert_eq!(
+            old_mainline.unsupported(&custom),
+            [
+                ("--tune 3", String::from("needs SVT-AV1-PSY")),
+                (
+                    "--enable-variance-boost 1",
+                    String::from("needs 2.0.0 or newer")
+                ),
+                ("--variance-octile", String::from("needs 2.0.0 or newer")),
+            ]
+        );
+
+        let mainline = EncoderVersion::parse("SVT-AV1 v2.1.0 (release)");
+        assert_eq!(
+            mainline.unsupported(&custom),
+            [("--tune 3", String::from("needs SVT-AV1-PSY"))]
+        );
+
+        let psy = EncoderVersion::parse("SVT-AV1-PSY v2.3.0-A (release)");
+        assert!(psy.unsupported(&custom).is_empty());
+        // Turning variance boost off works anywhere
+        assert!(old_mainline
+            .unsupported(&params("--enable-variance-boost 0"))
+            .is_empty());
+        // Without a version number only the fork check is left
+        assert!(EncoderVersion::parse("SvtAv1EncApp")
+            .unsupported(&params("--variance-octile 5"))
+            .is_empty());
+    }
 }
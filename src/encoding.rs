@@ -1,7 +1,93 @@
 use regex::Regex;
-use std::process::Command;
+use std::io::{BufRead, BufReader, Read};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::{mpsc, LazyLock};
+use std::time::{Duration, Instant};
 
 use crate::app::AV1Studio;
+use crate::models::{
+    photon_noise_strength, scale_size, ChromaSamplePosition, ChunkOrder, CustomParamsMode, Encoder,
+    GrainSynthesis, LogLevel, RateControl,
+};
+use crate::paths::{expand_path, expand_path_string};
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum EncodeState {
+    Starting,
+    Analyzing(Option<f32>), // Scene detection, with its progress from 0 to 1 once known
+    Encoding,
+}
+
+// Follows av1an from scene detection into encoding. Returns true for scene detection output,
+// which must not be read as encoding progress.
+pub fn parse_scene_detection(line: &str, state: &mut EncodeState) -> bool {
+    if *state == EncodeState::Starting && line.to_lowercase().contains("scene detection") {
+        *state = EncodeState::Analyzing(None);
+        return true;
+    }
+    if !matches!(state, EncodeState::Analyzing(_)) {
+        return false;
+    }
+
+    // e.g. "⠙ 00:00:06 ▕█████▏  42%  6042/14386 (1007.00 fps, eta 8s)"
+    static PROGRESS: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"(\d+(?:\.\d+)?)%\s+\d+/\d+").unwrap());
+    match PROGRESS.captures(line) {
+        Some(caps) => {
+            let percentage: f32 = caps[1].parse().unwrap_or_default();
+            *state = EncodeState::Analyzing(Some((percentage / 100.0).clamp(0.0, 1.0)));
+            true
+        }
+        None => false,
+    }
+}
+
+// The share of the overall progress each phase gets, roughly how long they usually take
+const SCENE_DETECTION_WEIGHT: f32 = 0.1;
+const ENCODING_WEIGHT: f32 = 1.0 - SCENE_DETECTION_WEIGHT;
+
+// Scene detection and encoding as one progress from 0 to 1. Without scene detection (with a
+// scenes file, for example) encoding gets the whole range.
+pub fn overall_progress(
+    state: EncodeState,
+    scene_detection_ran: bool,
+    encoded_frames: Option<u32>,
+    total_frames: Option<u32>,
+) -> f32 {
+    let encoding = match (encoded_frames, total_frames) {
+        (Some(encoded), Some(total)) if total > 0 => (encoded as f32 / total as f32).min(1.0),
+        _ => 0.0,
+    };
+
+    match state {
+        EncodeState::Starting => 0.0,
+        EncodeState::Analyzing(progress) => progress.unwrap_or_default() * SCENE_DETECTION_WEIGHT,
+        EncodeState::Encoding if scene_detection_ran => {
+            SCENE_DETECTION_WEIGHT + encoding * ENCODING_WEIGHT
+        }
+        EncodeState::Encoding => encoding,
+    }
+}
+
+// One line of av1an's output, scene detection or encoding
+pub fn parse_output_line(
+    line: &str,
+    state: &mut EncodeState,
+    encoded_frames: &mut Option<u32>,
+    total_frames: &mut Option<u32>,
+    fps: &mut Option<f64>,
+    eta_time: &mut Option<String>,
+    passes: u32,
+) {
+    if parse_scene_detection(line, state) {
+        return;
+    }
+
+    parse_av1an_output(line, encoded_frames, total_frames, fps, eta_time, passes);
+    if encoded_frames.is_some() {
+        *state = EncodeState::Encoding;
+    }
+}
 
 pub fn parse_av1an_output(
     output: &str,
@@ -9,84 +95,1915 @@ pub fn parse_av1an_output(
     total_frames: &mut Option<u32>,
     fps: &mut Option<f64>,
     eta_time: &mut Option<String>,
+    passes: u32,
 ) {
-    println!("parse_av1an_output called with: {}", output);
-    let re = Regex::new(r"(\d+)\s+(\d+)").unwrap();
+    // Progress lines are nothing but "encoded total [fps eta]", e.g. "312 14386 18.20 13:04".
+    // Anything else with numbers in it, like "Queue 58 Workers 4", is left alone.
+    let re = Regex::new(r"^\s*(\d+)\s+(\d+)(?:\s+(\d+(?:\.\d+)?)\s+(\d+:\d{2}(?::\d{2})?))?\s*$")
+        .unwrap();
 
     for line in output.lines() {
-        if let Some(caps) = re.captures(line) {
-            *encoded_frames = caps.get(1).and_then(|m| m.as_str().parse().ok());
-            *total_frames = caps.get(2).and_then(|m| m.as_str().parse().ok());
-            *fps = caps.get(3).and_then(|m| m.as_str().parse().ok());
-            *eta_time = caps.get(4).map(|m| m.as_str().to_string());
+        // Bytes that weren't UTF-8 come through as U+FFFD, they mustn't cost the line its
+        // progress
+        let line: String = line.chars().filter(char::is_ascii).collect();
+        let Some(caps) = re.captures(&line) else {
+            continue;
+        };
+        let (Ok(encoded), Ok(total)) = (caps[1].parse::<u32>(), caps[2].parse::<u32>()) else {
+            continue;
+        };
+        // av1an counts the frames of every pass against the one total, so with two passes the
+        // count runs up to twice the frames. Split up, the progress ends at the total.
+        let passes = passes.max(1);
+        if encoded > total.saturating_mul(passes) {
+            continue;
+        }
+        *encoded_frames = Some(encoded / passes);
+        *total_frames = Some(total);
+        *fps = caps.get(3).and_then(|m| m.as_str().parse().ok());
+        *eta_time = caps.get(4).map(|m| m.as_str().to_string());
+    }
+}
+
+// av1an prints the ETA as H:MM:SS or MM:SS
+pub fn parse_eta(eta: &str) -> Option<Duration> {
+    let parts: Vec<&str> = eta.trim().split(':').collect();
+    if parts.len() > 3 {
+        return None;
+    }
+
+    let mut seconds = 0;
+    for part in parts {
+        seconds = seconds * 60 + part.parse::<u64>().ok()?;
+    }
+
+    Some(Duration::from_secs(seconds))
+}
+
+// H:MM:SS, the hours keep counting past a day
+pub fn format_duration(duration: Duration) -> String {
+    let total = duration.as_secs();
+    format!(
+        "{}:{:02}:{:02}",
+        total / 3600,
+        (total % 3600) / 60,
+        total % 60
+    )
+}
+
+// "0:42:15 (done ~ 14:32)", with the day as well when it finishes after today
+pub fn format_eta(remaining: Duration) -> String {
+    let now = chrono::Local::now();
+    let finish = now + chrono::Duration::from_std(remaining).unwrap_or_default();
+    let format = if finish.date_naive() == now.date_naive() {
+        "%H:%M"
+    } else {
+        "%a %H:%M"
+    };
+
+    format!(
+        "{} (done ~ {})",
+        format_duration(remaining),
+        finish.format(format)
+    )
+}
+
+// av1an's own ETA swings a lot early on, so the remaining time is derived from an exponentially
+// weighted moving average of the speed instead
+const FPS_SMOOTHING: f64 = 0.1; // Weight of the newest sample
+const ETA_WARMUP: Duration = Duration::from_secs(30);
+
+#[derive(Default, Clone, Copy, Debug)]
+pub struct FpsAverage {
+    fps: Option<f64>,
+}
+
+impl FpsAverage {
+    pub fn push(&mut self, fps: f64) {
+        if !fps.is_finite() || fps <= 0.0 {
+            return;
         }
+        self.fps = Some(match self.fps {
+            Some(average) => average + FPS_SMOOTHING * (fps - average),
+            None => fps,
+        });
+    }
+
+    // None until both the speed and the total frame count are known
+    pub fn remaining(
+        &self,
+        encoded_frames: Option<u32>,
+        total_frames: Option<u32>,
+    ) -> Option<Duration> {
+        let fps = self.fps?;
+        let left = total_frames?.saturating_sub(encoded_frames.unwrap_or_default());
+
+        Some(Duration::from_secs_f64(left as f64 / fps))
     }
 }
 
-pub fn generate_command(state: &AV1Studio) -> Command {
+// What av1an's environment differs in from AV1Studio's own. A configured ffmpeg gets its
+// folder put in front of the PATH so av1an finds it first, the user's overrides come after
+// and win.
+pub fn environment_overrides(state: &AV1Studio) -> Vec<(String, String)> {
+    let mut overrides = Vec::new();
+
+    let ffmpeg_path = expand_path(&state.ffmpeg_path);
+    if let Some(dir) = ffmpeg_path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+    {
+        let path = std::env::var_os("PATH").unwrap_or_default();
+        let dirs = std::iter::once(dir.to_path_buf()).chain(std::env::split_paths(&path));
+        if let Ok(joined) = std::env::join_paths(dirs) {
+            overrides.push((String::from("PATH"), joined.to_string_lossy().to_string()));
+        }
+    }
+    for row in &state.environment {
+        let key = row.key.trim();
+        if !key.is_empty() {
+            overrides.retain(|(existing, _)| existing != key);
+            overrides.push((key.to_string(), row.value.clone()));
+        }
+    }
+
+    overrides
+}
+
+// All active ffmpeg filters as one -vf chain, None when there are none. They run in this order:
+// deinterlacing needs the original fields, cropping first saves the denoiser work, and scaling
+// comes last.
+pub fn build_vf_chain(state: &AV1Studio) -> Option<String> {
+    let mut filters: Vec<String> = state
+        .deinterlace
+        .filter()
+        .map(str::to_string)
+        .into_iter()
+        .collect();
+    let (top, bottom, left, right) = (
+        state.crop_top,
+        state.crop_bottom,
+        state.crop_left,
+        state.crop_right,
+    );
+    if top + bottom + left + right > 0 {
+        filters.push(format!(
+            "crop=iw-{}:ih-{}:{}:{}",
+            left + right,
+            top + bottom,
+            left,
+            top
+        ));
+    }
+    filters.extend(state.denoise.filter(state.denoise_strength));
+    if let Some((width, height)) =
+        scale_size(&state.width, &state.height, state.source_resolution())
+    {
+        filters.push(format!(
+            "scale={}:{}:{}",
+            width,
+            height,
+            state.scale_algorithm.flags()
+        ));
+    }
+
+    (!filters.is_empty()).then(|| filters.join(","))
+}
+
+// Err when the custom encoder parameters don't parse, the encode would run without them
+pub fn generate_command(state: &AV1Studio) -> Result<Command, String> {
     let mut cmd = if state.av1an_verbosity_path.is_empty() {
         Command::new("av1an-verbosity")
     } else {
-        Command::new(&state.av1an_verbosity_path)
+        Command::new(expand_path(&state.av1an_verbosity_path))
     };
 
     // Build command arguments
     if !state.input_file.is_empty() {
-        cmd.arg("-i").arg(&state.input_file);
+        cmd.arg("-i").arg(expand_path(&state.input_file));
     } else {
         eprintln!("ERROR : Input file path needs to be provided\n");
     }
     if !state.output_file.is_empty() {
-        cmd.arg("-o").arg(&state.output_file);
+        cmd.arg("-o").arg(expand_path(&state.output_file));
     } else {
         eprintln!("ERROR : Output file path needs to be provided\n");
     }
     if !state.scenes_file.is_empty() {
-        cmd.arg("--scenes").arg(&state.scenes_file);
+        cmd.arg("--scenes").arg(expand_path(&state.scenes_file));
     }
     if !state.zones_file.is_empty() {
-        cmd.arg("--zones").arg(&state.zones_file);
+        cmd.arg("--zones").arg(expand_path(&state.zones_file));
+    }
+    if !state.temp_dir.is_empty() {
+        cmd.arg("--temp").arg(expand_path(&state.temp_dir));
+    }
+    if state.log_level != LogLevel::Default {
+        cmd.arg("--log-level").arg(state.log_level.as_str());
+    }
+    if state.resume {
+        cmd.arg("--resume");
     }
     cmd.arg("--verbose-frame-info")
         .arg("--split-method")
         .arg("av-scenechange");
+    if !state.min_scene_len.trim().is_empty() {
+        cmd.arg("--min-scene-len").arg(state.min_scene_len.trim());
+    }
+    if !state.max_scene_len.trim().is_empty() {
+        cmd.arg("--extra-split").arg(state.max_scene_len.trim());
+    }
 
-    cmd.arg("-c").arg(if !state.file_concatenation.is_empty() {
-        &state.file_concatenation
-    } else {
-        "mkvmerge"
-    });
+    // A concatenation method that can't produce the chosen container gets swapped for one that can
+    let concat = state.file_concatenation.trim();
+    cmd.arg("-c").arg(
+        if !concat.is_empty() && state.output_container.supports_concat(concat) {
+            concat
+        } else {
+            state.output_container.default_concat()
+        },
+    );
 
-    cmd.arg("-m")
-        .arg(state.source_library.as_str().to_lowercase());
+    // A VapourSynth script already does its own sourcing
+    if !state.is_vapoursynth_script() {
+        cmd.arg("-m")
+            .arg(state.chunk_method.arg(state.source_library));
+    }
 
-    if !state.width.is_empty() && !state.height.is_empty() {
-        let scale = format!(
-            "scale={}:{}:flags=bicubic:param0=0:param1=1/2",
-            state.width, state.height
-        );
-        cmd.arg("-f").arg(format!("-vf {}", scale));
+    if let Some(chain) = build_vf_chain(state) {
+        cmd.arg("-f").arg(format!("-vf {}", chain));
     }
 
     cmd.arg("--pix-format")
         .arg(state.output_pixel_format.as_str())
         .arg("-e")
-        .arg("svt-av1");
+        .arg(state.encoder.as_str());
+
+    if state.force {
+        cmd.arg("--force");
+    }
+    cmd.arg("-v").arg(encoder_params(state)?);
+
+    if let (GrainSynthesis::PhotonNoise, Some(strength)) = (
+        state.grain_synthesis,
+        photon_noise_strength(&state.photon_noise),
+    ) {
+        cmd.arg("--photon-noise").arg(strength.to_string());
+        if let Some((width, height)) = state.photon_noise_size() {
+            cmd.arg("--photon-noise-width")
+                .arg(width.to_string())
+                .arg("--photon-noise-height")
+                .arg(height.to_string());
+        }
+    }
 
-    if !state.custom_encode_params.is_empty() {
-        cmd.arg("-v").arg(&state.custom_encode_params);
+    cmd.arg("--passes").arg(state.encode_passes().to_string());
+
+    if state.chunk_order != ChunkOrder::default() {
+        cmd.arg("--chunk-order").arg(state.chunk_order.as_str());
+    }
+
+    cmd.arg("--set-thread-affinity").arg(&state.thread_affinity);
+    // Blank or 0 means auto for av1an too, so those are left out as well
+    let workers = state.workers.trim();
+    if !state.auto_workers && !workers.is_empty() && workers != "0" {
+        cmd.arg("-w").arg(workers);
+    }
+
+    let target_quality = state.target_quality.trim();
+    if !target_quality.is_empty() {
+        cmd.arg("--target-quality").arg(target_quality);
+        if !state.min_q.trim().is_empty() {
+            cmd.arg("--min-q").arg(state.min_q.trim());
+        }
+        if !state.max_q.trim().is_empty() {
+            cmd.arg("--max-q").arg(state.max_q.trim());
+        }
+    }
+
+    // Last, so they can override anything above. Unparseable input is left out, the UI
+    // shows why.
+    cmd.args(split_args(&state.extra_av1an_args).unwrap_or_default());
+
+    for (key, value) in environment_overrides(state) {
+        if value.is_empty() {
+            cmd.env_remove(key);
+        } else {
+            cmd.env(key, value);
+        }
+    }
+
+    if state.low_priority {
+        lower_priority(&mut cmd);
+    }
+
+    Ok(cmd)
+}
+
+// Niceness and priority class are both inherited, so av1an's encoder and ffmpeg children run
+// at low priority too
+#[cfg(unix)]
+fn lower_priority(cmd: &mut Command) {
+    use std::os::unix::process::CommandExt;
+
+    // SAFETY: nice() is async-signal-safe and touches nothing but the new process' priority
+    unsafe {
+        cmd.pre_exec(|| {
+            libc::nice(10);
+            Ok(())
+        });
+    }
+}
+
+#[cfg(windows)]
+fn lower_priority(cmd: &mut Command) {
+    use std::os::windows::process::CommandExt;
+
+    const BELOW_NORMAL_PRIORITY_CLASS: u32 = 0x0000_4000;
+    cmd.creation_flags(BELOW_NORMAL_PRIORITY_CLASS);
+}
+
+#[cfg(not(any(unix, windows)))]
+fn lower_priority(_cmd: &mut Command) {}
+
+// Shell-style splitting, quotes keep values with spaces together. av1an splits the -v value
+// the same way on its own, so the custom parameters are split here to catch mistakes like an
+// unclosed quote, then joined back with quoting it understands.
+pub fn split_args(args: &str) -> Result<Vec<String>, shell_words::ParseError> {
+    shell_words::split(args)
+}
+
+// What to tell the user when av1an doesn't start. Not finding it is what a first run without
+// a configured path runs into.
+pub fn start_failure(error: &std::io::Error) -> String {
+    if error.kind() == std::io::ErrorKind::NotFound {
+        String::from("av1an-verbosity not found; set its path in Settings")
     } else {
-        let params = format!(
-            "--tune 2 --keyint 1 --lp 2 --irefresh-type 2 --crf {} --preset {} --film-grain {} --color-primaries {:?} --transfer-characteristics {:?} --matrix-coefficients {:?} --color-range {:?}",
-            state.crf, state.preset, state.synthetic_grain, state.color_primaries.as_str(), state.transfer_characteristics.as_str(), state.matrix_coefficients.as_str(), state.color_range.as_str(),
+        format!("Couldn't start av1an: {}", error)
+    }
+}
+
+// av1an's --passes, 1 when it's left to av1an
+fn command_passes(cmd: &Command) -> u8 {
+    cmd.get_args()
+        .skip_while(|arg| *arg != "--passes")
+        .nth(1)
+        .and_then(|passes| passes.to_str()?.parse().ok())
+        .unwrap_or(1)
+}
+
+// The command as one shell line that can be copied and run, prefixed with env(1) when it
+// changes the environment
+pub fn command_to_string(cmd: &Command) -> String {
+    let mut all = Vec::new();
+    if cmd.get_envs().next().is_some() {
+        all.push(String::from("env"));
+    }
+    // env(1) takes its options before the first assignment, get_envs() is sorted by key
+    for (key, _) in cmd.get_envs().filter(|(_, value)| value.is_none()) {
+        all.push(format!("-u {}", shell_words::quote(&key.to_string_lossy())));
+    }
+    for (key, value) in cmd.get_envs() {
+        if let Some(value) = value {
+            all.push(
+                shell_words::quote(&format!(
+                    "{}={}",
+                    key.to_string_lossy(),
+                    value.to_string_lossy()
+                ))
+                .to_string(),
+            );
+        }
+    }
+    all.extend(
+        std::iter::once(cmd.get_program())
+            .chain(cmd.get_args())
+            .map(|arg| shell_words::quote(&arg.to_string_lossy()).to_string()),
+    );
+
+    all.join(" ")
+}
+
+// Encoder flags that the settings in the UI generate
+const GENERATED_FLAGS: [&str; 20] = [
+    "--crf",
+    "--rc",
+    "--tbr",
+    "--mbr",
+    "--buf-sz",
+    "--preset",
+    "--tune",
+    "--tile-columns",
+    "--tile-rows",
+    "--enable-qm",
+    "--qm-min",
+    "--qm-max",
+    "--film-grain",
+    "--fgs-table",
+    "--color-primaries",
+    "--transfer-characteristics",
+    "--matrix-coefficients",
+    "--color-range",
+    "--chroma-sample-position",
+    "--enable-hdr",
+];
+
+// Flags in the custom parameters that one of the UI settings controls as well, in the order
+// they appear
+pub fn conflicting_params(params: &str) -> Vec<&'static str> {
+    let mut conflicts = Vec::new();
+    for token in split_args(params).unwrap_or_default() {
+        let flag = token.split('=').next().unwrap_or_default();
+        if let Some(flag) = GENERATED_FLAGS.iter().find(|f| **f == flag) {
+            if !conflicts.contains(flag) {
+                conflicts.push(*flag);
+            }
+        }
+    }
+
+    conflicts
+}
+
+// Exactly what av1an gets after -v: the generated parameters with the custom ones merged in, or
+// only the custom ones in Replace mode
+pub fn encoder_params(state: &AV1Studio) -> Result<String, String> {
+    let custom = state.custom_encode_params.trim();
+    if custom.is_empty() {
+        return Ok(generated_params(state));
+    }
+
+    let params = split_args(custom).map_err(custom_params_problem)?;
+    Ok(match state.custom_params_mode {
+        CustomParamsMode::Replace => shell_words::join(params),
+        CustomParamsMode::Append => merge_params(&generated_params(state), custom),
+    })
+}
+
+pub fn custom_params_problem(error: shell_words::ParseError) -> String {
+    format!("Couldn't parse the custom encoder parameters: {}", error)
+}
+
+// A flag with its value, if it has one. `equals` keeps the --flag=value form.
+struct Param {
+    flag: String,
+    value: Option<String>,
+    equals: bool,
+}
+
+fn parse_params(params: &str) -> Vec<Param> {
+    let tokens = split_args(params).unwrap_or_default();
+    let mut parsed = Vec::new();
+    let mut tokens = tokens.into_iter().peekable();
+    while let Some(token) = tokens.next() {
+        if let Some((flag, value)) = token.split_once('=').filter(|_| token.starts_with('-')) {
+            parsed.push(Param {
+                flag: flag.to_string(),
+                value: Some(value.to_string()),
+                equals: true,
+            });
+            continue;
+        }
+        // Anything that isn't another flag is this one's value, negative numbers included
+        let value = tokens
+            .next_if(|next| !next.starts_with('-') || next.parse::<f64>().is_ok())
+            .filter(|_| token.starts_with('-'));
+        parsed.push(Param {
+            flag: token,
+            value,
+            equals: false,
+        });
+    }
+
+    parsed
+}
+
+// `generated` with the flags of `custom` added. A flag that's set more than once keeps only its
+// last value, at the last position.
+pub fn merge_params(generated: &str, custom: &str) -> String {
+    let params: Vec<Param> = parse_params(generated)
+        .into_iter()
+        .chain(parse_params(custom))
+        .collect();
+
+    params
+        .iter()
+        .enumerate()
+        .filter(|(index, param)| !params[index + 1..].iter().any(|p| p.flag == param.flag))
+        .map(|(_, param)| match &param.value {
+            // Only the value is quoted so --flag=value stays as it was written
+            Some(value) if param.equals => format!("{}={}", param.flag, shell_words::quote(value)),
+            Some(value) => format!(
+                "{} {}",
+                shell_words::quote(&param.flag),
+                shell_words::quote(value)
+            ),
+            None => shell_words::quote(&param.flag).to_string(),
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+// The parameters the settings in the UI make
+fn generated_params(state: &AV1Studio) -> String {
+    // The other encoders only get quality and speed, everything else is SVT-AV1 specific
+    let ranges = state.encoder.ranges();
+    let quality = state.crf.clamp(ranges.quality.0, ranges.quality.1);
+    let speed = state.preset.clamp(ranges.speed.0, ranges.speed.1);
+    let bitrate = state.target_bitrate.trim();
+    let rate_control = match (state.encoder, state.rate_control) {
+        (Encoder::SvtAv1, RateControl::Crf) => format!("--crf {}", state.crf),
+        (Encoder::SvtAv1, RateControl::TargetBitrate) => format!("--rc 1 --tbr {}", bitrate),
+        (Encoder::Aom, RateControl::Crf) => {
+            format!("--end-usage=q --cq-level={}", quality.round())
+        }
+        (Encoder::Aom, RateControl::TargetBitrate) => {
+            format!("--end-usage=vbr --target-bitrate={}", bitrate)
+        }
+        (Encoder::Rav1e, RateControl::Crf) => format!("--quantizer {}", quality.round()),
+        (Encoder::Rav1e, RateControl::TargetBitrate) => format!("--bitrate {}", bitrate),
+    };
+    match state.encoder {
+        Encoder::SvtAv1 => {}
+        Encoder::Aom => return format!("{} --cpu-used={}", rate_control, speed.round()),
+        Encoder::Rav1e => return format!("{} --speed {}", rate_control, speed.round()),
+    }
+
+    let mut params = format!(
+        "--tune {} --keyint 1 --lp 2 --irefresh-type 2 {} --preset {} --color-primaries {:?} --transfer-characteristics {:?} --matrix-coefficients {:?} --color-range {:?}",
+        state.tune.as_str(), rate_control, state.preset, state.color_primaries.as_str(), state.transfer_characteristics.as_str(), state.matrix_coefficients.as_str(), state.color_range.as_str(),
+    );
+    if state.chroma_sample_position != ChromaSamplePosition::Unknown {
+        params.push_str(&format!(
+            " --chroma-sample-position {}",
+            state.chroma_sample_position.as_str()
+        ));
+    }
+    if state.enable_hdr {
+        params.push_str(" --enable-hdr 1");
+    }
+    // Capped CRF: CRF as usual, with the bitrate held under the cap over the buffer
+    if state.rate_control == RateControl::Crf && !state.max_bitrate.trim().is_empty() {
+        params.push_str(&format!(" --mbr {}", state.max_bitrate.trim()));
+        if !state.buffer_size.trim().is_empty() {
+            params.push_str(&format!(" --buf-sz {}", state.buffer_size.trim()));
+        }
+    }
+    // Only passed when set, so the encoder's own default applies otherwise
+    if state.tile_columns > 0 {
+        params.push_str(&format!(" --tile-columns {}", state.tile_columns));
+    }
+    if state.tile_rows > 0 {
+        params.push_str(&format!(" --tile-rows {}", state.tile_rows));
+    }
+    if state.enable_qm {
+        params.push_str(&format!(
+            " --enable-qm 1 --qm-min {} --qm-max {}",
+            state.qm_min.min(state.qm_max),
+            state.qm_max
+        ));
+    }
+    match state.grain_synthesis {
+        GrainSynthesis::Synthetic => {
+            params.push_str(&format!(" --film-grain {}", state.synthetic_grain));
+        }
+        GrainSynthesis::GrainTable => {
+            params.push_str(&format!(
+                " --fgs-table {:?}",
+                expand_path_string(&state.grain_table)
+            ));
+        }
+        GrainSynthesis::PhotonNoise => {}
+    }
+
+    params
+}
+
+// What av1an, the encoders, ffmpeg and the shell print when memory runs out. av1an reports an
+// encoder the kernel's OOM killer took out as crashed with SIGKILL.
+pub fn is_out_of_memory_line(line: &str) -> bool {
+    let line = line.to_lowercase();
+    let patterns = [
+        "out of memory",
+        "cannot allocate memory",
+        "memory allocation of", // Rust's "memory allocation of N bytes failed"
+        "failed to allocate",
+        "std::bad_alloc",
+        "oom-kill",
+        "sigkill",
+    ];
+
+    patterns.iter().any(|pattern| line.contains(pattern)) || line.trim() == "killed"
+}
+
+// The score in a line like "VMAF score: 95.21", as av1an prints it with --vmaf. Per-chunk
+// target quality probes are skipped, they aren't the whole encode's.
+pub fn parse_vmaf_line(line: &str) -> Option<f64> {
+    static PER_CHUNK: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?i)chunk|probe").unwrap());
+    static SCORE: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"(?i)\bvmaf(?:\s+score)?\s*[:=]?\s*(\d{1,3}\.\d+)").unwrap());
+    if PER_CHUNK.is_match(line) {
+        return None;
+    }
+
+    SCORE.captures(line)?[1].parse().ok()
+}
+
+// A running av1an process along with the progress parsed from its output
+pub struct EncodeSession {
+    child: Child,
+    receiver: mpsc::Receiver<String>,
+    output_closed: bool,
+    last_progress: Option<(Instant, u32)>,
+
+    pub encoded_frames: Option<u32>,
+    pub total_frames: Option<u32>,
+    pub fps: Option<f64>,
+    pub eta_time: Option<String>,
+    pub status: Option<ExitStatus>,
+    pub state: EncodeState,
+    pub scene_detection_ran: bool,
+    pub average_fps: FpsAverage,
+    pub started_at: Instant,
+    pub paused: bool,
+    pub out_of_memory_output: bool, // Whether any output looked like memory ran out
+    pub vmaf: Option<f64>,          // The last score av1an printed
+    pub lines: Vec<String>,         // Output received since the caller last took it
+
+    passes: u8,
+}
+
+impl EncodeSession {
+    // Pausing needs process groups and signals
+    pub const CAN_PAUSE: bool = cfg!(unix);
+
+    pub fn start(mut cmd: Command) -> std::io::Result<Self> {
+        let passes = command_passes(&cmd);
+
+        // av1an gets a process group of its own, which its encoder and ffmpeg processes join, so
+        // pausing reaches all of them. This also keeps a Ctrl+C in AV1Studio's terminal from
+        // reaching them, so they're stopped through cancel(), which on_exit calls too.
+        #[cfg(unix)]
+        std::os::unix::process::CommandExt::process_group(&mut cmd, 0);
+
+        let mut child = cmd.stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()?;
+
+        let (sender, receiver) = mpsc::channel();
+        if let Some(stdout) = child.stdout.take() {
+            forward_lines(stdout, sender.clone());
+        }
+        if let Some(stderr) = child.stderr.take() {
+            forward_lines(stderr, sender);
+        }
+
+        Ok(EncodeSession {
+            child,
+            receiver,
+            output_closed: false,
+            last_progress: None,
+            encoded_frames: None,
+            total_frames: None,
+            fps: None,
+            eta_time: None,
+            status: None,
+            state: EncodeState::Starting,
+            scene_detection_ran: false,
+            average_fps: FpsAverage::default(),
+            started_at: Instant::now(),
+            paused: false,
+            out_of_memory_output: false,
+            vmaf: None,
+            lines: Vec::new(),
+            passes,
+        })
+    }
+
+    pub fn poll(&mut self) {
+        loop {
+            match self.receiver.try_recv() {
+                Ok(line) => {
+                    let previous_frames = self.encoded_frames;
+                    parse_output_line(
+                        &line,
+                        &mut self.state,
+                        &mut self.encoded_frames,
+                        &mut self.total_frames,
+                        &mut self.fps,
+                        &mut self.eta_time,
+                        u32::from(self.passes),
+                    );
+                    if self.encoded_frames != previous_frames {
+                        self.record_progress();
+                    }
+                    if matches!(self.state, EncodeState::Analyzing(_)) {
+                        self.scene_detection_ran = true;
+                    }
+                    if is_out_of_memory_line(&line) {
+                        self.out_of_memory_output = true;
+                    }
+                    if let Some(vmaf) = parse_vmaf_line(&line) {
+                        self.vmaf = Some(vmaf);
+                    }
+                    self.lines.push(line);
+                }
+                Err(mpsc::TryRecvError::Empty) => break,
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    self.output_closed = true;
+                    break;
+                }
+            }
+        }
+
+        if self.status.is_none() {
+            if let Ok(Some(status)) = self.child.try_wait() {
+                self.status = Some(status);
+            }
+        }
+    }
+
+    // Feeds the average with av1an's speed, or the speed since the last update if it has none
+    fn record_progress(&mut self) {
+        let Some(frames) = self.encoded_frames else {
+            return;
+        };
+        let now = Instant::now();
+        match (self.fps, self.last_progress) {
+            (Some(fps), _) => self.average_fps.push(fps),
+            (None, Some((at, previous))) => {
+                let seconds = now.duration_since(at).as_secs_f64();
+                if seconds > 0.0 {
+                    self.average_fps
+                        .push(frames.saturating_sub(previous) as f64 / seconds);
+                }
+            }
+            (None, None) => {}
+        }
+        self.last_progress = Some((now, frames));
+    }
+
+    // `total_frames` stands in for av1an's count until it reports one
+    pub fn overall_progress(&self, total_frames: Option<u32>) -> f32 {
+        overall_progress(
+            self.state,
+            self.scene_detection_ran,
+            self.encoded_frames,
+            self.total_frames.or(total_frames),
+        )
+    }
+
+    // The smoothed estimate, or av1an's own if there's none yet
+    pub fn remaining(&self) -> Option<Duration> {
+        self.average_fps
+            .remaining(self.encoded_frames, self.total_frames)
+            .or_else(|| self.eta_time.as_deref().and_then(parse_eta))
+    }
+
+    // What the UI shows for the remaining time, nothing is trusted during the first seconds
+    pub fn eta_text(&self) -> String {
+        match self.remaining() {
+            Some(remaining) if self.started_at.elapsed() >= ETA_WARMUP => format_eta(remaining),
+            _ => String::from("estimating…"),
+        }
+    }
+
+    // Finished once the process exited and all of its output has been read
+    pub fn is_finished(&self) -> bool {
+        self.status.is_some() && self.output_closed
+    }
+
+    pub fn succeeded(&self) -> bool {
+        self.status.is_some_and(|status| status.success())
+    }
+
+    // Whether the encode likely failed for lack of memory, by its output or by av1an itself
+    // getting killed (cancel() kills it too, so this only means something for unasked exits)
+    pub fn ran_out_of_memory(&self) -> bool {
+        #[cfg(unix)]
+        let killed = self.status.is_some_and(|status| {
+            std::os::unix::process::ExitStatusExt::signal(&status) == Some(libc::SIGKILL)
+        });
+        #[cfg(not(unix))]
+        let killed = false;
+
+        !self.succeeded() && (self.out_of_memory_output || killed)
+    }
+
+    // SIGSTOP and SIGCONT to av1an's whole process group
+    #[cfg(unix)]
+    pub fn set_paused(&mut self, paused: bool) -> std::io::Result<()> {
+        use nix::sys::signal::{killpg, Signal};
+        use nix::unistd::Pid;
+
+        let signal = if paused {
+            Signal::SIGSTOP
+        } else {
+            Signal::SIGCONT
+        };
+        killpg(Pid::from_raw(self.child.id() as i32), signal)?;
+        self.paused = paused;
+
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    pub fn set_paused(&mut self, _paused: bool) -> std::io::Result<()> {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "Pausing an encode is only supported on Unix",
+        ))
+    }
+
+    pub fn cancel(&mut self) {
+        // The whole group goes, av1an's encoders and ffmpeg would keep running without it. A
+        // paused group is woken first so nothing stays behind stopped.
+        #[cfg(unix)]
+        {
+            use nix::sys::signal::{killpg, Signal};
+            use nix::unistd::Pid;
+
+            let group = Pid::from_raw(self.child.id() as i32);
+            let _ = killpg(group, Signal::SIGCONT);
+            let _ = killpg(group, Signal::SIGKILL);
+            self.paused = false;
+        }
+        let _ = self.child.kill();
+        if let Ok(status) = self.child.wait() {
+            self.status = Some(status);
+        }
+    }
+}
+
+fn forward_lines<R: Read + Send + 'static>(stream: R, sender: mpsc::Sender<String>) {
+    std::thread::spawn(move || send_lines(BufReader::new(stream), &sender));
+}
+
+// Reads bytes rather than Strings, so a line with invalid UTF-8 (from the locale or a file
+// name) gets its odd bytes replaced instead of ending the output
+fn send_lines<R: BufRead>(mut reader: R, sender: &mpsc::Sender<String>) {
+    let mut buffer = Vec::new();
+    loop {
+        buffer.clear();
+        match reader.read_until(b'\n', &mut buffer) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {}
+        }
+        let line = String::from_utf8_lossy(&buffer);
+        let line = line.trim_end_matches(['\n', '\r']);
+        if sender.send(line.to_string()).is_err() {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::EnvOverride;
+    use crate::models::{ChunkMethod, Deinterlace, Denoise, ScaleAlgorithm, SourceLibrary};
+    use std::path::Path;
+
+    #[cfg(unix)]
+    #[test]
+    fn low_priority_is_inherited() {
+        let niceness = |cmd: &mut Command| -> i32 {
+            let output = cmd.output().unwrap();
+            String::from_utf8_lossy(&output.stdout)
+                .trim()
+                .parse()
+                .unwrap()
+        };
+
+        let normal = niceness(Command::new("sh").args(["-c", "sh -c nice"]));
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", "sh -c nice"]);
+        lower_priority(&mut cmd);
+
+        // The grandchild reports its own niceness, capped at 19
+        assert_eq!(niceness(&mut cmd), (normal + 10).min(19));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn pausing_stops_the_process_group() {
+        // The third field of /proc/<pid>/stat is the state, T when stopped
+        let state = |pid: u32| -> char {
+            let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).unwrap();
+            let after_name = &stat[stat.rfind(')').unwrap() + 2..];
+            after_name.chars().next().unwrap()
+        };
+        let mut cmd = Command::new("sleep");
+        cmd.arg("30");
+        let mut session = EncodeSession::start(cmd).unwrap();
+        let pid = session.child.id();
+
+        session.set_paused(true).unwrap();
+        std::thread::sleep(Duration::from_millis(100));
+        assert!(session.paused);
+        assert_eq!(state(pid), 'T');
+
+        session.set_paused(false).unwrap();
+        std::thread::sleep(Duration::from_millis(100));
+        assert_ne!(state(pid), 'T');
+
+        session.set_paused(true).unwrap();
+        session.cancel();
+        assert!(session.status.is_some());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn cancelling_kills_the_whole_group() {
+        let pid_file =
+            std::env::temp_dir().join(format!("av1studio-cancel-{}.pid", std::process::id()));
+        let _ = std::fs::remove_file(&pid_file);
+        // Stands in for av1an and an encoder it started
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c")
+            .arg(format!("sleep 30 & echo $! > {}; wait", pid_file.display()));
+        let mut session = EncodeSession::start(cmd).unwrap();
+        let mut pid = String::new();
+        for _ in 0..50 {
+            pid = std::fs::read_to_string(&pid_file).unwrap_or_default();
+            if !pid.trim().is_empty() {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(20));
+        }
+
+        session.set_paused(true).unwrap();
+        session.cancel();
+        std::thread::sleep(Duration::from_millis(100));
+        // Gone, or a zombie waiting for init to reap it
+        let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid.trim()));
+        assert!(stat.map_or(true, |stat| stat.contains(") Z ")));
+
+        std::fs::remove_file(&pid_file).unwrap();
+    }
+
+    #[test]
+    fn out_of_memory_messages_are_recognized() {
+        for line in [
+            "memory allocation of 2147483648 bytes failed",
+            "Svt[error]: EbMallocMemoryFailed: Cannot allocate memory",
+            "terminate called after throwing an instance of 'std::bad_alloc'",
+            "[libdav1d @ 0x55d1] Error allocating memory: Out of memory",
+            "ERROR [av1an_core::broker] [chunk 12] encoder crashed: signal: 9 (SIGKILL)",
+            "Killed",
+            "Failed to allocate frame buffer",
+        ] {
+            assert!(is_out_of_memory_line(line), "{}", line);
+        }
+        for line in [
+            "INFO [av1an_core::context] Queue 58 Workers 4 Passes 1",
+            "ERROR [av1an_core::broker] [chunk 12] encoder crashed: exit status: 1",
+            "Svt[info]: Memory usage: 1.2 GB",
+            "Process killed by the user",
+        ] {
+            assert!(!is_out_of_memory_line(line), "{}", line);
+        }
+    }
+
+    #[test]
+    fn vmaf_scores_are_read() {
+        assert_eq!(parse_vmaf_line("VMAF score: 95.21"), Some(95.21));
+        assert_eq!(parse_vmaf_line("INFO [av1an] VMAF: 93.5"), Some(93.5));
+        assert_eq!(parse_vmaf_line("[chunk 3] probe 2, q 28, VMAF 94.10"), None);
+        assert_eq!(parse_vmaf_line("Plotting VMAF"), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn being_killed_counts_as_out_of_memory() {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", "kill -9 $$"]);
+        let mut session = EncodeSession::start(cmd).unwrap();
+        while !session.is_finished() {
+            session.poll();
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert!(session.ran_out_of_memory());
+
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", "echo 'memory allocation of 64 bytes failed'; exit 1"]);
+        let mut session = EncodeSession::start(cmd).unwrap();
+        while !session.is_finished() {
+            session.poll();
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert!(session.ran_out_of_memory());
+
+        let mut session = EncodeSession::start(Command::new("false")).unwrap();
+        while !session.is_finished() {
+            session.poll();
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert!(!session.ran_out_of_memory());
+    }
+
+    #[test]
+    fn two_pass_progress_ends_at_the_total() {
+        let transcript = "0 1000\n600 1000\n1400 1000 30.00 0:40\n2000 1000\n";
+        let (_, encoded_frames, total_frames) = replay_passes(transcript, 2);
+        assert_eq!(encoded_frames, Some(1000));
+        assert_eq!(total_frames, Some(1000));
+
+        let mut state = EncodeState::Starting;
+        let (mut encoded_frames, mut total_frames, mut fps, mut eta_time) =
+            (None, None, None, None);
+        for line in transcript.lines() {
+            parse_output_line(
+                line,
+                &mut state,
+                &mut encoded_frames,
+                &mut total_frames,
+                &mut fps,
+                &mut eta_time,
+                2,
+            );
+            assert!(overall_progress(state, false, encoded_frames, total_frames) <= 1.0);
+        }
+        assert_eq!(encoded_frames, Some(1000));
+
+        let mut cmd = Command::new("av1an");
+        cmd.args(["-i", "in.mkv", "--passes", "2"]);
+        assert_eq!(command_passes(&cmd), 2);
+        assert_eq!(command_passes(&Command::new("av1an")), 1);
+    }
+
+    #[test]
+    fn decoy_lines_leave_the_progress_alone() {
+        let (states, encoded_frames, total_frames) = replay(
+            "312 14386 18.20 13:04\n\
+             using 2 passes\n\
+             spawned 8 workers\n\
+             INFO [av1an_core::context] Queue 58 Workers 4 Passes 1\n\
+             chunk 12 of 58 done\n\
+             1920x1080 24000/1001\n\
+             90 20\n",
+        );
+
+        assert_eq!(states, [EncodeState::Encoding]);
+        assert_eq!(encoded_frames, Some(312));
+        assert_eq!(total_frames, Some(14386));
+    }
+
+    #[test]
+    fn invalid_utf8_keeps_the_progress_lines() {
+        let output: &[u8] = b"INFO encoding /videos/caf\xe9.mkv\r\n312 14386 18.20 13:04\n\xff\xfe 400 14386 18.50 12:58\n";
+        let (sender, receiver) = mpsc::channel();
+        send_lines(output, &sender);
+        drop(sender);
+        let lines: Vec<String> = receiver.iter().collect();
+
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], "INFO encoding /videos/caf\u{FFFD}.mkv");
+
+        let mut state = EncodeState::Starting;
+        let (mut encoded_frames, mut total_frames, mut fps, mut eta_time) =
+            (None, None, None, None);
+        for line in &lines {
+            parse_output_line(
+                line,
+                &mut state,
+                &mut encoded_frames,
+                &mut total_frames,
+                &mut fps,
+                &mut eta_time,
+                1,
+            );
+        }
+        assert_eq!(encoded_frames, Some(400));
+        assert_eq!(total_frames, Some(14386));
+    }
+
+    fn replay(transcript: &str) -> (Vec<EncodeState>, Option<u32>, Option<u32>) {
+        replay_passes(transcript, 1)
+    }
+
+    fn replay_passes(
+        transcript: &str,
+        passes: u32,
+    ) -> (Vec<EncodeState>, Option<u32>, Option<u32>) {
+        let mut state = EncodeState::Starting;
+        let (mut encoded_frames, mut total_frames, mut fps, mut eta_time) =
+            (None, None, None, None);
+        let mut states = Vec::new();
+        for line in transcript.lines() {
+            parse_output_line(
+                line,
+                &mut state,
+                &mut encoded_frames,
+                &mut total_frames,
+                &mut fps,
+                &mut eta_time,
+                passes,
+            );
+            if states.last() != Some(&state) {
+                states.push(state);
+            }
+        }
+
+        (states, encoded_frames, total_frames)
+    }
+
+    #[test]
+    fn follows_scene_detection_into_encoding() {
+        let (states, encoded_frames, total_frames) =
+            replay(include_str!("../tests/fixtures/scene_detection.txt"));
+
+        assert_eq!(
+            states,
+            [
+                EncodeState::Starting,
+                EncodeState::Analyzing(None),
+                EncodeState::Analyzing(Some(0.0)),
+                EncodeState::Analyzing(Some(0.15)),
+                EncodeState::Analyzing(Some(0.42)),
+                EncodeState::Analyzing(Some(1.0)),
+                EncodeState::Encoding,
+            ]
+        );
+        assert_eq!(encoded_frames, Some(14386));
+        assert_eq!(total_frames, Some(14386));
+    }
+
+    #[test]
+    fn overall_progress_only_moves_forward() {
+        let transcript = include_str!("../tests/fixtures/scene_detection.txt");
+        let mut state = EncodeState::Starting;
+        let (mut encoded_frames, mut total_frames, mut fps, mut eta_time) =
+            (None, None, None, None);
+        let mut progress = Vec::new();
+        for line in transcript.lines() {
+            parse_output_line(
+                line,
+                &mut state,
+                &mut encoded_frames,
+                &mut total_frames,
+                &mut fps,
+                &mut eta_time,
+                1,
+            );
+            progress.push(overall_progress(state, true, encoded_frames, total_frames));
+        }
+
+        assert!(progress.windows(2).all(|pair| pair[0] <= pair[1]));
+        assert_eq!(progress.last(), Some(&1.0));
+        // Scene detection is done, encoding hasn't gotten anywhere yet
+        assert_eq!(
+            overall_progress(EncodeState::Encoding, true, Some(0), Some(100)),
+            SCENE_DETECTION_WEIGHT
+        );
+        assert_eq!(
+            overall_progress(EncodeState::Encoding, false, Some(50), Some(100)),
+            0.5
+        );
+    }
+
+    #[test]
+    fn scenes_file_skips_analyzing() {
+        let (states, encoded_frames, _) = replay(include_str!("../tests/fixtures/scenes_file.txt"));
+
+        assert_eq!(states, [EncodeState::Starting, EncodeState::Encoding]);
+        assert_eq!(encoded_frames, Some(14386));
+    }
+
+    #[test]
+    fn fps_average_smooths_samples() {
+        let mut average = FpsAverage::default();
+        assert_eq!(average.fps, None);
+
+        average.push(20.0);
+        assert_eq!(average.fps, Some(20.0));
+
+        // A single outlier only moves the average by the smoothing weight
+        average.push(120.0);
+        assert!((average.fps.unwrap() - 30.0).abs() < 1e-9);
+
+        // Nonsense samples are ignored
+        average.push(0.0);
+        average.push(f64::NAN);
+        assert!((average.fps.unwrap() - 30.0).abs() < 1e-9);
+
+        for _ in 0..200 {
+            average.push(10.0);
+        }
+        assert!((average.fps.unwrap() - 10.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn remaining_needs_total_frames() {
+        let mut average = FpsAverage::default();
+        average.push(25.0);
+
+        assert_eq!(average.remaining(Some(100), None), None);
+        assert_eq!(
+            average.remaining(Some(100), Some(2600)),
+            Some(Duration::from_secs(100))
+        );
+        assert_eq!(
+            average.remaining(Some(3000), Some(2600)),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(FpsAverage::default().remaining(Some(0), Some(10)), None);
+    }
+
+    #[test]
+    fn formats_durations() {
+        assert_eq!(format_duration(Duration::from_secs(0)), "0:00:00");
+        assert_eq!(format_duration(Duration::from_secs(2535)), "0:42:15");
+        assert_eq!(
+            format_duration(Duration::from_secs(3 * 3600 + 61)),
+            "3:01:01"
+        );
+        // More than a day
+        assert_eq!(format_duration(Duration::from_secs(90061)), "25:01:01");
+        assert_eq!(
+            format_duration(Duration::from_secs(100 * 3600)),
+            "100:00:00"
+        );
+    }
+
+    #[test]
+    fn eta_shows_the_finish_day_after_today() {
+        assert!(format_eta(Duration::from_secs(3 * 86400)).starts_with("72:00:00 (done ~ "));
+        let eta = format_eta(Duration::from_secs(3 * 86400));
+        let finish = eta
+            .trim_start_matches("72:00:00 (done ~ ")
+            .trim_end_matches(')');
+        // e.g. "Mon 14:32"
+        assert_eq!(finish.len(), 9);
+    }
+
+    fn args(cmd: &Command) -> Vec<String> {
+        cmd.get_args()
+            .map(|arg| arg.to_string_lossy().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn missing_av1an_is_explained() {
+        let Err(e) = EncodeSession::start(Command::new("/nonexistent/av1an-verbosity")) else {
+            panic!("a missing binary started");
+        };
+        assert_eq!(
+            start_failure(&e),
+            "av1an-verbosity not found; set its path in Settings"
+        );
+
+        let denied = std::io::Error::from(std::io::ErrorKind::PermissionDenied);
+        assert!(start_failure(&denied).starts_with("Couldn't start av1an: "));
+    }
+
+    #[test]
+    fn max_bitrate_caps_the_crf() {
+        let mut state = AV1Studio {
+            max_bitrate: String::from("8000"),
+            buffer_size: String::from("2000"),
+            ..AV1Studio::default()
+        };
+        let params = generated_params(&state);
+        assert!(params.contains("--crf 27 "));
+        assert!(params.contains(" --mbr 8000 --buf-sz 2000"));
+
+        // The bitrate mode has its own target
+        state.rate_control = RateControl::TargetBitrate;
+        state.target_bitrate = String::from("4000");
+        assert!(!generated_params(&state).contains("--mbr"));
+    }
+
+    #[test]
+    fn target_bitrate_runs_two_passes() {
+        let command = |encoder| {
+            let state = AV1Studio {
+                encoder,
+                rate_control: RateControl::TargetBitrate,
+                target_bitrate: String::from("4000"),
+                preset: 6.0,
+                ..AV1Studio::default()
+            };
+            args(&generate_command(&state).unwrap())
+        };
+        let value = |args: &[String], flag: &str| {
+            let index = args.iter().position(|arg| arg == flag).unwrap();
+            args[index + 1].clone()
+        };
+
+        let svt = command(Encoder::SvtAv1);
+        assert!(value(&svt, "-v").contains("--rc 1 --tbr 4000 --preset 6"));
+        assert!(!value(&svt, "-v").contains("--crf"));
+        assert_eq!(value(&svt, "--passes"), "2");
+
+        let aom = command(Encoder::Aom);
+        assert_eq!(
+            value(&aom, "-v"),
+            "--end-usage=vbr --target-bitrate=4000 --cpu-used=6"
+        );
+        assert_eq!(value(&aom, "--passes"), "2");
+
+        let rav1e = command(Encoder::Rav1e);
+        assert_eq!(value(&rav1e, "-v"), "--bitrate 4000 --speed 6");
+        assert_eq!(value(&rav1e, "--passes"), "1");
+    }
+
+    #[test]
+    fn command_string_quotes_args_with_spaces() {
+        let mut cmd = Command::new("av1an");
+        cmd.args([
+            "-i",
+            "/videos/My Movie.mkv",
+            "-v",
+            "--crf 30 --preset 6",
+            "-o",
+            "out.mkv",
+        ]);
+        assert_eq!(
+            command_to_string(&cmd),
+            "av1an -i '/videos/My Movie.mkv' -v '--crf 30 --preset 6' -o out.mkv"
+        );
+
+        cmd.env("SVT_LOG", "1 2").env_remove("DISPLAY");
+        assert_eq!(
+            command_to_string(&cmd),
+            "env -u DISPLAY 'SVT_LOG=1 2' av1an -i '/videos/My Movie.mkv' -v '--crf 30 --preset 6' -o out.mkv"
+        );
+    }
+
+    #[test]
+    fn command_string_removes_before_it_sets() {
+        let mut cmd = Command::new("av1an");
+        cmd.env("PATH", "/opt/ffmpeg/bin")
+            .env_remove("SVT_LOG")
+            .env("AOM_LOG", "1");
+        assert_eq!(
+            command_to_string(&cmd),
+            "env -u SVT_LOG 'AOM_LOG=1' 'PATH=/opt/ffmpeg/bin' av1an"
+        );
+    }
+
+    #[test]
+    fn splits_quoted_paths_with_spaces() {
+        assert_eq!(
+            split_args(r#"--log-file "/home/me/My Encodes/av1an.log" --chunk-order random"#)
+                .unwrap(),
+            [
+                "--log-file",
+                "/home/me/My Encodes/av1an.log",
+                "--chunk-order",
+                "random"
+            ]
+        );
+        assert_eq!(
+            split_args(r#"--temp '/tmp/a b' --keep"#).unwrap(),
+            ["--temp", "/tmp/a b", "--keep"]
+        );
+        assert_eq!(
+            split_args(r"--temp /tmp/a\ b").unwrap(),
+            ["--temp", "/tmp/a b"]
         );
-        cmd.arg("--force").arg("-v").arg(params);
+        assert!(split_args(r#"--temp "/tmp/a b"#).is_err());
+        assert!(split_args("   ").unwrap().is_empty());
     }
 
-    cmd.arg("--set-thread-affinity")
-        .arg(&state.thread_affinity)
-        .arg("-w")
-        .arg(&state.workers);
+    #[test]
+    fn extra_args_come_last() {
+        let state = AV1Studio {
+            workers: String::from("4"),
+            extra_av1an_args: String::from(r#"--chunk-order random --log-file "my log.log""#),
+            ..AV1Studio::default()
+        };
+        let args = args(&generate_command(&state).unwrap());
 
-    cmd
+        assert_eq!(
+            args[args.len() - 4..],
+            ["--chunk-order", "random", "--log-file", "my log.log"]
+        );
+        assert_eq!(args[args.len() - 6..args.len() - 4], ["-w", "4"]);
+    }
+
+    #[test]
+    fn log_level_is_only_passed_when_set() {
+        let state = AV1Studio::default();
+        assert!(!args(&generate_command(&state).unwrap()).contains(&String::from("--log-level")));
+
+        let state = AV1Studio {
+            log_level: LogLevel::Debug,
+            ..AV1Studio::default()
+        };
+        let args = args(&generate_command(&state).unwrap());
+        let index = args.iter().position(|arg| arg == "--log-level").unwrap();
+        assert_eq!(args[index + 1], "debug");
+    }
+
+    #[test]
+    fn q_bounds_only_come_with_a_quality_target() {
+        let state = AV1Studio {
+            min_q: String::from("15"),
+            max_q: String::from("35"),
+            ..AV1Studio::default()
+        };
+        let without_target = args(&generate_command(&state).unwrap());
+        assert!(!without_target.contains(&String::from("--target-quality")));
+        assert!(!without_target.contains(&String::from("--min-q")));
+
+        let state = AV1Studio {
+            target_quality: String::from("95"),
+            ..state
+        };
+        let with_target = args(&generate_command(&state).unwrap()).join(" ");
+        assert!(with_target.contains("--target-quality 95 --min-q 15 --max-q 35"));
+
+        let state = AV1Studio {
+            min_q: String::new(),
+            ..state
+        };
+        let without_min = args(&generate_command(&state).unwrap()).join(" ");
+        assert!(without_min.contains("--target-quality 95 --max-q 35"));
+    }
+
+    #[test]
+    fn scene_lengths_are_only_passed_when_set() {
+        let default_args = args(&generate_command(&AV1Studio::default()).unwrap());
+        assert!(!default_args.contains(&String::from("--min-scene-len")));
+        assert!(!default_args.contains(&String::from("--extra-split")));
+
+        let state = AV1Studio {
+            min_scene_len: String::from(" 48 "),
+            max_scene_len: String::from("480"),
+            ..AV1Studio::default()
+        };
+        let set = args(&generate_command(&state).unwrap()).join(" ");
+        assert!(set.contains("--min-scene-len 48 --extra-split 480"));
+    }
+
+    #[test]
+    fn photon_noise_size_defaults_to_the_output() {
+        let state = AV1Studio {
+            grain_synthesis: GrainSynthesis::PhotonNoise,
+            photon_noise: String::from("800"),
+            ..AV1Studio::default()
+        };
+        let unknown = args(&generate_command(&state).unwrap());
+        assert!(!unknown.contains(&String::from("--photon-noise-width")));
+
+        let state = AV1Studio {
+            width: String::from("1280"),
+            height: String::from("720"),
+            ..state
+        };
+        let scaled = args(&generate_command(&state).unwrap()).join(" ");
+        assert!(
+            scaled.contains("--photon-noise 8 --photon-noise-width 1280 --photon-noise-height 720")
+        );
+
+        let state = AV1Studio {
+            photon_noise_width: String::from("3840"),
+            photon_noise_height: String::from("2160"),
+            ..state
+        };
+        let set = args(&generate_command(&state).unwrap()).join(" ");
+        assert!(set.contains("--photon-noise-width 3840 --photon-noise-height 2160"));
+    }
+
+    #[test]
+    fn chunk_order_and_force_are_explicit() {
+        let state = AV1Studio::default();
+        let default_args = args(&generate_command(&state).unwrap());
+        assert!(!default_args.contains(&String::from("--chunk-order")));
+        assert!(default_args.contains(&String::from("--force")));
+
+        // Custom parameters used to drop --force without saying so
+        let state = AV1Studio {
+            custom_encode_params: String::from("--preset 4 --crf 24"),
+            chunk_order: ChunkOrder::Random,
+            ..AV1Studio::default()
+        };
+        let custom_args = args(&generate_command(&state).unwrap());
+        assert!(custom_args.contains(&String::from("--force")));
+        let index = custom_args
+            .iter()
+            .position(|arg| arg == "--chunk-order")
+            .unwrap();
+        assert_eq!(custom_args[index + 1], "random");
+
+        let state = AV1Studio {
+            force: false,
+            ..AV1Studio::default()
+        };
+        assert!(!args(&generate_command(&state).unwrap()).contains(&String::from("--force")));
+    }
+
+    #[test]
+    fn auto_workers_leave_the_flag_out() {
+        let state = AV1Studio {
+            workers: String::from("12"),
+            ..AV1Studio::default()
+        };
+        let manual = args(&generate_command(&state).unwrap());
+        let index = manual.iter().position(|arg| arg == "-w").unwrap();
+        assert_eq!(manual[index + 1], "12");
+
+        for (workers, auto_workers) in [("12", true), ("", false), ("0", false)] {
+            let state = AV1Studio {
+                workers: workers.to_string(),
+                auto_workers,
+                ..AV1Studio::default()
+            };
+            assert!(!args(&generate_command(&state).unwrap()).contains(&String::from("-w")));
+        }
+    }
+
+    #[test]
+    fn environment_overrides_reach_the_command() {
+        let state = AV1Studio {
+            ffmpeg_path: String::from("/opt/ffmpeg-vmaf/bin/ffmpeg"),
+            environment: vec![
+                EnvOverride {
+                    key: String::from(" VAPOURSYNTH_LIB_DIR "),
+                    value: String::from("/opt/vs/lib"),
+                },
+                EnvOverride {
+                    key: String::from("LD_PRELOAD"),
+                    value: String::new(),
+                },
+            ],
+            ..AV1Studio::default()
+        };
+        let cmd = generate_command(&state).unwrap();
+        let envs: Vec<(String, Option<String>)> = cmd
+            .get_envs()
+            .map(|(key, value)| {
+                (
+                    key.to_string_lossy().to_string(),
+                    value.map(|value| value.to_string_lossy().to_string()),
+                )
+            })
+            .collect();
+
+        let path = envs.iter().find(|(key, _)| key == "PATH").unwrap();
+        let first = std::env::split_paths(path.1.as_ref().unwrap())
+            .next()
+            .unwrap();
+        assert_eq!(first, Path::new("/opt/ffmpeg-vmaf/bin"));
+        assert!(envs.contains(&(
+            String::from("VAPOURSYNTH_LIB_DIR"),
+            Some(String::from("/opt/vs/lib"))
+        )));
+        assert!(envs.contains(&(String::from("LD_PRELOAD"), None)));
+    }
+
+    #[test]
+    fn scale_filter_uses_the_chosen_algorithm() {
+        let filter = |scale_algorithm| {
+            let state = AV1Studio {
+                width: String::from("1280"),
+                height: String::from("720"),
+                scale_algorithm,
+                ..AV1Studio::default()
+            };
+            let args = args(&generate_command(&state).unwrap());
+            let index = args.iter().position(|arg| arg == "-f").unwrap();
+            args[index + 1].clone()
+        };
+
+        assert_eq!(
+            filter(ScaleAlgorithm::Bicubic),
+            "-vf scale=1280:720:flags=bicubic:param0=0:param1=1/2"
+        );
+        assert_eq!(
+            filter(ScaleAlgorithm::Lanczos),
+            "-vf scale=1280:720:flags=lanczos"
+        );
+        assert_eq!(
+            filter(ScaleAlgorithm::Spline),
+            "-vf scale=1280:720:flags=spline"
+        );
+        assert_eq!(
+            filter(ScaleAlgorithm::Bilinear),
+            "-vf scale=1280:720:flags=bilinear"
+        );
+        assert_eq!(
+            filter(ScaleAlgorithm::Neighbor),
+            "-vf scale=1280:720:flags=neighbor"
+        );
+
+        // No resolution, no filter
+        let state = AV1Studio {
+            width: String::new(),
+            height: String::new(),
+            scale_algorithm: ScaleAlgorithm::Lanczos,
+            ..AV1Studio::default()
+        };
+        assert!(!args(&generate_command(&state).unwrap()).contains(&String::from("-f")));
+
+        // Only a width, without a probed input ffmpeg keeps the aspect ratio
+        let state = AV1Studio {
+            width: String::from("1280"),
+            height: String::new(),
+            scale_algorithm: ScaleAlgorithm::Lanczos,
+            ..AV1Studio::default()
+        };
+        assert!(args(&generate_command(&state).unwrap())
+            .contains(&String::from("-vf scale=1280:-2:flags=lanczos")));
+    }
+
+    #[test]
+    fn deinterlacing_runs_before_scaling() {
+        let filter = |deinterlace, width: &str| {
+            let state = AV1Studio {
+                width: width.to_string(),
+                height: if width.is_empty() {
+                    String::new()
+                } else {
+                    String::from("480")
+                },
+                deinterlace,
+                ..AV1Studio::default()
+            };
+            let args = args(&generate_command(&state).unwrap());
+            let index = args.iter().position(|arg| arg == "-f").unwrap();
+            args[index + 1].clone()
+        };
+
+        assert_eq!(filter(Deinterlace::Bwdif, ""), "-vf bwdif=mode=send_frame");
+        assert_eq!(
+            filter(Deinterlace::Ivtc, "720"),
+            "-vf fieldmatch,yadif=deint=interlaced,decimate,scale=720:480:flags=bicubic:param0=0:param1=1/2"
+        );
+        assert_eq!(Deinterlace::Ivtc.output_frames(1000), 800);
+        assert_eq!(Deinterlace::Yadif.output_frames(1000), 1000);
+    }
+
+    #[test]
+    fn chunk_method_maps_to_av1an_names() {
+        let chunk_method = |source_library, chunk_method| {
+            let state = AV1Studio {
+                input_file: String::from("in.mkv"),
+                source_library,
+                chunk_method,
+                ..AV1Studio::default()
+            };
+            let args = args(&generate_command(&state).unwrap());
+            let index = args.iter().position(|arg| arg == "-m").unwrap();
+            args[index + 1].clone()
+        };
+
+        assert_eq!(
+            chunk_method(SourceLibrary::LSMASH, ChunkMethod::SourceLibrary),
+            "lsmash"
+        );
+        assert_eq!(
+            chunk_method(SourceLibrary::BestSource, ChunkMethod::SourceLibrary),
+            "bestsource"
+        );
+        assert_eq!(
+            chunk_method(SourceLibrary::FFMS2, ChunkMethod::Hybrid),
+            "hybrid"
+        );
+        assert_eq!(
+            chunk_method(SourceLibrary::FFMS2, ChunkMethod::Select),
+            "select"
+        );
+    }
+
+    #[test]
+    fn filters_chain_in_order() {
+        let state = AV1Studio {
+            width: String::from("1280"),
+            height: String::from("536"),
+            crop_top: 140,
+            crop_bottom: 140,
+            denoise: Denoise::Hqdn3d,
+            denoise_strength: 4.0,
+            deinterlace: Deinterlace::Bwdif,
+            ..AV1Studio::default()
+        };
+        let args = args(&generate_command(&state).unwrap());
+
+        assert_eq!(args.iter().filter(|arg| *arg == "-f").count(), 1);
+        let index = args.iter().position(|arg| arg == "-f").unwrap();
+        assert_eq!(
+            args[index + 1],
+            "-vf bwdif=mode=send_frame,crop=iw-0:ih-280:0:140,hqdn3d=4,scale=1280:536:flags=bicubic:param0=0:param1=1/2"
+        );
+
+        let state = AV1Studio {
+            width: String::new(),
+            height: String::new(),
+            crop_left: 8,
+            crop_right: 8,
+            denoise: Denoise::Nlmeans,
+            denoise_strength: 2.5,
+            ..AV1Studio::default()
+        };
+        assert_eq!(
+            build_vf_chain(&state).unwrap(),
+            "crop=iw-16:ih-0:8:0,nlmeans=s=2.5"
+        );
+    }
+
+    #[test]
+    fn default_settings_keep_the_input_resolution() {
+        assert_eq!(build_vf_chain(&AV1Studio::default()), None);
+    }
+
+    #[test]
+    fn vf_chain_is_none_without_filters() {
+        let state = AV1Studio {
+            width: String::new(),
+            height: String::new(),
+            // Only used together with a resolution
+            scale_algorithm: ScaleAlgorithm::Lanczos,
+            // Strength without a denoiser
+            denoise_strength: 4.0,
+            ..AV1Studio::default()
+        };
+
+        assert_eq!(build_vf_chain(&state), None);
+        assert!(!args(&generate_command(&state).unwrap()).contains(&String::from("-f")));
+    }
+
+    #[test]
+    fn vf_chain_combinations() {
+        let base = || AV1Studio {
+            width: String::new(),
+            height: String::new(),
+            ..AV1Studio::default()
+        };
+
+        let state = AV1Studio {
+            width: String::from("1920"),
+            height: String::from("1080"),
+            ..base()
+        };
+        assert_eq!(
+            build_vf_chain(&state).unwrap(),
+            "scale=1920:1080:flags=bicubic:param0=0:param1=1/2"
+        );
+
+        let state = AV1Studio {
+            deinterlace: Deinterlace::Yadif,
+            crop_top: 2,
+            ..base()
+        };
+        assert_eq!(
+            build_vf_chain(&state).unwrap(),
+            "yadif=mode=send_frame,crop=iw-0:ih-2:0:2"
+        );
+
+        let state = AV1Studio {
+            denoise: Denoise::Hqdn3d,
+            denoise_strength: 1.5,
+            width: String::from("960"),
+            height: String::from("540"),
+            scale_algorithm: ScaleAlgorithm::Spline,
+            ..base()
+        };
+        assert_eq!(
+            build_vf_chain(&state).unwrap(),
+            "hqdn3d=1.5,scale=960:540:flags=spline"
+        );
+    }
+
+    fn video_params(state: &AV1Studio) -> String {
+        let cmd = generate_command(state).unwrap();
+        let args: Vec<String> = cmd
+            .get_args()
+            .map(|arg| arg.to_string_lossy().to_string())
+            .collect();
+        let index = args.iter().position(|arg| arg == "-v").unwrap();
+
+        args[index + 1].clone()
+    }
+
+    #[test]
+    fn signaling_flags_are_only_passed_when_set() {
+        let params = video_params(&AV1Studio::default());
+        assert!(!params.contains("--chroma-sample-position"));
+        assert!(!params.contains("--enable-hdr"));
+
+        let state = AV1Studio {
+            chroma_sample_position: ChromaSamplePosition::Colocated,
+            enable_hdr: true,
+            ..AV1Studio::default()
+        };
+        let params = video_params(&state);
+        assert!(params.contains(" --chroma-sample-position colocated"));
+        assert!(params.contains(" --enable-hdr 1"));
+    }
+
+    #[test]
+    fn custom_params_keep_separate_flags() {
+        let state = AV1Studio {
+            custom_encode_params: String::from("--preset 4   --crf 24"),
+            custom_params_mode: CustomParamsMode::Replace,
+            ..AV1Studio::default()
+        };
+        let params = video_params(&state);
+
+        assert_eq!(params, "--preset 4 --crf 24");
+        assert_eq!(
+            split_args(&params).unwrap(),
+            ["--preset", "4", "--crf", "24"]
+        );
+    }
+
+    #[test]
+    fn custom_params_keep_quoted_values() {
+        let state = AV1Studio {
+            custom_encode_params: String::from(r#"--foo "a b" --bar 1"#),
+            custom_params_mode: CustomParamsMode::Replace,
+            ..AV1Studio::default()
+        };
+
+        assert_eq!(
+            split_args(&video_params(&state)).unwrap(),
+            ["--foo", "a b", "--bar", "1"]
+        );
+    }
+
+    #[test]
+    fn appended_params_win_over_generated_ones() {
+        assert_eq!(
+            merge_params(
+                "--crf 30 --preset 6 --tune 2",
+                "--crf 24 --enable-overlays 1"
+            ),
+            "--preset 6 --tune 2 --crf 24 --enable-overlays 1"
+        );
+        // Within the custom parameters the last one wins too
+        assert_eq!(merge_params("--crf 30", "--crf 20 --crf 22"), "--crf 22");
+        assert_eq!(
+            merge_params("--end-usage=q --cq-level=30", "--cq-level=20"),
+            "--end-usage=q --cq-level=20"
+        );
+        assert_eq!(
+            merge_params("--tile-columns 2", "--tile-columns -1"),
+            "--tile-columns -1"
+        );
+    }
+
+    #[test]
+    fn appended_flags_without_values_stay_alone() {
+        assert_eq!(
+            merge_params("--crf 30 --keyint 1", "--no-scd --crf 25 --verbose"),
+            "--keyint 1 --no-scd --crf 25 --verbose"
+        );
+    }
+
+    #[test]
+    fn appended_params_keep_quoted_values() {
+        let merged = merge_params(
+            r#"--fgs-table "/tables/my grain.tbl" --crf 30"#,
+            r#"--fgs-table "/tables/other grain.tbl""#,
+        );
+
+        assert_eq!(
+            split_args(&merged).unwrap(),
+            ["--crf", "30", "--fgs-table", "/tables/other grain.tbl"]
+        );
+    }
+
+    #[test]
+    fn custom_params_append_by_default() {
+        let state = AV1Studio {
+            crf: 30.0,
+            custom_encode_params: String::from("--crf 24 --enable-overlays 1"),
+            ..AV1Studio::default()
+        };
+        let params = video_params(&state);
+
+        assert!(params.contains("--preset"));
+        assert!(!params.contains("--crf 30"));
+        assert!(params.ends_with("--crf 24 --enable-overlays 1"));
+    }
+
+    #[test]
+    fn conflicting_params_find_both_flag_forms() {
+        assert_eq!(
+            conflicting_params("--crf=30 --preset 4 --lp 2"),
+            vec!["--crf", "--preset"]
+        );
+        assert_eq!(
+            conflicting_params("--rc 1 --tbr=3000 --mbr 6000 --rc=2"),
+            vec!["--rc", "--tbr", "--mbr"]
+        );
+        assert!(conflicting_params("--lp 2 --keyint=240").is_empty());
+        assert!(conflicting_params("--crf \"30").is_empty());
+    }
+
+    #[test]
+    fn custom_params_reject_unclosed_quote() {
+        assert!(split_args(r#"--foo "a b"#).is_err());
+
+        // Neither mode encodes without what the user typed
+        for mode in [CustomParamsMode::Append, CustomParamsMode::Replace] {
+            let state = AV1Studio {
+                custom_encode_params: String::from(r#"--crf 24 --foo "a b"#),
+                custom_params_mode: mode,
+                ..AV1Studio::default()
+            };
+            assert!(generate_command(&state)
+                .is_err_and(|e| e.starts_with("Couldn't parse the custom encoder parameters")));
+        }
+    }
 }
@@ -0,0 +1,192 @@
+use std::io::Read;
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::thread::JoinHandle;
+
+use crate::config::config_dir;
+
+// libaom's example tool for writing photon noise grain tables
+pub const GRAIN_TOOL: &str = "photon_noise_table";
+
+pub enum GrainStage {
+    Idle,
+    Running(Child, JoinHandle<String>), // With the thread collecting its stderr
+    Done,
+    Failed(String),
+}
+
+// Writes a photon noise film grain table for --fgs-table. Photon noise tables are made for
+// one resolution, so the one the table was generated for is kept around.
+pub struct GrainTableGenerator {
+    pub iso: u32,
+    pub width: String, // Overrides, empty uses the output resolution
+    pub height: String,
+    pub stage: GrainStage,
+    pub output: PathBuf,
+    pub resolution: Option<(u32, u32)>,
+}
+
+impl Default for GrainTableGenerator {
+    fn default() -> Self {
+        GrainTableGenerator {
+            iso: 400,
+            width: String::new(),
+            height: String::new(),
+            stage: GrainStage::Idle,
+            output: PathBuf::new(),
+            resolution: None,
+        }
+    }
+}
+
+impl GrainTableGenerator {
+    pub fn is_running(&self) -> bool {
+        matches!(self.stage, GrainStage::Running(..))
+    }
+
+    // The override if both fields hold a size, otherwise `output_resolution`
+    pub fn target_resolution(&self, output_resolution: Option<(u32, u32)>) -> Option<(u32, u32)> {
+        match (self.width.trim().parse(), self.height.trim().parse()) {
+            (Ok(width), Ok(height)) if width > 0 && height > 0 => Some((width, height)),
+            _ => output_resolution,
+        }
+    }
+
+    pub fn start(&mut self, resolution: (u32, u32)) {
+        self.cancel();
+
+        let dir = config_dir()
+            .unwrap_or_else(std::env::temp_dir)
+            .join("grain_tables");
+        if let Err(e) = std::fs::create_dir_all(&dir) {
+            self.stage = GrainStage::Failed(format!("Couldn't create {:?}: {}", dir, e));
+            return;
+        }
+        self.output = dir.join(format!(
+            "photon_iso{}_{}x{}.tbl",
+            self.iso, resolution.0, resolution.1
+        ));
+        self.resolution = Some(resolution);
+
+        let mut cmd = Command::new(GRAIN_TOOL);
+        cmd.arg(format!("--width={}", resolution.0))
+            .arg(format!("--height={}", resolution.1))
+            .arg(format!("--iso={}", self.iso))
+            .arg(format!("--output={}", self.output.display()));
+        self.spawn(cmd);
+    }
+
+    fn spawn(&mut self, mut cmd: Command) {
+        let child = cmd
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn();
+
+        self.stage = match child {
+            Ok(mut child) => {
+                // Read as it comes, a full pipe would block the tool
+                let stderr = child.stderr.take();
+                let reader = std::thread::spawn(move || {
+                    let mut text = String::new();
+                    if let Some(mut pipe) = stderr {
+                        let _ = pipe.read_to_string(&mut text);
+                    }
+                    text
+                });
+                GrainStage::Running(child, reader)
+            }
+            Err(e) => GrainStage::Failed(format!("Couldn't start {}: {}", GRAIN_TOOL, e)),
+        };
+    }
+
+    // True once, right when the table got written
+    pub fn poll(&mut self) -> bool {
+        let stage = std::mem::replace(&mut self.stage, GrainStage::Idle);
+
+        self.stage = match stage {
+            GrainStage::Running(mut child, reader) => match child.try_wait() {
+                Ok(Some(status)) if status.success() && self.output.is_file() => {
+                    self.stage = GrainStage::Done;
+                    return true;
+                }
+                Ok(Some(status)) => {
+                    let stderr = reader.join().unwrap_or_default();
+                    let reason = stderr.trim();
+                    GrainStage::Failed(if reason.is_empty() {
+                        format!("{} failed ({})", GRAIN_TOOL, status)
+                    } else {
+                        format!("{} failed ({}): {}", GRAIN_TOOL, status, reason)
+                    })
+                }
+                Ok(None) => GrainStage::Running(child, reader),
+                Err(e) => GrainStage::Failed(e.to_string()),
+            },
+            stage => stage,
+        };
+
+        false
+    }
+
+    pub fn cancel(&mut self) {
+        if let GrainStage::Running(child, _) = &mut self.stage {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+        self.stage = GrainStage::Idle;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overrides_need_both_sides() {
+        let mut generator = GrainTableGenerator::default();
+        assert_eq!(
+            generator.target_resolution(Some((1920, 1080))),
+            Some((1920, 1080))
+        );
+
+        generator.width = String::from("3840");
+        assert_eq!(
+            generator.target_resolution(Some((1920, 1080))),
+            Some((1920, 1080))
+        );
+        generator.height = String::from(" 2160 ");
+        assert_eq!(generator.target_resolution(None), Some((3840, 2160)));
+
+        generator.height = String::from("0");
+        assert_eq!(generator.target_resolution(None), None);
+    }
+
+    // More than a pipe buffer of errors, then a failure
+    #[cfg(unix)]
+    #[test]
+    fn long_errors_dont_block_the_tool() {
+        let mut generator = GrainTableGenerator {
+            output: std::env::temp_dir().join("av1studio-grain-test-missing.tbl"),
+            ..GrainTableGenerator::default()
+        };
+        let mut cmd = Command::new("sh");
+        cmd.args([
+            "-c",
+            "i=0; while [ $i -lt 4000 ]; do echo 'invalid iso value, try again' >&2; i=$((i+1)); done; exit 3",
+        ]);
+        generator.spawn(cmd);
+
+        let started = std::time::Instant::now();
+        while generator.is_running() {
+            assert!(started.elapsed().as_secs() < 20, "the tool never finished");
+            assert!(!generator.poll());
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        match &generator.stage {
+            GrainStage::Failed(reason) => {
+                assert!(reason.contains("failed (exit status: 3): invalid iso value"));
+            }
+            _ => panic!("expected a failure"),
+        }
+    }
+}
@@ -0,0 +1,143 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+// Edits closer together than this are one step, so typing "1280" isn't four undos
+pub const SETTLE: Duration = Duration::from_millis(800);
+
+const MAX_STEPS: usize = 100;
+
+// Undo and redo for the settings, kept as the session JSON the autosave writes
+#[derive(Default)]
+pub struct SettingsHistory {
+    undo: VecDeque<String>,
+    redo: Vec<String>,
+    current: Option<String>,            // The last settled settings
+    pending: Option<(String, Instant)>, // Changed settings, and since when they're unchanged
+}
+
+impl SettingsHistory {
+    // Called with the settings every frame. Returns whether a change is still settling.
+    pub fn track(&mut self, json: &str, now: Instant) -> bool {
+        let Some(current) = &self.current else {
+            self.current = Some(json.to_string());
+            return false;
+        };
+        if current == json {
+            self.pending = None;
+            return false;
+        }
+
+        match &self.pending {
+            Some((pending, since)) if pending == json => {
+                if now.duration_since(*since) < SETTLE {
+                    return true;
+                }
+                self.commit(json.to_string());
+                false
+            }
+            _ => {
+                self.pending = Some((json.to_string(), now));
+                true
+            }
+        }
+    }
+
+    fn commit(&mut self, json: String) {
+        if let Some(previous) = self.current.replace(json) {
+            if self.undo.len() == MAX_STEPS {
+                self.undo.pop_front();
+            }
+            self.undo.push_back(previous);
+        }
+        self.redo.clear();
+        self.pending = None;
+    }
+
+    pub fn can_undo(&self, json: &str) -> bool {
+        !self.undo.is_empty()
+            || self
+                .current
+                .as_deref()
+                .is_some_and(|current| current != json)
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    // The settings to go back to. A change that's still settling counts as a step of its own.
+    pub fn undo(&mut self, json: &str) -> Option<String> {
+        if self
+            .current
+            .as_deref()
+            .is_some_and(|current| current != json)
+        {
+            self.commit(json.to_string());
+        }
+        let previous = self.undo.pop_back()?;
+        if let Some(current) = self.current.replace(previous.clone()) {
+            self.redo.push(current);
+        }
+
+        Some(previous)
+    }
+
+    pub fn redo(&mut self) -> Option<String> {
+        let next = self.redo.pop()?;
+        if let Some(current) = self.current.replace(next.clone()) {
+            self.undo.push_back(current);
+        }
+        self.pending = None;
+
+        Some(next)
+    }
+
+    // What the settings look like after applying an undo or redo, which may serialize a little
+    // differently than the snapshot did
+    pub fn applied(&mut self, json: &str) {
+        self.current = Some(json.to_string());
+        self.pending = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quick_edits_are_one_step() {
+        let start = Instant::now();
+        let mut history = SettingsHistory::default();
+        history.track("a", start);
+        // Typing, each keystroke right after the last
+        history.track("ab", start + Duration::from_millis(100));
+        history.track("abc", start + Duration::from_millis(200));
+        history.track("abc", start + Duration::from_millis(200) + SETTLE);
+        history.track("abcd", start + Duration::from_secs(5));
+        history.track("abcd", start + Duration::from_secs(5) + SETTLE);
+
+        assert_eq!(history.undo("abcd").as_deref(), Some("abc"));
+        assert_eq!(history.undo("abc").as_deref(), Some("a"));
+        assert_eq!(history.undo("a"), None);
+        assert_eq!(history.redo().as_deref(), Some("abc"));
+        assert_eq!(history.redo().as_deref(), Some("abcd"));
+        assert_eq!(history.redo(), None);
+    }
+
+    #[test]
+    fn unsettled_change_is_undone_and_new_edits_drop_redo() {
+        let start = Instant::now();
+        let mut history = SettingsHistory::default();
+        history.track("a", start);
+        history.track("b", start);
+
+        assert!(history.can_undo("b"));
+        assert_eq!(history.undo("b").as_deref(), Some("a"));
+        assert!(history.can_redo());
+
+        history.track("c", start + Duration::from_secs(1));
+        history.track("c", start + Duration::from_secs(1) + SETTLE);
+        assert!(!history.can_redo());
+        assert_eq!(history.undo("c").as_deref(), Some("a"));
+    }
+}
@@ -0,0 +1,302 @@
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc;
+
+use crate::probe::{probe_input, FfmpegPaths, MediaInfo};
+
+pub enum JoinStage {
+    Idle,
+    Joining(Child),
+    Done,
+    Failed(String),
+}
+
+// Several files of one source (e.g. Blu-ray segments) stream-copied into a single file with
+// ffmpeg's concat demuxer, since av1an takes one input
+pub struct InputJoin {
+    pub files: Vec<String>,
+    pub infos: Vec<Option<Result<MediaInfo, String>>>, // Same order as `files`, None while probing
+    pub stage: JoinStage,
+    pub output: PathBuf,
+
+    temp_dir: PathBuf,
+    ids: Vec<u64>, // Same order as `files`, so a file added twice gets both probe results
+    next_id: u64,
+    probes: Vec<(u64, mpsc::Receiver<Result<MediaInfo, String>>)>,
+}
+
+impl Default for InputJoin {
+    fn default() -> Self {
+        InputJoin {
+            files: Vec::new(),
+            infos: Vec::new(),
+            stage: JoinStage::Idle,
+            output: PathBuf::new(),
+            temp_dir: PathBuf::new(),
+            ids: Vec::new(),
+            next_id: 0,
+            probes: Vec::new(),
+        }
+    }
+}
+
+impl InputJoin {
+    pub fn is_running(&self) -> bool {
+        matches!(self.stage, JoinStage::Joining(_))
+    }
+
+    pub fn add(&mut self, tools: &FfmpegPaths, file: String) {
+        let (sender, receiver) = mpsc::channel();
+        let path = file.clone();
+        let tools = tools.clone();
+        std::thread::spawn(move || {
+            let _ = sender.send(probe_input(&tools, &path).map_err(|e| e.to_string()));
+        });
+        self.next_id += 1;
+        self.probes.push((self.next_id, receiver));
+        self.ids.push(self.next_id);
+        self.files.push(file);
+        self.infos.push(None);
+    }
+
+    pub fn remove(&mut self, index: usize) {
+        self.files.remove(index);
+        self.infos.remove(index);
+        self.ids.remove(index);
+    }
+
+    pub fn swap(&mut self, a: usize, b: usize) {
+        self.files.swap(a, b);
+        self.infos.swap(a, b);
+        self.ids.swap(a, b);
+    }
+
+    // Why the files can't be joined, going by their first video track. None once they all
+    // match the first file.
+    pub fn mismatch(&self) -> Option<String> {
+        if self.files.len() < 2 {
+            return Some(String::from("Add at least two files"));
+        }
+        if self.infos.iter().any(Option::is_none) {
+            return Some(String::from("Still checking the files"));
+        }
+
+        let infos: Vec<&MediaInfo> = self.infos.iter().flatten().flatten().collect();
+        if infos.len() != self.files.len() {
+            return Some(String::from("Some files couldn't be read"));
+        }
+        let first = infos[0];
+        let fps_differs = |info: &MediaInfo| match (first.fps, info.fps) {
+            (Some(a), Some(b)) => (a - b).abs() > 0.001,
+            _ => false,
+        };
+        let mismatched: Vec<String> = self
+            .files
+            .iter()
+            .zip(&infos)
+            .skip(1)
+            .filter(|(_, info)| {
+                (info.width, info.height) != (first.width, first.height) || fps_differs(info)
+            })
+            .map(|(file, _)| file_name(file))
+            .collect();
+
+        (!mismatched.is_empty()).then(|| {
+            format!(
+                "{} {} a different resolution or frame rate than {}",
+                mismatched.join(", "),
+                if mismatched.len() == 1 { "has" } else { "have" },
+                file_name(&self.files[0])
+            )
+        })
+    }
+
+    // The joined file is as big as the source, so it goes into `temp_folder`, the one av1an
+    // uses, rather than the system temp folder, which is often in RAM
+    pub fn start(&mut self, tools: &FfmpegPaths, temp_folder: &Path) {
+        self.remove_output();
+        self.temp_dir = temp_folder.join(format!("av1studio-join-{}", std::process::id()));
+        if let Err(e) = std::fs::create_dir_all(&self.temp_dir) {
+            self.stage = JoinStage::Failed(format!("Couldn't create {:?}: {}", self.temp_dir, e));
+            return;
+        }
+
+        // The concat demuxer reads a list of `file '...'` lines, with quotes escaped as '\''
+        let list: String = self
+            .files
+            .iter()
+            .map(|file| format!("file '{}'\n", file.replace('\'', r"'\''")))
+            .collect();
+        let list_path = self.temp_dir.join("inputs.txt");
+        if let Err(e) = std::fs::write(&list_path, list) {
+            self.stage = JoinStage::Failed(format!("Couldn't write {:?}: {}", list_path, e));
+            return;
+        }
+
+        let stem = Path::new(&self.files[0])
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_else(|| String::from("input"));
+        self.output = self.temp_dir.join(format!("{}_joined.mkv", stem));
+
+        let child = Command::new(&tools.ffmpeg)
+            .args(["-v", "error", "-y", "-f", "concat", "-safe", "0", "-i"])
+            .arg(&list_path)
+            .args(["-map", "0", "-c", "copy"])
+            .arg(&self.output)
+            .stdin(Stdio::null())
+            .spawn();
+        self.stage = match child {
+            Ok(child) => JoinStage::Joining(child),
+            Err(e) => JoinStage::Failed(format!("Couldn't start ffmpeg: {}", e)),
+        };
+    }
+
+    // True once, right when the joined file is ready
+    pub fn poll(&mut self) -> bool {
+        // Probes for files that were removed in the meantime are dropped
+        self.probes
+            .retain(|(id, receiver)| match receiver.try_recv() {
+                Ok(result) => {
+                    if let Some(index) = self.ids.iter().position(|i| i == id) {
+                        self.infos[index] = Some(result);
+                    }
+                    false
+                }
+                Err(mpsc::TryRecvError::Empty) => true,
+                Err(mpsc::TryRecvError::Disconnected) => false,
+            });
+
+        let stage = std::mem::replace(&mut self.stage, JoinStage::Idle);
+        self.stage = match stage {
+            JoinStage::Joining(mut child) => match child.try_wait() {
+                Ok(Some(status)) if status.success() => {
+                    self.stage = JoinStage::Done;
+                    return true;
+                }
+                Ok(Some(status)) => {
+                    JoinStage::Failed(format!("ffmpeg couldn't join the files ({})", status))
+                }
+                Ok(None) => JoinStage::Joining(child),
+                Err(e) => JoinStage::Failed(e.to_string()),
+            },
+            stage => stage,
+        };
+
+        false
+    }
+
+    pub fn cancel(&mut self) {
+        if let JoinStage::Joining(child) = &mut self.stage {
+            let _ = child.kill();
+            let _ = child.wait();
+            let _ = std::fs::remove_dir_all(&self.temp_dir);
+        }
+        self.stage = JoinStage::Idle;
+    }
+
+    // Stops a running join and deletes its files. Returns the joined file if there was one.
+    pub fn remove_output(&mut self) -> Option<PathBuf> {
+        self.cancel();
+        if self.temp_dir.as_os_str().is_empty() || !self.temp_dir.exists() {
+            return None;
+        }
+        let _ = std::fs::remove_dir_all(&self.temp_dir);
+
+        Some(std::mem::take(&mut self.output)).filter(|output| !output.as_os_str().is_empty())
+    }
+}
+
+fn file_name(path: &str) -> String {
+    Path::new(path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(width: u32, height: u32, fps: f64) -> Option<Result<MediaInfo, String>> {
+        Some(Ok(MediaInfo {
+            width: Some(width),
+            height: Some(height),
+            fps: Some(fps),
+            ..MediaInfo::default()
+        }))
+    }
+
+    fn join(infos: Vec<Option<Result<MediaInfo, String>>>) -> InputJoin {
+        InputJoin {
+            files: (1..=infos.len())
+                .map(|n| format!("/discs/0000{}.m2ts", n))
+                .collect(),
+            infos,
+            ..InputJoin::default()
+        }
+    }
+
+    #[test]
+    fn matching_files_can_be_joined() {
+        let files = join(vec![info(1920, 1080, 23.976), info(1920, 1080, 23.976)]);
+        assert_eq!(files.mismatch(), None);
+
+        assert_eq!(
+            join(vec![info(1920, 1080, 23.976)]).mismatch().unwrap(),
+            "Add at least two files"
+        );
+        assert_eq!(
+            join(vec![info(1920, 1080, 23.976), None])
+                .mismatch()
+                .unwrap(),
+            "Still checking the files"
+        );
+        assert_eq!(
+            join(vec![
+                info(1920, 1080, 23.976),
+                Some(Err(String::from("no video")))
+            ])
+            .mismatch()
+            .unwrap(),
+            "Some files couldn't be read"
+        );
+    }
+
+    #[test]
+    fn mismatches_name_the_odd_files() {
+        let files = join(vec![
+            info(1920, 1080, 23.976),
+            info(1280, 720, 23.976),
+            info(1920, 1080, 23.976),
+            info(1920, 1080, 29.97),
+        ]);
+        assert_eq!(
+            files.mismatch().unwrap(),
+            "00002.m2ts, 00004.m2ts have a different resolution or frame rate than 00001.m2ts"
+        );
+
+        let files = join(vec![info(1920, 1080, 23.976), info(1920, 1080, 23.9761)]);
+        assert_eq!(files.mismatch(), None);
+    }
+
+    #[test]
+    fn a_file_added_twice_gets_both_probe_results() {
+        let mut files = InputJoin::default();
+        let missing = std::env::temp_dir().join("av1studio-join-test-missing.mkv");
+        let missing = missing.display().to_string();
+        files.add(&FfmpegPaths::default(), missing.clone());
+        files.add(&FfmpegPaths::default(), missing);
+
+        let started = std::time::Instant::now();
+        while files.infos.iter().any(Option::is_none) {
+            assert!(
+                started.elapsed().as_secs() < 10,
+                "the probes never came back"
+            );
+            files.poll();
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        assert_ne!(files.mismatch().unwrap(), "Still checking the files");
+    }
+}
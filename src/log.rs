@@ -0,0 +1,26 @@
+use std::collections::VecDeque;
+
+// Oldest lines get dropped past this, av1an is chatty with --verbose-frame-info
+const LOG_LIMIT: usize = 5000;
+
+#[derive(Default)]
+pub struct EncodeLog {
+    lines: VecDeque<String>,
+}
+
+impl EncodeLog {
+    pub fn push(&mut self, line: String) {
+        if self.lines.len() >= LOG_LIMIT {
+            self.lines.pop_front();
+        }
+        self.lines.push_back(line);
+    }
+
+    pub fn lines(&self) -> impl Iterator<Item = &String> {
+        self.lines.iter()
+    }
+
+    pub fn clear(&mut self) {
+        self.lines.clear();
+    }
+}
@@ -1,7 +1,28 @@
+mod analyze;
 mod app;
+mod compare;
+mod config;
 mod depcheck;
 mod encoding;
+mod grain;
+mod history;
+mod join;
+mod log;
 mod models;
+mod notify;
+mod paths;
+mod probe;
+mod queue;
+mod recent;
+mod report;
+mod sample;
+mod session;
+mod stats;
+#[cfg(feature = "status-server")]
+mod status;
+mod sysload;
+mod tempusage;
+mod zones;
 
 fn main() -> Result<(), eframe::Error> {
     let native_options = eframe::NativeOptions::default();
@@ -1,6 +1,8 @@
+use egui::Visuals;
 use serde::{Deserialize, Serialize};
 
 #[derive(PartialEq, Eq, Clone, Copy, Default, Serialize, Deserialize)]
+#[allow(clippy::upper_case_acronyms)]
 pub enum SourceLibrary {
     #[default]
     BestSource,
@@ -16,52 +18,137 @@ impl SourceLibrary {
             SourceLibrary::LSMASH => "L-SMASH",
         }
     }
+
+    // What av1an's -m takes for it
+    pub fn to_av1an_arg(self) -> &'static str {
+        match self {
+            SourceLibrary::BestSource => "bestsource",
+            SourceLibrary::FFMS2 => "ffms2",
+            SourceLibrary::LSMASH => "lsmash",
+        }
+    }
 }
 
-#[derive(PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
-pub enum PixelFormat {
-    Yuv420p,
-    Yuv420p10le,
+// How av1an cuts the chunks out of the input. The source libraries go through VapourSynth,
+// hybrid and select cut with ffmpeg and don't use a source library at all.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub enum ChunkMethod {
+    #[default]
+    SourceLibrary,
+    Hybrid,
+    Select,
 }
 
-impl Default for PixelFormat {
-    fn default() -> Self {
-        PixelFormat::Yuv420p10le
+impl ChunkMethod {
+    pub fn as_str(&self) -> &str {
+        match self {
+            ChunkMethod::SourceLibrary => "Source Library",
+            ChunkMethod::Hybrid => "Hybrid",
+            ChunkMethod::Select => "Select",
+        }
+    }
+
+    pub fn arg(&self, source_library: SourceLibrary) -> &'static str {
+        match self {
+            ChunkMethod::SourceLibrary => source_library.to_av1an_arg(),
+            ChunkMethod::Hybrid => "hybrid",
+            ChunkMethod::Select => "select",
+        }
     }
 }
 
+#[derive(PartialEq, Eq, Clone, Copy, Default, Serialize, Deserialize)]
+pub enum PixelFormat {
+    Yuv420p,
+    #[default]
+    Yuv420p10le, // default
+    Yuv420p12le,
+    Yuv422p,
+    Yuv422p10le,
+    Yuv444p,
+    Yuv444p10le,
+}
+
 impl PixelFormat {
     pub fn as_str(&self) -> &str {
         match self {
             PixelFormat::Yuv420p => "yuv420p",
             PixelFormat::Yuv420p10le => "yuv420p10le",
+            PixelFormat::Yuv420p12le => "yuv420p12le",
+            PixelFormat::Yuv422p => "yuv422p",
+            PixelFormat::Yuv422p10le => "yuv422p10le",
+            PixelFormat::Yuv444p => "yuv444p",
+            PixelFormat::Yuv444p10le => "yuv444p10le",
+        }
+    }
+
+    // 420, 422 or 444
+    pub fn chroma_subsampling(&self) -> u32 {
+        match self {
+            PixelFormat::Yuv420p | PixelFormat::Yuv420p10le | PixelFormat::Yuv420p12le => 420,
+            PixelFormat::Yuv422p | PixelFormat::Yuv422p10le => 422,
+            PixelFormat::Yuv444p | PixelFormat::Yuv444p10le => 444,
+        }
+    }
+
+    pub fn bit_depth(&self) -> u32 {
+        match self {
+            PixelFormat::Yuv420p | PixelFormat::Yuv422p | PixelFormat::Yuv444p => 8,
+            PixelFormat::Yuv420p10le | PixelFormat::Yuv422p10le | PixelFormat::Yuv444p10le => 10,
+            PixelFormat::Yuv420p12le => 12,
         }
     }
+
+    // The closest option to an ffprobe pix_fmt like "yuv444p12le" or "yuvj420p". 8-bit sources
+    // still get 10-bit, which compresses better, and 12-bit only exists for 4:2:0 here.
+    pub fn from_ffprobe(pix_fmt: &str) -> Option<Self> {
+        let re = regex::Regex::new(r"^(?:yuva?j?(420|422|444)p|gbra?p)(\d+)?").unwrap();
+        let caps = re.captures(pix_fmt)?;
+        let subsampling = caps.get(1).map_or("444", |m| m.as_str()); // Planar RGB is 4:4:4
+        let depth: u32 = caps.get(2).map_or(8, |m| m.as_str().parse().unwrap_or(8));
+
+        Some(match (subsampling, depth) {
+            ("420", depth) if depth > 10 => PixelFormat::Yuv420p12le,
+            ("420", _) => PixelFormat::Yuv420p10le,
+            ("422", _) => PixelFormat::Yuv422p10le,
+            _ => PixelFormat::Yuv444p10le,
+        })
+    }
 }
 
-#[derive(PartialEq, Eq, Clone, Copy, Debug, Serialize, Deserialize)]
+#[derive(PartialEq, Eq, Clone, Copy, Default, Debug, Serialize, Deserialize)]
 pub enum ColorPrimaries {
-    Bt709,       // [1] BT.709
+    Bt709, // [1] BT.709
+    #[default]
     Unspecified, // [2] unspecified, default
-    Bt470m,      // [4] BT.470 System M (historical)
-    Bt470bg,     // [5] BT.470 System B, G (historical)
-    Bt601,       // [6] BT.601
-    Smpte240,    // [7] SMPTE 240
-    Film,        // [8] Generic film (color filters using illuminant C)
-    Bt2020,      // [9] SMPTE 428 (CIE 1921 XYZ)
-    Xyz,         // [10] SMPTE RP 431-2
-    Smpte431,    // [11] SMPTE EG 431-2
-    Smpte432,    // [12] SMPTE EG 432-1
-    Ebu3213,     // [22] EBU Tech. 3213-E
-}
-
-impl Default for ColorPrimaries {
-    fn default() -> Self {
-        ColorPrimaries::Unspecified
-    }
+    Bt470m, // [4] BT.470 System M (historical)
+    Bt470bg, // [5] BT.470 System B, G (historical)
+    Bt601, // [6] BT.601
+    Smpte240, // [7] SMPTE 240
+    Film,  // [8] Generic film (color filters using illuminant C)
+    Bt2020, // [9] SMPTE 428 (CIE 1921 XYZ)
+    Xyz,   // [10] SMPTE RP 431-2
+    Smpte431, // [11] SMPTE EG 431-2
+    Smpte432, // [12] SMPTE EG 432-1
+    Ebu3213, // [22] EBU Tech. 3213-E
 }
 
 impl ColorPrimaries {
+    pub const ALL: [ColorPrimaries; 12] = [
+        ColorPrimaries::Bt709,
+        ColorPrimaries::Unspecified,
+        ColorPrimaries::Bt470m,
+        ColorPrimaries::Bt470bg,
+        ColorPrimaries::Bt601,
+        ColorPrimaries::Smpte240,
+        ColorPrimaries::Film,
+        ColorPrimaries::Bt2020,
+        ColorPrimaries::Xyz,
+        ColorPrimaries::Smpte431,
+        ColorPrimaries::Smpte432,
+        ColorPrimaries::Ebu3213,
+    ];
+
     pub fn as_str(&self) -> &str {
         match self {
             ColorPrimaries::Bt709 => "1",
@@ -78,33 +165,81 @@ impl ColorPrimaries {
             ColorPrimaries::Ebu3213 => "22",
         }
     }
+
+    // ffprobe's color_primaries
+    pub fn from_ffprobe(primaries: &str) -> Option<Self> {
+        match primaries {
+            "bt709" => Some(ColorPrimaries::Bt709),
+            "bt470m" => Some(ColorPrimaries::Bt470m),
+            "bt470bg" => Some(ColorPrimaries::Bt470bg),
+            "smpte170m" => Some(ColorPrimaries::Bt601),
+            "smpte240m" => Some(ColorPrimaries::Smpte240),
+            "film" => Some(ColorPrimaries::Film),
+            "bt2020" => Some(ColorPrimaries::Bt2020),
+            "smpte428" => Some(ColorPrimaries::Xyz),
+            "smpte431" => Some(ColorPrimaries::Smpte431),
+            "smpte432" => Some(ColorPrimaries::Smpte432),
+            "jedec-p22" | "ebu3213" => Some(ColorPrimaries::Ebu3213),
+            _ => None,
+        }
+    }
+
+    // What the dropdown shows
+    pub fn label(&self) -> &'static str {
+        match self {
+            ColorPrimaries::Bt709 => "(1) BT.709",
+            ColorPrimaries::Unspecified => "(2) Unspecified, Default",
+            ColorPrimaries::Bt470m => "(4) BT.470 System M (historical)",
+            ColorPrimaries::Bt470bg => "(5) BT.470 System B, G (historical)",
+            ColorPrimaries::Bt601 => "(6) BT.601",
+            ColorPrimaries::Smpte240 => "(7) SMPTE 240",
+            ColorPrimaries::Film => "(8) Generic Film (color filters using illuminant C)",
+            ColorPrimaries::Bt2020 => "(9) BT.2020, BT.2100",
+            ColorPrimaries::Xyz => "(10) SMPTE 428 (CIE 1921 XYZ)",
+            ColorPrimaries::Smpte431 => "(11) SMPTE RP 431-2",
+            ColorPrimaries::Smpte432 => "(12) SMPT EG 432-1",
+            ColorPrimaries::Ebu3213 => "(22) EBU Tech. 3213-E",
+        }
+    }
 }
 
-#[derive(PartialEq, Eq, Clone, Copy, Debug, Serialize, Deserialize)]
+#[derive(PartialEq, Eq, Clone, Copy, Default, Debug, Serialize, Deserialize)]
 pub enum MatrixCoefficients {
-    Identity,    // [0] Identity matrix
-    Bt709,       // [1] BT.709
+    Identity, // [0] Identity matrix
+    Bt709,    // [1] BT.709
+    #[default]
     Unspecified, // [2] unspecified, default
-    Fcc,         // [4] US FCC 73.628
-    Bt470bg,     // [5] BT.470 System B, G (historical)
-    Bt601,       // [6] BT.601
-    Smpte240,    // [7] SMPTE 240 M
-    Ycgco,       // [8] YCgCo
-    Bt2020Ncl,   // [9] BT.2020 non-constant luminance, BT.2100 YCbCr
-    Bt2020Cl,    // [10] BT.2020 constant luminance
-    Smpte2085,   // [11] SMPTE ST 2085 YDzDx
-    ChromaNcl,   // [12] Chromaticity-derived non-constant luminance
-    ChromaCl,    // [13] Chromaticity-derived constant luminance
-    Ictcp,       // [14] BT.2100 ICtCp
-}
-
-impl Default for MatrixCoefficients {
-    fn default() -> Self {
-        MatrixCoefficients::Unspecified
-    }
+    Fcc,      // [4] US FCC 73.628
+    Bt470bg,  // [5] BT.470 System B, G (historical)
+    Bt601,    // [6] BT.601
+    Smpte240, // [7] SMPTE 240 M
+    Ycgco,    // [8] YCgCo
+    Bt2020Ncl, // [9] BT.2020 non-constant luminance, BT.2100 YCbCr
+    Bt2020Cl, // [10] BT.2020 constant luminance
+    Smpte2085, // [11] SMPTE ST 2085 YDzDx
+    ChromaNcl, // [12] Chromaticity-derived non-constant luminance
+    ChromaCl, // [13] Chromaticity-derived constant luminance
+    Ictcp,    // [14] BT.2100 ICtCp
 }
 
 impl MatrixCoefficients {
+    pub const ALL: [MatrixCoefficients; 14] = [
+        MatrixCoefficients::Identity,
+        MatrixCoefficients::Bt709,
+        MatrixCoefficients::Unspecified,
+        MatrixCoefficients::Fcc,
+        MatrixCoefficients::Bt470bg,
+        MatrixCoefficients::Bt601,
+        MatrixCoefficients::Smpte240,
+        MatrixCoefficients::Ycgco,
+        MatrixCoefficients::Bt2020Ncl,
+        MatrixCoefficients::Bt2020Cl,
+        MatrixCoefficients::Smpte2085,
+        MatrixCoefficients::ChromaNcl,
+        MatrixCoefficients::ChromaCl,
+        MatrixCoefficients::Ictcp,
+    ];
+
     pub fn as_str(&self) -> &str {
         match self {
             MatrixCoefficients::Identity => "0",
@@ -123,36 +258,91 @@ impl MatrixCoefficients {
             MatrixCoefficients::Ictcp => "14",
         }
     }
+
+    // ffprobe's color_space
+    pub fn from_ffprobe(space: &str) -> Option<Self> {
+        match space {
+            "gbr" => Some(MatrixCoefficients::Identity),
+            "bt709" => Some(MatrixCoefficients::Bt709),
+            "fcc" => Some(MatrixCoefficients::Fcc),
+            "bt470bg" => Some(MatrixCoefficients::Bt470bg),
+            "smpte170m" => Some(MatrixCoefficients::Bt601),
+            "smpte240m" => Some(MatrixCoefficients::Smpte240),
+            "ycgco" => Some(MatrixCoefficients::Ycgco),
+            "bt2020nc" => Some(MatrixCoefficients::Bt2020Ncl),
+            "bt2020c" => Some(MatrixCoefficients::Bt2020Cl),
+            "smpte2085" => Some(MatrixCoefficients::Smpte2085),
+            "chroma-derived-nc" => Some(MatrixCoefficients::ChromaNcl),
+            "chroma-derived-c" => Some(MatrixCoefficients::ChromaCl),
+            "ictcp" => Some(MatrixCoefficients::Ictcp),
+            _ => None,
+        }
+    }
+
+    // What the dropdown shows
+    pub fn label(&self) -> &'static str {
+        match self {
+            MatrixCoefficients::Identity => "(0) Identity matrix",
+            MatrixCoefficients::Bt709 => "(1) BT.709",
+            MatrixCoefficients::Unspecified => "(2) unspecified, default",
+            MatrixCoefficients::Fcc => "(4) US FCC 73.628",
+            MatrixCoefficients::Bt470bg => "(5) BT.470 System B, G (historical)",
+            MatrixCoefficients::Bt601 => "(6) BT.601",
+            MatrixCoefficients::Smpte240 => "(7) SMPTE 240 M",
+            MatrixCoefficients::Ycgco => "(8) YCgCo",
+            MatrixCoefficients::Bt2020Ncl => "(9) BT.2020 non-constant luminance, BT.2100 YCbCr",
+            MatrixCoefficients::Bt2020Cl => "(10) BT.2020 constant luminance",
+            MatrixCoefficients::Smpte2085 => "(11) SMPTE ST 2085 YDzDx",
+            MatrixCoefficients::ChromaNcl => "(12) Chromaticity-derived non-constant luminance",
+            MatrixCoefficients::ChromaCl => "(13) Chromaticity-derived constant luminance",
+            MatrixCoefficients::Ictcp => "(14) BT.2100 ICtCp",
+        }
+    }
 }
 
-#[derive(PartialEq, Eq, Clone, Copy, Debug, Serialize, Deserialize)]
+#[derive(PartialEq, Eq, Clone, Copy, Default, Debug, Serialize, Deserialize)]
 pub enum TransferCharacteristics {
-    Bt709,        // [1] BT.709
-    Unpsecified,  // [2] unspecified, default
-    Bt470m,       // [4] BT.470 System M (historical)
-    Bt470bg,      // [5] BT.470 System B, G (historical)
-    Bt601,        // [6] BT.601
-    Smpte240,     // [7] SMPTE 240 M
-    Linear,       // [8] Linear
-    Log100,       // [9] Logarithmic (100 : 1 range)
+    Bt709, // [1] BT.709
+    #[default]
+    Unpsecified, // [2] unspecified, default
+    Bt470m, // [4] BT.470 System M (historical)
+    Bt470bg, // [5] BT.470 System B, G (historical)
+    Bt601, // [6] BT.601
+    Smpte240, // [7] SMPTE 240 M
+    Linear, // [8] Linear
+    Log100, // [9] Logarithmic (100 : 1 range)
     Log100Sqrt10, // [10] Logarithmic (100 * Sqrt(10) : 1 range)
-    Iec61966,     // [11] IEC 61966-2-4
-    Bt1361,       // [12] BT.1361
-    Srgb,         // [13] sRGB or sYCC
-    Bt202010,     // [14] BT.2020 10-bit systems
-    Bt202012,     // [15] BT.2020 12-bit systems
-    Smpte2084,    // [16] SMPTE ST 2084, ITU BT.2100 PQ
-    Smpte428,     // [17] SMPTE ST 428
-    Hlg,          // [18] BT.2100 HLG, ARIB STD-B67
-}
-
-impl Default for TransferCharacteristics {
-    fn default() -> Self {
-        TransferCharacteristics::Unpsecified
-    }
+    Iec61966, // [11] IEC 61966-2-4
+    Bt1361, // [12] BT.1361
+    Srgb,  // [13] sRGB or sYCC
+    Bt202010, // [14] BT.2020 10-bit systems
+    Bt202012, // [15] BT.2020 12-bit systems
+    Smpte2084, // [16] SMPTE ST 2084, ITU BT.2100 PQ
+    Smpte428, // [17] SMPTE ST 428
+    Hlg,   // [18] BT.2100 HLG, ARIB STD-B67
 }
 
 impl TransferCharacteristics {
+    pub const ALL: [TransferCharacteristics; 17] = [
+        TransferCharacteristics::Bt709,
+        TransferCharacteristics::Unpsecified,
+        TransferCharacteristics::Bt470m,
+        TransferCharacteristics::Bt470bg,
+        TransferCharacteristics::Bt601,
+        TransferCharacteristics::Smpte240,
+        TransferCharacteristics::Linear,
+        TransferCharacteristics::Log100,
+        TransferCharacteristics::Log100Sqrt10,
+        TransferCharacteristics::Iec61966,
+        TransferCharacteristics::Bt1361,
+        TransferCharacteristics::Srgb,
+        TransferCharacteristics::Bt202010,
+        TransferCharacteristics::Bt202012,
+        TransferCharacteristics::Smpte2084,
+        TransferCharacteristics::Smpte428,
+        TransferCharacteristics::Hlg,
+    ];
+
     pub fn as_str(&self) -> &str {
         match self {
             TransferCharacteristics::Bt709 => "1",
@@ -174,18 +364,113 @@ impl TransferCharacteristics {
             TransferCharacteristics::Hlg => "18",
         }
     }
-}
 
-#[derive(PartialEq, Eq, Clone, Copy, Debug, Serialize, Deserialize)]
-pub enum ColorRange {
-    Studio, // [0], default
-    Full,   // [1] full
+    // ffprobe's color_transfer
+    pub fn from_ffprobe(transfer: &str) -> Option<Self> {
+        match transfer {
+            "bt709" => Some(TransferCharacteristics::Bt709),
+            "gamma22" => Some(TransferCharacteristics::Bt470m),
+            "gamma28" => Some(TransferCharacteristics::Bt470bg),
+            "smpte170m" => Some(TransferCharacteristics::Bt601),
+            "smpte240m" => Some(TransferCharacteristics::Smpte240),
+            "linear" => Some(TransferCharacteristics::Linear),
+            "log100" | "log" => Some(TransferCharacteristics::Log100),
+            "log316" | "log_sqrt" => Some(TransferCharacteristics::Log100Sqrt10),
+            "iec61966-2-4" => Some(TransferCharacteristics::Iec61966),
+            "bt1361e" => Some(TransferCharacteristics::Bt1361),
+            "iec61966-2-1" => Some(TransferCharacteristics::Srgb),
+            "bt2020-10" => Some(TransferCharacteristics::Bt202010),
+            "bt2020-12" => Some(TransferCharacteristics::Bt202012),
+            "smpte2084" => Some(TransferCharacteristics::Smpte2084),
+            "smpte428" => Some(TransferCharacteristics::Smpte428),
+            "arib-std-b67" => Some(TransferCharacteristics::Hlg),
+            _ => None,
+        }
+    }
+
+    // What the dropdown shows
+    pub fn label(&self) -> &'static str {
+        match self {
+            TransferCharacteristics::Bt709 => "(1) BT.709",
+            TransferCharacteristics::Unpsecified => "(2) unspecified, default",
+            TransferCharacteristics::Bt470m => "(4) BT.470 System M (historical)",
+            TransferCharacteristics::Bt470bg => "(5) BT.470 System B, G (historical)",
+            TransferCharacteristics::Bt601 => "(6) BT.601",
+            TransferCharacteristics::Smpte240 => "(7) SMPTE 240 M",
+            TransferCharacteristics::Linear => "(8) Linear",
+            TransferCharacteristics::Log100 => "(9) Logarithmic (100 : 1 range)",
+            TransferCharacteristics::Log100Sqrt10 => "(10) Logarithmic (100 * Sqrt(10) : 1 range)",
+            TransferCharacteristics::Iec61966 => "(11) IEC 61966-2-4",
+            TransferCharacteristics::Bt1361 => "(12) BT.1361",
+            TransferCharacteristics::Srgb => "(13) sRGB or sYCC",
+            TransferCharacteristics::Bt202010 => "(14) BT.2020 10-bit systems",
+            TransferCharacteristics::Bt202012 => "(15) BT.2020 12-bit systems",
+            TransferCharacteristics::Smpte2084 => "(16) SMPTE ST 2084, ITU BT.2100 PQ",
+            TransferCharacteristics::Smpte428 => "(17) SMPTE ST 428",
+            TransferCharacteristics::Hlg => "(18) BT.2100 HLG, ARIB STD-B67",
+        }
+    }
 }
 
-impl Default for ColorRange {
-    fn default() -> Self {
-        ColorRange::Studio
+// Combinations of color metadata that are almost always a mistake. They're only warned about,
+// since players mostly cope and some sources really are tagged that way.
+pub fn color_metadata_problems(
+    primaries: ColorPrimaries,
+    transfer: TransferCharacteristics,
+    matrix: MatrixCoefficients,
+) -> Vec<String> {
+    let hdr_transfer = match transfer {
+        TransferCharacteristics::Smpte2084 => Some("PQ"),
+        TransferCharacteristics::Hlg => Some("HLG"),
+        _ => None,
+    };
+    let bt2020_matrix = matches!(
+        matrix,
+        MatrixCoefficients::Bt2020Ncl | MatrixCoefficients::Bt2020Cl | MatrixCoefficients::Ictcp
+    );
+    let sdr_matrix = matches!(
+        matrix,
+        MatrixCoefficients::Bt709 | MatrixCoefficients::Bt601 | MatrixCoefficients::Bt470bg
+    );
+
+    let mut problems = Vec::new();
+    if let Some(hdr) = hdr_transfer {
+        if primaries != ColorPrimaries::Bt2020 {
+            problems.push(format!(
+                "{} transfer is HDR, which goes with BT.2020 primaries (9), not ({})",
+                hdr,
+                primaries.as_str()
+            ));
+        }
+        if !bt2020_matrix {
+            problems.push(format!(
+                "{} transfer is HDR, which goes with a BT.2020 matrix (9), not ({})",
+                hdr,
+                matrix.as_str()
+            ));
+        }
     }
+    if primaries == ColorPrimaries::Bt2020 && sdr_matrix {
+        problems.push(format!(
+            "BT.2020 primaries with an SD/HD matrix ({}) will shift the colors",
+            matrix.as_str()
+        ));
+    }
+    if primaries == ColorPrimaries::Bt709 && bt2020_matrix {
+        problems.push(format!(
+            "BT.709 primaries with a BT.2020 matrix ({}) will shift the colors",
+            matrix.as_str()
+        ));
+    }
+
+    problems
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Default, Debug, Serialize, Deserialize)]
+pub enum ColorRange {
+    #[default]
+    Studio, // [0], default
+    Full, // [1] full
 }
 
 impl ColorRange {
@@ -197,23 +482,1021 @@ impl ColorRange {
     }
 }
 
-#[derive(PartialEq, Eq, Clone, Copy, Debug, Serialize, Deserialize)]
-pub enum Theme {
-    Dark,
-    Light,
+#[derive(PartialEq, Eq, Clone, Copy, Default, Debug, Serialize, Deserialize)]
+pub enum ChromaSamplePosition {
+    #[default]
+    Unknown, // default
+    Vertical,  // Left, most 4:2:0 video
+    Colocated, // Top left, e.g. BT.2020 video
 }
 
-impl Default for Theme {
-    fn default() -> Self {
-        Theme::Dark
+impl ChromaSamplePosition {
+    pub fn as_str(&self) -> &str {
+        match self {
+            ChromaSamplePosition::Unknown => "unknown",
+            ChromaSamplePosition::Vertical => "vertical",
+            ChromaSamplePosition::Colocated => "colocated",
+        }
+    }
+
+    // ffprobe's chroma_location, None for the ones SVT-AV1 can't signal
+    pub fn from_ffprobe(location: &str) -> Option<Self> {
+        match location {
+            "left" => Some(ChromaSamplePosition::Vertical),
+            "topleft" => Some(ChromaSamplePosition::Colocated),
+            _ => None,
+        }
     }
 }
 
+#[derive(PartialEq, Eq, Clone, Copy, Default, Debug, Serialize, Deserialize)]
+pub enum Theme {
+    #[default]
+    Dark,
+    Light,
+    System, // Follows the OS preference
+}
+
 impl Theme {
     pub fn as_str(&self) -> &str {
         match self {
             Theme::Dark => "Dark",
             Theme::Light => "Light",
+            Theme::System => "System",
+        }
+    }
+
+    pub fn visuals(&self) -> Visuals {
+        let dark = match self {
+            Theme::Dark => true,
+            Theme::Light => false,
+            Theme::System => dark_light::detect() != dark_light::Mode::Light,
+        };
+
+        if dark {
+            Visuals::dark()
+        } else {
+            Visuals::light()
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Default, Debug, Serialize, Deserialize)]
+pub enum GrainSynthesis {
+    #[default]
+    Synthetic, // SVT-AV1 --film-grain, default
+    PhotonNoise, // Av1an --photon-noise
+    GrainTable,  // SVT-AV1 --fgs-table
+}
+
+impl GrainSynthesis {
+    pub fn as_str(&self) -> &str {
+        match self {
+            GrainSynthesis::Synthetic => "Synthetic",
+            GrainSynthesis::PhotonNoise => "Photon Noise",
+            GrainSynthesis::GrainTable => "Grain Table",
+        }
+    }
+}
+
+// Av1an's --min-scene-len and --extra-split in frames, empty leaves them to av1an. An extra split
+// of 0 turns splitting long scenes off.
+pub fn scene_length_problem(min: &str, max: &str) -> Option<String> {
+    let parse = |name: &str, value: &str| match value.trim() {
+        "" => Ok(None),
+        value => value.parse::<u32>().map(Some).map_err(|_| {
+            format!(
+                "The {} scene length has to be a whole number of frames",
+                name
+            )
+        }),
+    };
+    let (min, max) = match (parse("min", min), parse("max", max)) {
+        (Ok(min), Ok(max)) => (min, max),
+        (Err(e), _) | (_, Err(e)) => return Some(e),
+    };
+
+    match (min, max) {
+        (Some(0), _) => Some(String::from(
+            "The min scene length has to be at least 1 frame",
+        )),
+        (Some(min), Some(max)) if max > 0 && min > max => Some(format!(
+            "The min scene length ({}) is above the max scene length ({})",
+            min, max
+        )),
+        _ => None,
+    }
+}
+
+// Av1an's --photon-noise-width and --photon-noise-height, both empty means they're left to
+// the output size
+pub fn photon_noise_size_problem(width: &str, height: &str) -> Option<String> {
+    let (width, height) = (width.trim(), height.trim());
+    if width.is_empty() && height.is_empty() {
+        return None;
+    }
+    if width.is_empty() || height.is_empty() {
+        return Some(String::from(
+            "The photon noise size needs both a width and a height",
+        ));
+    }
+    for (name, value) in [("width", width), ("height", height)] {
+        match value.parse::<u32>() {
+            Ok(size) if size > 0 && size % 2 == 0 => {}
+            _ => {
+                return Some(format!(
+                    "The photon noise {} has to be a positive even number",
+                    name
+                ))
+            }
+        }
+    }
+
+    None
+}
+
+// Av1an's --photon-noise takes a strength from 0 to 64, where 1 is about ISO 100
+pub const MAX_PHOTON_NOISE: u32 = 64;
+
+// The ISO in the UI as av1an's strength, rounded and capped at its maximum
+pub fn photon_noise_strength(iso: &str) -> Option<u32> {
+    let iso: u32 = iso.trim().parse().ok()?;
+    Some(iso.saturating_add(50).div_euclid(100).min(MAX_PHOTON_NOISE))
+}
+
+pub fn photon_noise_problem(iso: &str) -> Option<String> {
+    photon_noise_strength(iso)
+        .is_none()
+        .then(|| String::from("The photon noise ISO has to be a whole number"))
+}
+
+// ISOs av1an can't take as they are
+pub fn photon_noise_warning(iso: &str) -> Option<String> {
+    let iso: u32 = iso.trim().parse().ok()?;
+    if iso > MAX_PHOTON_NOISE * 100 {
+        Some(format!(
+            "Photon noise tops out at ISO {}, higher values are capped",
+            MAX_PHOTON_NOISE * 100
+        ))
+    } else if iso > 0 && iso < 50 {
+        Some(String::from(
+            "Photon noise goes in steps of ISO 100, this rounds down to no grain",
+        ))
+    } else {
+        None
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Default, Debug, Serialize, Deserialize)]
+pub enum OutputContainer {
+    #[default]
+    Mkv,
+    Mp4,
+    WebM,
+    Ivf,
+}
+
+impl OutputContainer {
+    pub fn as_str(&self) -> &str {
+        match self {
+            OutputContainer::Mkv => "MKV",
+            OutputContainer::Mp4 => "MP4",
+            OutputContainer::WebM => "WebM",
+            OutputContainer::Ivf => "IVF",
+        }
+    }
+
+    pub fn extension(&self) -> &str {
+        match self {
+            OutputContainer::Mkv => "mkv",
+            OutputContainer::Mp4 => "mp4",
+            OutputContainer::WebM => "webm",
+            OutputContainer::Ivf => "ivf",
+        }
+    }
+
+    // The Av1an concatenation method (-c) to use when none is set, or the set one can't produce
+    // this container
+    pub fn default_concat(&self) -> &str {
+        match self {
+            OutputContainer::Mkv => "mkvmerge",
+            OutputContainer::Mp4 | OutputContainer::WebM => "ffmpeg",
+            OutputContainer::Ivf => "ivf",
+        }
+    }
+
+    pub fn supports_concat(&self, method: &str) -> bool {
+        matches!(
+            (self, method),
+            (OutputContainer::Mkv, "mkvmerge" | "ffmpeg")
+                | (OutputContainer::Mp4 | OutputContainer::WebM, "ffmpeg")
+                | (OutputContainer::Ivf, "ivf" | "ffmpeg")
+        )
+    }
+
+    // Whether audio in this codec (as ffprobe names it) can be copied into the container.
+    // Av1an copies the audio as is, so anything else fails at concatenation.
+    pub fn supports_audio(&self, codec: &str) -> bool {
+        match self {
+            OutputContainer::Mkv => true,
+            OutputContainer::Mp4 => matches!(
+                codec,
+                "aac" | "mp3" | "mp2" | "ac3" | "eac3" | "opus" | "flac" | "alac"
+            ),
+            OutputContainer::WebM => matches!(codec, "opus" | "vorbis"),
+            OutputContainer::Ivf => false,
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Default, Debug, Serialize, Deserialize)]
+pub enum Encoder {
+    #[default]
+    SvtAv1, // default
+    Aom,
+    Rav1e,
+}
+
+// The quality and speed knobs of an encoder, so the UI and the command agree on them
+pub struct EncoderRanges {
+    pub quality_label: &'static str,
+    pub quality: (f32, f32),
+    pub quality_step: f32, // Smallest step the encoder takes
+    pub speed_label: &'static str,
+    pub speed: (f32, f32),
+}
+
+impl Encoder {
+    // What av1an's -e takes
+    pub fn as_str(&self) -> &str {
+        match self {
+            Encoder::SvtAv1 => "svt-av1",
+            Encoder::Aom => "aom",
+            Encoder::Rav1e => "rav1e",
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Encoder::SvtAv1 => "SVT-AV1",
+            Encoder::Aom => "aomenc",
+            Encoder::Rav1e => "rav1e",
+        }
+    }
+
+    // Why the encoder can't take the pixel format, None if it can
+    pub fn pixel_format_problem(&self, format: PixelFormat) -> Option<String> {
+        match self {
+            Encoder::SvtAv1 if format.chroma_subsampling() != 420 => Some(format!(
+                "SVT-AV1 only encodes 4:2:0, {} needs aomenc or rav1e",
+                format.as_str()
+            )),
+            Encoder::SvtAv1 if format.bit_depth() > 10 => Some(format!(
+                "SVT-AV1 only encodes up to 10-bit, {} needs aomenc or rav1e",
+                format.as_str()
+            )),
+            _ => None,
+        }
+    }
+
+    // `format`, or the nearest one this encoder can encode. SVT-AV1 gets 10-bit 4:2:0 for
+    // anything it can't take.
+    pub fn closest_pixel_format(&self, format: PixelFormat) -> PixelFormat {
+        match self.pixel_format_problem(format) {
+            Some(_) => PixelFormat::Yuv420p10le,
+            None => format,
+        }
+    }
+
+    // Why av1an can't use the target quality settings, None if it can. Blank bounds leave
+    // av1an's own.
+    pub fn target_quality_problem(&self, target: &str, min_q: &str, max_q: &str) -> Option<String> {
+        let ranges = self.ranges();
+        let (low, high) = ranges.quality;
+        match target.trim().parse::<f32>() {
+            Ok(target) if (0.0..=100.0).contains(&target) => {}
+            _ => {
+                return Some(String::from(
+                    "Target Quality has to be a VMAF score from 0 to 100",
+                ))
+            }
+        }
+
+        let mut bounds = [None, None];
+        for (bound, (name, value)) in bounds.iter_mut().zip([("Min Q", min_q), ("Max Q", max_q)]) {
+            let value = value.trim();
+            if value.is_empty() {
+                continue;
+            }
+            match value.parse::<u32>() {
+                Ok(q) if (low..=high).contains(&(q as f32)) => *bound = Some(q),
+                _ => {
+                    return Some(format!(
+                        "{} has to be a whole {} from {} to {}",
+                        name, ranges.quality_label, low, high
+                    ))
+                }
+            }
+        }
+        match bounds {
+            [Some(min), Some(max)] if min > max => {
+                Some(format!("Min Q ({}) is above Max Q ({})", min, max))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn ranges(&self) -> EncoderRanges {
+        match self {
+            Encoder::SvtAv1 => EncoderRanges {
+                quality_label: "CRF",
+                quality: (0.0, 70.0),
+                quality_step: 0.25,
+                speed_label: "Preset",
+                speed: (0.0, 13.0),
+            },
+            Encoder::Aom => EncoderRanges {
+                quality_label: "CQ Level",
+                quality: (0.0, 63.0),
+                quality_step: 1.0,
+                speed_label: "CPU Used",
+                speed: (0.0, 9.0),
+            },
+            Encoder::Rav1e => EncoderRanges {
+                quality_label: "Quantizer",
+                quality: (0.0, 255.0),
+                quality_step: 1.0,
+                speed_label: "Speed",
+                speed: (0.0, 10.0),
+            },
         }
     }
 }
+
+// ffmpeg's swscale filter for resizing to the output resolution
+#[derive(PartialEq, Eq, Clone, Copy, Default, Debug, Serialize, Deserialize)]
+pub enum ScaleAlgorithm {
+    #[default]
+    Bicubic, // default, Catmull-Rom
+    Lanczos,
+    #[serde(alias = "Spline36")] // What presets saved before the rename call it
+    Spline,
+    Bilinear,
+    Neighbor,
+}
+
+impl ScaleAlgorithm {
+    pub const ALL: [ScaleAlgorithm; 5] = [
+        ScaleAlgorithm::Bicubic,
+        ScaleAlgorithm::Lanczos,
+        ScaleAlgorithm::Spline,
+        ScaleAlgorithm::Bilinear,
+        ScaleAlgorithm::Neighbor,
+    ];
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            ScaleAlgorithm::Bicubic => "Bicubic (Catmull-Rom)",
+            ScaleAlgorithm::Lanczos => "Lanczos",
+            ScaleAlgorithm::Spline => "Spline",
+            ScaleAlgorithm::Bilinear => "Bilinear",
+            ScaleAlgorithm::Neighbor => "Neighbor",
+        }
+    }
+
+    // What goes after the size in the scale filter
+    pub fn flags(&self) -> &str {
+        match self {
+            // B = 0, C = 1/2
+            ScaleAlgorithm::Bicubic => "flags=bicubic:param0=0:param1=1/2",
+            ScaleAlgorithm::Lanczos => "flags=lanczos",
+            // swscale's natural bicubic spline, not a fixed-tap one like zscale's spline36
+            ScaleAlgorithm::Spline => "flags=spline",
+            ScaleAlgorithm::Bilinear => "flags=bilinear",
+            ScaleAlgorithm::Neighbor => "flags=neighbor",
+        }
+    }
+
+    pub fn description(&self) -> &str {
+        match self {
+            ScaleAlgorithm::Bicubic => "Sharp without much ringing, a good default.",
+            ScaleAlgorithm::Lanczos => "Sharpest, best for downscales, can ring around hard edges.",
+            ScaleAlgorithm::Spline => "Close to Lanczos with less ringing, good for downscales.",
+            ScaleAlgorithm::Bilinear => "Fast and soft.",
+            ScaleAlgorithm::Neighbor => {
+                "Keeps hard pixel edges, for pixel art and integer upscales."
+            }
+        }
+    }
+}
+
+// Filters for interlaced and telecined sources, run before anything else in the ffmpeg chain
+#[derive(PartialEq, Eq, Clone, Copy, Default, Debug, Serialize, Deserialize)]
+pub enum Deinterlace {
+    #[default]
+    None, // default
+    Yadif,
+    Bwdif,
+    Ivtc,
+}
+
+impl Deinterlace {
+    pub const ALL: [Deinterlace; 4] = [
+        Deinterlace::None,
+        Deinterlace::Yadif,
+        Deinterlace::Bwdif,
+        Deinterlace::Ivtc,
+    ];
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            Deinterlace::None => "None",
+            Deinterlace::Yadif => "yadif",
+            Deinterlace::Bwdif => "bwdif",
+            Deinterlace::Ivtc => "IVTC (fieldmatch+decimate)",
+        }
+    }
+
+    // One frame out per frame in, except IVTC which drops the duplicate of every 5
+    pub fn filter(&self) -> Option<&str> {
+        match self {
+            Deinterlace::None => None,
+            Deinterlace::Yadif => Some("yadif=mode=send_frame"),
+            Deinterlace::Bwdif => Some("bwdif=mode=send_frame"),
+            // What ffmpeg's docs suggest, yadif only touches frames fieldmatch couldn't match
+            Deinterlace::Ivtc => Some("fieldmatch,yadif=deint=interlaced,decimate"),
+        }
+    }
+
+    // How many frames come out of `frames` going in. Only an estimate for IVTC: decimate drops
+    // one frame in five within each chunk, so chunks that don't split on a five-frame cycle come
+    // out a frame or so off. The progress bar uses it until av1an reports its own total.
+    pub fn output_frames(&self, frames: u64) -> u64 {
+        match self {
+            Deinterlace::Ivtc => frames * 4 / 5,
+            _ => frames,
+        }
+    }
+
+    pub fn description(&self) -> &str {
+        match self {
+            Deinterlace::None => "For progressive sources.",
+            Deinterlace::Yadif => "Fast deinterlacer for truly interlaced video.",
+            Deinterlace::Bwdif => "Deinterlacer with fewer artifacts than yadif, a bit slower.",
+            Deinterlace::Ivtc => "Restores the original 24p frames of telecined film (29.97 fps DVDs of movies and most anime).",
+        }
+    }
+}
+
+// ffmpeg denoisers, run on the source before scaling
+#[derive(PartialEq, Eq, Clone, Copy, Default, Debug, Serialize, Deserialize)]
+pub enum Denoise {
+    #[default]
+    None, // default
+    Hqdn3d,
+    Nlmeans,
+}
+
+impl Denoise {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Denoise::None => "None",
+            Denoise::Hqdn3d => "hqdn3d",
+            Denoise::Nlmeans => "nlmeans",
+        }
+    }
+
+    // Strengths that make sense for the filter, and where to start
+    pub fn strength_range(&self) -> (f32, f32) {
+        match self {
+            Denoise::None => (0.0, 0.0),
+            Denoise::Hqdn3d => (0.0, 10.0),
+            Denoise::Nlmeans => (1.0, 30.0),
+        }
+    }
+
+    pub fn default_strength(&self) -> f32 {
+        match self {
+            Denoise::None => 0.0,
+            Denoise::Hqdn3d => 4.0,
+            Denoise::Nlmeans => 3.0,
+        }
+    }
+
+    pub fn filter(&self, strength: f32) -> Option<String> {
+        match self {
+            Denoise::None => None,
+            // The chroma and temporal strengths follow the luma one
+            Denoise::Hqdn3d => Some(format!("hqdn3d={}", strength)),
+            Denoise::Nlmeans => Some(format!("nlmeans=s={}", strength)),
+        }
+    }
+}
+
+// What Custom Encoder Parameters do with the generated ones
+#[derive(PartialEq, Eq, Clone, Copy, Default, Debug, Serialize, Deserialize)]
+pub enum CustomParamsMode {
+    #[default]
+    Append, // default, later flags win
+    Replace,
+}
+
+impl CustomParamsMode {
+    pub fn as_str(&self) -> &str {
+        match self {
+            CustomParamsMode::Append => "Append to generated",
+            CustomParamsMode::Replace => "Replace entirely",
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Default, Debug, Serialize, Deserialize)]
+pub enum RateControl {
+    #[default]
+    Crf, // default
+    TargetBitrate,
+}
+
+impl RateControl {
+    pub const ALL: [RateControl; 2] = [RateControl::Crf, RateControl::TargetBitrate];
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            RateControl::Crf => "Constant quality",
+            RateControl::TargetBitrate => "Target bitrate (2-pass)",
+        }
+    }
+}
+
+// The average bitrate in kbps, a positive whole number
+pub fn target_bitrate_problem(bitrate: &str) -> Option<String> {
+    match bitrate.trim().parse::<u32>() {
+        Ok(kbps) if kbps > 0 => None,
+        _ => Some(String::from(
+            "The target bitrate has to be a whole number of kbps above 0",
+        )),
+    }
+}
+
+// Common output sizes for the resolution fields
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub enum ResolutionPreset {
+    KeepSource,
+    Uhd,
+    FullHd,
+    Hd,
+    HalfSource,
+}
+
+impl ResolutionPreset {
+    pub const ALL: [ResolutionPreset; 5] = [
+        ResolutionPreset::KeepSource,
+        ResolutionPreset::Uhd,
+        ResolutionPreset::FullHd,
+        ResolutionPreset::Hd,
+        ResolutionPreset::HalfSource,
+    ];
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            ResolutionPreset::KeepSource => "Keep Source",
+            ResolutionPreset::Uhd => "3840×2160",
+            ResolutionPreset::FullHd => "1920×1080",
+            ResolutionPreset::Hd => "1280×720",
+            ResolutionPreset::HalfSource => "Half Source",
+        }
+    }
+
+    // What to fill the fields with. None for Keep Source (empty fields don't scale) and for
+    // Half Source without a probed input. With `lock_aspect` the fixed sizes only set the
+    // width and the height follows the source.
+    pub fn resolution(&self, source: Option<(u32, u32)>, lock_aspect: bool) -> Option<(u32, u32)> {
+        let fixed = |width: u32, height: u32| match source {
+            Some(source) if lock_aspect => Some((width, height_for_width(width, source))),
+            _ => Some((width, height)),
+        };
+
+        match self {
+            ResolutionPreset::KeepSource => None,
+            ResolutionPreset::Uhd => fixed(3840, 2160),
+            ResolutionPreset::FullHd => fixed(1920, 1080),
+            ResolutionPreset::Hd => fixed(1280, 720),
+            ResolutionPreset::HalfSource => source.map(|(width, height)| {
+                (
+                    round_even(width as f64 / 2.0),
+                    round_even(height as f64 / 2.0),
+                )
+            }),
+        }
+    }
+}
+
+// The height that keeps the source's aspect ratio at `width`, rounded to even for 4:2:0
+pub fn height_for_width(width: u32, source: (u32, u32)) -> u32 {
+    round_even(width as f64 * source.1 as f64 / source.0.max(1) as f64)
+}
+
+pub fn width_for_height(height: u32, source: (u32, u32)) -> u32 {
+    round_even(height as f64 * source.0 as f64 / source.1.max(1) as f64)
+}
+
+// Empty, or ffmpeg's -1 and -2, which keep the aspect ratio
+fn follows_aspect(side: &str) -> bool {
+    matches!(side, "" | "-1" | "-2")
+}
+
+// What to scale to from the Width and Height fields, None when neither is set. A side left to
+// follow the other gets computed from the source's aspect ratio, rounded to even, or is left
+// to ffmpeg as -2 when the source isn't known.
+pub fn scale_size(
+    width: &str,
+    height: &str,
+    source: Option<(u32, u32)>,
+) -> Option<(String, String)> {
+    let (width, height) = (width.trim(), height.trim());
+    match (follows_aspect(width), follows_aspect(height)) {
+        (true, true) => None,
+        (false, false) => Some((width.to_string(), height.to_string())),
+        (false, true) => Some(match (width.parse(), source) {
+            (Ok(value), Some(source)) => (
+                width.to_string(),
+                height_for_width(value, source).to_string(),
+            ),
+            _ => (width.to_string(), String::from("-2")),
+        }),
+        (true, false) => Some(match (height.parse(), source) {
+            (Ok(value), Some(source)) => (
+                width_for_height(value, source).to_string(),
+                height.to_string(),
+            ),
+            _ => (String::from("-2"), height.to_string()),
+        }),
+    }
+}
+
+fn round_even(value: f64) -> u32 {
+    ((value / 2.0).round() * 2.0).max(2.0) as u32
+}
+
+// The cap for capped CRF and the buffer it's averaged over, both optional
+pub fn max_bitrate_problem(max_bitrate: &str, buffer_size: &str) -> Option<String> {
+    let (max_bitrate, buffer_size) = (max_bitrate.trim(), buffer_size.trim());
+    if !max_bitrate.is_empty() && !matches!(max_bitrate.parse::<u32>(), Ok(kbps) if kbps > 0) {
+        return Some(String::from(
+            "The max bitrate has to be a whole number of kbps above 0",
+        ));
+    }
+    if buffer_size.is_empty() {
+        return None;
+    }
+    if !matches!(buffer_size.parse::<u32>(), Ok(ms) if ms > 0) {
+        return Some(String::from(
+            "The buffer size has to be a whole number of milliseconds above 0",
+        ));
+    }
+    if max_bitrate.is_empty() {
+        return Some(String::from(
+            "The buffer size only applies with a max bitrate",
+        ));
+    }
+
+    None
+}
+
+// About 1000 kbps for 4K, less for fewer pixels. A tighter cap starves every scene with some
+// motion in it.
+const LOW_MAX_BITRATE_4K: f64 = 1000.0;
+
+pub fn max_bitrate_warning(kbps: u32, resolution: (u32, u32)) -> Option<String> {
+    let pixels = resolution.0 as f64 * resolution.1 as f64;
+    let plausible = LOW_MAX_BITRATE_4K * pixels / (3840.0 * 2160.0);
+    (f64::from(kbps) < plausible).then(|| {
+        format!(
+            "A max bitrate of {} kbps is very low for {}x{}, detailed or moving scenes will fall apart. Around {} kbps or more is safer.",
+            kbps,
+            resolution.0,
+            resolution.1,
+            (plausible / 50.0).ceil() as u32 * 50
+        )
+    })
+}
+
+// av1an's --chunk-order, LongToShort is av1an's own default and leaves the flag out
+#[derive(PartialEq, Eq, Clone, Copy, Default, Debug, Serialize, Deserialize)]
+pub enum ChunkOrder {
+    #[default]
+    LongToShort, // default
+    ShortToLong,
+    Sequential,
+    Random,
+}
+
+impl ChunkOrder {
+    pub fn as_str(&self) -> &str {
+        match self {
+            ChunkOrder::LongToShort => "long-to-short",
+            ChunkOrder::ShortToLong => "short-to-long",
+            ChunkOrder::Sequential => "sequential",
+            ChunkOrder::Random => "random",
+        }
+    }
+}
+
+// av1an's --log-level, Default leaves the flag out
+#[derive(PartialEq, Eq, Clone, Copy, Default, Debug, Serialize, Deserialize)]
+pub enum LogLevel {
+    #[default]
+    Default,
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl LogLevel {
+    pub fn as_str(&self) -> &str {
+        match self {
+            LogLevel::Default => "default",
+            LogLevel::Error => "error",
+            LogLevel::Warn => "warn",
+            LogLevel::Info => "info",
+            LogLevel::Debug => "debug",
+            LogLevel::Trace => "trace",
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Default, Debug, Serialize, Deserialize)]
+pub enum Tune {
+    Vq,   // [0] Visual quality
+    Psnr, // [1] PSNR
+    #[default]
+    Ssim, // [2] SSIM, default
+    SubjectiveSsim, // [3] Subjective SSIM (SVT-AV1-PSY)
+    StillPicture, // [4] Still picture (SVT-AV1-PSY)
+}
+
+impl Tune {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Tune::Vq => "0",
+            Tune::Psnr => "1",
+            Tune::Ssim => "2",
+            Tune::SubjectiveSsim => "3",
+            Tune::StillPicture => "4",
+        }
+    }
+}
+
+// Starting points for common kinds of content, applied on top of the current settings
+pub struct BuiltinPreset {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub preset: f32,
+    pub crf: f32,
+    pub synthetic_grain: u32,
+    pub tune: Tune,
+    pub color_primaries: ColorPrimaries,
+    pub transfer_characteristics: TransferCharacteristics,
+    pub matrix_coefficients: MatrixCoefficients,
+    pub color_range: ColorRange,
+}
+
+pub const BUILTIN_PRESETS: &[BuiltinPreset] = &[
+    BuiltinPreset {
+        name: "Anime",
+        description: "Flat shading and clean lines compress well, so a higher CRF and no grain.",
+        preset: 4.0,
+        crf: 28.0,
+        synthetic_grain: 0,
+        tune: Tune::Ssim,
+        color_primaries: ColorPrimaries::Bt709,
+        transfer_characteristics: TransferCharacteristics::Bt709,
+        matrix_coefficients: MatrixCoefficients::Bt709,
+        color_range: ColorRange::Studio,
+    },
+    BuiltinPreset {
+        name: "Live-Action Film",
+        description: "Keeps fine detail with a lower CRF and a light layer of synthetic grain.",
+        preset: 4.0,
+        crf: 25.0,
+        synthetic_grain: 8,
+        tune: Tune::Vq,
+        color_primaries: ColorPrimaries::Bt709,
+        transfer_characteristics: TransferCharacteristics::Bt709,
+        matrix_coefficients: MatrixCoefficients::Bt709,
+        color_range: ColorRange::Studio,
+    },
+    BuiltinPreset {
+        name: "Grain-Heavy",
+        description: "For noisy or grainy sources. Low CRF, slower preset and strong synthetic grain so the grain doesn't eat the bitrate.",
+        preset: 3.0,
+        crf: 22.0,
+        synthetic_grain: 16,
+        tune: Tune::Vq,
+        color_primaries: ColorPrimaries::Bt709,
+        transfer_characteristics: TransferCharacteristics::Bt709,
+        matrix_coefficients: MatrixCoefficients::Bt709,
+        color_range: ColorRange::Studio,
+    },
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spline_keeps_its_old_name_readable() {
+        let old: ScaleAlgorithm = serde_yaml::from_str("Spline36").unwrap();
+        assert!(old == ScaleAlgorithm::Spline);
+    }
+
+    #[test]
+    fn pixel_format_follows_the_source() {
+        assert!(PixelFormat::from_ffprobe("yuv420p") == Some(PixelFormat::Yuv420p10le));
+        assert!(PixelFormat::from_ffprobe("yuvj420p") == Some(PixelFormat::Yuv420p10le));
+        assert!(PixelFormat::from_ffprobe("yuv420p10le") == Some(PixelFormat::Yuv420p10le));
+        assert!(PixelFormat::from_ffprobe("yuv420p12le") == Some(PixelFormat::Yuv420p12le));
+        assert!(PixelFormat::from_ffprobe("yuv422p10le") == Some(PixelFormat::Yuv422p10le));
+        assert!(PixelFormat::from_ffprobe("yuv444p12le") == Some(PixelFormat::Yuv444p10le));
+        assert!(PixelFormat::from_ffprobe("gbrp") == Some(PixelFormat::Yuv444p10le));
+        assert!(PixelFormat::from_ffprobe("nv12").is_none());
+    }
+
+    #[test]
+    fn pixel_format_fits_the_encoder() {
+        for format in [PixelFormat::Yuv444p10le, PixelFormat::Yuv420p12le] {
+            assert!(Encoder::SvtAv1.closest_pixel_format(format) == PixelFormat::Yuv420p10le);
+            assert!(Encoder::Aom.closest_pixel_format(format) == format);
+        }
+        assert!(Encoder::SvtAv1.closest_pixel_format(PixelFormat::Yuv420p) == PixelFormat::Yuv420p);
+    }
+
+    #[test]
+    fn source_libraries_map_to_av1an_chunk_methods() {
+        assert_eq!(SourceLibrary::BestSource.to_av1an_arg(), "bestsource");
+        assert_eq!(SourceLibrary::FFMS2.to_av1an_arg(), "ffms2");
+        assert_eq!(SourceLibrary::LSMASH.to_av1an_arg(), "lsmash");
+        assert_eq!(
+            ChunkMethod::SourceLibrary.arg(SourceLibrary::LSMASH),
+            "lsmash"
+        );
+    }
+
+    #[test]
+    fn one_side_follows_the_source_aspect_ratio() {
+        let source = Some((1920, 1080));
+        let size = |width: &str, height: &str| scale_size(width, height, source);
+        let pair = |width: &str, height: &str| Some((width.to_string(), height.to_string()));
+
+        assert_eq!(size("1280", ""), pair("1280", "720"));
+        assert_eq!(size("1280", "-1"), pair("1280", "720"));
+        assert_eq!(size(" 854 ", "-2"), pair("854", "480"));
+        assert_eq!(size("", "720"), pair("1280", "720"));
+        assert_eq!(size("1280", "536"), pair("1280", "536"));
+        assert_eq!(size("", ""), None);
+        assert_eq!(size("-2", "-1"), None);
+
+        // Without a probed source ffmpeg works it out
+        assert_eq!(scale_size("1280", "", None), pair("1280", "-2"));
+        assert_eq!(scale_size("-1", "720", None), pair("-2", "720"));
+    }
+
+    #[test]
+    fn max_bitrate_is_checked() {
+        assert_eq!(max_bitrate_problem("", ""), None);
+        assert_eq!(max_bitrate_problem(" 8000 ", "2000"), None);
+        assert!(max_bitrate_problem("8 Mbps", "").is_some());
+        assert!(max_bitrate_problem("0", "").is_some());
+        assert!(max_bitrate_problem("8000", "-1").is_some());
+        assert!(max_bitrate_problem("", "2000").is_some());
+
+        assert!(max_bitrate_warning(800, (3840, 2160)).is_some());
+        assert_eq!(max_bitrate_warning(800, (1920, 1080)), None);
+        assert!(max_bitrate_warning(200, (1920, 1080)).is_some());
+    }
+
+    #[test]
+    fn target_bitrate_is_checked() {
+        assert_eq!(target_bitrate_problem(" 4000 "), None);
+        assert!(target_bitrate_problem("").is_some());
+        assert!(target_bitrate_problem("0").is_some());
+        assert!(target_bitrate_problem("4.5M").is_some());
+    }
+
+    #[test]
+    fn scene_lengths_are_checked() {
+        assert_eq!(scene_length_problem("", ""), None);
+        assert_eq!(scene_length_problem("24", "240"), None);
+        assert_eq!(scene_length_problem("48", " "), None);
+        assert_eq!(scene_length_problem("48", "0"), None); // No extra splits
+        assert!(scene_length_problem("240", "24").is_some());
+        assert!(scene_length_problem("0", "").is_some());
+        assert!(scene_length_problem("1.5", "").is_some());
+        assert!(scene_length_problem("", "ten").is_some());
+    }
+
+    #[test]
+    fn photon_noise_size_is_checked() {
+        assert_eq!(photon_noise_size_problem("", " "), None);
+        assert_eq!(photon_noise_size_problem("1920", "1036"), None);
+        assert!(photon_noise_size_problem("1920", "").is_some());
+        assert!(photon_noise_size_problem("1919", "1080").is_some());
+        assert!(photon_noise_size_problem("1920", "0").is_some());
+        assert!(photon_noise_size_problem("1920", "-2").is_some());
+    }
+
+    #[test]
+    fn photon_noise_iso_becomes_a_strength() {
+        assert_eq!(photon_noise_strength("800"), Some(8));
+        assert_eq!(photon_noise_strength(" 150 "), Some(2));
+        assert_eq!(photon_noise_strength("0"), Some(0));
+        assert_eq!(photon_noise_strength("12800"), Some(MAX_PHOTON_NOISE));
+        assert_eq!(photon_noise_strength("ISO 800"), None);
+        assert!(photon_noise_problem("8.5").is_some());
+        assert!(photon_noise_warning("12800").is_some());
+        assert!(photon_noise_warning("20").is_some());
+        assert!(photon_noise_warning("6400").is_none());
+    }
+
+    #[test]
+    fn target_quality_bounds_are_checked() {
+        let svt = Encoder::SvtAv1;
+        assert_eq!(svt.target_quality_problem("95", "", ""), None);
+        assert_eq!(svt.target_quality_problem("95", "10", "40"), None);
+        assert_eq!(svt.target_quality_problem("95", "30", "30"), None);
+        assert_eq!(
+            svt.target_quality_problem("95", "40", "10"),
+            Some(String::from("Min Q (40) is above Max Q (10)"))
+        );
+        assert!(svt.target_quality_problem("95", "", "71").is_some());
+        assert!(svt.target_quality_problem("95", "a", "").is_some());
+        assert!(svt.target_quality_problem("101", "", "").is_some());
+        // rav1e's quantizer goes further
+        assert_eq!(Encoder::Rav1e.target_quality_problem("95", "", "200"), None);
+    }
+
+    #[test]
+    fn matching_color_metadata_passes() {
+        assert!(color_metadata_problems(
+            ColorPrimaries::Bt709,
+            TransferCharacteristics::Bt709,
+            MatrixCoefficients::Bt709
+        )
+        .is_empty());
+        assert!(color_metadata_problems(
+            ColorPrimaries::Bt2020,
+            TransferCharacteristics::Smpte2084,
+            MatrixCoefficients::Bt2020Ncl
+        )
+        .is_empty());
+        assert!(color_metadata_problems(
+            ColorPrimaries::Bt2020,
+            TransferCharacteristics::Hlg,
+            MatrixCoefficients::Ictcp
+        )
+        .is_empty());
+        assert!(color_metadata_problems(
+            ColorPrimaries::default(),
+            TransferCharacteristics::default(),
+            MatrixCoefficients::default()
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn pq_with_bt709_is_flagged() {
+        let problems = color_metadata_problems(
+            ColorPrimaries::Bt709,
+            TransferCharacteristics::Smpte2084,
+            MatrixCoefficients::Bt709,
+        );
+
+        assert_eq!(problems.len(), 2);
+        assert!(problems[0].contains("BT.2020 primaries"));
+        assert!(problems[1].contains("BT.2020 matrix"));
+    }
+
+    #[test]
+    fn mismatched_primaries_and_matrix_are_flagged() {
+        assert_eq!(
+            color_metadata_problems(
+                ColorPrimaries::Bt2020,
+                TransferCharacteristics::Bt202010,
+                MatrixCoefficients::Bt709
+            )
+            .len(),
+            1
+        );
+        assert_eq!(
+            color_metadata_problems(
+                ColorPrimaries::Bt709,
+                TransferCharacteristics::Bt709,
+                MatrixCoefficients::Bt2020Ncl
+            )
+            .len(),
+            1
+        );
+    }
+}
@@ -0,0 +1,161 @@
+use std::path::Path;
+use std::sync::mpsc;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::encoding::{format_duration, EncodeSession};
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum NotifyEvent {
+    JobStarted,
+    JobFinished,
+    JobFailed,
+    QueueEmpty,
+}
+
+impl NotifyEvent {
+    pub fn as_str(&self) -> &str {
+        match self {
+            NotifyEvent::JobStarted => "job_started",
+            NotifyEvent::JobFinished => "job_finished",
+            NotifyEvent::JobFailed => "job_failed",
+            NotifyEvent::QueueEmpty => "queue_empty",
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotificationSettings {
+    pub webhook_url: String,
+    pub job_started: bool,
+    pub job_finished: bool,
+    pub job_failed: bool,
+    pub queue_empty: bool,
+}
+
+impl Default for NotificationSettings {
+    fn default() -> Self {
+        NotificationSettings {
+            webhook_url: String::new(),
+            job_started: false,
+            job_finished: true,
+            job_failed: true,
+            queue_empty: true,
+        }
+    }
+}
+
+impl NotificationSettings {
+    fn wants(&self, event: NotifyEvent) -> bool {
+        !self.webhook_url.trim().is_empty()
+            && match event {
+                NotifyEvent::JobStarted => self.job_started,
+                NotifyEvent::JobFinished => self.job_finished,
+                NotifyEvent::JobFailed => self.job_failed,
+                NotifyEvent::QueueEmpty => self.queue_empty,
+            }
+    }
+}
+
+// What gets posted about a single encode
+#[derive(Default)]
+pub struct JobReport {
+    pub filename: String,
+    pub duration: Option<Duration>,
+    pub size: Option<u64>, // In bytes
+    pub average_fps: Option<f64>,
+    pub status: String,
+}
+
+impl JobReport {
+    pub fn new(output_file: &str, session: Option<&EncodeSession>, status: &str) -> Self {
+        let duration = session.map(|session| session.started_at.elapsed());
+        let average_fps = match (session.and_then(|s| s.encoded_frames), duration) {
+            (Some(frames), Some(duration)) if duration.as_secs_f64() > 0.0 => {
+                Some(frames as f64 / duration.as_secs_f64())
+            }
+            _ => None,
+        };
+
+        JobReport {
+            filename: Path::new(output_file)
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_else(|| output_file.to_string()),
+            duration,
+            size: std::fs::metadata(output_file).map(|m| m.len()).ok(),
+            average_fps,
+            status: status.to_string(),
+        }
+    }
+
+    fn message(&self, event: NotifyEvent) -> String {
+        let mut message = match event {
+            NotifyEvent::JobStarted => format!("▶ Started encoding {}", self.filename),
+            NotifyEvent::JobFinished => format!("✅ Finished encoding {}", self.filename),
+            NotifyEvent::JobFailed => format!("❌ {} failed: {}", self.filename, self.status),
+            NotifyEvent::QueueEmpty => String::from("🏁 The queue is empty"),
+        };
+        if let Some(duration) = self.duration {
+            message.push_str(&format!(" in {}", format_duration(duration)));
+        }
+        if let Some(size) = self.size {
+            message.push_str(&format!(", {:.1} MiB", size as f64 / 1024.0 / 1024.0));
+        }
+        if let Some(fps) = self.average_fps {
+            message.push_str(&format!(", {:.2} fps", fps));
+        }
+
+        message
+    }
+}
+
+// Posts webhook notifications off the UI thread. Delivery failures come back through
+// `errors()` for the log, they never affect the encode.
+pub struct Notifier {
+    sender: mpsc::Sender<String>,
+    receiver: mpsc::Receiver<String>,
+}
+
+impl Default for Notifier {
+    fn default() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Notifier { sender, receiver }
+    }
+}
+
+impl Notifier {
+    pub fn send(&self, settings: &NotificationSettings, event: NotifyEvent, report: JobReport) {
+        if !settings.wants(event) {
+            return;
+        }
+
+        // "content" is what Discord shows, the other fields are for anything else listening
+        let payload = serde_json::json!({
+            "content": report.message(event),
+            "event": event.as_str(),
+            "filename": report.filename,
+            "duration_seconds": report.duration.map(|d| d.as_secs()),
+            "size_bytes": report.size,
+            "average_fps": report.average_fps,
+            "status": report.status,
+        });
+        let url = settings.webhook_url.trim().to_string();
+        let errors = self.sender.clone();
+
+        std::thread::spawn(move || {
+            let result = ureq::post(&url)
+                .timeout(Duration::from_secs(15))
+                .send_json(payload);
+            if let Err(e) = result {
+                let _ = errors.send(format!("Couldn't deliver the webhook notification: {}", e));
+            }
+        });
+    }
+
+    pub fn errors(&self) -> impl Iterator<Item = String> + '_ {
+        self.receiver.try_iter()
+    }
+}
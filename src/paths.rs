@@ -0,0 +1,162 @@
+use std::path::{Path, PathBuf};
+
+use regex::{Captures, Regex};
+
+// Expands what a shell would in a pasted path: a leading ~ or ~user, and $VAR or ${VAR}
+// (%VAR% on Windows). Unknown users and variables are left as they are.
+pub fn expand_path(path: &str) -> PathBuf {
+    let path = expand_home(path);
+
+    let re = if cfg!(windows) {
+        Regex::new(r"\$\{(\w+)\}|\$(\w+)|%(\w+)%").unwrap()
+    } else {
+        Regex::new(r"\$\{(\w+)\}|\$(\w+)").unwrap()
+    };
+    let expanded = re.replace_all(&path, |caps: &Captures| {
+        let name = caps
+            .get(1)
+            .or(caps.get(2))
+            .or(caps.get(3))
+            .map_or("", |m| m.as_str());
+        std::env::var(name).unwrap_or_else(|_| caps[0].to_string())
+    });
+
+    PathBuf::from(expanded.as_ref())
+}
+
+// For Command args and other places that take a string
+pub fn expand_path_string(path: &str) -> String {
+    expand_path(path).display().to_string()
+}
+
+fn expand_home(path: &str) -> String {
+    let Some(rest) = path.strip_prefix('~') else {
+        return path.to_string();
+    };
+    let (user, rest) = match rest.find(['/', '\\']) {
+        Some(index) => rest.split_at(index),
+        None => (rest, ""),
+    };
+
+    let home = if user.is_empty() {
+        directories::BaseDirs::new().map(|dirs| dirs.home_dir().to_path_buf())
+    } else {
+        user_home(user)
+    };
+    match home {
+        Some(home) => format!("{}{}", home.display(), rest),
+        None => path.to_string(),
+    }
+}
+
+#[cfg(unix)]
+fn user_home(user: &str) -> Option<PathBuf> {
+    use std::ffi::{CStr, CString};
+    use std::os::unix::ffi::OsStrExt;
+
+    let name = CString::new(user).ok()?;
+    // getpwnam's result points into static storage, so it's copied out right away
+    unsafe {
+        let entry = libc::getpwnam(name.as_ptr());
+        if entry.is_null() || (*entry).pw_dir.is_null() {
+            return None;
+        }
+        let dir = CStr::from_ptr((*entry).pw_dir);
+        Some(PathBuf::from(std::ffi::OsStr::from_bytes(dir.to_bytes())))
+    }
+}
+
+// Other users' profiles sit next to this one's
+#[cfg(not(unix))]
+fn user_home(user: &str) -> Option<PathBuf> {
+    let home = directories::BaseDirs::new()?.home_dir().to_path_buf();
+    home.parent().map(|profiles| profiles.join(user))
+}
+
+// Whether a file can be created in the folder. Permissions alone don't tell, e.g. on a
+// read-only mount, so a file is written and removed again.
+pub fn is_writable_dir(dir: &Path) -> bool {
+    let probe = dir.join(format!(".av1studio-write-test-{}", std::process::id()));
+    match std::fs::File::create(&probe) {
+        Ok(_) => {
+            let _ = std::fs::remove_file(&probe);
+            true
+        }
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn home() -> PathBuf {
+        directories::BaseDirs::new()
+            .unwrap()
+            .home_dir()
+            .to_path_buf()
+    }
+
+    #[test]
+    fn checks_folders_for_writing() {
+        let dir = std::env::temp_dir().join(format!("av1studio-writable-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        assert!(!is_writable_dir(&dir));
+
+        std::fs::create_dir_all(&dir).unwrap();
+        assert!(is_writable_dir(&dir));
+        // The probe file doesn't stay behind
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn expands_the_home_folder() {
+        assert_eq!(expand_path("~"), home());
+        assert_eq!(expand_path("~/videos/in.mkv"), home().join("videos/in.mkv"));
+        // Only at the start
+        assert_eq!(expand_path("/tmp/~/in.mkv"), PathBuf::from("/tmp/~/in.mkv"));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn expands_other_users_home_folders() {
+        assert_eq!(expand_path("~root/in.mkv"), PathBuf::from("/root/in.mkv"));
+        assert_eq!(
+            expand_path("~no-such-user-av1studio/in.mkv"),
+            PathBuf::from("~no-such-user-av1studio/in.mkv")
+        );
+    }
+
+    #[test]
+    fn expands_variables() {
+        std::env::set_var("AV1STUDIO_TEST_DIR", "/mnt/media");
+        assert_eq!(
+            expand_path("$AV1STUDIO_TEST_DIR/in.mkv"),
+            PathBuf::from("/mnt/media/in.mkv")
+        );
+        assert_eq!(
+            expand_path("${AV1STUDIO_TEST_DIR}_old/in.mkv"),
+            PathBuf::from("/mnt/media_old/in.mkv")
+        );
+        assert_eq!(
+            expand_path("$AV1STUDIO_TEST_UNSET/in.mkv"),
+            PathBuf::from("$AV1STUDIO_TEST_UNSET/in.mkv")
+        );
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn expands_windows_variables() {
+        std::env::set_var("AV1STUDIO_TEST_WIN", r"D:\Media");
+        assert_eq!(
+            expand_path(r"%AV1STUDIO_TEST_WIN%\in.mkv"),
+            PathBuf::from(r"D:\Media\in.mkv")
+        );
+        assert_eq!(
+            expand_path(r"%AV1STUDIO_TEST_UNSET%\in.mkv"),
+            PathBuf::from(r"%AV1STUDIO_TEST_UNSET%\in.mkv")
+        );
+    }
+}
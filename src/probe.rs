@@ -0,0 +1,351 @@
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::LazyLock;
+
+use egui::ColorImage;
+use serde::Deserialize;
+
+// The ffmpeg and ffprobe AV1Studio runs itself: the configured ffmpeg with the ffprobe next to
+// it, or both from the PATH
+#[derive(Clone, Debug, PartialEq)]
+pub struct FfmpegPaths {
+    pub ffmpeg: PathBuf,
+    pub ffprobe: PathBuf,
+}
+
+impl Default for FfmpegPaths {
+    fn default() -> Self {
+        FfmpegPaths::new(Path::new(""))
+    }
+}
+
+impl FfmpegPaths {
+    pub fn new(ffmpeg: &Path) -> Self {
+        match ffmpeg.parent() {
+            _ if ffmpeg.as_os_str().is_empty() => FfmpegPaths {
+                ffmpeg: PathBuf::from("ffmpeg"),
+                ffprobe: PathBuf::from("ffprobe"),
+            },
+            Some(dir) => FfmpegPaths {
+                ffmpeg: ffmpeg.to_path_buf(),
+                ffprobe: dir.join(format!("ffprobe{}", std::env::consts::EXE_SUFFIX)),
+            },
+            None => FfmpegPaths {
+                ffmpeg: ffmpeg.to_path_buf(),
+                ffprobe: PathBuf::from("ffprobe"),
+            },
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct MediaInfo {
+    pub duration: Option<f64>, // In seconds
+    pub frame_count: Option<u64>,
+    pub fps: Option<f64>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub pixel_format: Option<String>,
+    pub chroma_location: Option<String>,
+    pub field_order: Option<String>, // ffprobe's, e.g. "progressive" or "tt"
+    pub color_transfer: Option<String>, // ffprobe's, e.g. "bt709" or "smpte2084"
+    pub color_primaries: Option<String>, // ffprobe's, e.g. "bt2020"
+    pub color_space: Option<String>, // ffprobe's name for the matrix, e.g. "bt2020nc"
+    pub codec: Option<String>,
+    pub bitrate: Option<u64>, // In bits per second
+    pub audio_tracks: Vec<AudioTrack>,
+}
+
+impl MediaInfo {
+    // Top or bottom field first, in either coding order
+    pub fn is_interlaced(&self) -> bool {
+        matches!(self.field_order.as_deref(), Some("tt" | "bb" | "tb" | "bt"))
+    }
+
+    // PQ or HLG
+    pub fn is_hdr(&self) -> bool {
+        matches!(
+            self.color_transfer.as_deref(),
+            Some("smpte2084" | "arib-std-b67")
+        )
+    }
+
+    // Bits per component of the pix_fmt, e.g. 10 for "yuv420p10le". Planar formats without a
+    // depth in the name are 8-bit. Packed formats name the whole pixel's bits instead, so only
+    // the common ones are known.
+    pub fn bit_depth(&self) -> Option<u32> {
+        static PLANAR: LazyLock<regex::Regex> = LazyLock::new(|| {
+            regex::Regex::new(r"^(?:yuva?j?4\d\dp|gbra?p|gray|ya)(\d+)?(?:le|be)?$").unwrap()
+        });
+        let pix_fmt = self.pixel_format.as_deref()?;
+        if let Some(caps) = PLANAR.captures(pix_fmt) {
+            return Some(caps.get(1).map_or(8, |m| m.as_str().parse().unwrap_or(8)));
+        }
+
+        match pix_fmt {
+            "nv12" | "nv21" | "yuyv422" | "uyvy422" | "rgb24" | "bgr24" | "rgba" | "bgra"
+            | "argb" | "abgr" | "rgb0" | "bgr0" | "0rgb" | "0bgr" => Some(8),
+            "p010le" | "p010be" | "x2rgb10le" | "x2bgr10le" => Some(10),
+            "p012le" | "p012be" => Some(12),
+            "p016le" | "p016be" | "rgb48le" | "rgb48be" | "bgr48le" | "bgr48be" | "rgba64le"
+            | "rgba64be" | "bgra64le" | "bgra64be" => Some(16),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct AudioTrack {
+    pub codec: Option<String>,
+    pub channels: Option<u32>,
+    pub language: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct FfprobeOutput {
+    #[serde(default)]
+    format: Option<FfprobeFormat>,
+    #[serde(default)]
+    streams: Vec<FfprobeStream>,
+}
+
+#[derive(Deserialize)]
+struct FfprobeFormat {
+    duration: Option<String>,
+    bit_rate: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct FfprobeStream {
+    codec_type: Option<String>,
+    codec_name: Option<String>,
+    width: Option<u32>,
+    height: Option<u32>,
+    pix_fmt: Option<String>,
+    chroma_location: Option<String>,
+    field_order: Option<String>,
+    color_transfer: Option<String>,
+    color_primaries: Option<String>,
+    color_space: Option<String>,
+    avg_frame_rate: Option<String>,
+    r_frame_rate: Option<String>,
+    nb_frames: Option<String>,
+    duration: Option<String>,
+    channels: Option<u32>,
+    tags: std::collections::HashMap<String, String>,
+}
+
+// ffprobe reports frame rates as fractions, e.g. "24000/1001"
+fn parse_frame_rate(rate: &str) -> Option<f64> {
+    let (numerator, denominator) = rate.split_once('/').unwrap_or((rate, "1"));
+    let numerator: f64 = numerator.parse().ok()?;
+    let denominator: f64 = denominator.parse().ok()?;
+
+    (numerator > 0.0 && denominator > 0.0).then(|| numerator / denominator)
+}
+
+pub fn probe_input(
+    tools: &FfmpegPaths,
+    path: &str,
+) -> Result<MediaInfo, Box<dyn std::error::Error>> {
+    let output = Command::new(&tools.ffprobe)
+        .args([
+            "-v",
+            "error",
+            "-print_format",
+            "json",
+            "-show_format",
+            "-show_streams",
+        ])
+        .arg(path)
+        .stdin(Stdio::null())
+        .output()?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "ffprobe failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+        .into());
+    }
+
+    parse_probe_output(&output.stdout)
+}
+
+// ffprobe's -print_format json output
+pub fn parse_probe_output(json: &[u8]) -> Result<MediaInfo, Box<dyn std::error::Error>> {
+    let parsed: FfprobeOutput = serde_json::from_slice(json)?;
+    let format = parsed.format;
+    let video = parsed
+        .streams
+        .iter()
+        .find(|s| s.codec_type.as_deref() == Some("video"));
+
+    let mut info = MediaInfo {
+        duration: format
+            .as_ref()
+            .and_then(|f| f.duration.as_ref())
+            .or(video.and_then(|v| v.duration.as_ref()))
+            .and_then(|d| d.parse().ok()),
+        bitrate: format
+            .as_ref()
+            .and_then(|f| f.bit_rate.as_ref())
+            .and_then(|b| b.parse().ok()),
+        audio_tracks: parsed
+            .streams
+            .iter()
+            .filter(|s| s.codec_type.as_deref() == Some("audio"))
+            .map(|s| AudioTrack {
+                codec: s.codec_name.clone(),
+                channels: s.channels,
+                language: s.tags.get("language").cloned(),
+            })
+            .collect(),
+        ..MediaInfo::default()
+    };
+
+    if let Some(video) = video {
+        info.codec = video.codec_name.clone();
+        info.width = video.width;
+        info.height = video.height;
+        info.pixel_format = video.pix_fmt.clone();
+        info.chroma_location = video.chroma_location.clone();
+        info.field_order = video.field_order.clone();
+        info.color_transfer = video.color_transfer.clone();
+        info.color_primaries = video.color_primaries.clone();
+        info.color_space = video.color_space.clone();
+        info.fps = video
+            .avg_frame_rate
+            .as_deref()
+            .and_then(parse_frame_rate)
+            .or(video.r_frame_rate.as_deref().and_then(parse_frame_rate));
+        // MKV doesn't store a frame count in the stream header, so fall back to duration × fps
+        info.frame_count = video.nb_frames.as_ref().and_then(|n| n.parse().ok()).or(
+            match (info.duration, info.fps) {
+                (Some(duration), Some(fps)) => Some((duration * fps).round() as u64),
+                _ => None,
+            },
+        );
+    }
+
+    Ok(info)
+}
+
+pub fn extract_frame(
+    tools: &FfmpegPaths,
+    path: &str,
+    timestamp: f64,
+) -> Result<ColorImage, Box<dyn std::error::Error>> {
+    let output = Command::new(&tools.ffmpeg)
+        .args(["-v", "error", "-ss"])
+        .arg(format!("{:.3}", timestamp))
+        .arg("-i")
+        .arg(path)
+        .args(["-frames:v", "1", "-f", "image2pipe", "-vcodec", "png", "-"])
+        .stdin(Stdio::null())
+        .output()?;
+
+    if !output.status.success() || output.stdout.is_empty() {
+        return Err(format!(
+            "ffmpeg could not extract a frame: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+        .into());
+    }
+
+    let image = image::load_from_memory_with_format(&output.stdout, image::ImageFormat::Png)?;
+    let rgba = image.to_rgba8();
+    let size = [rgba.width() as usize, rgba.height() as usize];
+
+    Ok(ColorImage::from_rgba_unmultiplied(size, rgba.as_raw()))
+}
+
+// Stream-copies the first video track of `length` seconds from `start` on, so it's fast but
+// snaps to keyframes
+pub fn cut_sample(
+    tools: &FfmpegPaths,
+    input: &str,
+    start: f64,
+    length: f64,
+    output: &Path,
+) -> std::io::Result<Child> {
+    Command::new(&tools.ffmpeg)
+        .args(["-v", "error", "-y", "-ss"])
+        .arg(format!("{:.3}", start))
+        .arg("-t")
+        .arg(format!("{:.3}", length))
+        .arg("-i")
+        .arg(input)
+        .args(["-map", "0:v:0", "-c", "copy"])
+        .arg(output)
+        .stdin(Stdio::null())
+        .spawn()
+}
+
+pub fn format_timestamp(seconds: f64) -> String {
+    let total = seconds.max(0.0) as u64;
+    format!(
+        "{}:{:02}:{:02}",
+        total / 3600,
+        (total % 3600) / 60,
+        total % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ffprobe_sits_next_to_the_configured_ffmpeg() {
+        let tools = FfmpegPaths::new(Path::new("/opt/ffmpeg-vmaf/bin/ffmpeg"));
+        assert_eq!(tools.ffmpeg, PathBuf::from("/opt/ffmpeg-vmaf/bin/ffmpeg"));
+        assert_eq!(
+            tools.ffprobe,
+            Path::new("/opt/ffmpeg-vmaf/bin")
+                .join(format!("ffprobe{}", std::env::consts::EXE_SUFFIX))
+        );
+
+        let path = FfmpegPaths::default();
+        assert_eq!(path.ffmpeg, PathBuf::from("ffmpeg"));
+        assert_eq!(path.ffprobe, PathBuf::from("ffprobe"));
+    }
+
+    #[test]
+    fn bit_depth_is_per_component() {
+        let depth = |pix_fmt: &str| {
+            MediaInfo {
+                pixel_format: Some(pix_fmt.to_string()),
+                ..MediaInfo::default()
+            }
+            .bit_depth()
+        };
+        assert_eq!(depth("yuvj420p"), Some(8));
+        assert_eq!(depth("yuv444p12le"), Some(12));
+        assert_eq!(depth("gbrp10be"), Some(10));
+        assert_eq!(depth("p010le"), Some(10));
+        assert_eq!(depth("rgb48le"), Some(16));
+        assert_eq!(depth("rgb565le"), None);
+    }
+
+    #[test]
+    fn reads_the_color_metadata() {
+        let sdr = parse_probe_output(include_bytes!("../tests/fixtures/ffprobe_sdr.json")).unwrap();
+        assert_eq!(sdr.color_primaries.as_deref(), Some("bt709"));
+        assert_eq!(sdr.bit_depth(), Some(8));
+        assert!(!sdr.is_hdr());
+        assert_eq!(sdr.audio_tracks.len(), 1);
+
+        let hdr10 =
+            parse_probe_output(include_bytes!("../tests/fixtures/ffprobe_hdr10.json")).unwrap();
+        assert_eq!(hdr10.color_transfer.as_deref(), Some("smpte2084"));
+        assert_eq!(hdr10.color_space.as_deref(), Some("bt2020nc"));
+        assert_eq!(hdr10.bit_depth(), Some(10));
+        assert!(hdr10.is_hdr());
+
+        let hlg = parse_probe_output(include_bytes!("../tests/fixtures/ffprobe_hlg.json")).unwrap();
+        assert!(hlg.is_hdr());
+        assert_eq!(hlg.frame_count, Some(1500));
+    }
+}
@@ -0,0 +1,576 @@
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::app::AV1StudioPreset;
+use crate::config::config_dir;
+use crate::encoding::EncodeSession;
+use crate::log::EncodeLog;
+use crate::notify::JobReport;
+use crate::recent::canonical;
+use crate::stats::EncodeRecord;
+
+// Extensions picked up when adding a whole folder to the queue
+pub const VIDEO_EXTENSIONS: &[&str] = &[
+    "mkv", "mp4", "m4v", "webm", "mov", "avi", "ts", "m2ts", "y4m", "vpy",
+];
+
+#[derive(PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
+pub enum JobStatus {
+    Pending,
+    Running,
+    Done,
+    Failed(String),
+    Cancelled,
+    Interrupted, // Was running when AV1Studio closed or crashed
+}
+
+impl JobStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JobStatus::Pending => "Pending",
+            JobStatus::Running => "Running",
+            JobStatus::Done => "Done",
+            JobStatus::Failed(_) => "Failed",
+            JobStatus::Cancelled => "Cancelled",
+            JobStatus::Interrupted => "Interrupted",
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self,
+            JobStatus::Done | JobStatus::Failed(_) | JobStatus::Cancelled | JobStatus::Interrupted
+        )
+    }
+}
+
+// One encode waiting in (or done with) the queue, with a snapshot of the settings it was added with
+#[derive(Serialize, Deserialize)]
+pub struct QueueJob {
+    pub id: u64,
+    pub input_file: String,
+    pub output_file: String,
+    pub scenes_file: String,
+    pub zones_file: String,
+    pub grain_table: String,
+    pub thread_affinity: String,
+    pub workers: String,
+    #[serde(deserialize_with = "crate::app::deserialize_preset")]
+    pub settings: AV1StudioPreset,
+    pub status: JobStatus,
+    pub temp_dir: String, // Kept apart per job so concurrent encodes don't share av1an's temp files
+    #[serde(default)]
+    pub resume: bool, // Pick up an interrupted encode from its temp files
+
+    #[serde(skip)]
+    pub session: Option<EncodeSession>,
+}
+
+// Bump when the layout of queue.json changes
+const QUEUE_FILE_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+struct QueueFile<J> {
+    version: u32,
+    jobs: J,
+}
+
+impl QueueJob {
+    pub fn is_editable(&self) -> bool {
+        matches!(
+            self.status,
+            JobStatus::Pending | JobStatus::Failed(_) | JobStatus::Cancelled
+        )
+    }
+
+    // A {name} template with the input's container points the output at the input itself, which
+    // the encode would overwrite
+    pub fn overwrites_input(&self) -> bool {
+        canonical(&self.input_file) == canonical(&self.output_file)
+    }
+
+    pub fn edit_blocked_reason(&self) -> &'static str {
+        match self.status {
+            JobStatus::Running => "This job is encoding right now, cancel it first to change its settings.",
+            JobStatus::Done => "This job is already done, changing its settings wouldn't do anything.",
+            JobStatus::Interrupted => "Resuming needs the settings the job was started with. Remove it and add it again to use different ones.",
+            _ => "",
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct EncodeQueue {
+    pub jobs: Vec<QueueJob>,
+    pub running: bool,
+
+    next_id: u64,
+}
+
+impl EncodeQueue {
+    pub fn push(&mut self, mut job: QueueJob) {
+        self.next_id += 1;
+        job.id = self.next_id;
+        if job.temp_dir.is_empty() {
+            job.temp_dir = std::env::temp_dir()
+                .join(format!("av1studio-{}-job{}", std::process::id(), job.id))
+                .display()
+                .to_string();
+        }
+        self.jobs.push(job);
+    }
+
+    // Replaces the queue with jobs restored from a previous session
+    pub fn restore(&mut self, jobs: Vec<QueueJob>) {
+        self.next_id = jobs.iter().map(|job| job.id).max().unwrap_or_default();
+        self.jobs = jobs;
+        self.running = false;
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(&QueueFile {
+            version: QUEUE_FILE_VERSION,
+            jobs: &self.jobs,
+        })
+    }
+
+    pub fn active_count(&self) -> usize {
+        self.jobs.iter().filter(|job| job.session.is_some()).count()
+    }
+
+    pub fn next_pending(&self) -> Option<usize> {
+        self.jobs
+            .iter()
+            .position(|job| job.status == JobStatus::Pending)
+    }
+
+    // Returns a report and a history record for every job that finished since the last poll
+    pub fn poll(&mut self, log: &mut EncodeLog) -> Vec<(JobStatus, JobReport, EncodeRecord)> {
+        let mut finished = Vec::new();
+        for job in &mut self.jobs {
+            let Some(session) = &mut job.session else {
+                continue;
+            };
+
+            session.poll();
+            for line in session.lines.drain(..) {
+                log.push(format!("[job {}] {}", job.id, line));
+            }
+            if session.is_finished() {
+                job.status = if session.succeeded() {
+                    JobStatus::Done
+                } else if job.status == JobStatus::Cancelled {
+                    JobStatus::Cancelled
+                } else {
+                    JobStatus::Failed(match session.status {
+                        Some(status) => format!("av1an exited with {}", status),
+                        None => String::from("av1an stopped unexpectedly"),
+                    })
+                };
+                let status = match &job.status {
+                    JobStatus::Failed(reason) => reason.as_str(),
+                    status => status.as_str(),
+                };
+                finished.push((
+                    job.status.clone(),
+                    JobReport::new(&job.output_file, Some(session), status),
+                    EncodeRecord::new(
+                        &job.input_file,
+                        &job.output_file,
+                        &job.settings,
+                        session,
+                        status,
+                    ),
+                ));
+                job.session = None;
+            }
+        }
+
+        finished
+    }
+
+    // Stops the queue and kills whatever it's encoding right now
+    pub fn cancel(&mut self) {
+        self.running = false;
+        for job in &mut self.jobs {
+            if let Some(session) = &mut job.session {
+                session.cancel();
+                job.status = JobStatus::Cancelled;
+                job.session = None;
+            }
+        }
+    }
+
+    // Stops the queue like cancel() and drops the pending jobs, returns how many were dropped
+    pub fn abort_all(&mut self) -> usize {
+        self.cancel();
+        let count = self.jobs.len();
+        self.jobs.retain(|job| job.status != JobStatus::Pending);
+
+        count - self.jobs.len()
+    }
+
+    pub fn pending_count(&self) -> usize {
+        self.jobs
+            .iter()
+            .filter(|job| job.status == JobStatus::Pending)
+            .count()
+    }
+
+    pub fn cancel_job(&mut self, id: u64) {
+        if let Some(job) = self.jobs.iter_mut().find(|job| job.id == id) {
+            if let Some(session) = &mut job.session {
+                session.cancel();
+                job.status = JobStatus::Cancelled;
+                job.session = None;
+            }
+        }
+    }
+
+    // Encoded and total frames over every running job
+    pub fn progress(&self) -> (u32, u32) {
+        self.jobs
+            .iter()
+            .filter_map(|job| job.session.as_ref())
+            .fold((0, 0), |(encoded, total), session| {
+                (
+                    encoded + session.encoded_frames.unwrap_or_default(),
+                    total + session.total_frames.unwrap_or_default(),
+                )
+            })
+    }
+
+    // Av1an workers the running jobs plus the next pending ones use once `concurrent` jobs run
+    // at a time. Jobs left on automatic (0) workers aren't counted.
+    pub fn planned_workers(&self, concurrent: usize) -> usize {
+        let running = self.jobs.iter().filter(|job| job.session.is_some());
+        let pending = self
+            .jobs
+            .iter()
+            .filter(|job| job.session.is_none() && job.status == JobStatus::Pending);
+
+        running
+            .chain(pending)
+            .take(concurrent.max(1))
+            .filter_map(|job| job.workers.trim().parse::<usize>().ok())
+            .sum()
+    }
+
+    pub fn retry(&mut self, id: u64, resume: bool) {
+        if let Some(job) = self.jobs.iter_mut().find(|job| job.id == id) {
+            job.status = JobStatus::Pending;
+            job.resume = resume;
+        }
+    }
+
+    pub fn remove(&mut self, id: u64) {
+        self.jobs
+            .retain(|job| job.id != id || job.status == JobStatus::Running);
+    }
+
+    pub fn clear_finished(&mut self) {
+        self.jobs.retain(|job| !job.status.is_finished());
+    }
+}
+
+// Jobs that were running when the file was written get marked as interrupted
+pub fn queue_from_json(json: &str) -> Result<Vec<QueueJob>, Box<dyn std::error::Error>> {
+    let file: QueueFile<Vec<QueueJob>> = serde_json::from_str(json)?;
+    if file.version > QUEUE_FILE_VERSION {
+        return Err(format!(
+            "queue file version {} is newer than this AV1Studio",
+            file.version
+        )
+        .into());
+    }
+
+    let mut jobs = file.jobs;
+    for job in &mut jobs {
+        if job.status == JobStatus::Running {
+            job.status = JobStatus::Interrupted;
+        }
+    }
+
+    Ok(jobs)
+}
+
+pub fn queue_file_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("queue.json"))
+}
+
+// Fills in the output filename template for `input`. Relative templates land next to the input.
+pub fn output_path_for(template: &str, input: &Path, settings: &AV1StudioPreset) -> PathBuf {
+    let name = input
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let file_name = template
+        .replace("{name}", &name)
+        .replace("{crf}", &settings.crf.to_string())
+        .replace("{preset}", &settings.preset.to_string());
+
+    // Appended rather than set, with_extension would cut "Show.S01E01.1080p" at its last dot
+    input.parent().unwrap_or(Path::new("")).join(format!(
+        "{}.{}",
+        file_name,
+        settings.output_container.extension()
+    ))
+}
+
+#[derive(Default)]
+pub struct FolderScanResult {
+    pub files: Vec<PathBuf>,
+    pub unreadable: Vec<(PathBuf, String)>,
+}
+
+// State of the "Add Folder" flow: scanning, waiting for confirmation, then the final report
+#[derive(Default)]
+pub struct FolderScan {
+    pub recursive: bool,
+    pub overwrite: bool,
+    pub receiver: Option<mpsc::Receiver<FolderScanResult>>,
+    pub found: Option<FolderScanResult>,
+    pub report: Option<String>,
+}
+
+impl FolderScan {
+    pub fn start(&mut self, dir: PathBuf) {
+        let (sender, receiver) = mpsc::channel();
+        let recursive = self.recursive;
+        std::thread::spawn(move || {
+            let _ = sender.send(scan_folder(&dir, recursive));
+        });
+        self.receiver = Some(receiver);
+        self.found = None;
+        self.report = None;
+    }
+
+    pub fn poll(&mut self) {
+        if let Some(receiver) = &self.receiver {
+            match receiver.try_recv() {
+                Ok(result) => {
+                    self.found = Some(result);
+                    self.receiver = None;
+                }
+                Err(mpsc::TryRecvError::Empty) => {}
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    self.report = Some(String::from("The folder scan stopped unexpectedly"));
+                    self.receiver = None;
+                }
+            }
+        }
+    }
+}
+
+// Collects the video files in `dir`. Anything that can't be read is reported instead of
+// stopping the scan.
+pub fn scan_folder(dir: &Path, recursive: bool) -> FolderScanResult {
+    let mut result = FolderScanResult::default();
+    let mut pending = vec![dir.to_path_buf()];
+
+    while let Some(dir) = pending.pop() {
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                result.unreadable.push((dir, e.to_string()));
+                continue;
+            }
+        };
+
+        for entry in entries {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    result.unreadable.push((dir.clone(), e.to_string()));
+                    continue;
+                }
+            };
+            let path = entry.path();
+
+            match entry.file_type() {
+                Ok(file_type) if file_type.is_dir() => {
+                    if recursive {
+                        pending.push(path);
+                    }
+                }
+                Ok(_) => {
+                    let is_video = path
+                        .extension()
+                        .and_then(|e| e.to_str())
+                        .is_some_and(|e| VIDEO_EXTENSIONS.contains(&e.to_lowercase().as_str()));
+                    if !is_video {
+                        continue;
+                    }
+                    match std::fs::File::open(&path) {
+                        Ok(_) => result.files.push(path),
+                        Err(e) => result.unreadable.push((path, e.to_string())),
+                    }
+                }
+                Err(e) => result.unreadable.push((path, e.to_string())),
+            }
+        }
+    }
+
+    result.files.sort();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{CustomParamsMode, OutputContainer};
+
+    fn job(status: JobStatus) -> QueueJob {
+        QueueJob {
+            id: 0,
+            input_file: String::from("/videos/episode 01.mkv"),
+            output_file: String::from("/videos/episode 01_av1.mkv"),
+            scenes_file: String::new(),
+            zones_file: String::new(),
+            grain_table: String::new(),
+            thread_affinity: String::from("2"),
+            workers: String::from("8"),
+            settings: AV1StudioPreset::default(),
+            status,
+            temp_dir: String::new(),
+            resume: false,
+            session: None,
+        }
+    }
+
+    #[test]
+    fn planned_workers_cover_the_next_concurrent_jobs() {
+        let mut queue = EncodeQueue::default();
+        queue.push(job(JobStatus::Done));
+        queue.push(job(JobStatus::Pending));
+        queue.push(job(JobStatus::Pending));
+        let mut automatic = job(JobStatus::Pending);
+        automatic.workers = String::from("0");
+        queue.push(automatic);
+
+        assert_eq!(queue.planned_workers(1), 8);
+        assert_eq!(queue.planned_workers(2), 16);
+        // Finished jobs don't count, automatic workers can't be
+        assert_eq!(queue.planned_workers(4), 16);
+    }
+
+    #[test]
+    fn abort_all_drops_only_pending_jobs() {
+        let mut queue = EncodeQueue::default();
+        queue.push(job(JobStatus::Done));
+        queue.push(job(JobStatus::Pending));
+        queue.push(job(JobStatus::Interrupted));
+        queue.push(job(JobStatus::Pending));
+        queue.running = true;
+
+        assert_eq!(queue.pending_count(), 2);
+        assert_eq!(queue.abort_all(), 2);
+        assert!(!queue.running);
+        assert_eq!(queue.next_pending(), None);
+        let statuses: Vec<&JobStatus> = queue.jobs.iter().map(|job| &job.status).collect();
+        assert_eq!(statuses, [&JobStatus::Done, &JobStatus::Interrupted]);
+    }
+
+    #[test]
+    fn queue_reloads_identically() {
+        let mut queue = EncodeQueue::default();
+        queue.push(job(JobStatus::Pending));
+        queue.push(job(JobStatus::Done));
+        queue.push(job(JobStatus::Failed(String::from("av1an exited with 1"))));
+        queue.push(job(JobStatus::Interrupted));
+
+        let json = queue.to_json().unwrap();
+        let mut reloaded = EncodeQueue::default();
+        reloaded.restore(queue_from_json(&json).unwrap());
+
+        assert_eq!(reloaded.to_json().unwrap(), json);
+        assert_eq!(reloaded.jobs.len(), 4);
+    }
+
+    #[test]
+    fn running_job_reloads_as_interrupted() {
+        let mut queue = EncodeQueue::default();
+        queue.push(job(JobStatus::Running));
+
+        let jobs = queue_from_json(&queue.to_json().unwrap()).unwrap();
+
+        assert_eq!(jobs[0].status, JobStatus::Interrupted);
+        assert_eq!(jobs[0].temp_dir, queue.jobs[0].temp_dir);
+    }
+
+    #[test]
+    fn restored_queue_continues_ids() {
+        let mut queue = EncodeQueue::default();
+        queue.push(job(JobStatus::Pending));
+        queue.push(job(JobStatus::Pending));
+
+        let mut reloaded = EncodeQueue::default();
+        reloaded.restore(queue_from_json(&queue.to_json().unwrap()).unwrap());
+        reloaded.push(job(JobStatus::Pending));
+
+        assert_eq!(reloaded.jobs[2].id, 3);
+    }
+
+    #[test]
+    fn older_jobs_keep_replacing_the_generated_params() {
+        let mut queue = EncodeQueue::default();
+        let mut old = job(JobStatus::Pending);
+        old.settings.custom_encode_params = String::from("--crf 20 --preset 4");
+        queue.push(old);
+
+        // As written before the settings said what custom parameters do
+        let mut file: serde_json::Value = serde_json::from_str(&queue.to_json().unwrap()).unwrap();
+        let settings = file["jobs"][0]["settings"].as_object_mut().unwrap();
+        settings.insert(String::from("version"), 2.into());
+        settings.remove("custom_params_mode");
+
+        let jobs = queue_from_json(&file.to_string()).unwrap();
+        assert_eq!(
+            jobs[0].settings.custom_params_mode,
+            CustomParamsMode::Replace
+        );
+        assert_eq!(jobs[0].settings.custom_encode_params, "--crf 20 --preset 4");
+    }
+
+    #[test]
+    fn rejects_newer_queue_file() {
+        assert!(queue_from_json(r#"{"version": 99, "jobs": []}"#).is_err());
+    }
+
+    #[test]
+    fn output_keeps_dotted_names() {
+        let settings = AV1StudioPreset::default();
+        let input = Path::new("/videos/Show.S01E01.1080p.mkv");
+
+        assert_eq!(
+            output_path_for("{name}_av1", input, &settings),
+            PathBuf::from("/videos/Show.S01E01.1080p_av1.mkv")
+        );
+        assert_ne!(
+            output_path_for(
+                "{name}_av1",
+                Path::new("/videos/Show.S01E02.1080p.mkv"),
+                &settings
+            ),
+            output_path_for("{name}_av1", input, &settings)
+        );
+    }
+
+    #[test]
+    fn output_equal_to_the_input_is_caught() {
+        let settings = AV1StudioPreset {
+            output_container: OutputContainer::Mkv,
+            ..AV1StudioPreset::default()
+        };
+        let input = Path::new("/videos/episode 01.mkv");
+        let output = output_path_for("{name}", input, &settings);
+        assert_eq!(output, input);
+
+        let mut same = job(JobStatus::Pending);
+        same.output_file = output.display().to_string();
+        assert!(same.overwrites_input());
+        assert!(!job(JobStatus::Pending).overwrites_input());
+    }
+}
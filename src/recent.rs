@@ -0,0 +1,161 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::config_dir;
+use crate::paths::expand_path;
+use crate::session::SessionState;
+
+const MAX_RECENT: usize = 15;
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RecentInput {
+    pub input: String,
+    pub output: String,
+    pub settings: Option<SessionState>, // What the last encode of it started with
+}
+
+// Most recent first, kept in recent.json next to the config
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RecentFiles {
+    pub inputs: Vec<RecentInput>,
+    pub presets: Vec<String>,
+}
+
+impl RecentFiles {
+    // Moves the input to the top. Settings from an earlier encode of it are kept unless new
+    // ones are given.
+    pub fn add_input(&mut self, input: &str, output: &str, settings: Option<SessionState>) {
+        let key = canonical(input);
+        let previous = self
+            .inputs
+            .iter()
+            .position(|recent| canonical(&recent.input) == key)
+            .map(|index| self.inputs.remove(index));
+
+        self.inputs.insert(
+            0,
+            RecentInput {
+                input: input.to_string(),
+                output: output.to_string(),
+                settings: settings.or(previous.and_then(|previous| previous.settings)),
+            },
+        );
+        self.inputs.truncate(MAX_RECENT);
+    }
+
+    pub fn add_preset(&mut self, path: &str) {
+        let key = canonical(path);
+        self.presets.retain(|preset| canonical(preset) != key);
+        self.presets.insert(0, path.to_string());
+        self.presets.truncate(MAX_RECENT);
+    }
+}
+
+// The same file under another spelling, e.g. with ~ or through a symlink, is one entry. Files
+// that are gone can't be resolved, those compare as written.
+pub fn canonical(path: &str) -> PathBuf {
+    let expanded = expand_path(path.trim());
+    std::fs::canonicalize(&expanded).unwrap_or(expanded)
+}
+
+pub fn file_exists(path: &str) -> bool {
+    expand_path(path.trim()).is_file()
+}
+
+// Just the file name, the full path goes into the hover text
+pub fn file_name(path: &str) -> String {
+    Path::new(path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.to_string())
+}
+
+fn recent_file_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("recent.json"))
+}
+
+pub fn load_recent() -> RecentFiles {
+    let Some(path) = recent_file_path().filter(|path| path.exists()) else {
+        return RecentFiles::default();
+    };
+    match std::fs::read_to_string(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|json| serde_json::from_str(&json).map_err(|e| e.to_string()))
+    {
+        Ok(recent) => recent,
+        Err(e) => {
+            eprintln!("ERROR : Couldn't read the recent files {:?}: {}", path, e);
+            RecentFiles::default()
+        }
+    }
+}
+
+pub fn save_recent(recent: &RecentFiles) {
+    let Some(path) = recent_file_path() else {
+        return;
+    };
+    let result = serde_json::to_string_pretty(recent)
+        .map_err(|e| e.to_string())
+        .and_then(|json| {
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+            }
+            std::fs::write(&path, json).map_err(|e| e.to_string())
+        });
+    if let Err(e) = result {
+        eprintln!(
+            "ERROR : Couldn't save the recent files to {:?}: {}",
+            path, e
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inputs_are_deduplicated_by_canonical_path() {
+        let dir = std::env::temp_dir().join(format!("av1studio-recent-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        std::fs::write(dir.join("movie.mkv"), "").unwrap();
+        let direct = dir.join("movie.mkv").display().to_string();
+        let roundabout = dir
+            .join("sub")
+            .join("..")
+            .join("movie.mkv")
+            .display()
+            .to_string();
+
+        let mut recent = RecentFiles::default();
+        recent.add_input(&direct, "/out/a.mkv", Some(SessionState::default()));
+        recent.add_input("/gone/other.mkv", "/out/b.mkv", None);
+        recent.add_input(&roundabout, "/out/c.mkv", None);
+
+        assert_eq!(recent.inputs.len(), 2);
+        assert_eq!(recent.inputs[0].input, roundabout);
+        assert_eq!(recent.inputs[0].output, "/out/c.mkv");
+        // The settings of the earlier encode stay with it
+        assert!(recent.inputs[0].settings.is_some());
+        assert!(!file_exists(&recent.inputs[1].input));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn list_is_bounded() {
+        let mut recent = RecentFiles::default();
+        for n in 0..20 {
+            recent.add_preset(&format!("/presets/{}.yaml", n));
+        }
+        recent.add_preset("/presets/3.yaml");
+
+        assert_eq!(recent.presets.len(), MAX_RECENT);
+        assert_eq!(recent.presets[0], "/presets/3.yaml");
+        assert_eq!(recent.presets[1], "/presets/19.yaml");
+    }
+}
@@ -0,0 +1,125 @@
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::encoding::EncodeSession;
+use crate::paths::expand_path;
+use crate::session::SessionState;
+
+pub const REPORT_VERSION: u32 = 1;
+
+// Everything needed to redo or compare an encode, written next to the output as
+// <output>.report.json
+#[derive(Clone, Serialize, Deserialize)]
+pub struct EncodeReport {
+    pub version: u32,
+    pub av1studio_version: String,
+    pub command: String, // As shown in the Command Preview
+    pub settings: SessionState,
+    pub started: u64, // Unix time in seconds
+    pub finished: Option<u64>,
+    pub duration_seconds: Option<f64>,
+    pub encoded_frames: Option<u32>,
+    pub total_frames: Option<u32>,
+    pub average_fps: Option<f64>,
+    pub vmaf: Option<f64>,       // Only when av1an printed one
+    pub input_size: Option<u64>, // In bytes
+    pub output_size: Option<u64>,
+}
+
+impl EncodeReport {
+    // Taken when the encode starts, the settings may change while it runs
+    pub fn start(command: String, settings: SessionState) -> Self {
+        let input_size = settings
+            .settings
+            .input_file
+            .as_deref()
+            .and_then(|input| std::fs::metadata(expand_path(input)).ok())
+            .map(|metadata| metadata.len());
+
+        EncodeReport {
+            version: REPORT_VERSION,
+            av1studio_version: env!("CARGO_PKG_VERSION").to_string(),
+            command,
+            settings,
+            started: unix_time(SystemTime::now()),
+            finished: None,
+            duration_seconds: None,
+            encoded_frames: None,
+            total_frames: None,
+            average_fps: None,
+            vmaf: None,
+            input_size,
+            output_size: None,
+        }
+    }
+
+    pub fn finish(&mut self, session: &EncodeSession, output: &Path) {
+        let duration = session.started_at.elapsed().as_secs_f64();
+        self.finished = Some(unix_time(SystemTime::now()));
+        self.duration_seconds = Some(duration);
+        self.encoded_frames = session.encoded_frames;
+        self.total_frames = session.total_frames;
+        self.average_fps = session
+            .encoded_frames
+            .filter(|_| duration > 0.0)
+            .map(|frames| frames as f64 / duration);
+        self.vmaf = session.vmaf;
+        self.output_size = std::fs::metadata(output)
+            .ok()
+            .map(|metadata| metadata.len());
+    }
+}
+
+// video.mkv gets video.mkv.report.json
+pub fn report_path(output: &Path) -> PathBuf {
+    let mut name = output.as_os_str().to_owned();
+    name.push(".report.json");
+    PathBuf::from(name)
+}
+
+pub fn write_report(report: &EncodeReport, output: &Path) -> Result<PathBuf, String> {
+    let path = report_path(output);
+    let json = serde_json::to_string_pretty(report).map_err(|e| e.to_string())?;
+    std::fs::write(&path, json).map_err(|e| format!("{:?}: {}", path, e))?;
+
+    Ok(path)
+}
+
+fn unix_time(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|since| since.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report_sits_next_to_the_output() {
+        assert_eq!(
+            report_path(Path::new("/videos/out.mkv")),
+            PathBuf::from("/videos/out.mkv.report.json")
+        );
+    }
+
+    #[test]
+    fn report_keeps_the_settings() {
+        let mut settings = SessionState {
+            workers: String::from("4"),
+            ..SessionState::default()
+        };
+        settings.settings.crf = 24.0;
+        let report = EncodeReport::start(String::from("av1an -i in.mkv"), settings);
+        let json = serde_json::to_string(&report).unwrap();
+
+        let loaded: EncodeReport = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.version, REPORT_VERSION);
+        assert_eq!(loaded.command, "av1an -i in.mkv");
+        assert_eq!(loaded.settings.workers, "4");
+        assert_eq!(loaded.settings.settings.crf, 24.0);
+        assert_eq!(loaded.vmaf, None);
+    }
+}
@@ -0,0 +1,140 @@
+use std::path::PathBuf;
+use std::process::{Child, Command};
+
+use crate::encoding::{start_failure, EncodeSession};
+use crate::probe::{cut_sample, FfmpegPaths};
+
+pub enum SampleStage {
+    Idle,
+    Cutting(Child),
+    Encoding(EncodeSession),
+    Done,
+    Failed(String),
+}
+
+// A short encode of part of the input with the current settings, to check quality before
+// committing to the whole thing
+pub struct SampleEncode {
+    pub timestamp: f64, // Middle of the sample
+    pub length: f64,
+    pub stage: SampleStage,
+    pub output: PathBuf,
+
+    temp_dir: PathBuf,
+    encode: Option<Command>,
+}
+
+impl Default for SampleEncode {
+    fn default() -> Self {
+        SampleEncode {
+            timestamp: 0.0,
+            length: 5.0,
+            stage: SampleStage::Idle,
+            output: PathBuf::new(),
+            temp_dir: std::env::temp_dir().join(format!("av1studio-sample-{}", std::process::id())),
+            encode: None,
+        }
+    }
+}
+
+impl SampleEncode {
+    pub fn is_running(&self) -> bool {
+        matches!(
+            self.stage,
+            SampleStage::Cutting(_) | SampleStage::Encoding(_)
+        )
+    }
+
+    pub fn source_path(&self) -> PathBuf {
+        self.temp_dir.join("source.mkv")
+    }
+
+    pub fn output_path(&self, extension: &str) -> PathBuf {
+        self.temp_dir.join(format!("sample.{}", extension))
+    }
+
+    // `encode` is the av1an command reading from `source_path()` and writing to `output`
+    pub fn start(&mut self, tools: &FfmpegPaths, input: &str, output: PathBuf, encode: Command) {
+        self.cancel();
+        if self.temp_dir.exists() {
+            let _ = std::fs::remove_dir_all(&self.temp_dir);
+        }
+        if let Err(e) = std::fs::create_dir_all(&self.temp_dir) {
+            self.stage = SampleStage::Failed(format!("Couldn't create {:?}: {}", self.temp_dir, e));
+            return;
+        }
+
+        let start = (self.timestamp - self.length / 2.0).max(0.0);
+        self.stage = match cut_sample(tools, input, start, self.length, &self.source_path()) {
+            Ok(child) => SampleStage::Cutting(child),
+            Err(e) => SampleStage::Failed(format!("Couldn't start ffmpeg: {}", e)),
+        };
+        self.output = output;
+        self.encode = Some(encode);
+    }
+
+    // True once, right when the sample finished encoding
+    pub fn poll(&mut self) -> bool {
+        let stage = std::mem::replace(&mut self.stage, SampleStage::Idle);
+
+        self.stage = match stage {
+            SampleStage::Cutting(mut child) => match child.try_wait() {
+                Ok(Some(status)) if status.success() => match self.encode.take() {
+                    Some(encode) => match EncodeSession::start(encode) {
+                        Ok(session) => SampleStage::Encoding(session),
+                        Err(e) => SampleStage::Failed(start_failure(&e)),
+                    },
+                    None => SampleStage::Idle,
+                },
+                Ok(Some(status)) => {
+                    SampleStage::Failed(format!("ffmpeg couldn't cut the sample ({})", status))
+                }
+                Ok(None) => SampleStage::Cutting(child),
+                Err(e) => SampleStage::Failed(e.to_string()),
+            },
+            SampleStage::Encoding(mut session) => {
+                session.poll();
+                session.lines.clear(); // Sample encodes stay out of the log
+                if !session.is_finished() {
+                    SampleStage::Encoding(session)
+                } else if session.succeeded() {
+                    self.stage = SampleStage::Done;
+                    return true;
+                } else {
+                    SampleStage::Failed(String::from("The sample failed to encode"))
+                }
+            }
+            stage => stage,
+        };
+
+        false
+    }
+
+    pub fn cancel(&mut self) {
+        match &mut self.stage {
+            SampleStage::Cutting(child) => {
+                let _ = child.kill();
+                let _ = child.wait();
+            }
+            SampleStage::Encoding(session) => session.cancel(),
+            _ => {}
+        }
+        self.stage = SampleStage::Idle;
+        self.encode = None;
+    }
+}
+
+pub fn open_in_default_player(path: &std::path::Path) -> std::io::Result<()> {
+    #[cfg(target_os = "windows")]
+    let mut cmd = {
+        let mut cmd = Command::new("cmd");
+        cmd.args(["/C", "start", ""]);
+        cmd
+    };
+    #[cfg(target_os = "macos")]
+    let mut cmd = Command::new("open");
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let mut cmd = Command::new("xdg-open");
+
+    cmd.arg(path).spawn().map(|_| ())
+}
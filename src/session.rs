@@ -0,0 +1,95 @@
+use std::fs::{File, OpenOptions};
+use std::path::PathBuf;
+
+use fs2::FileExt;
+use serde::{Deserialize, Serialize};
+
+use crate::app::AV1StudioPreset;
+use crate::config::config_dir;
+
+// The fields eframe's storage skips, written while AV1Studio runs. A session file that's still
+// there on startup means the last run didn't exit cleanly.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SessionState {
+    #[serde(deserialize_with = "crate::app::deserialize_preset")]
+    pub settings: AV1StudioPreset, // With the input, output, scenes and zones files
+    pub grain_table: String,
+    pub thread_affinity: String,
+    pub workers: String,
+    pub temp_dir: String,
+}
+
+pub fn session_file_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("session.json"))
+}
+
+// Held by the instance that owns session.json for as long as it runs. The OS drops the lock
+// when the process ends, crashed or not.
+pub struct SessionLock {
+    _file: File,
+}
+
+// None if another instance holds the lock, or it couldn't be taken
+pub fn lock_session() -> Option<SessionLock> {
+    let path = config_dir()?.join("session.lock");
+    let result = match path.parent() {
+        Some(dir) => std::fs::create_dir_all(dir),
+        None => Ok(()),
+    }
+    .and_then(|_| {
+        OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)
+    });
+    match result {
+        Ok(file) => file
+            .try_lock_exclusive()
+            .ok()
+            .map(|_| SessionLock { _file: file }),
+        Err(e) => {
+            eprintln!("ERROR : Couldn't open {:?}: {}", path, e);
+            None
+        }
+    }
+}
+
+// None if there's no session left over, or it couldn't be read
+pub fn load_session() -> Option<SessionState> {
+    let path = session_file_path().filter(|path| path.exists())?;
+    match std::fs::read_to_string(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|json| serde_json::from_str(&json).map_err(|e| e.to_string()))
+    {
+        Ok(session) => Some(session),
+        Err(e) => {
+            eprintln!("ERROR : Couldn't read the last session {:?}: {}", path, e);
+            None
+        }
+    }
+}
+
+pub fn save_session(json: &str) {
+    let Some(path) = session_file_path() else {
+        return;
+    };
+    let result = match path.parent() {
+        Some(dir) => std::fs::create_dir_all(dir),
+        None => Ok(()),
+    }
+    .and_then(|_| std::fs::write(&path, json));
+    if let Err(e) = result {
+        eprintln!("ERROR : Couldn't save the session to {:?}: {}", path, e);
+    }
+}
+
+// On a clean exit
+pub fn remove_session() {
+    if let Some(path) = session_file_path().filter(|path| path.exists()) {
+        if let Err(e) = std::fs::remove_file(&path) {
+            eprintln!("ERROR : Couldn't remove {:?}: {}", path, e);
+        }
+    }
+}
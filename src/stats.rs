@@ -0,0 +1,336 @@
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use serde::{Deserialize, Serialize};
+
+use crate::app::AV1StudioPreset;
+use crate::config::config_dir;
+use crate::encoding::EncodeSession;
+use crate::paths::expand_path;
+
+// One finished encode, as kept in encode_history.json and exported
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EncodeRecord {
+    pub date: String, // When it started, as UTC RFC 3339
+    pub input: String,
+    pub output: String,
+    pub duration_seconds: Option<f64>,
+    pub average_fps: Option<f64>,
+    pub frames: Option<u32>,
+    pub output_size: Option<u64>, // In bytes
+    pub crf: f32,
+    pub preset: f32,
+    pub grain: String,
+    pub encoder_version: String,
+    pub exit_status: String,
+}
+
+// The CSV columns, in the order of the fields
+const COLUMNS: [&str; 12] = [
+    "date",
+    "input",
+    "output",
+    "duration_seconds",
+    "average_fps",
+    "frames",
+    "output_size",
+    "crf",
+    "preset",
+    "grain",
+    "encoder_version",
+    "exit_status",
+];
+
+impl EncodeRecord {
+    pub fn new(
+        input: &str,
+        output: &str,
+        settings: &AV1StudioPreset,
+        session: &EncodeSession,
+        exit_status: &str,
+    ) -> Self {
+        let elapsed = session.started_at.elapsed();
+        let started = SystemTime::now()
+            .checked_sub(elapsed)
+            .unwrap_or(SystemTime::now());
+        let duration = elapsed.as_secs_f64();
+
+        EncodeRecord {
+            date: format_utc(started),
+            input: input.to_string(),
+            output: output.to_string(),
+            duration_seconds: Some(duration),
+            average_fps: session
+                .encoded_frames
+                .filter(|_| duration > 0.0)
+                .map(|frames| frames as f64 / duration),
+            frames: session.encoded_frames,
+            output_size: std::fs::metadata(expand_path(output))
+                .ok()
+                .map(|metadata| metadata.len()),
+            crf: settings.crf,
+            preset: settings.preset,
+            grain: settings.grain_summary(),
+            encoder_version: settings.encoder.as_str().to_string(), // Refined by the app
+            exit_status: exit_status.to_string(),
+        }
+    }
+
+    fn fields(&self) -> [String; 12] {
+        let optional = |value: Option<String>| value.unwrap_or_default();
+        [
+            self.date.clone(),
+            self.input.clone(),
+            self.output.clone(),
+            optional(self.duration_seconds.map(|value| value.to_string())),
+            optional(self.average_fps.map(|value| value.to_string())),
+            optional(self.frames.map(|value| value.to_string())),
+            optional(self.output_size.map(|value| value.to_string())),
+            self.crf.to_string(),
+            self.preset.to_string(),
+            self.grain.clone(),
+            self.encoder_version.clone(),
+            self.exit_status.clone(),
+        ]
+    }
+}
+
+// RFC 4180: fields with commas, quotes or line breaks are quoted, quotes in them doubled
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+pub fn to_csv(records: &[EncodeRecord]) -> String {
+    let mut csv = COLUMNS.join(",");
+    csv.push_str("\r\n");
+    for record in records {
+        let fields: Vec<String> = record
+            .fields()
+            .iter()
+            .map(|field| csv_field(field))
+            .collect();
+        csv.push_str(&fields.join(","));
+        csv.push_str("\r\n");
+    }
+
+    csv
+}
+
+// CSV unless the file ends in .json
+pub fn export_records(records: &[EncodeRecord], path: &Path) -> Result<(), String> {
+    let json = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("json"));
+    let content = if json {
+        serde_json::to_string_pretty(records).map_err(|e| e.to_string())?
+    } else {
+        to_csv(records)
+    };
+
+    std::fs::write(path, content).map_err(|e| format!("{:?}: {}", path, e))
+}
+
+// e.g. 2026-10-16T08:30:00Z
+pub fn format_utc(time: SystemTime) -> String {
+    chrono::DateTime::<chrono::Utc>::from(time)
+        .format("%Y-%m-%dT%H:%M:%SZ")
+        .to_string()
+}
+
+fn history_file_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("encode_history.json"))
+}
+
+pub fn load_history() -> Vec<EncodeRecord> {
+    let Some(path) = history_file_path().filter(|path| path.exists()) else {
+        return Vec::new();
+    };
+    match std::fs::read_to_string(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|json| serde_json::from_str(&json).map_err(|e| e.to_string()))
+    {
+        Ok(records) => records,
+        Err(e) => {
+            eprintln!("ERROR : Couldn't read the encode history {:?}: {}", path, e);
+            Vec::new()
+        }
+    }
+}
+
+pub fn save_history(records: &[EncodeRecord]) {
+    let Some(path) = history_file_path() else {
+        return;
+    };
+    let result = serde_json::to_string_pretty(records)
+        .map_err(|e| e.to_string())
+        .and_then(|json| {
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+            }
+            std::fs::write(&path, json).map_err(|e| e.to_string())
+        });
+    if let Err(e) = result {
+        eprintln!(
+            "ERROR : Couldn't save the encode history to {:?}: {}",
+            path, e
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    // One CSV row back into a record
+    fn record_from_fields(fields: &[String]) -> Result<EncodeRecord, String> {
+        let [date, input, output, duration_seconds, average_fps, frames, output_size, crf, preset, grain, encoder_version, exit_status] =
+            fields
+        else {
+            return Err(format!(
+                "Expected {} columns, found {}",
+                COLUMNS.len(),
+                fields.len()
+            ));
+        };
+        fn optional<T: std::str::FromStr>(name: &str, value: &str) -> Result<Option<T>, String> {
+            match value {
+                "" => Ok(None),
+                value => value
+                    .parse()
+                    .map(Some)
+                    .map_err(|_| format!("{} isn't a number: {}", name, value)),
+            }
+        }
+        let number = |name: &str, value: &str| {
+            value
+                .parse::<f32>()
+                .map_err(|_| format!("{} isn't a number: {}", name, value))
+        };
+
+        Ok(EncodeRecord {
+            date: date.clone(),
+            input: input.clone(),
+            output: output.clone(),
+            duration_seconds: optional("duration_seconds", duration_seconds)?,
+            average_fps: optional("average_fps", average_fps)?,
+            frames: optional("frames", frames)?,
+            output_size: optional("output_size", output_size)?,
+            crf: number("crf", crf)?,
+            preset: number("preset", preset)?,
+            grain: grain.clone(),
+            encoder_version: encoder_version.clone(),
+            exit_status: exit_status.clone(),
+        })
+    }
+
+    // The rows of a CSV file, quoted fields can span lines
+    fn parse_csv_rows(csv: &str) -> Result<Vec<Vec<String>>, String> {
+        let mut rows = Vec::new();
+        let mut row = Vec::new();
+        let mut field = String::new();
+        let mut quoted = false;
+        let mut chars = csv.chars().peekable();
+        while let Some(c) = chars.next() {
+            match (quoted, c) {
+                (true, '"') if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                (true, '"') => quoted = false,
+                (true, c) => field.push(c),
+                (false, '"') if field.is_empty() => quoted = true,
+                (false, ',') => row.push(std::mem::take(&mut field)),
+                (false, '\r') if chars.peek() == Some(&'\n') => {}
+                (false, '\n') => {
+                    row.push(std::mem::take(&mut field));
+                    rows.push(std::mem::take(&mut row));
+                }
+                (false, c) => field.push(c),
+            }
+        }
+        if quoted {
+            return Err(String::from("A quoted field isn't closed"));
+        }
+        if !field.is_empty() || !row.is_empty() {
+            row.push(field);
+            rows.push(row);
+        }
+
+        Ok(rows)
+    }
+
+    // Reads back what to_csv writes
+    fn from_csv(csv: &str) -> Result<Vec<EncodeRecord>, String> {
+        let mut rows = parse_csv_rows(csv)?.into_iter();
+        match rows.next() {
+            Some(header) if header == COLUMNS => {}
+            _ => return Err(String::from("The header row doesn't match")),
+        }
+
+        rows.map(|row| record_from_fields(&row)).collect()
+    }
+
+    fn records() -> Vec<EncodeRecord> {
+        vec![
+            EncodeRecord {
+                date: String::from("2026-10-16T08:30:00Z"),
+                input: String::from("/videos/Tom, Jerry & \"Friends\".mkv"),
+                output: String::from("/encodes/out.mkv"),
+                duration_seconds: Some(3723.456),
+                average_fps: Some(12.25),
+                frames: Some(45600),
+                output_size: Some(734_003_200),
+                crf: 27.25,
+                preset: 4.0,
+                grain: String::from("grain 4"),
+                encoder_version: String::from("SVT-AV1-PSY v2.3.0"),
+                exit_status: String::from("done"),
+            },
+            EncodeRecord {
+                date: String::from("2026-10-16T09:00:00Z"),
+                input: String::from("/videos/two\nlines.mkv"),
+                output: String::from("/encodes/failed.mkv"),
+                crf: 30.0,
+                preset: 6.0,
+                grain: String::from("photon noise ISO 400"),
+                exit_status: String::from("av1an exited with exit status: 1"),
+                ..EncodeRecord::default()
+            },
+        ]
+    }
+
+    #[test]
+    fn csv_round_trips() {
+        let csv = to_csv(&records());
+
+        assert!(csv.starts_with("date,input,output,duration_seconds,"));
+        assert!(csv.contains("\"/videos/Tom, Jerry & \"\"Friends\"\".mkv\""));
+        assert_eq!(from_csv(&csv).unwrap(), records());
+    }
+
+    #[test]
+    fn csv_needs_the_header() {
+        assert!(from_csv("2026-10-16T08:30:00Z,in.mkv\r\n").is_err());
+        assert!(from_csv("date,input\r\n\"unclosed\r\n").is_err());
+    }
+
+    #[test]
+    fn dates_are_utc() {
+        assert_eq!(format_utc(UNIX_EPOCH), "1970-01-01T00:00:00Z");
+        assert_eq!(
+            format_utc(UNIX_EPOCH + Duration::from_secs(1_700_000_000)),
+            "2023-11-14T22:13:20Z"
+        );
+        assert_eq!(
+            format_utc(UNIX_EPOCH + Duration::from_secs(951_782_400)),
+            "2000-02-29T00:00:00Z"
+        );
+    }
+}
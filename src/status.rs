@@ -0,0 +1,363 @@
+use std::path::Path;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread::JoinHandle;
+
+use serde_json::{json, Value};
+use tiny_http::Method;
+
+use crate::config::StatusServerSettings;
+use crate::encoding::EncodeSession;
+use crate::queue::{EncodeQueue, JobStatus};
+
+const STATUS_PAGE: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>AV1Studio</title>
+<style>
+body { font-family: sans-serif; margin: 1em; background: #1b1b1b; color: #ddd; }
+table { border-collapse: collapse; width: 100%; }
+td, th { text-align: left; padding: 0.3em 0.5em; border-bottom: 1px solid #333; }
+</style>
+</head>
+<body>
+<h2>AV1Studio: <span id="state">...</span></h2>
+<div id="current"></div>
+<h3>Queue</h3>
+<table><thead><tr><th>#</th><th>Input</th><th>Output</th><th>Status</th><th>Progress</th></tr></thead>
+<tbody id="queue"></tbody></table>
+<script>
+function progress(e) {
+  if (!e || e.total_frames == null) return "";
+  let text = e.encoded_frames + " / " + e.total_frames;
+  if (e.fps != null) text += ", " + e.fps.toFixed(2) + " fps";
+  if (e.eta) text += ", ETA " + e.eta;
+  return text;
+}
+function cell(row, text) { row.insertCell().textContent = text; }
+async function refresh() {
+  try {
+    const s = await (await fetch("status.json")).json();
+    document.getElementById("state").textContent = s.state;
+    const c = s.current;
+    document.getElementById("current").textContent = c ? c.input + " → " + c.output + ": " + progress(c) : "";
+    const body = document.getElementById("queue");
+    body.replaceChildren();
+    for (const job of s.queue) {
+      const row = body.insertRow();
+      cell(row, job.id); cell(row, job.input); cell(row, job.output);
+      cell(row, job.error ? job.status + ": " + job.error : job.status);
+      cell(row, progress(job));
+    }
+  } catch (e) {
+    document.getElementById("state").textContent = "unreachable";
+  }
+}
+refresh();
+setInterval(refresh, 2000);
+</script>
+</body>
+</html>
+"#;
+
+fn file_name(path: &str) -> String {
+    Path::new(path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.to_string())
+}
+
+fn progress_json(session: Option<&EncodeSession>) -> Value {
+    json!({
+        "encoded_frames": session.and_then(|s| s.encoded_frames),
+        "total_frames": session.and_then(|s| s.total_frames),
+        "fps": session.and_then(|s| s.fps),
+        "eta": session.map(|s| s.eta_text()),
+    })
+}
+
+// What the endpoint reports: the single encode from the main window, if any, and the queue
+pub fn status_json(
+    session: Option<&EncodeSession>,
+    input_file: &str,
+    output_file: &str,
+    queue: &EncodeQueue,
+) -> String {
+    let current = session.map(|session| {
+        let mut current = progress_json(Some(session));
+        current["input"] = json!(file_name(input_file));
+        current["output"] = json!(file_name(output_file));
+        current
+    });
+    let jobs: Vec<Value> = queue
+        .jobs
+        .iter()
+        .map(|job| {
+            let mut entry = progress_json(job.session.as_ref());
+            entry["id"] = json!(job.id);
+            entry["input"] = json!(file_name(&job.input_file));
+            entry["output"] = json!(file_name(&job.output_file));
+            entry["status"] = json!(job.status.as_str());
+            if let JobStatus::Failed(error) = &job.status {
+                entry["error"] = json!(error);
+            }
+            entry
+        })
+        .collect();
+    let encoding = session.is_some() || queue.active_count() > 0;
+
+    json!({
+        "state": if encoding { "encoding" } else { "idle" },
+        "current": current,
+        "queue": jobs,
+    })
+    .to_string()
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum RemoteCommand {
+    StartEncode,
+    CancelEncode,
+    PauseQueue,
+    ResumeQueue,
+}
+
+impl RemoteCommand {
+    pub fn from_path(path: &str) -> Option<Self> {
+        match path {
+            "/encode/start" => Some(RemoteCommand::StartEncode),
+            "/encode/cancel" => Some(RemoteCommand::CancelEncode),
+            "/queue/pause" => Some(RemoteCommand::PauseQueue),
+            "/queue/resume" => Some(RemoteCommand::ResumeQueue),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            RemoteCommand::StartEncode => "start encode",
+            RemoteCommand::CancelEncode => "cancel encode",
+            RemoteCommand::PauseQueue => "pause queue",
+            RemoteCommand::ResumeQueue => "resume queue",
+        }
+    }
+}
+
+// A command and the address it came from
+pub type RemoteRequest = (RemoteCommand, String);
+
+// The path and the address of a command that came without the right token
+pub type RejectedRequest = (String, String);
+
+// Serves the last published state on its own thread, so requests never wait on the UI.
+// Commands only get queued here, the update loop carries them out through the same paths
+// as the buttons.
+pub struct StatusServer {
+    pub error: Option<String>, // Why the server couldn't start, e.g. the port is taken
+
+    state: Arc<Mutex<String>>,
+    settings: Option<StatusServerSettings>, // What the server was last started with
+    running: Option<(Arc<tiny_http::Server>, JoinHandle<()>)>,
+    sender: mpsc::Sender<RemoteRequest>,
+    receiver: mpsc::Receiver<RemoteRequest>,
+    rejected_sender: mpsc::Sender<RejectedRequest>,
+    rejected_receiver: mpsc::Receiver<RejectedRequest>,
+}
+
+impl Default for StatusServer {
+    fn default() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let (rejected_sender, rejected_receiver) = mpsc::channel();
+        StatusServer {
+            error: None,
+            state: Arc::default(),
+            settings: None,
+            running: None,
+            sender,
+            receiver,
+            rejected_sender,
+            rejected_receiver,
+        }
+    }
+}
+
+impl StatusServer {
+    // Starts, stops or restarts the server to match the settings
+    pub fn update(&mut self, settings: &StatusServerSettings) {
+        let settings = settings.enabled.then(|| settings.clone());
+        if settings == self.settings {
+            return;
+        }
+
+        self.stop();
+        self.settings = settings.clone();
+        self.error = None;
+        if let Some(settings) = settings {
+            let address = settings.address.trim();
+            match tiny_http::Server::http(address) {
+                Ok(server) => {
+                    let server = Arc::new(server);
+                    let handle = {
+                        let server = server.clone();
+                        let state = self.state.clone();
+                        let commands = self.sender.clone();
+                        let rejected = self.rejected_sender.clone();
+                        let token = settings.token.trim().to_string();
+                        std::thread::spawn(move || {
+                            serve(&server, &state, &commands, &rejected, &token)
+                        })
+                    };
+                    self.running = Some((server, handle));
+                }
+                Err(e) => self.error = Some(format!("Couldn't listen on {}: {}", address, e)),
+            }
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.is_some()
+    }
+
+    pub fn publish(&self, state: String) {
+        if let Ok(mut current) = self.state.lock() {
+            *current = state;
+        }
+    }
+
+    pub fn commands(&self) -> impl Iterator<Item = RemoteRequest> + '_ {
+        self.receiver.try_iter()
+    }
+
+    pub fn rejected(&self) -> impl Iterator<Item = RejectedRequest> + '_ {
+        self.rejected_receiver.try_iter()
+    }
+
+    fn stop(&mut self) {
+        if let Some((server, handle)) = self.running.take() {
+            server.unblock();
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for StatusServer {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+// Looks at every byte whatever the first difference, so the response time doesn't give away
+// how much of a guess was right
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+// `authorization` is the value of the request's Authorization header
+fn authorized(authorization: Option<&str>, token: &str) -> bool {
+    // No token means remote control is off
+    !token.is_empty()
+        && authorization
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .is_some_and(|given| constant_time_eq(given.as_bytes(), token.as_bytes()))
+}
+
+fn authorization(request: &tiny_http::Request) -> Option<&str> {
+    request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv("Authorization"))
+        .map(|header| header.value.as_str())
+}
+
+fn serve(
+    server: &tiny_http::Server,
+    state: &Mutex<String>,
+    commands: &mpsc::Sender<RemoteRequest>,
+    rejected: &mpsc::Sender<RejectedRequest>,
+    token: &str,
+) {
+    for request in server.incoming_requests() {
+        let path = request
+            .url()
+            .split('?')
+            .next()
+            .unwrap_or_default()
+            .to_string();
+        let source = request
+            .remote_addr()
+            .map(|addr| addr.ip().to_string())
+            .unwrap_or_else(|| String::from("unknown"));
+
+        if *request.method() == Method::Post {
+            let response = match RemoteCommand::from_path(&path) {
+                Some(_) if !authorized(authorization(&request), token) => {
+                    eprintln!("WARNING: Rejected unauthorized {} from {}", path, source);
+                    let _ = rejected.send((path.clone(), source));
+                    401
+                }
+                Some(command) => {
+                    let _ = commands.send((command, source));
+                    202
+                }
+                None => 404,
+            };
+            let _ = request.respond(tiny_http::Response::empty(response));
+            continue;
+        }
+
+        let (body, content_type) = match path.as_str() {
+            "/" | "/index.html" => (STATUS_PAGE.to_string(), "text/html; charset=utf-8"),
+            "/status.json" => (
+                state.lock().map(|s| s.clone()).unwrap_or_default(),
+                "application/json",
+            ),
+            _ => {
+                let _ = request.respond(tiny_http::Response::empty(404));
+                continue;
+            }
+        };
+
+        let header = tiny_http::Header::from_bytes("Content-Type", content_type)
+            .expect("static header is valid");
+        let _ = request.respond(tiny_http::Response::from_string(body).with_header(header));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_the_right_bearer_token_is_authorized() {
+        assert!(authorized(Some("Bearer s3cret"), "s3cret"));
+        assert!(!authorized(Some("Bearer s3cre"), "s3cret"));
+        assert!(!authorized(Some("Bearer s3cret!"), "s3cret"));
+        assert!(!authorized(Some("s3cret"), "s3cret"));
+        assert!(!authorized(None, "s3cret"));
+        // Without a token remote control is off, even for an empty bearer
+        assert!(!authorized(Some("Bearer "), ""));
+    }
+
+    #[test]
+    fn commands_come_from_their_paths() {
+        assert_eq!(
+            RemoteCommand::from_path("/encode/start"),
+            Some(RemoteCommand::StartEncode)
+        );
+        assert_eq!(
+            RemoteCommand::from_path("/encode/cancel"),
+            Some(RemoteCommand::CancelEncode)
+        );
+        assert_eq!(
+            RemoteCommand::from_path("/queue/pause"),
+            Some(RemoteCommand::PauseQueue)
+        );
+        assert_eq!(
+            RemoteCommand::from_path("/queue/resume"),
+            Some(RemoteCommand::ResumeQueue)
+        );
+        assert_eq!(RemoteCommand::from_path("/encode/start/"), None);
+        assert_eq!(RemoteCommand::from_path("/status.json"), None);
+    }
+}
@@ -0,0 +1,57 @@
+use std::time::{Duration, Instant};
+
+use sysinfo::{CpuRefreshKind, MemoryRefreshKind, RefreshKind, System};
+
+// The UI repaints every frame while encoding, so the load gets sampled on its own clock
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
+// CPU and memory use of the whole machine, for telling whether the workers saturate the CPU
+pub struct SystemLoad {
+    system: System,
+    sampled_at: Option<Instant>,
+    pub cpu: Option<f32>, // Percent over all cores, None until two samples were taken
+    pub memory_used: u64, // In bytes
+    pub memory_total: u64,
+}
+
+impl Default for SystemLoad {
+    fn default() -> Self {
+        SystemLoad {
+            system: System::new_with_specifics(RefreshKind::nothing()),
+            sampled_at: None,
+            cpu: None,
+            memory_used: 0,
+            memory_total: 0,
+        }
+    }
+}
+
+impl SystemLoad {
+    pub fn sample(&mut self) {
+        if self
+            .sampled_at
+            .is_some_and(|at| at.elapsed() < SAMPLE_INTERVAL)
+        {
+            return;
+        }
+
+        self.system.refresh_specifics(
+            RefreshKind::nothing()
+                .with_cpu(CpuRefreshKind::nothing().with_cpu_usage())
+                .with_memory(MemoryRefreshKind::nothing().with_ram()),
+        );
+        // CPU usage is the difference between two refreshes, the first one has nothing to compare to
+        if self.sampled_at.is_some() {
+            self.cpu = Some(self.system.global_cpu_usage());
+        }
+        self.memory_used = self.system.used_memory();
+        self.memory_total = self.system.total_memory();
+        self.sampled_at = Some(Instant::now());
+    }
+
+    // Starts over, so the next encode doesn't show the last one's numbers
+    pub fn reset(&mut self) {
+        self.sampled_at = None;
+        self.cpu = None;
+    }
+}
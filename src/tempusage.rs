@@ -0,0 +1,172 @@
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::{Duration, Instant, SystemTime};
+
+// Walking thousands of chunk files takes a moment, so it's done on its own thread and clock
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(2);
+
+// Less free space than this next to the temp folder likely ends the encode with a full disk
+pub const LOW_SPACE: u64 = 5 * 1024 * 1024 * 1024;
+
+struct Sample {
+    folder: Option<PathBuf>,
+    used: Option<u64>,
+    available: Option<u64>,
+}
+
+// How big av1an's temp folder got during the encode, and what's left on its disk
+pub struct TempUsage {
+    pub folder: Option<PathBuf>, // None until av1an created it
+    pub used: Option<u64>,       // In bytes
+    pub available: Option<u64>,
+
+    since: SystemTime,
+    sampled_at: Option<Instant>,
+    receiver: Option<mpsc::Receiver<Sample>>,
+}
+
+impl Default for TempUsage {
+    fn default() -> Self {
+        TempUsage {
+            folder: None,
+            used: None,
+            available: None,
+            since: SystemTime::now(),
+            sampled_at: None,
+            receiver: None,
+        }
+    }
+}
+
+impl TempUsage {
+    // Forgets the last encode's folder, av1an's own one gets looked for anew
+    pub fn start(&mut self) {
+        *self = TempUsage::default();
+    }
+
+    // `configured` is av1an's --temp, None when it picks its own folder
+    pub fn sample(&mut self, configured: Option<&Path>) {
+        if let Some(receiver) = &self.receiver {
+            match receiver.try_recv() {
+                Ok(sample) => {
+                    self.folder = sample.folder;
+                    self.used = sample.used;
+                    self.available = sample.available;
+                    self.receiver = None;
+                }
+                Err(mpsc::TryRecvError::Empty) => return,
+                Err(mpsc::TryRecvError::Disconnected) => self.receiver = None,
+            }
+        }
+        if self
+            .sampled_at
+            .is_some_and(|at| at.elapsed() < SAMPLE_INTERVAL)
+        {
+            return;
+        }
+        self.sampled_at = Some(Instant::now());
+
+        let folder = configured.map(Path::to_path_buf).or(self.folder.clone());
+        let since = self.since;
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || {
+            let folder = folder.or_else(|| find_av1an_temp(Path::new("."), since));
+            let used = folder
+                .as_deref()
+                .filter(|folder| folder.is_dir())
+                .map(folder_size);
+            // The folder itself may not exist yet
+            let existing = folder
+                .as_deref()
+                .unwrap_or(Path::new("."))
+                .ancestors()
+                .find(|dir| dir.is_dir());
+            let available = existing.and_then(|dir| fs2::available_space(dir).ok());
+            let _ = sender.send(Sample {
+                folder,
+                used,
+                available,
+            });
+        });
+        self.receiver = Some(receiver);
+    }
+
+    pub fn is_low(&self) -> bool {
+        self.available
+            .is_some_and(|available| available < LOW_SPACE)
+    }
+}
+
+// Everything in the folder, symlinks aren't followed
+pub fn folder_size(folder: &Path) -> u64 {
+    let Ok(entries) = std::fs::read_dir(folder) else {
+        return 0;
+    };
+
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            Some(if metadata.is_dir() {
+                folder_size(&entry.path())
+            } else {
+                metadata.len()
+            })
+        })
+        .sum()
+}
+
+// av1an's own temp folder is a hidden folder named after a hash of the input, in the working
+// directory. This is the one with av1an's layout in it that changed last, if that was after
+// `since`.
+pub fn find_av1an_temp(dir: &Path, since: SystemTime) -> Option<PathBuf> {
+    let last_change = |folder: &Path| {
+        ["", "split", "encode"]
+            .iter()
+            .filter_map(|sub| std::fs::metadata(folder.join(sub)).ok()?.modified().ok())
+            .max()
+    };
+
+    std::fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+                && path.is_dir()
+                && (path.join("split").is_dir() || path.join("chunks.json").is_file())
+        })
+        .filter_map(|path| Some((last_change(&path)?, path)))
+        .filter(|(changed, _)| *changed >= since)
+        .max_by_key(|(changed, _)| *changed)
+        .map(|(_, path)| path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_the_temp_folder_and_its_size() {
+        let dir = std::env::temp_dir().join(format!("av1studio-temp-usage-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let since = SystemTime::now() - Duration::from_secs(1);
+        let temp = dir.join(".4f9a2c1");
+        std::fs::create_dir_all(temp.join("split")).unwrap();
+        std::fs::create_dir_all(temp.join("encode")).unwrap();
+        std::fs::write(temp.join("chunks.json"), [0; 100]).unwrap();
+        std::fs::write(temp.join("encode").join("00000.ivf"), [0; 1000]).unwrap();
+        // Hidden, but not av1an's
+        std::fs::create_dir_all(dir.join(".git")).unwrap();
+        std::fs::create_dir_all(dir.join("split")).unwrap();
+
+        assert_eq!(find_av1an_temp(&dir, since), Some(temp.clone()));
+        assert_eq!(folder_size(&temp), 1100);
+        // Left over from an earlier encode
+        let later = SystemTime::now() + Duration::from_secs(60);
+        assert_eq!(find_av1an_temp(&dir, later), None);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
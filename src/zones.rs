@@ -0,0 +1,191 @@
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::models::Encoder;
+
+// The parts of av1an's scenes JSON the zones need
+#[derive(Deserialize)]
+struct ScenesFile {
+    scenes: Vec<SceneEntry>,
+}
+
+#[derive(Deserialize)]
+struct SceneEntry {
+    start_frame: u64,
+    end_frame: u64, // Exclusive
+}
+
+// A scene with the settings it should get instead of the main ones, None keeps the main one
+#[derive(Clone, Debug, PartialEq)]
+pub struct Scene {
+    pub start: u64,
+    pub end: u64,
+    pub crf: Option<f32>,
+    pub preset: Option<f32>,
+}
+
+impl Scene {
+    pub fn is_overridden(&self) -> bool {
+        self.crf.is_some() || self.preset.is_some()
+    }
+}
+
+// Per-scene overrides on top of a scenes file, written out as an av1an zones file
+#[derive(Default)]
+pub struct ZoneEditor {
+    pub scenes_file: String, // What `scenes` were loaded from
+    pub scenes: Vec<Scene>,
+    pub error: Option<String>,
+
+    exported: u32, // Numbers the zones files, so queued jobs keep the ones they were added with
+    exports: Vec<PathBuf>, // Written and not removed yet
+}
+
+impl ZoneEditor {
+    pub fn load(&mut self, scenes_file: &Path) {
+        self.scenes_file = scenes_file.display().to_string();
+        match load_scenes(scenes_file) {
+            Ok(scenes) => {
+                self.scenes = scenes;
+                self.error = None;
+            }
+            Err(e) => {
+                self.scenes.clear();
+                self.error = Some(e);
+            }
+        }
+    }
+
+    // A new zones file for av1an's --zones in the temp folder. An earlier one may belong to a
+    // queued job, so it's never overwritten.
+    pub fn write_temp(&mut self, encoder: Encoder) -> std::io::Result<PathBuf> {
+        self.exported += 1;
+        let path = std::env::temp_dir().join(format!(
+            "av1studio-zones-{}-{}.txt",
+            std::process::id(),
+            self.exported
+        ));
+        std::fs::write(&path, zones_text(&self.scenes, encoder))?;
+        self.exports.push(path.clone());
+
+        Ok(path)
+    }
+
+    // Deletes the exported zones files that nothing in `in_use` points to anymore
+    pub fn remove_unused_exports(&mut self, in_use: &[&str]) {
+        self.exports.retain(|path| {
+            if in_use.iter().any(|used| Path::new(used) == path) {
+                return true;
+            }
+            if let Err(e) = std::fs::remove_file(path) {
+                eprintln!("WARNING: Couldn't remove {:?}: {}", path, e);
+            }
+            false
+        });
+    }
+}
+
+pub fn load_scenes(path: &Path) -> Result<Vec<Scene>, String> {
+    let content = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let file: ScenesFile = serde_json::from_str(&content).map_err(|e| e.to_string())?;
+
+    Ok(file
+        .scenes
+        .into_iter()
+        .map(|scene| Scene {
+            start: scene.start_frame,
+            end: scene.end_frame,
+            crf: None,
+            preset: None,
+        })
+        .collect())
+}
+
+// One "start end encoder params" line per overridden scene. Without "reset" av1an keeps the
+// main encoder parameters and only replaces the ones given here.
+pub fn zones_text(scenes: &[Scene], encoder: Encoder) -> String {
+    // Written the way `encoder_params` writes them
+    let (quality_flag, speed_flag, separator) = match encoder {
+        Encoder::SvtAv1 => ("--crf", "--preset", " "),
+        Encoder::Aom => ("--cq-level", "--cpu-used", "="),
+        Encoder::Rav1e => ("--quantizer", "--speed", " "),
+    };
+    // aomenc and rav1e only take whole numbers
+    let step = encoder.ranges().quality_step;
+
+    scenes
+        .iter()
+        .filter(|scene| scene.is_overridden())
+        .map(|scene| {
+            let mut line = format!("{} {} {}", scene.start, scene.end, encoder.as_str());
+            if let Some(crf) = scene.crf {
+                let crf = (crf / step).round() * step;
+                line.push_str(&format!(" {}{}{}", quality_flag, separator, crf));
+            }
+            if let Some(preset) = scene.preset {
+                line.push_str(&format!(" {}{}{}", speed_flag, separator, preset));
+            }
+            line + "\n"
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_overridden_scenes_become_zones() {
+        let file =
+            std::env::temp_dir().join(format!("av1studio-scenes-{}.json", std::process::id()));
+        std::fs::write(
+            &file,
+            r#"{"scenes":[{"start_frame":0,"end_frame":120,"zone_overrides":null},{"start_frame":120,"end_frame":300,"zone_overrides":null},{"start_frame":300,"end_frame":410,"zone_overrides":null}],"frames":410}"#,
+        )
+        .unwrap();
+        let mut scenes = load_scenes(&file).unwrap();
+        std::fs::remove_file(&file).unwrap();
+
+        assert_eq!(scenes.len(), 3);
+        scenes[1].crf = Some(22.5);
+        scenes[2].preset = Some(6.0);
+        assert_eq!(
+            zones_text(&scenes, Encoder::SvtAv1),
+            "120 300 svt-av1 --crf 22.5\n300 410 svt-av1 --preset 6\n"
+        );
+        assert_eq!(
+            zones_text(&scenes, Encoder::Aom),
+            "120 300 aom --cq-level=23\n300 410 aom --cpu-used=6\n"
+        );
+    }
+
+    #[test]
+    fn every_export_gets_its_own_file() {
+        let mut editor = ZoneEditor {
+            scenes: vec![Scene {
+                start: 0,
+                end: 120,
+                crf: Some(30.0),
+                preset: None,
+            }],
+            ..ZoneEditor::default()
+        };
+        let first = editor.write_temp(Encoder::SvtAv1).unwrap();
+        editor.scenes[0].crf = Some(20.0);
+        let second = editor.write_temp(Encoder::SvtAv1).unwrap();
+
+        assert_ne!(first, second);
+        assert_eq!(
+            std::fs::read_to_string(&first).unwrap(),
+            "0 120 svt-av1 --crf 30\n"
+        );
+
+        let first_name = first.display().to_string();
+        editor.remove_unused_exports(&[&first_name]);
+        assert!(first.exists());
+        assert!(!second.exists());
+        editor.remove_unused_exports(&[]);
+        assert!(!first.exists());
+    }
+}